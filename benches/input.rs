@@ -0,0 +1,50 @@
+//! Times Zen programs that read a lot of preloaded input, as when grading
+//! solutions against large test cases.
+//!
+//! Run with `cargo bench --bench input`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+use zen::run_program;
+
+const RUNS: u32 = 10;
+const VALUES: usize = 90_000;
+
+const SUM_NUMBERS: &str = "PARAMPARA PRATISHTA ANUSHASHAN
+N BOLE TOH INPUT LE LE RE BABA
+TOTAL BOLE TOH 0
+EK DO TEEN I BOLE TOH 1 SE N TAK
+    TOTAL BOLE TOH TOTAL + INPUT LE LE RE BABA
+JAHAN
+PRINT BASANTI PRINT TOTAL
+KHATAM TATA BYE BYE";
+
+const COUNT_LINES: &str = "PARAMPARA PRATISHTA ANUSHASHAN
+N BOLE TOH INPUT LE LE RE BABA
+TOTAL BOLE TOH 0
+EK DO TEEN I BOLE TOH 1 SE N TAK
+    TOTAL BOLE TOH TOTAL + LAMBAI(PURI LINE LE LE RE BABA)
+JAHAN
+PRINT BASANTI PRINT TOTAL
+KHATAM TATA BYE BYE";
+
+fn bench(name: &str, code: &str, input: &str) {
+    let mut total = Duration::ZERO;
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        let output = run_program(black_box(code.to_string()), black_box(input), false);
+        total += start.elapsed();
+        assert!(output.is_ok(), "{} failed: {:?}", name, output);
+    }
+    println!("{:<14} {:>10.2?} per run", name, total / RUNS);
+}
+
+fn main() {
+    let numbers: Vec<String> = (1..=VALUES).map(|i| i.to_string()).collect();
+    let one_line = format!("{}\n{}\n", VALUES, numbers.join(" "));
+    let per_line = format!("{}\n{}\n", VALUES, numbers.join("\n"));
+
+    bench("numbers", SUM_NUMBERS, &one_line);
+    bench("number lines", SUM_NUMBERS, &per_line);
+    bench("whole lines", COUNT_LINES, &per_line);
+}
@@ -0,0 +1,68 @@
+//! Times loop-heavy Zen programs from source to output.
+//!
+//! Run with `cargo bench --bench loops`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+use zen::run_program;
+
+const RUNS: u32 = 10;
+
+const NESTED_WHILE: &str = "PARAMPARA PRATISHTA ANUSHASHAN
+TOTAL BOLE TOH 0
+I BOLE TOH 0
+JAB TAK HAI JAAN I < 300 TAB TAK
+    J BOLE TOH 0
+    JAB TAK HAI JAAN J < 300 TAB TAK
+        TOTAL BOLE TOH TOTAL + (I * J) % 7
+        J BOLE TOH J + 1
+    JAHAN
+    I BOLE TOH I + 1
+JAHAN
+PRINT BASANTI PRINT TOTAL
+KHATAM TATA BYE BYE";
+
+const COUNTED_FOR: &str = "PARAMPARA PRATISHTA ANUSHASHAN
+PRIMES BOLE TOH []
+EK DO TEEN N BOLE TOH 2 SE 20000 TAK
+    PRIME BOLE TOH true
+    D BOLE TOH 2
+    JAB TAK HAI JAAN PRIME && D * D <= N TAB TAK
+        AGAR N % D == 0 TAB
+            PRIME BOLE TOH false
+        BAS ITNA HI
+        D BOLE TOH D + 1
+    JAHAN
+    AGAR PRIME TAB
+        JODO(PRIMES, N)
+    BAS ITNA HI
+JAHAN
+PRINT BASANTI PRINT LAMBAI(PRIMES)
+KHATAM TATA BYE BYE";
+
+const RECURSION: &str = "PARAMPARA PRATISHTA ANUSHASHAN
+AAO KABHI HAVELI PE fib(N)
+    AGAR N < 2 TAB
+        LAUT KE AAJA N
+    BAS ITNA HI
+    LAUT KE AAJA fib(N - 1) + fib(N - 2)
+PHIR MILENGE
+PRINT BASANTI PRINT fib(20)
+KHATAM TATA BYE BYE";
+
+fn bench(name: &str, code: &str) {
+    let mut total = Duration::ZERO;
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        let output = run_program(black_box(code.to_string()), "", false);
+        total += start.elapsed();
+        assert!(output.is_ok(), "{} failed: {:?}", name, output);
+    }
+    println!("{:<14} {:>10.2?} per run", name, total / RUNS);
+}
+
+fn main() {
+    bench("nested while", NESTED_WHILE);
+    bench("counted for", COUNTED_FOR);
+    bench("recursion", RECURSION);
+}
@@ -0,0 +1,13 @@
+use std::{io, process};
+
+/// The Zen language server. Editors start it and talk to it over stdin and
+/// stdout, see `zen::lsp::Server` for what it can do.
+fn main() {
+    match zen::lsp::run(io::stdin().lock(), io::stdout().lock()) {
+        Ok(code) => process::exit(code),
+        Err(err) => {
+            eprintln!("zen-lsp: {}", err);
+            process::exit(1);
+        }
+    }
+}
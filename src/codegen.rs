@@ -0,0 +1,45 @@
+use crate::evaluator::interpreter::InterpreterErrorType;
+use crate::evaluator::interpreter::InterpreterErrorType::{
+    ArgumentCountMismatch, DivisionByZero, EmptyCustomInputStack, IncompatibleDataType,
+    IndexOutOfBounds, InvalidInputError, InvalidLoopStep, LoopControlOutsideLoop,
+    MaxCallDepthExceeded, MaxLoopsExceeded, MissingKey, MissingReturnValue, ReturnOutsideFunction,
+    VariableOutOfScope,
+};
+use crate::parser::ast::{Ident, InputKind};
+
+pub mod c;
+mod case;
+pub mod wasm;
+
+/// Errors the runtimes raise with the interpreter's own messages
+const RUNTIME_ERRORS: [InterpreterErrorType; 14] = [
+    ArgumentCountMismatch,
+    DivisionByZero,
+    EmptyCustomInputStack,
+    IncompatibleDataType,
+    IndexOutOfBounds,
+    InvalidInputError,
+    InvalidLoopStep,
+    LoopControlOutsideLoop,
+    MaxCallDepthExceeded,
+    MaxLoopsExceeded,
+    MissingKey,
+    MissingReturnValue,
+    ReturnOutsideFunction,
+    VariableOutOfScope,
+];
+
+/// The name the runtimes give the function reading this kind of input
+fn input_name(kind: &InputKind) -> &'static str {
+    match kind {
+        InputKind::Number => "number",
+        InputKind::Word => "word",
+        InputKind::Line => "line",
+    }
+}
+
+/// Whether calling `function` changes the case of a string, so the program
+/// needs the case tables
+fn changes_case(function: &Ident) -> bool {
+    matches!(function.0.as_str(), "BADA" | "CHHOTA")
+}
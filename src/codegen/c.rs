@@ -0,0 +1,671 @@
+use super::case::CASE_TABLES;
+use super::{RUNTIME_ERRORS, changes_case, input_name};
+use crate::error::ParseError;
+use crate::evaluator::constants::{MAX_CALL_DEPTH, MAX_ITER_COUNT};
+use crate::evaluator::interpreter::InterpreterError;
+use crate::evaluator::interpreter::InterpreterErrorType::MissingStartSymbol;
+use crate::lexer::span::Span;
+use crate::parser::ast::{Expression, Ident, Infix, Literal, Prefix, Program, Statement};
+use std::collections::HashMap;
+use std::fmt::Write;
+
+/// Values, variables and builtins of compiled programs
+const RUNTIME: &str = include_str!("runtime.c");
+
+/// Translates a program to C99 source that builds into a standalone
+/// executable (`cc program.c -lm`).
+///
+/// The compiled program prints what the interpreter would print. Runtime
+/// errors go to stderr as `error[Type]: message` followed by the line and
+/// column, and the program exits with status 1.
+pub fn generate(program: &Program) -> Result<String, InterpreterError> {
+    if program.statements.first().map(Statement::unspanned) != Some(&Statement::ProgramStart) {
+        return Err(InterpreterError::new(MissingStartSymbol));
+    }
+
+    let mut generator = Generator::default();
+    generator.block(&program.statements[1..]);
+    let main = std::mem::take(&mut generator.body);
+    Ok(generator.finish(main))
+}
+
+#[derive(Default)]
+struct Generator {
+    symbols: Vec<String>,
+    symbol_ids: HashMap<String, usize>,
+    /// Definitions of the C functions that the program's functions became
+    functions: Vec<String>,
+    /// Spans of the statements and expressions being translated, innermost
+    /// last
+    spans: Vec<Span>,
+    body: Body,
+    /// Whether the program calls BADA or CHHOTA
+    changes_case: bool,
+}
+
+/// The C function being written: the program itself or one of its functions
+#[derive(Default)]
+struct Body {
+    code: String,
+    indent: usize,
+    temporaries: usize,
+    loops_seen: usize,
+    /// Scopes opened since the start of the function (or the program)
+    scope_depth: usize,
+    /// Scopes open outside of the body of each enclosing loop
+    loops: Vec<usize>,
+    in_function: bool,
+}
+
+impl Generator {
+    /// The case tables of the runtime's zen_change_case, left empty when the
+    /// program never changes case
+    fn case_tables(&self, out: &mut String) {
+        let tables = self.changes_case.then(|| &*CASE_TABLES);
+        *out += "#include <stdint.h>\n";
+        c_table(out, "upper_runs", tables.map(|t| &t.upper[..]));
+        c_table(out, "upper_special", tables.map(|t| &t.upper_special[..]));
+        c_table(out, "lower_runs", tables.map(|t| &t.lower[..]));
+        c_table(out, "lower_special", tables.map(|t| &t.lower_special[..]));
+        c_table(out, "cased", tables.map(|t| &t.cased[..]));
+        c_table(out, "case_ignorable", tables.map(|t| &t.case_ignorable[..]));
+    }
+
+    fn finish(self, main: Body) -> String {
+        let mut out = String::new();
+        out += "/* Compiled from a Zen program. Build with: cc program.c -lm */\n\n";
+        let _ = writeln!(out, "#define ZEN_MAX_ITER_COUNT {}", MAX_ITER_COUNT);
+        let _ = writeln!(out, "#define ZEN_MAX_CALL_DEPTH {}", MAX_CALL_DEPTH);
+        for error_type in RUNTIME_ERRORS {
+            let message = InterpreterError::new(error_type.clone()).msg;
+            let _ = writeln!(
+                out,
+                "#define ZEN_{0} \"{0}\", {1}",
+                error_type,
+                c_string(&message)
+            );
+        }
+        out += "\n";
+        self.case_tables(&mut out);
+        out += "\n";
+        out += RUNTIME;
+
+        out += "\nstatic const char *const zen_symbol_names[] = {\n";
+        for name in &self.symbols {
+            let _ = writeln!(out, "    {},", c_string(name));
+        }
+        if self.symbols.is_empty() {
+            out += "    0,\n";
+        }
+        out += "};\n";
+
+        for function in &self.functions {
+            out += "\n";
+            out += function;
+        }
+
+        out += "\nint main(void) {\n";
+        let _ = writeln!(
+            out,
+            "    zen_start(zen_symbol_names, {});",
+            self.symbols.len()
+        );
+        out += &main.code;
+        out += "    return 0;\n}\n";
+        out
+    }
+
+    fn symbol(&mut self, name: &Ident) -> usize {
+        if let Some(id) = self.symbol_ids.get(&name.0) {
+            return *id;
+        }
+        self.symbols.push(name.0.clone());
+        self.symbol_ids
+            .insert(name.0.clone(), self.symbols.len() - 1);
+        self.symbols.len() - 1
+    }
+
+    fn line(&mut self, text: &str) {
+        let indent = self.body.indent + 1;
+        let _ = writeln!(self.body.code, "{}{}", "    ".repeat(indent), text);
+    }
+
+    fn open(&mut self, text: &str) {
+        self.line(text);
+        self.body.indent += 1;
+    }
+
+    fn close(&mut self, text: &str) {
+        self.body.indent -= 1;
+        self.line(text);
+    }
+
+    /// Where the innermost statement or expression being translated is
+    fn at(&self) -> String {
+        match self.spans.last() {
+            Some(span) => format!("ZEN_AT({}, {})", span.line, span.column),
+            None => "ZEN_NOWHERE".to_string(),
+        }
+    }
+
+    fn temporary(&mut self) -> String {
+        self.body.temporaries += 1;
+        format!("t{}", self.body.temporaries)
+    }
+
+    fn block(&mut self, statements: &[Statement]) {
+        for statement in statements {
+            // Nothing after 'KHATAM TATA BYE BYE' runs, in any block
+            if let Statement::ProgramEnd = statement.unspanned() {
+                break;
+            }
+            self.statement(statement);
+        }
+    }
+
+    /// Runs a block body in a fresh scope, dropping its variables afterwards
+    fn scoped_block(&mut self, statements: &[Statement]) {
+        self.line("zen_push_scope();");
+        self.body.scope_depth += 1;
+        self.block(statements);
+        self.body.scope_depth -= 1;
+        self.line("zen_pop_scope();");
+    }
+
+    fn statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::Spanned { span, statement } => {
+                self.spans.push(*span);
+                self.statement(statement);
+                self.spans.pop();
+            }
+            Statement::ProgramStart => {
+                let message = c_string(
+                    "Only one 'PARAMPARA PRATISHTA ANUSHASHAN' allowed! 'Ek hi baar bolna kaafi hai.'",
+                );
+                let fail = format!(
+                    "zen_fail(\"SyntaxError\", {}, {}, NULL);",
+                    message,
+                    self.at()
+                );
+                self.line(&fail);
+            }
+            Statement::ProgramEnd => {}
+            Statement::Let { name, value } => {
+                let value = self.expression(value);
+                let symbol = self.symbol(name);
+                self.line(&format!("zen_assign({}, {});", symbol, value));
+            }
+            Statement::LocalLet { name, value } => {
+                let value = self.expression(value);
+                let symbol = self.symbol(name);
+                self.line(&format!("zen_declare({}, {});", symbol, value));
+            }
+            Statement::If {
+                condition,
+                consequence,
+                alternative,
+            } => {
+                let condition = self.expression(condition);
+                self.open(&format!("if (zen_is_true({})) {{", condition));
+                self.scoped_block(consequence);
+                if let Some(alternative) = alternative {
+                    self.close("} else {");
+                    self.body.indent += 1;
+                    self.scoped_block(alternative);
+                }
+                self.close("}");
+            }
+            Statement::While { condition, body } => self.while_loop(condition, body),
+            Statement::For {
+                variable,
+                start,
+                end,
+                step,
+                body,
+            } => self.for_loop(variable, start, end, step.as_ref(), body),
+            Statement::Print(expr) => {
+                let value = self.expression(expr);
+                self.line(&format!("zen_print({});", value));
+            }
+            Statement::Function {
+                name,
+                parameters,
+                body,
+            } => self.function(name, parameters, body),
+            Statement::Return(expr) => self.return_statement(expr.as_ref()),
+            Statement::Break | Statement::Continue => {
+                self.loop_control(statement == &Statement::Break)
+            }
+            Statement::IndexLet { left, index, value } => {
+                let left = self.expression(left);
+                let index = self.expression(index);
+                let value = self.expression(value);
+                let at = self.at();
+                self.line(&format!(
+                    "zen_set_element({}, {}, {}, {});",
+                    left, index, value, at
+                ));
+            }
+            Statement::Expression(expr) => match expr.unspanned() {
+                // A call used as a statement may return nothing
+                Expression::Call {
+                    function,
+                    arguments,
+                } => {
+                    let call = self.call(function, arguments, false);
+                    self.line(&format!("{};", call));
+                }
+                _ => {
+                    let value = self.expression(expr);
+                    self.line(&format!("(void){};", value));
+                }
+            },
+            Statement::Invalid(err) => self.invalid_statement(err),
+        }
+    }
+
+    fn while_loop(&mut self, condition: &Expression, body: &[Statement]) {
+        self.body.loops_seen += 1;
+        let iterations = format!("iterations{}", self.body.loops_seen);
+        let at = self.at();
+
+        self.open(&format!("for (long {0} = 0;; {0}++) {{", iterations));
+        self.open(&format!("if ({} > ZEN_MAX_ITER_COUNT) {{", iterations));
+        self.line(&format!("zen_fail(ZEN_MaxLoopsExceeded, {}, NULL);", at));
+        self.close("}");
+        let condition = self.expression(condition);
+        self.open(&format!("if (!zen_is_true({})) {{", condition));
+        self.line("break;");
+        self.close("}");
+        self.loop_body(body);
+        self.close("}");
+    }
+
+    fn for_loop(
+        &mut self,
+        variable: &Ident,
+        start: &Expression,
+        end: &Expression,
+        step: Option<&Expression>,
+        body: &[Statement],
+    ) {
+        self.body.loops_seen += 1;
+        let id = self.body.loops_seen;
+        let at = self.at();
+
+        // The loop variable lives in its own scope around the body
+        self.line("zen_push_scope();");
+        self.body.scope_depth += 1;
+        self.open("{");
+        for (name, bound) in [("start", Some(start)), ("end", Some(end)), ("step", step)] {
+            let bound = match bound {
+                Some(bound) => {
+                    let bound = self.expression(bound);
+                    format!("zen_loop_bound({}, {})", bound, at)
+                }
+                None => "1.0".to_string(),
+            };
+            self.line(&format!("double {}{} = {};", name, id, bound));
+        }
+        self.open(&format!("if (step{} == 0) {{", id));
+        self.line(&format!("zen_fail(ZEN_InvalidLoopStep, {}, NULL);", at));
+        self.close("}");
+
+        // Counting iterations instead of adding up the steps keeps fractional
+        // steps from drifting
+        self.open(&format!(
+            "for (long iterations{0} = 0;; iterations{0}++) {{",
+            id
+        ));
+        self.line(&format!(
+            "double current{0} = start{0} + step{0} * (double)iterations{0};",
+            id
+        ));
+        self.open(&format!(
+            "if ((step{0} > 0 && current{0} > end{0}) || (step{0} < 0 && current{0} < end{0})) {{",
+            id
+        ));
+        self.line("break;");
+        self.close("}");
+        self.open(&format!("if (iterations{} >= ZEN_MAX_ITER_COUNT) {{", id));
+        self.line(&format!("zen_fail(ZEN_MaxLoopsExceeded, {}, NULL);", at));
+        self.close("}");
+        let variable = self.symbol(variable);
+        self.line(&format!(
+            "zen_declare({}, zen_number(current{}));",
+            variable, id
+        ));
+        self.loop_body(body);
+        self.close("}");
+
+        self.close("}");
+        self.body.scope_depth -= 1;
+        self.line("zen_pop_scope();");
+    }
+
+    fn loop_body(&mut self, body: &[Statement]) {
+        self.body.loops.push(self.body.scope_depth);
+        self.scoped_block(body);
+        self.body.loops.pop();
+    }
+
+    fn loop_control(&mut self, is_break: bool) {
+        let keyword = if is_break { "break;" } else { "continue;" };
+        let Some(&loop_scope_depth) = self.body.loops.last() else {
+            if self.body.in_function {
+                self.pop_scopes(self.body.scope_depth);
+                self.line("return zen_loop_control();");
+            } else {
+                let at = self.at();
+                self.line(&format!(
+                    "zen_fail(ZEN_LoopControlOutsideLoop, {}, NULL);",
+                    at
+                ));
+            }
+            return;
+        };
+
+        self.pop_scopes(self.body.scope_depth - loop_scope_depth);
+        self.line(keyword);
+    }
+
+    fn return_statement(&mut self, expr: Option<&Expression>) {
+        let value = expr.map(|expr| self.expression(expr));
+
+        if !self.body.in_function {
+            if let Some(value) = value {
+                self.line(&format!("(void){};", value));
+            }
+            let at = self.at();
+            self.line(&format!(
+                "zen_fail(ZEN_ReturnOutsideFunction, {}, NULL);",
+                at
+            ));
+            return;
+        }
+
+        // Leaving the blocks one by one lets their variables expire properly
+        self.pop_scopes(self.body.scope_depth);
+        match value {
+            Some(value) => self.line(&format!("return {};", value)),
+            None => self.line("return zen_nothing();"),
+        }
+    }
+
+    fn pop_scopes(&mut self, count: usize) {
+        for _ in 0..count {
+            self.line("zen_pop_scope();");
+        }
+    }
+
+    fn function(&mut self, name: &Ident, parameters: &[Ident], body: &[Statement]) {
+        let id = self.functions.len();
+        // Reserve the slot, so that functions defined inside this one come
+        // after it
+        self.functions.push(String::new());
+        let symbol = self.symbol(name);
+        let parameters: Vec<String> = parameters
+            .iter()
+            .map(|parameter| self.symbol(parameter).to_string())
+            .collect();
+
+        let outer_body = std::mem::replace(
+            &mut self.body,
+            Body {
+                in_function: true,
+                ..Body::default()
+            },
+        );
+        let outer_spans = std::mem::take(&mut self.spans);
+        self.block(body);
+        self.line("return zen_nothing();");
+        let function_body = std::mem::replace(&mut self.body, outer_body);
+        self.spans = outer_spans;
+
+        let mut definition = format!("/* AAO KABHI HAVELI PE {} */\n", name.0);
+        if !parameters.is_empty() {
+            let _ = writeln!(
+                definition,
+                "static const int zen_parameters_{}[] = {{{}}};",
+                id,
+                parameters.join(", ")
+            );
+        }
+        let _ = write!(
+            definition,
+            "static ZenValue zen_function_{}(void) {{\n{}}}\n",
+            id, function_body.code
+        );
+        self.functions[id] = definition;
+
+        let parameter_list = if parameters.is_empty() {
+            "NULL".to_string()
+        } else {
+            format!("zen_parameters_{}", id)
+        };
+        self.line(&format!(
+            "zen_define({}, zen_function_{}, {}, {});",
+            symbol,
+            id,
+            parameters.len(),
+            parameter_list
+        ));
+    }
+
+    fn invalid_statement(&mut self, err: &ParseError) {
+        let at = match err.span {
+            Some(span) => format!("ZEN_AT({}, {})", span.line, span.column),
+            None => self.at(),
+        };
+        self.line(&format!(
+            "zen_fail(\"SyntaxError\", {}, {}, NULL);",
+            c_string(&err.to_string()),
+            at
+        ));
+    }
+
+    /// Translates a call, giving back the C expression that makes it. The
+    /// arguments are worked out first, left to right.
+    fn call(&mut self, function: &Ident, arguments: &[Expression], wants_value: bool) -> String {
+        self.changes_case |= changes_case(function);
+        let symbol = self.symbol(function);
+        let at = self.at();
+        self.line(&format!(
+            "zen_prepare_call({}, {}, {});",
+            symbol,
+            arguments.len(),
+            at
+        ));
+        let values: Vec<String> = arguments
+            .iter()
+            .map(|argument| self.expression(argument))
+            .collect();
+        format!(
+            "zen_call({}, {}, {}, {}, {})",
+            symbol,
+            arguments.len(),
+            value_array(&values),
+            wants_value,
+            at
+        )
+    }
+
+    /// Writes out the code that works out an expression, giving back the
+    /// temporary that holds its value. Going through temporaries keeps the
+    /// left-to-right order of evaluation, which C does not promise for the
+    /// arguments of a call.
+    fn expression(&mut self, expression: &Expression) -> String {
+        let value = match expression {
+            Expression::Spanned { span, expression } => {
+                self.spans.push(*span);
+                let value = self.expression(expression);
+                self.spans.pop();
+                return value;
+            }
+            Expression::IdentifierExpr(ident) => {
+                let symbol = self.symbol(ident);
+                format!("zen_load({}, {})", symbol, self.at())
+            }
+            Expression::LiteralExpr(literal) => match literal {
+                Literal::Number(num) => format!("zen_number({})", c_number(*num)),
+                Literal::BoolLiteral(bool) => format!("zen_bool({})", bool),
+                Literal::StringLiteral(str) => format!("zen_string({})", c_string(str)),
+            },
+            Expression::PrefixExpr { operator, right } => {
+                let right = self.expression(right);
+                let function = match operator {
+                    Prefix::PrefixPlus => "zen_unary_plus",
+                    Prefix::PrefixMinus => "zen_negate",
+                    Prefix::Not => "zen_not",
+                };
+                format!("{}({}, {})", function, right, self.at())
+            }
+            Expression::InfixExpr {
+                left,
+                operator,
+                right,
+            } => {
+                let left = self.expression(left);
+                let right = self.expression(right);
+                format!(
+                    "{}({}, {}, {})",
+                    infix_function(operator),
+                    left,
+                    right,
+                    self.at()
+                )
+            }
+            Expression::Call {
+                function,
+                arguments,
+            } => self.call(function, arguments, true),
+            Expression::ListExpr(elements) => {
+                let values: Vec<String> = elements
+                    .iter()
+                    .map(|element| self.expression(element))
+                    .collect();
+                format!("zen_list_of({}, {})", values.len(), value_array(&values))
+            }
+            Expression::MapExpr(entries) => {
+                let map = self.temporary();
+                self.line(&format!("ZenValue {} = zen_new_map();", map));
+                for (key, value) in entries {
+                    let key = self.expression(key);
+                    let value = self.expression(value);
+                    let at = self.at();
+                    self.line(&format!(
+                        "zen_map_insert({}, {}, {}, {});",
+                        map, key, value, at
+                    ));
+                }
+                return map;
+            }
+            Expression::Index { left, index } => {
+                let left = self.expression(left);
+                let index = self.expression(index);
+                format!("zen_index({}, {}, {})", left, index, self.at())
+            }
+            Expression::Slice { left, start, end } => {
+                let left = self.expression(left);
+                let mut bound = |bound: &Option<Box<Expression>>| match bound {
+                    Some(bound) => self.expression(bound),
+                    None => "zen_nothing()".to_string(),
+                };
+                let start = bound(start);
+                let end = bound(end);
+                format!("zen_slice({}, {}, {}, {})", left, start, end, self.at())
+            }
+            Expression::Input(kind) => format!("zen_input_{}({})", input_name(kind), self.at()),
+        };
+
+        let temporary = self.temporary();
+        self.line(&format!("ZenValue {} = {};", temporary, value));
+        temporary
+    }
+}
+
+fn infix_function(operator: &Infix) -> &'static str {
+    match operator {
+        Infix::Plus => "zen_add",
+        Infix::Minus => "zen_subtract",
+        Infix::Multiply => "zen_multiply",
+        Infix::Divide => "zen_divide",
+        Infix::Modulo => "zen_modulo",
+        Infix::Equal => "zen_equal",
+        Infix::NotEqual => "zen_not_equal",
+        Infix::GreaterThan => "zen_greater_than",
+        Infix::GreaterThanEqual => "zen_greater_than_equal",
+        Infix::LessThan => "zen_less_than",
+        Infix::LessThanEqual => "zen_less_than_equal",
+        Infix::LogicalAnd => "zen_and",
+        Infix::LogicalOr => "zen_or",
+    }
+}
+
+fn value_array(values: &[String]) -> String {
+    if values.is_empty() {
+        "NULL".to_string()
+    } else {
+        format!("(ZenValue[]){{{}}}", values.join(", "))
+    }
+}
+
+/// A number literal that C reads back as exactly the same double
+fn c_number(num: f64) -> String {
+    if num.is_nan() {
+        "NAN".to_string()
+    } else if num.is_infinite() {
+        if num > 0_f64 { "HUGE_VAL" } else { "-HUGE_VAL" }.to_string()
+    } else {
+        format!("{:?}", num)
+    }
+}
+
+/// A C string literal holding `text`. Bytes outside of printable ASCII are
+/// written as octal escapes, and question marks are escaped so that no
+/// trigraphs sneak in.
+fn c_string(text: &str) -> String {
+    let mut literal = String::from("\"");
+    for byte in text.bytes() {
+        match byte {
+            b'"' => literal += "\\\"",
+            b'\\' => literal += "\\\\",
+            b'?' => literal += "\\?",
+            b'\n' => literal += "\\n",
+            b'\t' => literal += "\\t",
+            b' '..=b'~' => literal.push(byte as char),
+            _ => {
+                let _ = write!(literal, "\\{:03o}", byte);
+            }
+        }
+    }
+    literal.push('"');
+    literal
+}
+
+/// Writes `rows` as a C array called `zen_<name>`, with its length defined
+/// as `ZEN_<NAME>`
+fn c_table<const N: usize>(out: &mut String, name: &str, rows: Option<&[[u32; N]]>) {
+    let rows = rows.unwrap_or_default();
+    let _ = writeln!(out, "#define ZEN_{} {}", name.to_uppercase(), rows.len());
+    let _ = writeln!(out, "static const uint32_t zen_{}[][{}] = {{", name, N);
+    // C has no empty arrays
+    let placeholder = [[0; N]];
+    let rows = if rows.is_empty() {
+        &placeholder[..]
+    } else {
+        rows
+    };
+    for line in rows.chunks(4) {
+        let line: Vec<_> = line
+            .iter()
+            .map(|row| {
+                let row: Vec<_> = row.iter().map(|value| format!("{}u", value)).collect();
+                format!("{{{}}}", row.join(", "))
+            })
+            .collect();
+        let _ = writeln!(out, "    {},", line.join(", "));
+    }
+    out.push_str("};\n");
+}
@@ -0,0 +1,112 @@
+use lazy_static::lazy_static;
+
+lazy_static! {
+    /// Worked out once, and only for programs that change case
+    pub(crate) static ref CASE_TABLES: CaseTables = CaseTables::new();
+}
+
+/// How BADA and CHHOTA change the case of every character, for the compiled
+/// programs to look up.
+///
+/// The tables are worked out from Rust's own `char::to_uppercase` and
+/// `char::to_lowercase`, the very functions the interpreter uses, so the
+/// compiled programs change case exactly like it does, on the same version
+/// of Unicode.
+pub(crate) struct CaseTables {
+    /// Runs of characters that change into one other character: the first
+    /// and last of the run, the difference to add (wrapping) and the stride
+    /// of the run. Case pairs often alternate, like `Ā ā Ă ă`, so a run
+    /// with a stride of 2 leaves every other character as it is.
+    pub upper: Vec<[u32; 4]>,
+    /// Characters that turn into two or three characters, like `ß` into
+    /// `SS`, padded with 0
+    pub upper_special: Vec<[u32; 4]>,
+    pub lower: Vec<[u32; 4]>,
+    pub lower_special: Vec<[u32; 4]>,
+    /// 'Σ' becomes 'ς' at the end of a word: after a cased character and
+    /// not before one, skipping the characters that case ignores on the
+    /// way. These are runs (first and last) of both kinds of characters.
+    pub cased: Vec<[u32; 2]>,
+    pub case_ignorable: Vec<[u32; 2]>,
+}
+
+impl CaseTables {
+    fn new() -> Self {
+        let (upper, upper_special) = mappings(|c| c.to_uppercase().collect());
+        let (lower, lower_special) = mappings(|c| c.to_lowercase().collect());
+
+        // Rust keeps its Cased and Case_Ignorable tables to itself, so
+        // they are read off how it lowers a final 'Σ'. After a cased `c`
+        // it becomes 'ς'. After "A" and then `c` it does too when `c` is
+        // cased or is skipped over.
+        let mut cased = Vec::new();
+        let mut case_ignorable = Vec::new();
+        let mut probe = String::new();
+        for c in all_chars() {
+            probe.clear();
+            probe.push(c);
+            probe.push('Σ');
+            let after_c = probe.to_lowercase().ends_with('ς');
+            probe.insert(0, 'A');
+            let after_a_c = probe.to_lowercase().ends_with('ς');
+            if after_c {
+                add_to_run(&mut cased, c as u32);
+            } else if after_a_c {
+                add_to_run(&mut case_ignorable, c as u32);
+            }
+        }
+
+        CaseTables {
+            upper,
+            upper_special,
+            lower,
+            lower_special,
+            cased,
+            case_ignorable,
+        }
+    }
+}
+
+fn all_chars() -> impl Iterator<Item = char> {
+    (0..=char::MAX as u32).filter_map(char::from_u32)
+}
+
+/// The runs of characters that `change` turns into one other character, and
+/// the characters it turns into several
+fn mappings(change: impl Fn(char) -> Vec<char>) -> (Vec<[u32; 4]>, Vec<[u32; 4]>) {
+    let mut runs: Vec<[u32; 4]> = Vec::new();
+    let mut special = Vec::new();
+    for c in all_chars() {
+        let changed = change(c);
+        if let [single] = changed[..] {
+            if single == c {
+                continue;
+            }
+            let (c, delta) = (c as u32, (single as u32).wrapping_sub(c as u32));
+            if let Some([first, last, run_delta, stride]) = runs.last_mut() {
+                let gap = c - *last;
+                // A run of one character can still pick its stride
+                let fits = gap == *stride || (first == last && gap == 2);
+                if *run_delta == delta && fits {
+                    (*last, *stride) = (c, gap);
+                    continue;
+                }
+            }
+            runs.push([c, c, delta, 1]);
+        } else {
+            let mut row = [c as u32, 0, 0, 0];
+            for (slot, changed) in row[1..].iter_mut().zip(changed) {
+                *slot = changed as u32;
+            }
+            special.push(row);
+        }
+    }
+    (runs, special)
+}
+
+fn add_to_run(runs: &mut Vec<[u32; 2]>, c: u32) {
+    match runs.last_mut() {
+        Some([_, last]) if *last + 1 == c => *last = c,
+        _ => runs.push([c, c]),
+    }
+}
@@ -0,0 +1,1236 @@
+/*
+ * Runtime of Zen programs compiled to C.
+ *
+ * Values, variables and built-in functions behave exactly as they do in the
+ * interpreter, down to the error messages. Values are never freed: compiled
+ * programs are short-lived, so memory is simply given back when they exit.
+ *
+ * The compiled program defines ZEN_<ErrorType> as the name and message of
+ * each error before this file, and ZEN_MAX_ITER_COUNT and ZEN_MAX_CALL_DEPTH.
+ * It also defines the case tables of BADA and CHHOTA (see zen_change_case),
+ * which are empty when the program does not change case.
+ */
+
+#include <math.h>
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+typedef enum {
+    ZEN_NUMBER,
+    ZEN_BOOL,
+    ZEN_STRING,
+    ZEN_LIST,
+    ZEN_MAP,
+    /* Not a value the program can see: what a function gives back when it
+     * ends without 'LAUT KE AAJA' a value, or a slice bound left out */
+    ZEN_NOTHING,
+    /* A function was left by 'BAS KAR PAGLE' or 'JAANE BHI DO' */
+    ZEN_LOOP_CONTROL
+} ZenType;
+
+typedef struct ZenList ZenList;
+typedef struct ZenMap ZenMap;
+
+typedef struct {
+    ZenType type;
+    union {
+        double number;
+        bool boolean;
+        const char *string;
+        ZenList *list;
+        ZenMap *map;
+    } as;
+} ZenValue;
+
+struct ZenList {
+    ZenValue *elements;
+    size_t len, cap;
+};
+
+typedef struct {
+    ZenValue key;
+    ZenValue value;
+} ZenEntry;
+
+/* Entries are kept sorted by key, like the interpreter's BTreeMap */
+struct ZenMap {
+    ZenEntry *entries;
+    size_t len, cap;
+};
+
+/* Where in the source something happened; line 0 means nowhere */
+typedef struct {
+    int line;
+    int column;
+} ZenSpan;
+
+#define ZEN_AT(line, column) ((ZenSpan){(line), (column)})
+#define ZEN_NOWHERE ZEN_AT(0, 0)
+
+typedef ZenValue (*ZenFunctionBody)(void);
+
+typedef struct {
+    ZenFunctionBody body;
+    int arity;
+    const int *parameters;
+} ZenFunction;
+
+typedef struct {
+    int symbol;
+    ZenValue value;
+} ZenBinding;
+
+typedef struct {
+    ZenBinding *bindings;
+    size_t len, cap;
+} ZenScope;
+
+/* A function call's stack of block scopes, innermost last */
+typedef struct {
+    ZenScope *scopes;
+    size_t len, cap;
+} ZenFrame;
+
+static const char *const *zen_names;
+static size_t zen_symbol_count;
+static ZenFunction *zen_functions;
+/* Names that lived in a scope which has already ended, for better errors */
+static bool *zen_expired;
+static ZenFrame *zen_frames;
+static size_t zen_frame_count, zen_frame_cap;
+static int zen_call_depth;
+
+#define ZEN_GROW(array, len, cap)                                           \
+    do {                                                                    \
+        if ((len) == (cap)) {                                               \
+            (cap) = (cap) ? (cap) * 2 : 4;                                  \
+            (array) = zen_alloc((array), (cap) * sizeof *(array));          \
+        }                                                                   \
+    } while (0)
+
+static inline void *zen_alloc(void *old, size_t size) {
+    void *memory = realloc(old, size ? size : 1);
+    if (!memory) {
+        fputs("Out of memory\n", stderr);
+        exit(1);
+    }
+    return memory;
+}
+
+/* Text that is built up bit by bit */
+typedef struct {
+    char *data;
+    size_t len, cap;
+} ZenBuffer;
+
+static inline void zen_buffer_add(ZenBuffer *buffer, const char *text, size_t len) {
+    if (buffer->len + len + 1 > buffer->cap) {
+        buffer->cap = (buffer->len + len + 1) * 2;
+        buffer->data = zen_alloc(buffer->data, buffer->cap);
+    }
+    memcpy(buffer->data + buffer->len, text, len);
+    buffer->len += len;
+    buffer->data[buffer->len] = '\0';
+}
+
+static inline void zen_buffer_puts(ZenBuffer *buffer, const char *text) {
+    zen_buffer_add(buffer, text, strlen(text));
+}
+
+static inline void zen_buffer_vprintf(ZenBuffer *buffer, const char *format, va_list args) {
+    va_list copy;
+    va_copy(copy, args);
+    int len = vsnprintf(NULL, 0, format, copy);
+    va_end(copy);
+    char *text = zen_alloc(NULL, (size_t)len + 1);
+    vsnprintf(text, (size_t)len + 1, format, args);
+    zen_buffer_add(buffer, text, (size_t)len);
+    free(text);
+}
+
+static inline void zen_buffer_printf(ZenBuffer *buffer, const char *format, ...) {
+    va_list args;
+    va_start(args, format);
+    zen_buffer_vprintf(buffer, format, args);
+    va_end(args);
+}
+
+static const char *zen_buffer_finish(ZenBuffer *buffer) {
+    if (!buffer->data) {
+        zen_buffer_add(buffer, "", 0);
+    }
+    return buffer->data;
+}
+
+/* Stops the program with an error. The message is `message` followed by
+ * `format` (if any), mirroring the interpreter's appended error messages. */
+static inline void zen_fail(const char *code, const char *message, ZenSpan at, const char *format, ...) {
+    ZenBuffer text = {0};
+    zen_buffer_puts(&text, message);
+    if (format) {
+        va_list args;
+        va_start(args, format);
+        zen_buffer_vprintf(&text, format, args);
+        va_end(args);
+    }
+
+    fflush(stdout);
+    fprintf(stderr, "error[%s]: %s\n", code, zen_buffer_finish(&text));
+    if (at.line > 0) {
+        fprintf(stderr, " --> %d:%d\n", at.line, at.column);
+    }
+    exit(1);
+}
+
+/* ---------------------------------------------------------------- values */
+
+static inline ZenValue zen_number(double number) {
+    ZenValue value = {ZEN_NUMBER, {.number = number}};
+    return value;
+}
+
+static inline ZenValue zen_bool(bool boolean) {
+    ZenValue value = {ZEN_BOOL, {.boolean = boolean}};
+    return value;
+}
+
+static inline ZenValue zen_string(const char *string) {
+    ZenValue value = {ZEN_STRING, {.string = string}};
+    return value;
+}
+
+static inline ZenValue zen_nothing(void) {
+    ZenValue value = {ZEN_NOTHING, {.number = 0}};
+    return value;
+}
+
+static inline ZenValue zen_loop_control(void) {
+    ZenValue value = {ZEN_LOOP_CONTROL, {.number = 0}};
+    return value;
+}
+
+static inline ZenValue zen_new_list(void) {
+    ZenList *list = zen_alloc(NULL, sizeof *list);
+    *list = (ZenList){0};
+    ZenValue value = {ZEN_LIST, {.list = list}};
+    return value;
+}
+
+static inline void zen_list_push(ZenList *list, ZenValue element) {
+    ZEN_GROW(list->elements, list->len, list->cap);
+    list->elements[list->len++] = element;
+}
+
+static inline ZenValue zen_list_of(size_t len, const ZenValue *elements) {
+    ZenValue list = zen_new_list();
+    for (size_t i = 0; i < len; i++) {
+        zen_list_push(list.as.list, elements[i]);
+    }
+    return list;
+}
+
+static inline ZenValue zen_new_map(void) {
+    ZenMap *map = zen_alloc(NULL, sizeof *map);
+    *map = (ZenMap){0};
+    ZenValue value = {ZEN_MAP, {.map = map}};
+    return value;
+}
+
+static inline bool zen_is_true(ZenValue value) {
+    return value.type == ZEN_BOOL && value.as.boolean;
+}
+
+static const char *zen_type_name(ZenValue value) {
+    switch (value.type) {
+    case ZEN_NUMBER:
+        return "number";
+    case ZEN_BOOL:
+        return "boolean";
+    case ZEN_STRING:
+        return "string";
+    case ZEN_LIST:
+        return "list";
+    case ZEN_MAP:
+        return "map";
+    default:
+        return "nothing";
+    }
+}
+
+/* Number of characters in UTF-8 text */
+static inline size_t zen_char_count(const char *text) {
+    size_t count = 0;
+    for (; *text; text++) {
+        count += ((unsigned char)*text & 0xC0) != 0x80;
+    }
+    return count;
+}
+
+/* Byte offset of character `position` of UTF-8 text */
+static inline size_t zen_char_offset(const char *text, size_t position) {
+    size_t offset = 0;
+    while (text[offset] && position > 0) {
+        offset++;
+        while (((unsigned char)text[offset] & 0xC0) == 0x80) {
+            offset++;
+        }
+        position--;
+    }
+    return offset;
+}
+
+static inline char *zen_substring(const char *text, size_t start, size_t len) {
+    char *copy = zen_alloc(NULL, len + 1);
+    memcpy(copy, text + start, len);
+    copy[len] = '\0';
+    return copy;
+}
+
+/* Numbers are written the way Rust writes an f64: the shortest digits that
+ * read back as the same number, and never in scientific notation */
+static inline void zen_format_number(ZenBuffer *out, double number) {
+    if (isnan(number)) {
+        zen_buffer_puts(out, "NaN");
+        return;
+    }
+    if (isinf(number)) {
+        zen_buffer_puts(out, number > 0 ? "inf" : "-inf");
+        return;
+    }
+
+    char scientific[40];
+    for (int precision = 0; precision < 17; precision++) {
+        snprintf(scientific, sizeof scientific, "%.*e", precision, number);
+        if (strtod(scientific, NULL) == number) {
+            break;
+        }
+    }
+
+    const char *cursor = scientific;
+    if (*cursor == '-') {
+        zen_buffer_puts(out, "-");
+        cursor++;
+    }
+    char digits[20];
+    size_t digit_count = 0;
+    for (; *cursor != 'e'; cursor++) {
+        if (*cursor != '.') {
+            digits[digit_count++] = *cursor;
+        }
+    }
+    int exponent = atoi(cursor + 1);
+
+    if (exponent < 0) {
+        zen_buffer_puts(out, "0.");
+        for (int i = -1; i > exponent; i--) {
+            zen_buffer_puts(out, "0");
+        }
+        zen_buffer_add(out, digits, digit_count);
+    } else if ((size_t)exponent + 1 >= digit_count) {
+        zen_buffer_add(out, digits, digit_count);
+        for (size_t i = digit_count; i < (size_t)exponent + 1; i++) {
+            zen_buffer_puts(out, "0");
+        }
+    } else {
+        zen_buffer_add(out, digits, (size_t)exponent + 1);
+        zen_buffer_puts(out, ".");
+        zen_buffer_add(out, digits + exponent + 1, digit_count - (size_t)exponent - 1);
+    }
+}
+
+/* A string as Rust's Debug writes it, quoted and escaped */
+static inline void zen_format_quoted(ZenBuffer *out, const char *text) {
+    zen_buffer_puts(out, "\"");
+    for (; *text; text++) {
+        unsigned char c = (unsigned char)*text;
+        switch (c) {
+        case '"':
+            zen_buffer_puts(out, "\\\"");
+            break;
+        case '\\':
+            zen_buffer_puts(out, "\\\\");
+            break;
+        case '\n':
+            zen_buffer_puts(out, "\\n");
+            break;
+        case '\r':
+            zen_buffer_puts(out, "\\r");
+            break;
+        case '\t':
+            zen_buffer_puts(out, "\\t");
+            break;
+        case '\0':
+            zen_buffer_puts(out, "\\0");
+            break;
+        default:
+            if (c < 0x20 || c == 0x7F) {
+                zen_buffer_printf(out, "\\u{%x}", c);
+            } else {
+                zen_buffer_add(out, (const char *)&c, 1);
+            }
+        }
+    }
+    zen_buffer_puts(out, "\"");
+}
+
+static inline void zen_format(ZenBuffer *out, ZenValue value, bool nested) {
+    switch (value.type) {
+    case ZEN_NUMBER:
+        zen_format_number(out, value.as.number);
+        break;
+    case ZEN_BOOL:
+        zen_buffer_puts(out, value.as.boolean ? "true" : "false");
+        break;
+    case ZEN_STRING:
+        if (nested) {
+            zen_format_quoted(out, value.as.string);
+        } else {
+            zen_buffer_puts(out, value.as.string);
+        }
+        break;
+    case ZEN_LIST:
+        zen_buffer_puts(out, "[");
+        for (size_t i = 0; i < value.as.list->len; i++) {
+            if (i > 0) {
+                zen_buffer_puts(out, ", ");
+            }
+            zen_format(out, value.as.list->elements[i], true);
+        }
+        zen_buffer_puts(out, "]");
+        break;
+    case ZEN_MAP:
+        zen_buffer_puts(out, "{");
+        for (size_t i = 0; i < value.as.map->len; i++) {
+            if (i > 0) {
+                zen_buffer_puts(out, ", ");
+            }
+            zen_format(out, value.as.map->entries[i].key, true);
+            zen_buffer_puts(out, ": ");
+            zen_format(out, value.as.map->entries[i].value, true);
+        }
+        zen_buffer_puts(out, "}");
+        break;
+    default:
+        break;
+    }
+}
+
+static const char *zen_to_string(ZenValue value) {
+    if (value.type == ZEN_STRING) {
+        return value.as.string;
+    }
+    ZenBuffer out = {0};
+    zen_format(&out, value, false);
+    return zen_buffer_finish(&out);
+}
+
+static const char *zen_to_quoted_string(ZenValue value) {
+    ZenBuffer out = {0};
+    zen_format(&out, value, true);
+    return zen_buffer_finish(&out);
+}
+
+static inline void zen_print(ZenValue value) {
+    puts(zen_to_string(value));
+}
+
+/* ------------------------------------------------------------------ maps */
+
+/* Orders numbers like f64::total_cmp, and numbers before strings */
+static inline int zen_compare_keys(ZenValue left, ZenValue right) {
+    if (left.type != right.type) {
+        return left.type == ZEN_NUMBER ? -1 : 1;
+    }
+    if (left.type == ZEN_STRING) {
+        int order = strcmp(left.as.string, right.as.string);
+        return (order > 0) - (order < 0);
+    }
+    int64_t left_bits, right_bits;
+    memcpy(&left_bits, &left.as.number, sizeof left_bits);
+    memcpy(&right_bits, &right.as.number, sizeof right_bits);
+    left_bits ^= (int64_t)((uint64_t)(left_bits >> 63) >> 1);
+    right_bits ^= (int64_t)((uint64_t)(right_bits >> 63) >> 1);
+    return (left_bits > right_bits) - (left_bits < right_bits);
+}
+
+/* Turns a value into a map key, rejecting types that cannot be keys */
+static inline ZenValue zen_map_key(ZenValue key, ZenSpan at) {
+    if (key.type == ZEN_NUMBER) {
+        /* -0 and 0 are the same key */
+        return zen_number(key.as.number + 0.0);
+    }
+    if (key.type != ZEN_STRING) {
+        zen_fail(ZEN_IncompatibleDataType, at, " A %s cannot be used as a map key.", zen_type_name(key));
+    }
+    return key;
+}
+
+/* Position of `key` in the map, or where it would go */
+static inline size_t zen_map_find(const ZenMap *map, ZenValue key, bool *found) {
+    size_t low = 0, high = map->len;
+    while (low < high) {
+        size_t middle = low + (high - low) / 2;
+        int order = zen_compare_keys(map->entries[middle].key, key);
+        if (order == 0) {
+            *found = true;
+            return middle;
+        }
+        if (order < 0) {
+            low = middle + 1;
+        } else {
+            high = middle;
+        }
+    }
+    *found = false;
+    return low;
+}
+
+static inline void zen_map_put(ZenMap *map, ZenValue key, ZenValue value) {
+    bool found;
+    size_t position = zen_map_find(map, key, &found);
+    if (found) {
+        map->entries[position].value = value;
+        return;
+    }
+    ZEN_GROW(map->entries, map->len, map->cap);
+    memmove(&map->entries[position + 1], &map->entries[position],
+            (map->len - position) * sizeof *map->entries);
+    map->entries[position] = (ZenEntry){key, value};
+    map->len++;
+}
+
+static inline void zen_map_insert(ZenValue map, ZenValue key, ZenValue value, ZenSpan at) {
+    zen_map_put(map.as.map, zen_map_key(key, at), value);
+}
+
+static inline void zen_fail_missing_key(ZenValue key, ZenSpan at) {
+    zen_fail(ZEN_MissingKey, at, " There is no key %s in this map.", zen_to_quoted_string(key));
+}
+
+/* ------------------------------------------------------------- variables */
+
+static ZenFrame *zen_current_frame(void) {
+    return &zen_frames[zen_frame_count - 1];
+}
+
+static inline void zen_push_scope(void) {
+    ZenFrame *frame = zen_current_frame();
+    ZEN_GROW(frame->scopes, frame->len, frame->cap);
+    frame->scopes[frame->len++] = (ZenScope){0};
+}
+
+static inline void zen_pop_scope(void) {
+    ZenFrame *frame = zen_current_frame();
+    /* The outermost scope of a frame lives as long as the frame itself */
+    if (frame->len > 1) {
+        ZenScope *scope = &frame->scopes[--frame->len];
+        for (size_t i = 0; i < scope->len; i++) {
+            zen_expired[scope->bindings[i].symbol] = true;
+        }
+        free(scope->bindings);
+    }
+}
+
+static inline void zen_push_frame(void) {
+    ZEN_GROW(zen_frames, zen_frame_count, zen_frame_cap);
+    zen_frames[zen_frame_count++] = (ZenFrame){0};
+    zen_push_scope();
+}
+
+static inline void zen_pop_frame(void) {
+    ZenFrame *frame = zen_current_frame();
+    for (size_t i = 0; i < frame->len; i++) {
+        free(frame->scopes[i].bindings);
+    }
+    free(frame->scopes);
+    zen_frame_count--;
+}
+
+static inline ZenValue *zen_find_in_scope(ZenScope *scope, int symbol) {
+    for (size_t i = 0; i < scope->len; i++) {
+        if (scope->bindings[i].symbol == symbol) {
+            return &scope->bindings[i].value;
+        }
+    }
+    return NULL;
+}
+
+/* The nearest binding in the current frame, or else a global one */
+static inline ZenValue *zen_find(int symbol, bool with_globals) {
+    ZenFrame *frame = zen_current_frame();
+    for (size_t i = frame->len; i-- > 0;) {
+        ZenValue *slot = zen_find_in_scope(&frame->scopes[i], symbol);
+        if (slot) {
+            return slot;
+        }
+    }
+    return with_globals ? zen_find_in_scope(&zen_frames[0].scopes[0], symbol) : NULL;
+}
+
+/* Creates a variable in the innermost scope, shadowing any outer one */
+static inline void zen_declare(int symbol, ZenValue value) {
+    ZenFrame *frame = zen_current_frame();
+    ZenScope *scope = &frame->scopes[frame->len - 1];
+    ZenValue *slot = zen_find_in_scope(scope, symbol);
+    if (slot) {
+        *slot = value;
+        return;
+    }
+    ZEN_GROW(scope->bindings, scope->len, scope->cap);
+    scope->bindings[scope->len++] = (ZenBinding){symbol, value};
+}
+
+/* Updates the nearest variable of the current frame, or creates one */
+static inline void zen_assign(int symbol, ZenValue value) {
+    ZenValue *slot = zen_find(symbol, false);
+    if (slot) {
+        *slot = value;
+    } else {
+        zen_declare(symbol, value);
+    }
+}
+
+static inline ZenValue zen_load(int symbol, ZenSpan at) {
+    ZenValue *slot = zen_find(symbol, true);
+    if (slot) {
+        return *slot;
+    }
+    if (zen_expired[symbol]) {
+        zen_fail(ZEN_VariableOutOfScope, at, " '%s' only lived inside a block that has already ended.",
+                 zen_names[symbol]);
+    }
+    zen_fail("UndefinedVariable", "", at,
+             "Undefined variable! 'Tumhara value kya hai, %s? , Batao bhi Basanti !'", zen_names[symbol]);
+    return zen_nothing();
+}
+
+/* ------------------------------------------------------------- operators */
+
+static inline ZenValue zen_unary_plus(ZenValue right, ZenSpan at) {
+    if (right.type != ZEN_NUMBER) {
+        zen_fail(ZEN_IncompatibleDataType, at, "Only numeral types allowed with unary addition!");
+    }
+    return right;
+}
+
+static inline ZenValue zen_negate(ZenValue right, ZenSpan at) {
+    if (right.type != ZEN_NUMBER) {
+        zen_fail(ZEN_IncompatibleDataType, at, "Only numeral types allowed with unary negation!");
+    }
+    return zen_number(-right.as.number);
+}
+
+static inline ZenValue zen_not(ZenValue right, ZenSpan at) {
+    if (right.type != ZEN_BOOL) {
+        zen_fail("IncompatibleDataType", "", at,
+                 "Only boolean types allowed with not! 'Yeh Kya Ho gya hai duniyan ko ?\?'");
+    }
+    return zen_bool(!right.as.boolean);
+}
+
+static inline ZenValue zen_add(ZenValue left, ZenValue right, ZenSpan at) {
+    if (left.type == ZEN_NUMBER && right.type == ZEN_NUMBER) {
+        return zen_number(left.as.number + right.as.number);
+    }
+    /* Joining with a string converts the other side to text */
+    if (left.type == ZEN_STRING || right.type == ZEN_STRING) {
+        ZenBuffer out = {0};
+        zen_buffer_puts(&out, zen_to_string(left));
+        zen_buffer_puts(&out, zen_to_string(right));
+        return zen_string(zen_buffer_finish(&out));
+    }
+    zen_fail(ZEN_IncompatibleDataType, at, "Only numeral or string types allowed with addition! ");
+    return zen_nothing();
+}
+
+#define ZEN_ARITHMETIC(name, operation, message)                                \
+    static inline ZenValue name(ZenValue left, ZenValue right, ZenSpan at) {           \
+        if (left.type != ZEN_NUMBER || right.type != ZEN_NUMBER) {              \
+            zen_fail(ZEN_IncompatibleDataType, at, message);                    \
+        }                                                                       \
+        return zen_number(operation);                                           \
+    }
+
+ZEN_ARITHMETIC(zen_subtract, left.as.number - right.as.number,
+               "Only numeral types allowed with subtraction! 'Number chahiye, number!")
+ZEN_ARITHMETIC(zen_multiply, left.as.number * right.as.number,
+               "Only numeral types allowed with multiplication!")
+ZEN_ARITHMETIC(zen_modulo, fmod(left.as.number, right.as.number),
+               "Only numeral types allowed with modulus! ")
+
+static inline ZenValue zen_divide(ZenValue left, ZenValue right, ZenSpan at) {
+    if (left.type != ZEN_NUMBER || right.type != ZEN_NUMBER) {
+        zen_fail(ZEN_IncompatibleDataType, at, "Only numeral types allowed with division!");
+    }
+    if (right.as.number == 0.0) {
+        zen_fail(ZEN_DivisionByZero, at, NULL);
+    }
+    return zen_number(left.as.number / right.as.number);
+}
+
+#define ZEN_COMPARISON(name, operator, message)                                 \
+    static inline ZenValue name(ZenValue left, ZenValue right, ZenSpan at) {           \
+        if (left.type != ZEN_NUMBER || right.type != ZEN_NUMBER) {              \
+            zen_fail(ZEN_IncompatibleDataType, at, message);                    \
+        }                                                                       \
+        return zen_bool(left.as.number operator right.as.number);               \
+    }
+
+ZEN_COMPARISON(zen_greater_than, >, "Comparison with only numeral data types is allowed!")
+ZEN_COMPARISON(zen_greater_than_equal, >=, "Comparison with only numeral data types is allowed! ")
+ZEN_COMPARISON(zen_less_than, <, "Comparison with only numeral data types is allowed! ")
+ZEN_COMPARISON(zen_less_than_equal, <=, "Comparison with only numeral data types is allowed! ")
+
+#define ZEN_LOGICAL(name, operator, message)                                    \
+    static inline ZenValue name(ZenValue left, ZenValue right, ZenSpan at) {           \
+        if (left.type != ZEN_BOOL || right.type != ZEN_BOOL) {                  \
+            zen_fail(ZEN_IncompatibleDataType, at, message);                    \
+        }                                                                       \
+        return zen_bool(left.as.boolean operator right.as.boolean);             \
+    }
+
+ZEN_LOGICAL(zen_and, &&, "Comparison with only boolean data types is allowed! ")
+ZEN_LOGICAL(zen_or, ||, "Comparison with only boolean data types is allowed!")
+
+/* Deep equality, as derived for the interpreter's values */
+static inline bool zen_values_equal(ZenValue left, ZenValue right) {
+    if (left.type != right.type) {
+        return false;
+    }
+    switch (left.type) {
+    case ZEN_NUMBER:
+        return left.as.number == right.as.number;
+    case ZEN_BOOL:
+        return left.as.boolean == right.as.boolean;
+    case ZEN_STRING:
+        return strcmp(left.as.string, right.as.string) == 0;
+    case ZEN_LIST:
+        if (left.as.list->len != right.as.list->len) {
+            return false;
+        }
+        for (size_t i = 0; i < left.as.list->len; i++) {
+            if (!zen_values_equal(left.as.list->elements[i], right.as.list->elements[i])) {
+                return false;
+            }
+        }
+        return true;
+    case ZEN_MAP:
+        if (left.as.map->len != right.as.map->len) {
+            return false;
+        }
+        for (size_t i = 0; i < left.as.map->len; i++) {
+            ZenEntry *left_entry = &left.as.map->entries[i];
+            ZenEntry *right_entry = &right.as.map->entries[i];
+            if (zen_compare_keys(left_entry->key, right_entry->key) != 0 ||
+                !zen_values_equal(left_entry->value, right_entry->value)) {
+                return false;
+            }
+        }
+        return true;
+    default:
+        return true;
+    }
+}
+
+static inline ZenValue zen_equal(ZenValue left, ZenValue right, ZenSpan at) {
+    if (left.type != right.type) {
+        zen_fail("IncompatibleDataType", "", at,
+                 "Comparison with only homogeneous data types is allowed! 'Ek chutki datatype ki keemat, tum kya jaano Ramesh babu.'");
+    }
+    return zen_bool(zen_values_equal(left, right));
+}
+
+static inline ZenValue zen_not_equal(ZenValue left, ZenValue right, ZenSpan at) {
+    if (left.type != right.type) {
+        zen_fail(ZEN_IncompatibleDataType, at, "Comparison with only homogeneous data types is allowed!");
+    }
+    return zen_bool(!zen_values_equal(left, right));
+}
+
+/* ------------------------------------------------------ lists and strings */
+
+static inline double zen_whole_number(ZenValue value, ZenSpan at) {
+    if (value.type != ZEN_NUMBER || value.as.number != floor(value.as.number)) {
+        zen_fail(ZEN_IncompatibleDataType, at, " Positions must be whole numbers, not %s.",
+                 zen_to_string(value));
+    }
+    return value.as.number;
+}
+
+/* Checks that `index` points at an element of a sequence of `len` elements */
+static inline size_t zen_index_position(ZenValue index, size_t len, ZenSpan at) {
+    double position = zen_whole_number(index, at);
+    if (position < 0 || position >= (double)len) {
+        ZenBuffer text = {0};
+        zen_format_number(&text, position);
+        zen_fail(ZEN_IndexOutOfBounds, at, " Position %s asked, but length is only %zu.",
+                 zen_buffer_finish(&text), len);
+    }
+    return (size_t)position;
+}
+
+static inline ZenValue zen_index(ZenValue left, ZenValue index, ZenSpan at) {
+    switch (left.type) {
+    case ZEN_STRING: {
+        size_t position = zen_index_position(index, zen_char_count(left.as.string), at);
+        size_t start = zen_char_offset(left.as.string, position);
+        size_t end = zen_char_offset(left.as.string, position + 1);
+        return zen_string(zen_substring(left.as.string, start, end - start));
+    }
+    case ZEN_LIST:
+        return left.as.list->elements[zen_index_position(index, left.as.list->len, at)];
+    case ZEN_MAP: {
+        ZenValue key = zen_map_key(index, at);
+        bool found;
+        size_t position = zen_map_find(left.as.map, key, &found);
+        if (!found) {
+            zen_fail_missing_key(key, at);
+        }
+        return left.as.map->entries[position].value;
+    }
+    default:
+        zen_fail(ZEN_IncompatibleDataType, at, " A %s cannot be indexed.", zen_type_name(left));
+        return zen_nothing();
+    }
+}
+
+/* Resolves an optional slice bound, clamping it to the sequence */
+static inline size_t zen_slice_bound(ZenValue bound, size_t fallback, size_t len, ZenSpan at) {
+    if (bound.type == ZEN_NOTHING) {
+        return fallback;
+    }
+    double position = zen_whole_number(bound, at);
+    if (position < 0) {
+        ZenBuffer text = {0};
+        zen_format_number(&text, position);
+        zen_fail(ZEN_IndexOutOfBounds, at, " Slice bounds cannot be negative, got %s.",
+                 zen_buffer_finish(&text));
+    }
+    return position < (double)len ? (size_t)position : len;
+}
+
+static inline ZenValue zen_slice(ZenValue left, ZenValue start, ZenValue end, ZenSpan at) {
+    size_t len;
+    if (left.type == ZEN_STRING) {
+        len = zen_char_count(left.as.string);
+    } else if (left.type == ZEN_LIST) {
+        len = left.as.list->len;
+    } else {
+        zen_fail(ZEN_IncompatibleDataType, at, " A %s cannot be sliced.", zen_type_name(left));
+        return zen_nothing();
+    }
+
+    size_t from = zen_slice_bound(start, 0, len, at);
+    size_t to = zen_slice_bound(end, len, len, at);
+    if (to < from) {
+        to = from;
+    }
+
+    if (left.type == ZEN_STRING) {
+        size_t first = zen_char_offset(left.as.string, from);
+        size_t last = zen_char_offset(left.as.string, to);
+        return zen_string(zen_substring(left.as.string, first, last - first));
+    }
+    return zen_list_of(to - from, left.as.list->elements + from);
+}
+
+static inline void zen_set_element(ZenValue target, ZenValue index, ZenValue value, ZenSpan at) {
+    if (target.type == ZEN_LIST) {
+        target.as.list->elements[zen_index_position(index, target.as.list->len, at)] = value;
+    } else if (target.type == ZEN_MAP) {
+        zen_map_insert(target, index, value, at);
+    } else {
+        zen_fail(ZEN_IncompatibleDataType, at, " Cannot change an element of a %s.", zen_type_name(target));
+    }
+}
+
+/* ----------------------------------------------------------------- loops */
+
+static inline double zen_loop_bound(ZenValue bound, ZenSpan at) {
+    if (bound.type != ZEN_NUMBER) {
+        zen_fail(ZEN_IncompatibleDataType, at, " Loop bounds must be numbers, not a %s.", zen_type_name(bound));
+    }
+    return bound.as.number;
+}
+
+/* ----------------------------------------------------------------- input */
+
+/* Reads a number the way Rust's f64::from_str does */
+static inline bool zen_parse_number(const char *text, double *number) {
+    while (*text == ' ' || (*text >= '\t' && *text <= '\r')) {
+        text++;
+    }
+    size_t len = strlen(text);
+    while (len > 0 && (text[len - 1] == ' ' || (text[len - 1] >= '\t' && text[len - 1] <= '\r'))) {
+        len--;
+    }
+    char *trimmed = zen_substring(text, 0, len);
+    /* strtod also reads hexadecimal numbers and NaN payloads, Rust does not */
+    bool valid = len > 0 && !strpbrk(trimmed, "xX(");
+    char *end;
+    if (valid) {
+        *number = strtod(trimmed, &end);
+        valid = end == trimmed + len;
+    }
+    free(trimmed);
+    return valid;
+}
+
+/* The line input is read from, and how much of it is read already */
+static const char *zen_input_text = "";
+static size_t zen_input_position = 0;
+
+static inline bool zen_is_space(char c) {
+    return c == ' ' || (c >= '\t' && c <= '\r');
+}
+
+/* Moves on to the next line of input, failing once input is over */
+static inline void zen_next_line(ZenSpan at) {
+    ZenBuffer line = {0};
+    int c;
+    fflush(stdout);
+    while ((c = getchar()) != EOF && c != '\n') {
+        char byte = (char)c;
+        zen_buffer_add(&line, &byte, 1);
+    }
+    if (c == EOF && line.len == 0) {
+        zen_fail(ZEN_EmptyCustomInputStack, at, NULL);
+    }
+    if (line.len > 0 && line.data[line.len - 1] == '\r') {
+        line.data[--line.len] = '\0';
+    }
+    zen_input_text = zen_buffer_finish(&line);
+    zen_input_position = 0;
+}
+
+/* Skips the spaces at the input position, returning whether anything but
+ * spaces is left on the line */
+static inline bool zen_input_rest(void) {
+    while (zen_is_space(zen_input_text[zen_input_position])) {
+        zen_input_position++;
+    }
+    return zen_input_text[zen_input_position] != '\0';
+}
+
+/* The next word of input, whichever line it is on */
+static inline ZenValue zen_input_word(ZenSpan at) {
+    while (!zen_input_rest()) {
+        zen_next_line(at);
+    }
+    size_t start = zen_input_position;
+    while (zen_input_text[zen_input_position] != '\0' && !zen_is_space(zen_input_text[zen_input_position])) {
+        zen_input_position++;
+    }
+    return zen_string(zen_substring(zen_input_text, start, zen_input_position - start));
+}
+
+/* The rest of the line the last word was read from, or the whole next line
+ * when nothing but spaces is left there */
+static inline ZenValue zen_input_line(ZenSpan at) {
+    if (!zen_input_rest()) {
+        zen_next_line(at);
+    }
+    size_t start = zen_input_position;
+    zen_input_position += strlen(zen_input_text + start);
+    return zen_string(zen_substring(zen_input_text, start, zen_input_position - start));
+}
+
+static inline ZenValue zen_input_number(ZenSpan at) {
+    double number;
+    if (!zen_parse_number(zen_input_word(at).as.string, &number)) {
+        zen_fail(ZEN_InvalidInputError, at, NULL);
+    }
+    return zen_number(number);
+}
+
+/* -------------------------------------------------------------- builtins */
+
+static inline void zen_expect_arguments(const char *name, int expected, int count, ZenSpan at) {
+    if (expected != count) {
+        zen_fail(ZEN_ArgumentCountMismatch, at, " '%s' expects %d argument(s) but got %d.", name, expected,
+                 count);
+    }
+}
+
+static inline void zen_expect_type(const char *name, ZenValue value, ZenType type, const char *type_name, ZenSpan at) {
+    if (value.type != type) {
+        zen_fail(ZEN_IncompatibleDataType, at, " '%s' needs a %s, not a %s.", name, type_name,
+                 zen_type_name(value));
+    }
+}
+
+/* Decodes the UTF-8 character at `text`, giving its length in bytes */
+static inline size_t zen_decode_char(const char *text, uint32_t *c) {
+    const unsigned char *bytes = (const unsigned char *)text;
+    if (bytes[0] < 0x80) {
+        *c = bytes[0];
+        return 1;
+    }
+    size_t len = bytes[0] >= 0xF0 ? 4 : bytes[0] >= 0xE0 ? 3 : 2;
+    *c = bytes[0] & (0x7F >> len);
+    for (size_t i = 1; i < len; i++) {
+        *c = (*c << 6) | (bytes[i] & 0x3F);
+    }
+    return len;
+}
+
+static inline void zen_buffer_char(ZenBuffer *out, uint32_t c) {
+    char bytes[4];
+    size_t len = c < 0x80 ? 1 : c < 0x800 ? 2 : c < 0x10000 ? 3 : 4;
+    for (size_t i = len - 1; i > 0; i--) {
+        bytes[i] = (char)(0x80 | (c & 0x3F));
+        c >>= 6;
+    }
+    bytes[0] = (char)(len == 1 ? c : ((0xFF00u >> len) & 0xFF) | c);
+    zen_buffer_add(out, bytes, len);
+}
+
+/* The row of a case table whose run could hold `c`: the last one starting
+ * at or before it */
+static inline const uint32_t *zen_case_row(const uint32_t (*rows)[4], size_t count, uint32_t c) {
+    size_t low = 0, high = count;
+    while (low < high) {
+        size_t middle = (low + high) / 2;
+        if (rows[middle][0] <= c) {
+            low = middle + 1;
+        } else {
+            high = middle;
+        }
+    }
+    return low == 0 ? NULL : rows[low - 1];
+}
+
+static inline bool zen_in_runs(const uint32_t (*runs)[2], size_t count, uint32_t c) {
+    size_t low = 0, high = count;
+    while (low < high) {
+        size_t middle = (low + high) / 2;
+        if (runs[middle][0] <= c) {
+            low = middle + 1;
+        } else {
+            high = middle;
+        }
+    }
+    return low > 0 && c <= runs[low - 1][1];
+}
+
+/* Whether the first character before `at` that case does not ignore is a
+ * cased one */
+static inline bool zen_cased_before(const char *text, size_t at) {
+    while (at > 0) {
+        do {
+            at--;
+        } while (at > 0 && ((unsigned char)text[at] & 0xC0) == 0x80);
+        uint32_t c;
+        zen_decode_char(text + at, &c);
+        if (!zen_in_runs(zen_case_ignorable, ZEN_CASE_IGNORABLE, c)) {
+            return zen_in_runs(zen_cased, ZEN_CASED, c);
+        }
+    }
+    return false;
+}
+
+/* Whether the first character of `text` that case does not ignore is a
+ * cased one */
+static inline bool zen_cased_after(const char *text) {
+    while (*text) {
+        uint32_t c;
+        text += zen_decode_char(text, &c);
+        if (!zen_in_runs(zen_case_ignorable, ZEN_CASE_IGNORABLE, c)) {
+            return zen_in_runs(zen_cased, ZEN_CASED, c);
+        }
+    }
+    return false;
+}
+
+static inline void zen_change_char(ZenBuffer *out, uint32_t c, bool upper) {
+    const uint32_t *special = upper ? zen_case_row(zen_upper_special, ZEN_UPPER_SPECIAL, c)
+                                    : zen_case_row(zen_lower_special, ZEN_LOWER_SPECIAL, c);
+    if (special && special[0] == c) {
+        for (int i = 1; i < 4 && special[i]; i++) {
+            zen_buffer_char(out, special[i]);
+        }
+        return;
+    }
+    const uint32_t *run = upper ? zen_case_row(zen_upper_runs, ZEN_UPPER_RUNS, c)
+                                : zen_case_row(zen_lower_runs, ZEN_LOWER_RUNS, c);
+    if (run && c <= run[1] && (c - run[0]) % run[3] == 0) {
+        c += run[2];
+    }
+    zen_buffer_char(out, c);
+}
+
+/* A copy of UTF-8 text in upper or lower case, the way Rust's to_uppercase
+ * and to_lowercase do it, through the case tables the compiled program
+ * defines before this file */
+static inline const char *zen_change_case(const char *text, bool upper) {
+    ZenBuffer out = {0};
+    for (size_t at = 0; text[at];) {
+        uint32_t c;
+        size_t len = zen_decode_char(text + at, &c);
+        /* A capital sigma ending a word becomes the final sigma */
+        if (!upper && c == 0x3A3 && zen_cased_before(text, at) && !zen_cased_after(text + at + len)) {
+            zen_buffer_char(&out, 0x3C2);
+        } else {
+            zen_change_char(&out, c, upper);
+        }
+        at += len;
+    }
+    return zen_buffer_finish(&out);
+}
+
+/* Runs the built-in function called `name`, if there is one */
+static inline bool zen_call_builtin(const char *name, int count, ZenValue *args, ZenValue *result, ZenSpan at) {
+    if (strcmp(name, "LAMBAI") == 0) {
+        zen_expect_arguments(name, 1, count, at);
+        if (args[0].type == ZEN_LIST) {
+            *result = zen_number((double)args[0].as.list->len);
+        } else if (args[0].type == ZEN_MAP) {
+            *result = zen_number((double)args[0].as.map->len);
+        } else {
+            zen_expect_type(name, args[0], ZEN_STRING, "string", at);
+            *result = zen_number((double)zen_char_count(args[0].as.string));
+        }
+    } else if (strcmp(name, "AKSHAR") == 0) {
+        zen_expect_arguments(name, 1, count, at);
+        *result = zen_string(zen_to_string(args[0]));
+    } else if (strcmp(name, "ANK") == 0) {
+        zen_expect_arguments(name, 1, count, at);
+        double number;
+        if (args[0].type == ZEN_NUMBER) {
+            *result = args[0];
+        } else if (args[0].type != ZEN_STRING) {
+            zen_fail(ZEN_IncompatibleDataType, at, " A %s cannot be turned into a number.",
+                     zen_type_name(args[0]));
+        } else if (zen_parse_number(args[0].as.string, &number)) {
+            *result = zen_number(number);
+        } else {
+            zen_fail(ZEN_IncompatibleDataType, at, " \"%s\" is not a number.", args[0].as.string);
+        }
+    } else if (strcmp(name, "BADA") == 0 || strcmp(name, "CHHOTA") == 0) {
+        zen_expect_arguments(name, 1, count, at);
+        zen_expect_type(name, args[0], ZEN_STRING, "string", at);
+        *result = zen_string(zen_change_case(args[0].as.string, name[0] == 'B'));
+    } else if (strcmp(name, "KHOJO") == 0) {
+        zen_expect_arguments(name, 2, count, at);
+        zen_expect_type(name, args[0], ZEN_STRING, "string", at);
+        zen_expect_type(name, args[1], ZEN_STRING, "string", at);
+        const char *found = strstr(args[0].as.string, args[1].as.string);
+        double position = -1;
+        if (found) {
+            char *before = zen_substring(args[0].as.string, 0, (size_t)(found - args[0].as.string));
+            position = (double)zen_char_count(before);
+            free(before);
+        }
+        *result = zen_number(position);
+    } else if (strcmp(name, "JODO") == 0) {
+        zen_expect_arguments(name, 2, count, at);
+        zen_expect_type(name, args[0], ZEN_LIST, "list", at);
+        zen_list_push(args[0].as.list, args[1]);
+        *result = args[0];
+    } else if (strcmp(name, "HATAO") == 0) {
+        zen_expect_arguments(name, 2, count, at);
+        if (args[0].type == ZEN_MAP) {
+            ZenMap *map = args[0].as.map;
+            ZenValue key = zen_map_key(args[1], at);
+            bool found;
+            size_t position = zen_map_find(map, key, &found);
+            if (!found) {
+                zen_fail_missing_key(key, at);
+            }
+            *result = map->entries[position].value;
+            memmove(&map->entries[position], &map->entries[position + 1],
+                    (map->len - position - 1) * sizeof *map->entries);
+            map->len--;
+        } else {
+            zen_expect_type(name, args[0], ZEN_LIST, "list", at);
+            ZenList *list = args[0].as.list;
+            size_t position = zen_index_position(args[1], list->len, at);
+            *result = list->elements[position];
+            memmove(&list->elements[position], &list->elements[position + 1],
+                    (list->len - position - 1) * sizeof *list->elements);
+            list->len--;
+        }
+    } else if (strcmp(name, "HAI_KYA") == 0) {
+        zen_expect_arguments(name, 2, count, at);
+        zen_expect_type(name, args[0], ZEN_MAP, "map", at);
+        bool found;
+        zen_map_find(args[0].as.map, zen_map_key(args[1], at), &found);
+        *result = zen_bool(found);
+    } else if (strcmp(name, "CHABIYAN") == 0) {
+        zen_expect_arguments(name, 1, count, at);
+        zen_expect_type(name, args[0], ZEN_MAP, "map", at);
+        *result = zen_new_list();
+        for (size_t i = 0; i < args[0].as.map->len; i++) {
+            zen_list_push(result->as.list, args[0].as.map->entries[i].key);
+        }
+    } else {
+        return false;
+    }
+    return true;
+}
+
+/* ------------------------------------------------------------- functions */
+
+/* Redefinition of functions is allowed, just like variables */
+static inline void zen_define(int symbol, ZenFunctionBody body, int arity, const int *parameters) {
+    zen_functions[symbol] = (ZenFunction){body, arity, parameters};
+}
+
+/* Checks a call to a program-defined function before its arguments are
+ * worked out */
+static inline void zen_prepare_call(int symbol, int count, ZenSpan at) {
+    ZenFunction *function = &zen_functions[symbol];
+    if (!function->body) {
+        /* Builtins only check their arguments once they are worked out */
+        return;
+    }
+    if (function->arity != count) {
+        zen_fail(ZEN_ArgumentCountMismatch, at, " '%s' expects %d argument(s) but got %d.", zen_names[symbol],
+                 function->arity, count);
+    }
+    if (zen_call_depth >= ZEN_MAX_CALL_DEPTH) {
+        zen_fail(ZEN_MaxCallDepthExceeded, at, NULL);
+    }
+}
+
+static inline ZenValue zen_call(int symbol, int count, ZenValue *args, bool wants_value, ZenSpan at) {
+    ZenFunction *function = &zen_functions[symbol];
+    if (!function->body) {
+        ZenValue result;
+        if (!zen_call_builtin(zen_names[symbol], count, args, &result, at)) {
+            zen_fail("UndefinedFunction", "", at,
+                     "Undefined function! 'Kaun hai ye %s? Kahan se aate hain ye log?'", zen_names[symbol]);
+        }
+        return result;
+    }
+
+    zen_push_frame();
+    for (int i = 0; i < count; i++) {
+        zen_declare(function->parameters[i], args[i]);
+    }
+    zen_call_depth++;
+    ZenValue result = function->body();
+    zen_call_depth--;
+    zen_pop_frame();
+
+    if (result.type == ZEN_LOOP_CONTROL) {
+        /* Loops never reach across a function call */
+        zen_fail(ZEN_LoopControlOutsideLoop, at, NULL);
+    }
+    if (wants_value && result.type == ZEN_NOTHING) {
+        zen_fail(ZEN_MissingReturnValue, at, " '%s' did not return anything.", zen_names[symbol]);
+    }
+    return result;
+}
+
+static inline void zen_start(const char *const *names, size_t symbol_count) {
+    zen_names = names;
+    zen_symbol_count = symbol_count;
+    zen_functions = calloc(symbol_count + 1, sizeof *zen_functions);
+    zen_expired = calloc(symbol_count + 1, sizeof *zen_expired);
+    zen_push_frame();
+}
@@ -0,0 +1,2215 @@
+  ;; Runtime of Zen programs compiled to WebAssembly.
+  ;;
+  ;; Values, variables and built-in functions behave exactly as they do in the
+  ;; interpreter, down to the error messages. Memory is never freed: an
+  ;; instance runs one program and is thrown away afterwards.
+  ;;
+  ;; The host provides three functions:
+  ;;   env.print(text, len)            prints a line of UTF-8 text
+  ;;   env.input(buffer, cap) -> len   writes the next line of input without
+  ;;                                   its line ending (at most `cap` bytes)
+  ;;                                   into the buffer, returning its length,
+  ;;                                   or -1 once input is over
+  ;;   env.error(code, code_len, message, message_len, line, column)
+  ;;                                   reports a runtime error. Line 0 means
+  ;;                                   the error has no location. The program
+  ;;                                   traps right after.
+  ;;
+  ;; (string "..."), (message Type) and (code Type) stand for the addresses of
+  ;; static strings, filled in when the program is compiled. The compiled
+  ;; program defines $max_iter_count, $max_call_depth, $symbol_count,
+  ;; $symbol_names (a table of string values), $heap and the $functions table,
+  ;; along with the case tables of $change_case and their counts, which are
+  ;; empty when the program does not change case.
+
+  (import "env" "print" (func $host_print (param i32 i32)))
+  (import "env" "input" (func $host_input (param i32 i32) (result i32)))
+  (import "env" "error" (func $host_error (param i32 i32 i32 i32 i32 i32)))
+
+  ;; Every value is a pointer to an object whose first word is its type:
+  ;;   0  number   the f64 at +8
+  ;;   1  bool     0 or 1 at +4
+  ;;   2  string   length in bytes at +4, pointer to the UTF-8 bytes at +8
+  ;;   3  list     length at +4, capacity at +8, pointer to the elements at +12
+  ;;   4  map      length at +4, capacity at +8, pointer to the key/value
+  ;;               pairs at +12, kept sorted by key like the interpreter's
+  ;;               BTreeMap
+  ;;   5  nothing  not a value the program can see: what a function gives
+  ;;               back when it ends without 'LAUT KE AAJA' a value, or a
+  ;;               slice bound left out. Lives at address 0.
+  ;;   6  loop control, given back by a function left by 'BAS KAR PAGLE' or
+  ;;               'JAANE BHI DO'. Lives at address 8.
+  ;; false and true live at 16 and 24.
+  (data (i32.const 0) "\05\00\00\00\00\00\00\00\06\00\00\00\00\00\00\00\01\00\00\00\00\00\00\00\01\00\00\00\01\00\00\00")
+
+  (type $function (func (result i32)))
+
+  ;; Where the function records, the expired names and the variables live,
+  ;; set up by $start
+  (global $function_records (mut i32) (i32.const 0))
+  (global $expired (mut i32) (i32.const 0))
+  (global $bindings (mut i32) (i32.const 0))
+  (global $scopes (mut i32) (i32.const 0))
+  (global $frames (mut i32) (i32.const 0))
+  (global $arguments (mut i32) (i32.const 0))
+  (global $call_depth (mut i32) (i32.const 0))
+
+  ;; ------------------------------------------------------------- memory
+
+  (func $alloc (param $size i32) (result i32)
+    (local $start i32)
+    (local $end i32)
+    (local.set $start (global.get $heap))
+    (local.set $end
+      (i32.and (i32.add (i32.add (local.get $start) (local.get $size)) (i32.const 7)) (i32.const -8)))
+    (if (i32.gt_u (local.get $end) (i32.mul (memory.size) (i32.const 65536)))
+      (then
+        (if (i32.eq
+              (memory.grow
+                (i32.sub (i32.shr_u (i32.add (local.get $end) (i32.const 65535)) (i32.const 16))
+                         (memory.size)))
+              (i32.const -1))
+          (then unreachable))))
+    (global.set $heap (local.get $end))
+    (local.get $start))
+
+  ;; A growable array of i32s: length at +0, capacity at +4, data at +8
+  (func $vector (result i32)
+    (local $vector i32)
+    (local.set $vector (call $alloc (i32.const 12)))
+    (i32.store (local.get $vector) (i32.const 0))
+    (i32.store offset=4 (local.get $vector) (i32.const 4))
+    (i32.store offset=8 (local.get $vector) (call $alloc (i32.const 16)))
+    (local.get $vector))
+
+  (func $vector_push (param $vector i32) (param $item i32)
+    (local $len i32)
+    (local $data i32)
+    (local.set $len (i32.load (local.get $vector)))
+    (if (i32.eq (local.get $len) (i32.load offset=4 (local.get $vector)))
+      (then
+        (local.set $data (call $alloc (i32.shl (local.get $len) (i32.const 3))))
+        (memory.copy (local.get $data) (i32.load offset=8 (local.get $vector))
+                     (i32.shl (local.get $len) (i32.const 2)))
+        (i32.store offset=4 (local.get $vector) (i32.shl (local.get $len) (i32.const 1)))
+        (i32.store offset=8 (local.get $vector) (local.get $data))))
+    (i32.store (call $vector_slot (local.get $vector) (local.get $len)) (local.get $item))
+    (i32.store (local.get $vector) (i32.add (local.get $len) (i32.const 1))))
+
+  (func $vector_pop (param $vector i32) (result i32)
+    (local $len i32)
+    (local.set $len (i32.sub (i32.load (local.get $vector)) (i32.const 1)))
+    (i32.store (local.get $vector) (local.get $len))
+    (i32.load (call $vector_slot (local.get $vector) (local.get $len))))
+
+  (func $vector_get (param $vector i32) (param $index i32) (result i32)
+    (i32.load (call $vector_slot (local.get $vector) (local.get $index))))
+
+  (func $vector_slot (param $vector i32) (param $index i32) (result i32)
+    (i32.add (i32.load offset=8 (local.get $vector)) (i32.shl (local.get $index) (i32.const 2))))
+
+  ;; Text that is built up bit by bit: data at +0, length at +4, capacity at +8
+  (func $buffer (result i32)
+    (local $buffer i32)
+    (local.set $buffer (call $alloc (i32.const 12)))
+    (i32.store (local.get $buffer) (call $alloc (i32.const 16)))
+    (i32.store offset=4 (local.get $buffer) (i32.const 0))
+    (i32.store offset=8 (local.get $buffer) (i32.const 16))
+    (local.get $buffer))
+
+  ;; Makes room for `len` more bytes, giving back where they go
+  (func $buffer_reserve (param $buffer i32) (param $len i32) (result i32)
+    (local $used i32)
+    (local $data i32)
+    (local.set $used (i32.load offset=4 (local.get $buffer)))
+    (if (i32.gt_u (i32.add (local.get $used) (local.get $len)) (i32.load offset=8 (local.get $buffer)))
+      (then
+        (i32.store offset=8 (local.get $buffer)
+          (i32.shl (i32.add (local.get $used) (local.get $len)) (i32.const 1)))
+        (local.set $data (call $alloc (i32.load offset=8 (local.get $buffer))))
+        (memory.copy (local.get $data) (i32.load (local.get $buffer)) (local.get $used))
+        (i32.store (local.get $buffer) (local.get $data))))
+    (i32.store offset=4 (local.get $buffer) (i32.add (local.get $used) (local.get $len)))
+    (i32.add (i32.load (local.get $buffer)) (local.get $used)))
+
+  (func $buffer_add (param $buffer i32) (param $data i32) (param $len i32)
+    (memory.copy (call $buffer_reserve (local.get $buffer) (local.get $len))
+                 (local.get $data) (local.get $len)))
+
+  (func $buffer_byte (param $buffer i32) (param $byte i32)
+    (i32.store8 (call $buffer_reserve (local.get $buffer) (i32.const 1)) (local.get $byte)))
+
+  ;; Adds the text of a string value
+  (func $buffer_string (param $buffer i32) (param $string i32)
+    (call $buffer_add (local.get $buffer)
+      (i32.load offset=8 (local.get $string)) (i32.load offset=4 (local.get $string))))
+
+  (func $buffer_integer (param $buffer i32) (param $integer i32)
+    (call $format_number (local.get $buffer) (f64.convert_i32_s (local.get $integer))))
+
+  ;; A buffer holding `string` to begin with
+  (func $text (param $string i32) (result i32)
+    (local $buffer i32)
+    (local.set $buffer (call $buffer))
+    (call $buffer_string (local.get $buffer) (local.get $string))
+    (local.get $buffer))
+
+  (func $buffer_finish (param $buffer i32) (result i32)
+    (call $string (i32.load (local.get $buffer)) (i32.load offset=4 (local.get $buffer))))
+
+  ;; Stops the program with an error. Line 0 means nowhere.
+  (func $fail (param $code i32) (param $message i32) (param $line i32) (param $column i32)
+    (call $host_error
+      (i32.load offset=8 (local.get $code)) (i32.load offset=4 (local.get $code))
+      (i32.load offset=8 (local.get $message)) (i32.load offset=4 (local.get $message))
+      (local.get $line) (local.get $column))
+    unreachable)
+
+  ;; Stops the program with the interpreter's own message for the error,
+  ;; followed by whatever is in `detail`
+  (func $fail_with (param $code i32) (param $message i32) (param $detail i32)
+                   (param $line i32) (param $column i32)
+    (local $text i32)
+    (local.set $text (call $text (local.get $message)))
+    (call $buffer_string (local.get $text) (call $buffer_finish (local.get $detail)))
+    (call $fail (local.get $code) (call $buffer_finish (local.get $text))
+      (local.get $line) (local.get $column)))
+
+  ;; --------------------------------------------------------------- values
+
+  (func $number (param $number f64) (result i32)
+    (local $value i32)
+    (local.set $value (call $alloc (i32.const 16)))
+    (i32.store (local.get $value) (i32.const 0))
+    (f64.store offset=8 (local.get $value) (local.get $number))
+    (local.get $value))
+
+  (func $bool (param $bool i32) (result i32)
+    (select (i32.const 24) (i32.const 16) (local.get $bool)))
+
+  (func $string (param $data i32) (param $len i32) (result i32)
+    (local $value i32)
+    (local.set $value (call $alloc (i32.const 12)))
+    (i32.store (local.get $value) (i32.const 2))
+    (i32.store offset=4 (local.get $value) (local.get $len))
+    (i32.store offset=8 (local.get $value) (local.get $data))
+    (local.get $value))
+
+  (func $new_list (result i32)
+    (local $value i32)
+    (local.set $value (call $alloc (i32.const 16)))
+    (i32.store (local.get $value) (i32.const 3))
+    (i32.store offset=4 (local.get $value) (i32.const 0))
+    (i32.store offset=8 (local.get $value) (i32.const 0))
+    (i32.store offset=12 (local.get $value) (i32.const 0))
+    (local.get $value))
+
+  ;; Adds an element to the end of a list, giving back the list
+  (func $list_push (param $list i32) (param $element i32) (result i32)
+    (local $len i32)
+    (local $cap i32)
+    (local $elements i32)
+    (local.set $len (i32.load offset=4 (local.get $list)))
+    (local.set $cap (i32.load offset=8 (local.get $list)))
+    (if (i32.eq (local.get $len) (local.get $cap))
+      (then
+        (local.set $cap (select (i32.shl (local.get $cap) (i32.const 1)) (i32.const 4) (local.get $cap)))
+        (local.set $elements (call $alloc (i32.shl (local.get $cap) (i32.const 2))))
+        (memory.copy (local.get $elements) (i32.load offset=12 (local.get $list))
+                     (i32.shl (local.get $len) (i32.const 2)))
+        (i32.store offset=8 (local.get $list) (local.get $cap))
+        (i32.store offset=12 (local.get $list) (local.get $elements))))
+    (i32.store (call $element (local.get $list) (local.get $len)) (local.get $element))
+    (i32.store offset=4 (local.get $list) (i32.add (local.get $len) (i32.const 1)))
+    (local.get $list))
+
+  ;; Where element `index` of a list, or entry `index` of a map, is stored
+  (func $element (param $collection i32) (param $index i32) (result i32)
+    (i32.add (i32.load offset=12 (local.get $collection))
+             (i32.shl (local.get $index)
+                      (select (i32.const 3) (i32.const 2)
+                              (i32.eq (call $type (local.get $collection)) (i32.const 4))))))
+
+  (func $new_map (result i32)
+    (local $value i32)
+    (local.set $value (call $new_list))
+    (i32.store (local.get $value) (i32.const 4))
+    (local.get $value))
+
+  (func $type (param $value i32) (result i32)
+    (i32.load (local.get $value)))
+
+  (func $number_of (param $value i32) (result f64)
+    (f64.load offset=8 (local.get $value)))
+
+  (func $len (param $value i32) (result i32)
+    (i32.load offset=4 (local.get $value)))
+
+  (func $is_true (param $value i32) (result i32)
+    (i32.eq (local.get $value) (i32.const 24)))
+
+  (func $type_name (param $value i32) (result i32)
+    (block $nothing
+      (block $map
+        (block $list
+          (block $string
+            (block $bool
+              (block $number
+                (br_table $number $bool $string $list $map $nothing
+                  (call $type (local.get $value))))
+              (return (string "number")))
+            (return (string "boolean")))
+          (return (string "string")))
+        (return (string "list")))
+      (return (string "map")))
+    (string "nothing"))
+
+  ;; ------------------------------------------------------------- bignums
+
+  ;; Numbers are written and read exactly, with the help of big unsigned
+  ;; integers: a length at +0, then that many 32-bit limbs, least
+  ;; significant first, the top one never 0. Five of them are set up by
+  ;; $start, each with room for $big_limbs limbs.
+  (global $big_limbs i32 (i32.const 160))
+  (global $big_r (mut i32) (i32.const 0))
+  (global $big_s (mut i32) (i32.const 0))
+  (global $big_plus (mut i32) (i32.const 0))
+  (global $big_minus (mut i32) (i32.const 0))
+  (global $big_t (mut i32) (i32.const 0))
+
+  (func $big_new (result i32)
+    (call $alloc (i32.shl (i32.add (global.get $big_limbs) (i32.const 1)) (i32.const 2))))
+
+  (func $big_limb (param $big i32) (param $index i32) (result i32)
+    (i32.add (i32.add (local.get $big) (i32.const 4)) (i32.shl (local.get $index) (i32.const 2))))
+
+  ;; Limb `index`, or 0 past the top
+  (func $big_get (param $big i32) (param $index i32) (result i64)
+    (if (result i64) (i32.lt_u (local.get $index) (i32.load (local.get $big)))
+      (then (i64.load32_u (call $big_limb (local.get $big) (local.get $index))))
+      (else (i64.const 0))))
+
+  ;; Drops zero limbs from the top, given how many limbs there may be
+  (func $big_trim (param $big i32) (param $len i32)
+    (block $done
+      (loop $next
+        (br_if $done (i32.eqz (local.get $len)))
+        (br_if $done (i32.load (call $big_limb (local.get $big) (i32.sub (local.get $len) (i32.const 1)))))
+        (local.set $len (i32.sub (local.get $len) (i32.const 1)))
+        (br $next)))
+    (i32.store (local.get $big) (local.get $len)))
+
+  (func $big_set (param $big i32) (param $value i64)
+    (i32.store (call $big_limb (local.get $big) (i32.const 0)) (i32.wrap_i64 (local.get $value)))
+    (i32.store (call $big_limb (local.get $big) (i32.const 1))
+      (i32.wrap_i64 (i64.shr_u (local.get $value) (i64.const 32))))
+    (call $big_trim (local.get $big) (i32.const 2)))
+
+  (func $big_copy (param $to i32) (param $from i32)
+    (memory.copy (local.get $to) (local.get $from)
+      (i32.shl (i32.add (i32.load (local.get $from)) (i32.const 1)) (i32.const 2))))
+
+  (func $big_bits (param $big i32) (result i32)
+    (local $len i32)
+    (local.set $len (i32.load (local.get $big)))
+    (if (result i32) (i32.eqz (local.get $len))
+      (then (i32.const 0))
+      (else
+        (i32.sub (i32.shl (local.get $len) (i32.const 5))
+          (i32.clz (i32.load (call $big_limb (local.get $big) (i32.sub (local.get $len) (i32.const 1)))))))))
+
+  (func $big_mul_small (param $big i32) (param $factor i32)
+    (call $big_mul_add (local.get $big) (local.get $factor) (i32.const 0)))
+
+  ;; big = big * factor + addend
+  (func $big_mul_add (param $big i32) (param $factor i32) (param $addend i32)
+    (local $len i32)
+    (local $i i32)
+    (local $carry i64)
+    (local.set $carry (i64.extend_i32_u (local.get $addend)))
+    (local.set $len (i32.load (local.get $big)))
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_u (local.get $i) (local.get $len)))
+        (local.set $carry
+          (i64.add (i64.mul (call $big_get (local.get $big) (local.get $i))
+                            (i64.extend_i32_u (local.get $factor)))
+                   (local.get $carry)))
+        (i32.store (call $big_limb (local.get $big) (local.get $i)) (i32.wrap_i64 (local.get $carry)))
+        (local.set $carry (i64.shr_u (local.get $carry) (i64.const 32)))
+        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+        (br $next)))
+    (i32.store (call $big_limb (local.get $big) (local.get $len)) (i32.wrap_i64 (local.get $carry)))
+    (call $big_trim (local.get $big) (i32.add (local.get $len) (i32.const 1))))
+
+  (func $big_mul_pow10 (param $big i32) (param $power i32)
+    (local $factor i32)
+    (block $done
+      (loop $next
+        (br_if $done (i32.lt_s (local.get $power) (i32.const 9)))
+        (call $big_mul_small (local.get $big) (i32.const 1000000000))
+        (local.set $power (i32.sub (local.get $power) (i32.const 9)))
+        (br $next)))
+    (local.set $factor (i32.const 1))
+    (block $done
+      (loop $next
+        (br_if $done (i32.le_s (local.get $power) (i32.const 0)))
+        (local.set $factor (i32.mul (local.get $factor) (i32.const 10)))
+        (local.set $power (i32.sub (local.get $power) (i32.const 1)))
+        (br $next)))
+    (call $big_mul_small (local.get $big) (local.get $factor)))
+
+  (func $big_shl (param $big i32) (param $bits i32)
+    (local $len i32)
+    (local $words i32)
+    (local $i i32)
+    (local.set $len (i32.load (local.get $big)))
+    (local.set $words (i32.shr_u (local.get $bits) (i32.const 5)))
+    (local.set $bits (i32.and (local.get $bits) (i32.const 31)))
+    (local.set $i (local.get $len))
+    ;; From the top down, so that no limb is overwritten before it is read
+    (loop $next
+      (i32.store (call $big_limb (local.get $big) (i32.add (local.get $i) (local.get $words)))
+        (i32.wrap_i64
+          (i64.shr_u
+            (i64.shl
+              (i64.or (i64.shl (call $big_get (local.get $big) (local.get $i)) (i64.const 32))
+                      (if (result i64) (local.get $i)
+                        (then (call $big_get (local.get $big) (i32.sub (local.get $i) (i32.const 1))))
+                        (else (i64.const 0))))
+              (i64.extend_i32_u (local.get $bits)))
+            (i64.const 32))))
+      (if (local.get $i)
+        (then
+          (local.set $i (i32.sub (local.get $i) (i32.const 1)))
+          (br $next))))
+    (memory.fill (call $big_limb (local.get $big) (i32.const 0)) (i32.const 0)
+      (i32.shl (local.get $words) (i32.const 2)))
+    (call $big_trim (local.get $big)
+      (i32.add (i32.add (local.get $len) (local.get $words)) (i32.const 1))))
+
+  ;; to = a + b, where `to` may be `a` or `b`
+  (func $big_add (param $to i32) (param $a i32) (param $b i32)
+    (local $len i32)
+    (local $i i32)
+    (local $carry i64)
+    (local.set $len
+      (select (i32.load (local.get $a)) (i32.load (local.get $b))
+              (i32.gt_u (i32.load (local.get $a)) (i32.load (local.get $b)))))
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_u (local.get $i) (local.get $len)))
+        (local.set $carry
+          (i64.add (i64.add (call $big_get (local.get $a) (local.get $i))
+                            (call $big_get (local.get $b) (local.get $i)))
+                   (local.get $carry)))
+        (i32.store (call $big_limb (local.get $to) (local.get $i)) (i32.wrap_i64 (local.get $carry)))
+        (local.set $carry (i64.shr_u (local.get $carry) (i64.const 32)))
+        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+        (br $next)))
+    (i32.store (call $big_limb (local.get $to) (local.get $len)) (i32.wrap_i64 (local.get $carry)))
+    (call $big_trim (local.get $to) (i32.add (local.get $len) (i32.const 1))))
+
+  ;; a = a - b, where b is at most a
+  (func $big_sub (param $a i32) (param $b i32)
+    (local $len i32)
+    (local $i i32)
+    (local $difference i64)
+    (local $borrow i64)
+    (local.set $len (i32.load (local.get $a)))
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_u (local.get $i) (local.get $len)))
+        (local.set $difference
+          (i64.sub (i64.sub (call $big_get (local.get $a) (local.get $i))
+                            (call $big_get (local.get $b) (local.get $i)))
+                   (local.get $borrow)))
+        (local.set $borrow (i64.extend_i32_u (i64.lt_s (local.get $difference) (i64.const 0))))
+        (i32.store (call $big_limb (local.get $a) (local.get $i)) (i32.wrap_i64 (local.get $difference)))
+        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+        (br $next)))
+    (call $big_trim (local.get $a) (local.get $len)))
+
+  ;; -1, 0 or 1 as a is less than, equal to or greater than b
+  (func $big_cmp (param $a i32) (param $b i32) (result i32)
+    (local $i i32)
+    (local $left i64)
+    (local $right i64)
+    (local.set $i (i32.load (local.get $a)))
+    (if (i32.ne (local.get $i) (i32.load (local.get $b)))
+      (then
+        (return (select (i32.const 1) (i32.const -1)
+                        (i32.gt_u (local.get $i) (i32.load (local.get $b)))))))
+    (block $done
+      (loop $next
+        (br_if $done (i32.eqz (local.get $i)))
+        (local.set $i (i32.sub (local.get $i) (i32.const 1)))
+        (local.set $left (call $big_get (local.get $a) (local.get $i)))
+        (local.set $right (call $big_get (local.get $b) (local.get $i)))
+        (if (i64.ne (local.get $left) (local.get $right))
+          (then
+            (return (select (i32.const 1) (i32.const -1)
+                            (i64.gt_u (local.get $left) (local.get $right))))))
+        (br $next)))
+    (i32.const 0))
+
+  ;; Compares $big_r + $big_plus with $big_s, through $big_t
+  (func $big_cmp_high (result i32)
+    (call $big_add (global.get $big_t) (global.get $big_r) (global.get $big_plus))
+    (call $big_cmp (global.get $big_t) (global.get $big_s)))
+
+  ;; ------------------------------------------------------- writing values
+
+  ;; Digits being written out, most significant first
+  (global $digits i32 (i32.const 32))
+
+  (func $buffer_digits (param $buffer i32) (param $from i32) (param $to i32)
+    (call $buffer_add (local.get $buffer)
+      (i32.add (global.get $digits) (local.get $from)) (i32.sub (local.get $to) (local.get $from))))
+
+  (func $buffer_zeros (param $buffer i32) (param $count i32)
+    (block $done
+      (loop $next
+        (br_if $done (i32.le_s (local.get $count) (i32.const 0)))
+        (call $buffer_byte (local.get $buffer) (i32.const 48))
+        (local.set $count (i32.sub (local.get $count) (i32.const 1)))
+        (br $next))))
+
+  ;; Numbers are written the way Rust writes an f64: the shortest digits that
+  ;; read back as the same number, and never in scientific notation. The
+  ;; digits come from the free-format algorithm of Steele, White, Burger and
+  ;; Dybvig, working on exact big integers, with Rust's choices for the
+  ;; rounding interval and for ties.
+  (func $format_number (param $buffer i32) (param $number f64)
+    (local $bits i64)
+    (local $mantissa i64)
+    (local $exponent i32)
+    (local $inclusive i32)
+    (local $k i32)
+    (local $count i32)
+    (local $digit i32)
+    (local $low i32)
+    (local $high i32)
+    (local $integer i64)
+    (if (f64.ne (local.get $number) (local.get $number))
+      (then
+        (call $buffer_string (local.get $buffer) (string "NaN"))
+        (return)))
+    (local.set $bits (i64.reinterpret_f64 (local.get $number)))
+    (if (i64.lt_s (local.get $bits) (i64.const 0))
+      (then (call $buffer_byte (local.get $buffer) (i32.const 45))))
+    (local.set $number (f64.abs (local.get $number)))
+    (if (f64.eq (local.get $number) (f64.const inf))
+      (then
+        (call $buffer_string (local.get $buffer) (string "inf"))
+        (return)))
+
+    ;; Whole numbers that fit in the mantissa are simply counted out
+    (if (i32.and (f64.lt (local.get $number) (f64.const 0x1p53))
+                 (f64.eq (local.get $number) (f64.floor (local.get $number))))
+      (then
+        (local.set $integer (i64.trunc_f64_u (local.get $number)))
+        (local.set $count (i32.const 20))
+        (loop $next
+          (local.set $count (i32.sub (local.get $count) (i32.const 1)))
+          (i32.store8 (i32.add (global.get $digits) (local.get $count))
+            (i32.add (i32.const 48) (i32.wrap_i64 (i64.rem_u (local.get $integer) (i64.const 10)))))
+          (local.set $integer (i64.div_u (local.get $integer) (i64.const 10)))
+          (br_if $next (i64.ne (local.get $integer) (i64.const 0))))
+        (call $buffer_digits (local.get $buffer) (local.get $count) (i32.const 20))
+        (return)))
+
+    ;; The number is mantissa * 2^exponent, and anything strictly between
+    ;; (mantissa - minus) * 2^exponent and (mantissa + plus) * 2^exponent reads
+    ;; back as it. The ends count too when the mantissa is even.
+    (local.set $exponent (i32.wrap_i64 (i64.shr_u (local.get $bits) (i64.const 52))))
+    (local.set $exponent (i32.and (local.get $exponent) (i32.const 0x7ff)))
+    (local.set $mantissa (i64.and (local.get $bits) (i64.const 0xfffffffffffff)))
+    (local.set $inclusive (i64.eqz (i64.and (local.get $mantissa) (i64.const 1))))
+    (call $big_set (global.get $big_minus) (i64.const 1))
+    (call $big_set (global.get $big_plus) (i64.const 1))
+    (if (i32.eqz (local.get $exponent))
+      (then
+        ;; Like Rust, which looks at the mantissa after doubling it
+        (local.set $inclusive (i32.const 1))
+        (local.set $mantissa (i64.shl (local.get $mantissa) (i64.const 1)))
+        (local.set $exponent (i32.const -1075)))
+      (else
+        (if (i64.eqz (local.get $mantissa))
+          (then
+            ;; The number below is closer than the one above
+            (local.set $mantissa (i64.const 0x40000000000000))
+            (call $big_set (global.get $big_plus) (i64.const 2))
+            (local.set $exponent (i32.sub (local.get $exponent) (i32.const 1077))))
+          (else
+            (local.set $mantissa
+              (i64.shl (i64.or (local.get $mantissa) (i64.const 0x10000000000000)) (i64.const 1)))
+            (local.set $exponent (i32.sub (local.get $exponent) (i32.const 1076)))))))
+
+    ;; r / s is the number, plus and minus are relative to s as well
+    (call $big_set (global.get $big_r) (local.get $mantissa))
+    (call $big_set (global.get $big_s) (i64.const 1))
+    (if (i32.ge_s (local.get $exponent) (i32.const 0))
+      (then
+        (call $big_shl (global.get $big_r) (local.get $exponent))
+        (call $big_shl (global.get $big_plus) (local.get $exponent))
+        (call $big_shl (global.get $big_minus) (local.get $exponent)))
+      (else
+        (call $big_shl (global.get $big_s) (i32.sub (i32.const 0) (local.get $exponent)))))
+
+    ;; Scales by 10^k, where k is about the number of digits before the
+    ;; point. The estimate can be one short, which the loop below fixes.
+    (call $big_add (global.get $big_t) (global.get $big_r) (global.get $big_plus))
+    (local.set $k
+      (i32.trunc_f64_s
+        (f64.ceil
+          (f64.sub
+            (f64.mul (f64.convert_i32_s
+                       (i32.add (i32.sub (call $big_bits (global.get $big_t)) (call $big_bits (global.get $big_s)))
+                                (i32.const -1)))
+                     (f64.const 0.30102999566398114))
+            (f64.const 1e-10)))))
+    (if (i32.ge_s (local.get $k) (i32.const 0))
+      (then (call $big_mul_pow10 (global.get $big_s) (local.get $k)))
+      (else
+        (call $big_mul_pow10 (global.get $big_r) (i32.sub (i32.const 0) (local.get $k)))
+        (call $big_mul_pow10 (global.get $big_plus) (i32.sub (i32.const 0) (local.get $k)))
+        (call $big_mul_pow10 (global.get $big_minus) (i32.sub (i32.const 0) (local.get $k)))))
+    (block $scaled
+      (loop $next
+        (br_if $scaled (i32.lt_s (call $big_cmp_high) (i32.sub (i32.const 1) (local.get $inclusive))))
+        (call $big_mul_small (global.get $big_s) (i32.const 10))
+        (local.set $k (i32.add (local.get $k) (i32.const 1)))
+        (br $next)))
+
+    ;; Digits are produced until the rest can be left out
+    (loop $next
+      (call $big_mul_small (global.get $big_r) (i32.const 10))
+      (call $big_mul_small (global.get $big_plus) (i32.const 10))
+      (call $big_mul_small (global.get $big_minus) (i32.const 10))
+      (local.set $digit (i32.const 0))
+      (block $divided
+        (loop $subtract
+          (br_if $divided (i32.lt_s (call $big_cmp (global.get $big_r) (global.get $big_s)) (i32.const 0)))
+          (call $big_sub (global.get $big_r) (global.get $big_s))
+          (local.set $digit (i32.add (local.get $digit) (i32.const 1)))
+          (br $subtract)))
+      (local.set $low
+        (i32.lt_s (call $big_cmp (global.get $big_r) (global.get $big_minus)) (local.get $inclusive)))
+      (local.set $high
+        (i32.gt_s (call $big_cmp_high) (i32.sub (i32.const 0) (local.get $inclusive))))
+      (if (i32.and (local.get $high)
+                   (i32.or (i32.eqz (local.get $low))
+                           (block (result i32)
+                             ;; Halfway rounds up
+                             (call $big_add (global.get $big_t) (global.get $big_r) (global.get $big_r))
+                             (i32.ge_s (call $big_cmp (global.get $big_t) (global.get $big_s)) (i32.const 0)))))
+        (then (local.set $digit (i32.add (local.get $digit) (i32.const 1)))))
+      (i32.store8 (i32.add (global.get $digits) (local.get $count))
+        (i32.add (i32.const 48) (local.get $digit)))
+      (local.set $count (i32.add (local.get $count) (i32.const 1)))
+      (br_if $next (i32.eqz (i32.or (local.get $low) (local.get $high)))))
+
+    ;; The number is 0.<digits> * 10^k
+    (if (i32.le_s (local.get $k) (i32.const 0))
+      (then
+        (call $buffer_string (local.get $buffer) (string "0."))
+        (call $buffer_zeros (local.get $buffer) (i32.sub (i32.const 0) (local.get $k)))
+        (call $buffer_digits (local.get $buffer) (i32.const 0) (local.get $count)))
+      (else
+        (if (i32.lt_s (local.get $k) (local.get $count))
+          (then
+            (call $buffer_digits (local.get $buffer) (i32.const 0) (local.get $k))
+            (call $buffer_byte (local.get $buffer) (i32.const 46))
+            (call $buffer_digits (local.get $buffer) (local.get $k) (local.get $count)))
+          (else
+            (call $buffer_digits (local.get $buffer) (i32.const 0) (local.get $count))
+            (call $buffer_zeros (local.get $buffer) (i32.sub (local.get $k) (local.get $count))))))))
+
+  ;; A string as Rust's Debug writes it, quoted and escaped
+  (func $format_quoted (param $buffer i32) (param $string i32)
+    (local $i i32)
+    (local $byte i32)
+    (call $buffer_byte (local.get $buffer) (i32.const 34))
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_u (local.get $i) (call $len (local.get $string))))
+        (local.set $byte
+          (i32.load8_u (i32.add (i32.load offset=8 (local.get $string)) (local.get $i))))
+        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+        (if (i32.eq (local.get $byte) (i32.const 34))
+          (then (call $buffer_string (local.get $buffer) (string "\\\"")) (br $next)))
+        (if (i32.eq (local.get $byte) (i32.const 92))
+          (then (call $buffer_string (local.get $buffer) (string "\\\\")) (br $next)))
+        (if (i32.eq (local.get $byte) (i32.const 10))
+          (then (call $buffer_string (local.get $buffer) (string "\\n")) (br $next)))
+        (if (i32.eq (local.get $byte) (i32.const 13))
+          (then (call $buffer_string (local.get $buffer) (string "\\r")) (br $next)))
+        (if (i32.eq (local.get $byte) (i32.const 9))
+          (then (call $buffer_string (local.get $buffer) (string "\\t")) (br $next)))
+        (if (i32.eqz (local.get $byte))
+          (then (call $buffer_string (local.get $buffer) (string "\\0")) (br $next)))
+        (if (i32.or (i32.lt_u (local.get $byte) (i32.const 32)) (i32.eq (local.get $byte) (i32.const 127)))
+          (then
+            (call $buffer_string (local.get $buffer) (string "\\u{"))
+            (if (i32.ge_u (local.get $byte) (i32.const 16))
+              (then (call $buffer_hex_digit (local.get $buffer) (i32.shr_u (local.get $byte) (i32.const 4)))))
+            (call $buffer_hex_digit (local.get $buffer) (i32.and (local.get $byte) (i32.const 15)))
+            (call $buffer_byte (local.get $buffer) (i32.const 125))
+            (br $next)))
+        (call $buffer_byte (local.get $buffer) (local.get $byte))
+        (br $next)))
+    (call $buffer_byte (local.get $buffer) (i32.const 34)))
+
+  (func $buffer_hex_digit (param $buffer i32) (param $digit i32)
+    (call $buffer_byte (local.get $buffer)
+      (i32.add (local.get $digit)
+               (select (i32.const 87) (i32.const 48) (i32.ge_u (local.get $digit) (i32.const 10))))))
+
+  (func $format (param $buffer i32) (param $value i32) (param $nested i32)
+    (local $i i32)
+    (block $nothing
+      (block $map
+        (block $list
+          (block $string
+            (block $bool
+              (block $number
+                (br_table $number $bool $string $list $map $nothing
+                  (call $type (local.get $value))))
+              (call $format_number (local.get $buffer) (call $number_of (local.get $value)))
+              (return))
+            (call $buffer_string (local.get $buffer)
+              (select (string "true") (string "false") (call $is_true (local.get $value))))
+            (return))
+          (if (local.get $nested)
+            (then (call $format_quoted (local.get $buffer) (local.get $value)))
+            (else (call $buffer_string (local.get $buffer) (local.get $value))))
+          (return))
+        (call $buffer_byte (local.get $buffer) (i32.const 91))
+        (block $done
+          (loop $next
+            (br_if $done (i32.ge_u (local.get $i) (call $len (local.get $value))))
+            (if (local.get $i)
+              (then (call $buffer_string (local.get $buffer) (string ", "))))
+            (call $format (local.get $buffer)
+              (i32.load (call $element (local.get $value) (local.get $i))) (i32.const 1))
+            (local.set $i (i32.add (local.get $i) (i32.const 1)))
+            (br $next)))
+        (call $buffer_byte (local.get $buffer) (i32.const 93))
+        (return))
+      (call $buffer_byte (local.get $buffer) (i32.const 123))
+      (block $done
+        (loop $next
+          (br_if $done (i32.ge_u (local.get $i) (call $len (local.get $value))))
+          (if (local.get $i)
+            (then (call $buffer_string (local.get $buffer) (string ", "))))
+          (call $format (local.get $buffer)
+            (i32.load (call $element (local.get $value) (local.get $i))) (i32.const 1))
+          (call $buffer_string (local.get $buffer) (string ": "))
+          (call $format (local.get $buffer)
+            (i32.load offset=4 (call $element (local.get $value) (local.get $i))) (i32.const 1))
+          (local.set $i (i32.add (local.get $i) (i32.const 1)))
+          (br $next)))
+      (call $buffer_byte (local.get $buffer) (i32.const 125))))
+
+  (func $to_string (param $value i32) (result i32)
+    (local $buffer i32)
+    (if (i32.eq (call $type (local.get $value)) (i32.const 2))
+      (then (return (local.get $value))))
+    (local.set $buffer (call $buffer))
+    (call $format (local.get $buffer) (local.get $value) (i32.const 0))
+    (call $buffer_finish (local.get $buffer)))
+
+  (func $to_quoted_string (param $value i32) (result i32)
+    (local $buffer i32)
+    (local.set $buffer (call $buffer))
+    (call $format (local.get $buffer) (local.get $value) (i32.const 1))
+    (call $buffer_finish (local.get $buffer)))
+
+  (func $print (param $value i32)
+    (local $text i32)
+    (local.set $text (call $to_string (local.get $value)))
+    (call $host_print (i32.load offset=8 (local.get $text)) (call $len (local.get $text))))
+
+  ;; ----------------------------------------------------------------- maps
+
+  ;; Orders numbers like f64::total_cmp, and numbers before strings
+  (func $compare_keys (param $left i32) (param $right i32) (result i32)
+    (local $left_bits i64)
+    (local $right_bits i64)
+    (local $i i32)
+    (local $len i32)
+    (local $left_byte i32)
+    (local $right_byte i32)
+    (if (i32.ne (call $type (local.get $left)) (call $type (local.get $right)))
+      (then
+        (return (select (i32.const -1) (i32.const 1) (i32.eqz (call $type (local.get $left)))))))
+    (if (i32.eqz (call $type (local.get $left)))
+      (then
+        (local.set $left_bits (i64.reinterpret_f64 (call $number_of (local.get $left))))
+        (local.set $right_bits (i64.reinterpret_f64 (call $number_of (local.get $right))))
+        (local.set $left_bits
+          (i64.xor (local.get $left_bits)
+                   (i64.shr_u (i64.shr_s (local.get $left_bits) (i64.const 63)) (i64.const 1))))
+        (local.set $right_bits
+          (i64.xor (local.get $right_bits)
+                   (i64.shr_u (i64.shr_s (local.get $right_bits) (i64.const 63)) (i64.const 1))))
+        (return
+          (i32.sub (i64.gt_s (local.get $left_bits) (local.get $right_bits))
+                   (i64.lt_s (local.get $left_bits) (local.get $right_bits))))))
+    ;; Strings compare byte by byte, a prefix first
+    (local.set $len
+      (select (call $len (local.get $left)) (call $len (local.get $right))
+              (i32.lt_u (call $len (local.get $left)) (call $len (local.get $right)))))
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_u (local.get $i) (local.get $len)))
+        (local.set $left_byte
+          (i32.load8_u (i32.add (i32.load offset=8 (local.get $left)) (local.get $i))))
+        (local.set $right_byte
+          (i32.load8_u (i32.add (i32.load offset=8 (local.get $right)) (local.get $i))))
+        (if (i32.ne (local.get $left_byte) (local.get $right_byte))
+          (then
+            (return (select (i32.const -1) (i32.const 1)
+                            (i32.lt_u (local.get $left_byte) (local.get $right_byte))))))
+        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+        (br $next)))
+    (i32.sub (i32.gt_u (call $len (local.get $left)) (call $len (local.get $right)))
+             (i32.lt_u (call $len (local.get $left)) (call $len (local.get $right)))))
+
+  ;; Turns a value into a map key, rejecting types that cannot be keys
+  (func $map_key (param $key i32) (param $line i32) (param $column i32) (result i32)
+    (local $detail i32)
+    (if (i32.eqz (call $type (local.get $key)))
+      (then
+        ;; -0 and 0 are the same key
+        (return (call $number (f64.add (call $number_of (local.get $key)) (f64.const 0))))))
+    (if (i32.ne (call $type (local.get $key)) (i32.const 2))
+      (then
+        (local.set $detail (call $text (string " A ")))
+        (call $buffer_string (local.get $detail) (call $type_name (local.get $key)))
+        (call $buffer_string (local.get $detail) (string " cannot be used as a map key."))
+        (call $fail_with (code IncompatibleDataType) (message IncompatibleDataType)
+          (local.get $detail) (local.get $line) (local.get $column))))
+    (local.get $key))
+
+  ;; Whether the last $map_find found its key
+  (global $found (mut i32) (i32.const 0))
+
+  ;; Position of `key` in the map, or where it would go
+  (func $map_find (param $map i32) (param $key i32) (result i32)
+    (local $low i32)
+    (local $high i32)
+    (local $middle i32)
+    (local $order i32)
+    (local.set $high (call $len (local.get $map)))
+    (global.set $found (i32.const 0))
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_u (local.get $low) (local.get $high)))
+        (local.set $middle
+          (i32.add (local.get $low) (i32.shr_u (i32.sub (local.get $high) (local.get $low)) (i32.const 1))))
+        (local.set $order
+          (call $compare_keys (i32.load (call $element (local.get $map) (local.get $middle)))
+                              (local.get $key)))
+        (if (i32.eqz (local.get $order))
+          (then
+            (global.set $found (i32.const 1))
+            (return (local.get $middle))))
+        (if (i32.lt_s (local.get $order) (i32.const 0))
+          (then (local.set $low (i32.add (local.get $middle) (i32.const 1))))
+          (else (local.set $high (local.get $middle))))
+        (br $next)))
+    (local.get $low))
+
+  (func $map_put (param $map i32) (param $key i32) (param $value i32)
+    (local $position i32)
+    (local $len i32)
+    (local $cap i32)
+    (local $entries i32)
+    (local.set $position (call $map_find (local.get $map) (local.get $key)))
+    (if (global.get $found)
+      (then
+        (i32.store offset=4 (call $element (local.get $map) (local.get $position)) (local.get $value))
+        (return)))
+    (local.set $len (call $len (local.get $map)))
+    (local.set $cap (i32.load offset=8 (local.get $map)))
+    (if (i32.eq (local.get $len) (local.get $cap))
+      (then
+        (local.set $cap (select (i32.shl (local.get $cap) (i32.const 1)) (i32.const 4) (local.get $cap)))
+        (local.set $entries (call $alloc (i32.shl (local.get $cap) (i32.const 3))))
+        (memory.copy (local.get $entries) (i32.load offset=12 (local.get $map))
+                     (i32.shl (local.get $len) (i32.const 3)))
+        (i32.store offset=8 (local.get $map) (local.get $cap))
+        (i32.store offset=12 (local.get $map) (local.get $entries))))
+    (memory.copy (call $element (local.get $map) (i32.add (local.get $position) (i32.const 1)))
+                 (call $element (local.get $map) (local.get $position))
+                 (i32.shl (i32.sub (local.get $len) (local.get $position)) (i32.const 3)))
+    (i32.store (call $element (local.get $map) (local.get $position)) (local.get $key))
+    (i32.store offset=4 (call $element (local.get $map) (local.get $position)) (local.get $value))
+    (i32.store offset=4 (local.get $map) (i32.add (local.get $len) (i32.const 1))))
+
+  ;; Adds an entry to a map, giving back the map
+  (func $map_insert (param $map i32) (param $key i32) (param $value i32)
+                    (param $line i32) (param $column i32) (result i32)
+    (call $map_put (local.get $map)
+      (call $map_key (local.get $key) (local.get $line) (local.get $column)) (local.get $value))
+    (local.get $map))
+
+  (func $fail_missing_key (param $key i32) (param $line i32) (param $column i32)
+    (local $detail i32)
+    (local.set $detail (call $text (string " There is no key ")))
+    (call $buffer_string (local.get $detail) (call $to_quoted_string (local.get $key)))
+    (call $buffer_string (local.get $detail) (string " in this map."))
+    (call $fail_with (code MissingKey) (message MissingKey)
+      (local.get $detail) (local.get $line) (local.get $column)))
+
+  ;; ------------------------------------------------------------ variables
+
+  ;; Variables are (symbol, value) pairs in $bindings, innermost last. Each
+  ;; block scope is where its bindings start in $bindings, and each function
+  ;; call's frame is where its scopes start in $scopes. The program's own
+  ;; outermost scope holds the globals.
+
+  (func $name (param $symbol i32) (result i32)
+    (i32.load (i32.add (global.get $symbol_names) (i32.shl (local.get $symbol) (i32.const 2)))))
+
+  (func $binding_count (result i32)
+    (i32.shr_u (i32.load (global.get $bindings)) (i32.const 1)))
+
+  (func $binding (param $index i32) (result i32)
+    (call $vector_slot (global.get $bindings) (i32.shl (local.get $index) (i32.const 1))))
+
+  (func $frame_start (result i32)
+    (call $vector_get (global.get $frames) (i32.sub (i32.load (global.get $frames)) (i32.const 1))))
+
+  (func $push_scope
+    (call $vector_push (global.get $scopes) (call $binding_count)))
+
+  (func $pop_scope
+    (local $start i32)
+    (local $i i32)
+    ;; The outermost scope of a frame lives as long as the frame itself
+    (if (i32.le_u (i32.sub (i32.load (global.get $scopes)) (call $frame_start)) (i32.const 1))
+      (then (return)))
+    (local.set $start (call $vector_pop (global.get $scopes)))
+    (local.set $i (local.get $start))
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_u (local.get $i) (call $binding_count)))
+        (i32.store8 (i32.add (global.get $expired) (i32.load (call $binding (local.get $i))))
+                    (i32.const 1))
+        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+        (br $next)))
+    (i32.store (global.get $bindings) (i32.shl (local.get $start) (i32.const 1))))
+
+  (func $push_frame
+    (call $vector_push (global.get $frames) (i32.load (global.get $scopes)))
+    (call $push_scope))
+
+  (func $pop_frame
+    (local $first_scope i32)
+    (local.set $first_scope (call $vector_pop (global.get $frames)))
+    (i32.store (global.get $bindings)
+      (i32.shl (call $vector_get (global.get $scopes) (local.get $first_scope)) (i32.const 1)))
+    (i32.store (global.get $scopes) (local.get $first_scope)))
+
+  ;; Where the value of the innermost binding of `symbol` among bindings
+  ;; [from, to) is kept, or 0
+  (func $find_between (param $symbol i32) (param $from i32) (param $to i32) (result i32)
+    (block $done
+      (loop $next
+        (br_if $done (i32.le_u (local.get $to) (local.get $from)))
+        (local.set $to (i32.sub (local.get $to) (i32.const 1)))
+        (if (i32.eq (i32.load (call $binding (local.get $to))) (local.get $symbol))
+          (then (return (i32.add (call $binding (local.get $to)) (i32.const 4)))))
+        (br $next)))
+    (i32.const 0))
+
+  ;; The nearest binding in the current frame, or else a global one
+  (func $find (param $symbol i32) (param $with_globals i32) (result i32)
+    (local $slot i32)
+    (local.set $slot
+      (call $find_between (local.get $symbol)
+        (call $vector_get (global.get $scopes) (call $frame_start)) (call $binding_count)))
+    (if (i32.or (local.get $slot) (i32.eqz (local.get $with_globals)))
+      (then (return (local.get $slot))))
+    (call $find_between (local.get $symbol) (i32.const 0)
+      (if (result i32) (i32.gt_u (i32.load (global.get $scopes)) (i32.const 1))
+        (then (call $vector_get (global.get $scopes) (i32.const 1)))
+        (else (call $binding_count)))))
+
+  ;; Creates a variable in the innermost scope, shadowing any outer one
+  (func $declare (param $symbol i32) (param $value i32)
+    (local $slot i32)
+    (local.set $slot
+      (call $find_between (local.get $symbol)
+        (call $vector_get (global.get $scopes) (i32.sub (i32.load (global.get $scopes)) (i32.const 1)))
+        (call $binding_count)))
+    (if (local.get $slot)
+      (then
+        (i32.store (local.get $slot) (local.get $value))
+        (return)))
+    (call $vector_push (global.get $bindings) (local.get $symbol))
+    (call $vector_push (global.get $bindings) (local.get $value)))
+
+  ;; Updates the nearest variable of the current frame, or creates one
+  (func $assign (param $symbol i32) (param $value i32)
+    (local $slot i32)
+    (local.set $slot (call $find (local.get $symbol) (i32.const 0)))
+    (if (local.get $slot)
+      (then (i32.store (local.get $slot) (local.get $value)))
+      (else (call $declare (local.get $symbol) (local.get $value)))))
+
+  (func $load (param $symbol i32) (param $line i32) (param $column i32) (result i32)
+    (local $slot i32)
+    (local $detail i32)
+    (local.set $slot (call $find (local.get $symbol) (i32.const 1)))
+    (if (local.get $slot)
+      (then (return (i32.load (local.get $slot)))))
+    (if (i32.load8_u (i32.add (global.get $expired) (local.get $symbol)))
+      (then
+        (local.set $detail (call $text (string " '")))
+        (call $buffer_string (local.get $detail) (call $name (local.get $symbol)))
+        (call $buffer_string (local.get $detail) (string "' only lived inside a block that has already ended."))
+        (call $fail_with (code VariableOutOfScope) (message VariableOutOfScope)
+          (local.get $detail) (local.get $line) (local.get $column))))
+    (local.set $detail (call $text (string "Undefined variable! 'Tumhara value kya hai, ")))
+    (call $buffer_string (local.get $detail) (call $name (local.get $symbol)))
+    (call $buffer_string (local.get $detail) (string "? , Batao bhi Basanti !'"))
+    (call $fail (code UndefinedVariable) (call $buffer_finish (local.get $detail))
+      (local.get $line) (local.get $column))
+    (i32.const 0))
+
+  ;; ------------------------------------------------------------ operators
+
+  ;; Fails with `detail` after the IncompatibleDataType message unless both
+  ;; values have the type `type`
+  (func $expect_both (param $left i32) (param $right i32) (param $type i32) (param $detail i32)
+                     (param $line i32) (param $column i32)
+    (if (i32.and (i32.eq (call $type (local.get $left)) (local.get $type))
+                 (i32.eq (call $type (local.get $right)) (local.get $type)))
+      (then (return)))
+    (call $fail_with (code IncompatibleDataType) (message IncompatibleDataType)
+      (call $text (local.get $detail)) (local.get $line) (local.get $column)))
+
+  (func $unary_plus (param $right i32) (param $line i32) (param $column i32) (result i32)
+    (call $expect_both (local.get $right) (local.get $right) (i32.const 0)
+      (string "Only numeral types allowed with unary addition!") (local.get $line) (local.get $column))
+    (local.get $right))
+
+  (func $negate (param $right i32) (param $line i32) (param $column i32) (result i32)
+    (call $expect_both (local.get $right) (local.get $right) (i32.const 0)
+      (string "Only numeral types allowed with unary negation!") (local.get $line) (local.get $column))
+    (call $number (f64.neg (call $number_of (local.get $right)))))
+
+  (func $not (param $right i32) (param $line i32) (param $column i32) (result i32)
+    (if (i32.ne (call $type (local.get $right)) (i32.const 1))
+      (then
+        (call $fail (code IncompatibleDataType)
+          (string "Only boolean types allowed with not! 'Yeh Kya Ho gya hai duniyan ko ??'")
+          (local.get $line) (local.get $column))))
+    (call $bool (i32.eqz (call $is_true (local.get $right)))))
+
+  (func $add (param $left i32) (param $right i32) (param $line i32) (param $column i32) (result i32)
+    (local $text i32)
+    (if (i32.and (i32.eqz (call $type (local.get $left))) (i32.eqz (call $type (local.get $right))))
+      (then
+        (return (call $number (f64.add (call $number_of (local.get $left))
+                                       (call $number_of (local.get $right)))))))
+    ;; Joining with a string converts the other side to text
+    (if (i32.or (i32.eq (call $type (local.get $left)) (i32.const 2))
+                (i32.eq (call $type (local.get $right)) (i32.const 2)))
+      (then
+        (local.set $text (call $text (call $to_string (local.get $left))))
+        (call $buffer_string (local.get $text) (call $to_string (local.get $right)))
+        (return (call $buffer_finish (local.get $text)))))
+    (call $fail_with (code IncompatibleDataType) (message IncompatibleDataType)
+      (call $text (string "Only numeral or string types allowed with addition! "))
+      (local.get $line) (local.get $column))
+    (i32.const 0))
+
+  (func $subtract (param $left i32) (param $right i32) (param $line i32) (param $column i32) (result i32)
+    (call $expect_both (local.get $left) (local.get $right) (i32.const 0)
+      (string "Only numeral types allowed with subtraction! 'Number chahiye, number!")
+      (local.get $line) (local.get $column))
+    (call $number (f64.sub (call $number_of (local.get $left)) (call $number_of (local.get $right)))))
+
+  (func $multiply (param $left i32) (param $right i32) (param $line i32) (param $column i32) (result i32)
+    (call $expect_both (local.get $left) (local.get $right) (i32.const 0)
+      (string "Only numeral types allowed with multiplication!") (local.get $line) (local.get $column))
+    (call $number (f64.mul (call $number_of (local.get $left)) (call $number_of (local.get $right)))))
+
+  (func $modulo (param $left i32) (param $right i32) (param $line i32) (param $column i32) (result i32)
+    (call $expect_both (local.get $left) (local.get $right) (i32.const 0)
+      (string "Only numeral types allowed with modulus! ") (local.get $line) (local.get $column))
+    (call $number (call $fmod (call $number_of (local.get $left)) (call $number_of (local.get $right)))))
+
+  (func $divide (param $left i32) (param $right i32) (param $line i32) (param $column i32) (result i32)
+    (call $expect_both (local.get $left) (local.get $right) (i32.const 0)
+      (string "Only numeral types allowed with division!") (local.get $line) (local.get $column))
+    (if (f64.eq (call $number_of (local.get $right)) (f64.const 0))
+      (then
+        (call $fail (code DivisionByZero) (message DivisionByZero) (local.get $line) (local.get $column))))
+    (call $number (f64.div (call $number_of (local.get $left)) (call $number_of (local.get $right)))))
+
+  ;; The remainder of x / y with the sign of x, worked out exactly like C's
+  ;; fmod, which Rust's % on floats is
+  (func $fmod (param $x f64) (param $y f64) (result f64)
+    (local $ux i64)
+    (local $uy i64)
+    (local $ex i32)
+    (local $ey i32)
+    (local $difference i64)
+    (local.set $ux (i64.reinterpret_f64 (local.get $x)))
+    (local.set $uy (i64.reinterpret_f64 (local.get $y)))
+    (local.set $ex (i32.and (i32.wrap_i64 (i64.shr_u (local.get $ux) (i64.const 52))) (i32.const 0x7ff)))
+    (local.set $ey (i32.and (i32.wrap_i64 (i64.shr_u (local.get $uy) (i64.const 52))) (i32.const 0x7ff)))
+    (if (i32.or (i32.or (i64.eqz (i64.shl (local.get $uy) (i64.const 1)))
+                        (f64.ne (local.get $y) (local.get $y)))
+                (i32.eq (local.get $ex) (i32.const 0x7ff)))
+      (then (return (f64.div (f64.mul (local.get $x) (local.get $y))
+                             (f64.mul (local.get $x) (local.get $y))))))
+    (if (i64.le_u (i64.shl (local.get $ux) (i64.const 1)) (i64.shl (local.get $uy) (i64.const 1)))
+      (then
+        (if (i64.eq (i64.shl (local.get $ux) (i64.const 1)) (i64.shl (local.get $uy) (i64.const 1)))
+          (then (return (f64.mul (f64.const 0) (local.get $x)))))
+        (return (local.get $x))))
+
+    ;; Both mantissas as integers, with the exponents to match
+    (if (i32.eqz (local.get $ex))
+      (then
+        (local.set $ex
+          (i32.sub (i32.const 0) (i32.wrap_i64 (i64.clz (i64.shl (local.get $ux) (i64.const 12))))))
+        (local.set $ux (i64.shl (local.get $ux) (i64.extend_i32_u (i32.sub (i32.const 1) (local.get $ex))))))
+      (else
+        (local.set $ux (i64.or (i64.and (local.get $ux) (i64.const 0xfffffffffffff))
+                               (i64.const 0x10000000000000)))))
+    (if (i32.eqz (local.get $ey))
+      (then
+        (local.set $ey
+          (i32.sub (i32.const 0) (i32.wrap_i64 (i64.clz (i64.shl (local.get $uy) (i64.const 12))))))
+        (local.set $uy (i64.shl (local.get $uy) (i64.extend_i32_u (i32.sub (i32.const 1) (local.get $ey))))))
+      (else
+        (local.set $uy (i64.or (i64.and (local.get $uy) (i64.const 0xfffffffffffff))
+                               (i64.const 0x10000000000000)))))
+
+    ;; Long division, keeping only the remainder
+    (block $done
+      (loop $next
+        (br_if $done (i32.le_s (local.get $ex) (local.get $ey)))
+        (local.set $difference (i64.sub (local.get $ux) (local.get $uy)))
+        (if (i64.ge_s (local.get $difference) (i64.const 0))
+          (then
+            (if (i64.eqz (local.get $difference))
+              (then (return (f64.mul (f64.const 0) (local.get $x)))))
+            (local.set $ux (local.get $difference))))
+        (local.set $ux (i64.shl (local.get $ux) (i64.const 1)))
+        (local.set $ex (i32.sub (local.get $ex) (i32.const 1)))
+        (br $next)))
+    (local.set $difference (i64.sub (local.get $ux) (local.get $uy)))
+    (if (i64.ge_s (local.get $difference) (i64.const 0))
+      (then
+        (if (i64.eqz (local.get $difference))
+          (then (return (f64.mul (f64.const 0) (local.get $x)))))
+        (local.set $ux (local.get $difference))))
+    (block $done
+      (loop $next
+        (br_if $done (i64.ne (i64.shr_u (local.get $ux) (i64.const 52)) (i64.const 0)))
+        (local.set $ux (i64.shl (local.get $ux) (i64.const 1)))
+        (local.set $ex (i32.sub (local.get $ex) (i32.const 1)))
+        (br $next)))
+
+    (if (i32.gt_s (local.get $ex) (i32.const 0))
+      (then
+        (local.set $ux (i64.or (i64.sub (local.get $ux) (i64.const 0x10000000000000))
+                               (i64.shl (i64.extend_i32_u (local.get $ex)) (i64.const 52)))))
+      (else
+        (local.set $ux (i64.shr_u (local.get $ux) (i64.extend_i32_u (i32.sub (i32.const 1) (local.get $ex)))))))
+    (f64.copysign (f64.reinterpret_i64 (local.get $ux)) (local.get $x)))
+
+  (func $greater_than (param $left i32) (param $right i32) (param $line i32) (param $column i32) (result i32)
+    (call $expect_both (local.get $left) (local.get $right) (i32.const 0)
+      (string "Comparison with only numeral data types is allowed!") (local.get $line) (local.get $column))
+    (call $bool (f64.gt (call $number_of (local.get $left)) (call $number_of (local.get $right)))))
+
+  (func $greater_than_equal (param $left i32) (param $right i32) (param $line i32) (param $column i32) (result i32)
+    (call $expect_both (local.get $left) (local.get $right) (i32.const 0)
+      (string "Comparison with only numeral data types is allowed! ") (local.get $line) (local.get $column))
+    (call $bool (f64.ge (call $number_of (local.get $left)) (call $number_of (local.get $right)))))
+
+  (func $less_than (param $left i32) (param $right i32) (param $line i32) (param $column i32) (result i32)
+    (call $expect_both (local.get $left) (local.get $right) (i32.const 0)
+      (string "Comparison with only numeral data types is allowed! ") (local.get $line) (local.get $column))
+    (call $bool (f64.lt (call $number_of (local.get $left)) (call $number_of (local.get $right)))))
+
+  (func $less_than_equal (param $left i32) (param $right i32) (param $line i32) (param $column i32) (result i32)
+    (call $expect_both (local.get $left) (local.get $right) (i32.const 0)
+      (string "Comparison with only numeral data types is allowed! ") (local.get $line) (local.get $column))
+    (call $bool (f64.le (call $number_of (local.get $left)) (call $number_of (local.get $right)))))
+
+  (func $and (param $left i32) (param $right i32) (param $line i32) (param $column i32) (result i32)
+    (call $expect_both (local.get $left) (local.get $right) (i32.const 1)
+      (string "Comparison with only boolean data types is allowed! ") (local.get $line) (local.get $column))
+    (call $bool (i32.and (call $is_true (local.get $left)) (call $is_true (local.get $right)))))
+
+  (func $or (param $left i32) (param $right i32) (param $line i32) (param $column i32) (result i32)
+    (call $expect_both (local.get $left) (local.get $right) (i32.const 1)
+      (string "Comparison with only boolean data types is allowed!") (local.get $line) (local.get $column))
+    (call $bool (i32.or (call $is_true (local.get $left)) (call $is_true (local.get $right)))))
+
+  ;; Deep equality, as derived for the interpreter's values
+  (func $values_equal (param $left i32) (param $right i32) (result i32)
+    (local $i i32)
+    (local $type i32)
+    (local.set $type (call $type (local.get $left)))
+    (if (i32.ne (local.get $type) (call $type (local.get $right)))
+      (then (return (i32.const 0))))
+    (if (i32.eqz (local.get $type))
+      (then
+        (return (f64.eq (call $number_of (local.get $left)) (call $number_of (local.get $right))))))
+    (if (i32.eq (local.get $type) (i32.const 2))
+      (then
+        (return (i32.eqz (call $compare_keys (local.get $left) (local.get $right))))))
+    (if (i32.lt_u (local.get $type) (i32.const 3))
+      (then (return (i32.eq (local.get $left) (local.get $right)))))
+    (if (i32.gt_u (local.get $type) (i32.const 4))
+      (then (return (i32.const 1))))
+    (if (i32.ne (call $len (local.get $left)) (call $len (local.get $right)))
+      (then (return (i32.const 0))))
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_u (local.get $i) (call $len (local.get $left))))
+        (if (i32.eq (local.get $type) (i32.const 4))
+          (then
+            (if (call $compare_keys (i32.load (call $element (local.get $left) (local.get $i)))
+                                    (i32.load (call $element (local.get $right) (local.get $i))))
+              (then (return (i32.const 0))))
+            (if (i32.eqz (call $values_equal
+                           (i32.load offset=4 (call $element (local.get $left) (local.get $i)))
+                           (i32.load offset=4 (call $element (local.get $right) (local.get $i)))))
+              (then (return (i32.const 0)))))
+          (else
+            (if (i32.eqz (call $values_equal
+                           (i32.load (call $element (local.get $left) (local.get $i)))
+                           (i32.load (call $element (local.get $right) (local.get $i)))))
+              (then (return (i32.const 0))))))
+        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+        (br $next)))
+    (i32.const 1))
+
+  (func $equal (param $left i32) (param $right i32) (param $line i32) (param $column i32) (result i32)
+    (if (i32.ne (call $type (local.get $left)) (call $type (local.get $right)))
+      (then
+        (call $fail (code IncompatibleDataType)
+          (string "Comparison with only homogeneous data types is allowed! 'Ek chutki datatype ki keemat, tum kya jaano Ramesh babu.'")
+          (local.get $line) (local.get $column))))
+    (call $bool (call $values_equal (local.get $left) (local.get $right))))
+
+  (func $not_equal (param $left i32) (param $right i32) (param $line i32) (param $column i32) (result i32)
+    (if (i32.ne (call $type (local.get $left)) (call $type (local.get $right)))
+      (then
+        (call $fail_with (code IncompatibleDataType) (message IncompatibleDataType)
+          (call $text (string "Comparison with only homogeneous data types is allowed!"))
+          (local.get $line) (local.get $column))))
+    (call $bool (i32.eqz (call $values_equal (local.get $left) (local.get $right)))))
+
+  ;; ---------------------------------------------------- lists and strings
+
+  (func $whole_number (param $value i32) (param $line i32) (param $column i32) (result f64)
+    (local $detail i32)
+    (if (i32.or (i32.ne (call $type (local.get $value)) (i32.const 0))
+                (f64.ne (call $number_of (local.get $value))
+                        (f64.floor (call $number_of (local.get $value)))))
+      (then
+        (local.set $detail (call $text (string " Positions must be whole numbers, not ")))
+        (call $buffer_string (local.get $detail) (call $to_string (local.get $value)))
+        (call $buffer_byte (local.get $detail) (i32.const 46))
+        (call $fail_with (code IncompatibleDataType) (message IncompatibleDataType)
+          (local.get $detail) (local.get $line) (local.get $column))))
+    (call $number_of (local.get $value)))
+
+  ;; Checks that `index` points at an element of a sequence of `len` elements
+  (func $index_position (param $index i32) (param $len i32) (param $line i32) (param $column i32)
+                        (result i32)
+    (local $position f64)
+    (local $detail i32)
+    (local.set $position (call $whole_number (local.get $index) (local.get $line) (local.get $column)))
+    (if (i32.or (f64.lt (local.get $position) (f64.const 0))
+                (f64.ge (local.get $position) (f64.convert_i32_u (local.get $len))))
+      (then
+        (local.set $detail (call $text (string " Position ")))
+        (call $format_number (local.get $detail) (local.get $position))
+        (call $buffer_string (local.get $detail) (string " asked, but length is only "))
+        (call $buffer_integer (local.get $detail) (local.get $len))
+        (call $buffer_byte (local.get $detail) (i32.const 46))
+        (call $fail_with (code IndexOutOfBounds) (message IndexOutOfBounds)
+          (local.get $detail) (local.get $line) (local.get $column))))
+    (i32.trunc_f64_u (local.get $position)))
+
+  ;; Number of characters in a string's UTF-8 text
+  (func $char_count (param $string i32) (result i32)
+    (local $i i32)
+    (local $count i32)
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_u (local.get $i) (call $len (local.get $string))))
+        (local.set $count
+          (i32.add (local.get $count)
+            (i32.ne (i32.and (i32.load8_u (i32.add (i32.load offset=8 (local.get $string)) (local.get $i)))
+                             (i32.const 0xc0))
+                    (i32.const 0x80))))
+        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+        (br $next)))
+    (local.get $count))
+
+  ;; Byte offset of character `position` of a string
+  (func $char_offset (param $string i32) (param $position i32) (result i32)
+    (local $offset i32)
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_u (local.get $offset) (call $len (local.get $string))))
+        (br_if $done (i32.eqz (local.get $position)))
+        (local.set $offset (i32.add (local.get $offset) (i32.const 1)))
+        (block $character
+          (loop $continuation
+            (br_if $character (i32.ge_u (local.get $offset) (call $len (local.get $string))))
+            (br_if $character
+              (i32.ne (i32.and (i32.load8_u (i32.add (i32.load offset=8 (local.get $string)) (local.get $offset)))
+                               (i32.const 0xc0))
+                      (i32.const 0x80)))
+            (local.set $offset (i32.add (local.get $offset) (i32.const 1)))
+            (br $continuation)))
+        (local.set $position (i32.sub (local.get $position) (i32.const 1)))
+        (br $next)))
+    (local.get $offset))
+
+  ;; Characters [from, to) of a string, sharing its bytes
+  (func $substring (param $string i32) (param $from i32) (param $to i32) (result i32)
+    (local $start i32)
+    (local.set $start (call $char_offset (local.get $string) (local.get $from)))
+    (call $string (i32.add (i32.load offset=8 (local.get $string)) (local.get $start))
+      (i32.sub (call $char_offset (local.get $string) (local.get $to)) (local.get $start))))
+
+  (func $index (param $left i32) (param $index i32) (param $line i32) (param $column i32) (result i32)
+    (local $position i32)
+    (local $detail i32)
+    (local $key i32)
+    (if (i32.eq (call $type (local.get $left)) (i32.const 2))
+      (then
+        (local.set $position
+          (call $index_position (local.get $index) (call $char_count (local.get $left))
+            (local.get $line) (local.get $column)))
+        (return (call $substring (local.get $left) (local.get $position)
+                  (i32.add (local.get $position) (i32.const 1))))))
+    (if (i32.eq (call $type (local.get $left)) (i32.const 3))
+      (then
+        (return
+          (i32.load (call $element (local.get $left)
+            (call $index_position (local.get $index) (call $len (local.get $left))
+              (local.get $line) (local.get $column)))))))
+    (if (i32.eq (call $type (local.get $left)) (i32.const 4))
+      (then
+        (local.set $key (call $map_key (local.get $index) (local.get $line) (local.get $column)))
+        (local.set $position (call $map_find (local.get $left) (local.get $key)))
+        (if (i32.eqz (global.get $found))
+          (then (call $fail_missing_key (local.get $key) (local.get $line) (local.get $column))))
+        (return (i32.load offset=4 (call $element (local.get $left) (local.get $position))))))
+    (local.set $detail (call $text (string " A ")))
+    (call $buffer_string (local.get $detail) (call $type_name (local.get $left)))
+    (call $buffer_string (local.get $detail) (string " cannot be indexed."))
+    (call $fail_with (code IncompatibleDataType) (message IncompatibleDataType)
+      (local.get $detail) (local.get $line) (local.get $column))
+    (i32.const 0))
+
+  ;; Resolves an optional slice bound, clamping it to the sequence
+  (func $slice_bound (param $bound i32) (param $fallback i32) (param $len i32)
+                     (param $line i32) (param $column i32) (result i32)
+    (local $position f64)
+    (local $detail i32)
+    (if (i32.eq (call $type (local.get $bound)) (i32.const 5))
+      (then (return (local.get $fallback))))
+    (local.set $position (call $whole_number (local.get $bound) (local.get $line) (local.get $column)))
+    (if (f64.lt (local.get $position) (f64.const 0))
+      (then
+        (local.set $detail (call $text (string " Slice bounds cannot be negative, got ")))
+        (call $format_number (local.get $detail) (local.get $position))
+        (call $buffer_byte (local.get $detail) (i32.const 46))
+        (call $fail_with (code IndexOutOfBounds) (message IndexOutOfBounds)
+          (local.get $detail) (local.get $line) (local.get $column))))
+    (if (result i32) (f64.lt (local.get $position) (f64.convert_i32_u (local.get $len)))
+      (then (i32.trunc_f64_u (local.get $position)))
+      (else (local.get $len))))
+
+  (func $slice (param $left i32) (param $start i32) (param $end i32)
+               (param $line i32) (param $column i32) (result i32)
+    (local $len i32)
+    (local $from i32)
+    (local $to i32)
+    (local $list i32)
+    (local $detail i32)
+    (if (i32.eq (call $type (local.get $left)) (i32.const 2))
+      (then (local.set $len (call $char_count (local.get $left))))
+      (else
+        (if (i32.eq (call $type (local.get $left)) (i32.const 3))
+          (then (local.set $len (call $len (local.get $left))))
+          (else
+            (local.set $detail (call $text (string " A ")))
+            (call $buffer_string (local.get $detail) (call $type_name (local.get $left)))
+            (call $buffer_string (local.get $detail) (string " cannot be sliced."))
+            (call $fail_with (code IncompatibleDataType) (message IncompatibleDataType)
+              (local.get $detail) (local.get $line) (local.get $column))))))
+
+    (local.set $from
+      (call $slice_bound (local.get $start) (i32.const 0) (local.get $len) (local.get $line) (local.get $column)))
+    (local.set $to
+      (call $slice_bound (local.get $end) (local.get $len) (local.get $len) (local.get $line) (local.get $column)))
+    (if (i32.lt_u (local.get $to) (local.get $from))
+      (then (local.set $to (local.get $from))))
+
+    (if (i32.eq (call $type (local.get $left)) (i32.const 2))
+      (then (return (call $substring (local.get $left) (local.get $from) (local.get $to)))))
+    (local.set $list (call $new_list))
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_u (local.get $from) (local.get $to)))
+        (drop (call $list_push (local.get $list)
+                (i32.load (call $element (local.get $left) (local.get $from)))))
+        (local.set $from (i32.add (local.get $from) (i32.const 1)))
+        (br $next)))
+    (local.get $list))
+
+  (func $set_element (param $target i32) (param $index i32) (param $value i32)
+                     (param $line i32) (param $column i32)
+    (local $detail i32)
+    (if (i32.eq (call $type (local.get $target)) (i32.const 3))
+      (then
+        (i32.store
+          (call $element (local.get $target)
+            (call $index_position (local.get $index) (call $len (local.get $target))
+              (local.get $line) (local.get $column)))
+          (local.get $value))
+        (return)))
+    (if (i32.eq (call $type (local.get $target)) (i32.const 4))
+      (then
+        (drop (call $map_insert (local.get $target) (local.get $index) (local.get $value)
+                (local.get $line) (local.get $column)))
+        (return)))
+    (local.set $detail (call $text (string " Cannot change an element of a ")))
+    (call $buffer_string (local.get $detail) (call $type_name (local.get $target)))
+    (call $buffer_byte (local.get $detail) (i32.const 46))
+    (call $fail_with (code IncompatibleDataType) (message IncompatibleDataType)
+      (local.get $detail) (local.get $line) (local.get $column)))
+
+  ;; ---------------------------------------------------------------- loops
+
+  (func $loop_bound (param $bound i32) (param $line i32) (param $column i32) (result f64)
+    (local $detail i32)
+    (if (i32.ne (call $type (local.get $bound)) (i32.const 0))
+      (then
+        (local.set $detail (call $text (string " Loop bounds must be numbers, not a ")))
+        (call $buffer_string (local.get $detail) (call $type_name (local.get $bound)))
+        (call $buffer_byte (local.get $detail) (i32.const 46))
+        (call $fail_with (code IncompatibleDataType) (message IncompatibleDataType)
+          (local.get $detail) (local.get $line) (local.get $column))))
+    (call $number_of (local.get $bound)))
+
+
+  ;; ---------------------------------------------------------------- input
+
+  ;; What the last successful $parse_number read
+  (global $parsed (mut f64) (f64.const 0))
+
+  ;; The line input is read from, and how much of it is read already
+  (global $input_text (mut i32) (i32.const 0))
+  (global $input_len (mut i32) (i32.const 0))
+  (global $input_position (mut i32) (i32.const 0))
+
+  (func $is_space (param $byte i32) (result i32)
+    (i32.or (i32.eq (local.get $byte) (i32.const 32))
+            (i32.lt_u (i32.sub (local.get $byte) (i32.const 9)) (i32.const 5))))
+
+  ;; Whether the `len` bytes at `text` are `word`, ignoring ASCII case
+  (func $is_word (param $text i32) (param $len i32) (param $word i32) (result i32)
+    (local $i i32)
+    (if (i32.ne (local.get $len) (call $len (local.get $word)))
+      (then (return (i32.const 0))))
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_u (local.get $i) (local.get $len)))
+        (if (i32.ne (i32.or (i32.load8_u (i32.add (local.get $text) (local.get $i))) (i32.const 0x20))
+                    (i32.load8_u (i32.add (i32.load offset=8 (local.get $word)) (local.get $i))))
+          (then (return (i32.const 0))))
+        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+        (br $next)))
+    (i32.const 1))
+
+  ;; Reads a number the way Rust's f64::from_str does, into $parsed. Spaces
+  ;; around it are left out first, like the interpreter's trim.
+  (func $parse_number (param $string i32) (result i32)
+    (local $text i32)
+    (local $end i32)
+    (local $byte i32)
+    (local $negative i32)
+    (local $fraction i32)
+    (local $digits i32)
+    (local $kept i32)
+    (local $sticky i32)
+    (local $exponent i32)
+    (local $explicit i32)
+    (local $explicit_negative i32)
+    (local $explicit_digits i32)
+    (local $number f64)
+    (local.set $text (i32.load offset=8 (local.get $string)))
+    (local.set $end (i32.add (local.get $text) (call $len (local.get $string))))
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_u (local.get $text) (local.get $end)))
+        (br_if $done (i32.eqz (call $is_space (i32.load8_u (local.get $text)))))
+        (local.set $text (i32.add (local.get $text) (i32.const 1)))
+        (br $next)))
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_u (local.get $text) (local.get $end)))
+        (br_if $done (i32.eqz (call $is_space (i32.load8_u (i32.sub (local.get $end) (i32.const 1))))))
+        (local.set $end (i32.sub (local.get $end) (i32.const 1)))
+        (br $next)))
+    (if (i32.ge_u (local.get $text) (local.get $end))
+      (then (return (i32.const 0))))
+    (local.set $byte (i32.load8_u (local.get $text)))
+    (if (i32.or (i32.eq (local.get $byte) (i32.const 43)) (i32.eq (local.get $byte) (i32.const 45)))
+      (then
+        (local.set $negative (i32.eq (local.get $byte) (i32.const 45)))
+        (local.set $text (i32.add (local.get $text) (i32.const 1)))))
+
+    (block $read
+      (if (i32.or (call $is_word (local.get $text) (i32.sub (local.get $end) (local.get $text)) (string "inf"))
+                  (call $is_word (local.get $text) (i32.sub (local.get $end) (local.get $text)) (string "infinity")))
+        (then
+          (local.set $number (f64.const inf))
+          (br $read)))
+      (if (call $is_word (local.get $text) (i32.sub (local.get $end) (local.get $text)) (string "nan"))
+        (then
+          (local.set $number (f64.const nan))
+          (br $read)))
+
+      ;; The digits go into $big_r, up to 800 of them. Leaving out the rest
+      ;; cannot change the result, as long as it is known whether they were
+      ;; all zeros.
+      (call $big_set (global.get $big_r) (i64.const 0))
+      (block $done
+        (loop $next
+          (br_if $done (i32.ge_u (local.get $text) (local.get $end)))
+          (local.set $byte (i32.load8_u (local.get $text)))
+          (local.set $text (i32.add (local.get $text) (i32.const 1)))
+          (if (i32.and (i32.eq (local.get $byte) (i32.const 46)) (i32.eqz (local.get $fraction)))
+            (then
+              (local.set $fraction (i32.const 1))
+              (br $next)))
+          (local.set $byte (i32.sub (local.get $byte) (i32.const 48)))
+          (if (i32.gt_u (local.get $byte) (i32.const 9))
+            (then
+              (local.set $text (i32.sub (local.get $text) (i32.const 1)))
+              (br $done)))
+          (local.set $digits (i32.add (local.get $digits) (i32.const 1)))
+          (if (i32.and (i32.eqz (i32.load (global.get $big_r))) (i32.eqz (local.get $byte)))
+            (then
+              ;; Leading zeros only move the point
+              (local.set $exponent (i32.sub (local.get $exponent) (local.get $fraction))))
+            (else
+              (if (i32.lt_u (local.get $kept) (i32.const 800))
+                (then
+                  (call $big_mul_add (global.get $big_r) (i32.const 10) (local.get $byte))
+                  (local.set $kept (i32.add (local.get $kept) (i32.const 1)))
+                  (local.set $exponent (i32.sub (local.get $exponent) (local.get $fraction))))
+                (else
+                  (local.set $sticky (i32.or (local.get $sticky) (i32.ne (local.get $byte) (i32.const 0))))
+                  (local.set $exponent
+                    (i32.add (local.get $exponent) (i32.eqz (local.get $fraction))))))))
+          (br $next)))
+      (if (i32.eqz (local.get $digits))
+        (then (return (i32.const 0))))
+
+      (if (i32.lt_u (local.get $text) (local.get $end))
+        (then
+          (if (i32.ne (i32.or (i32.load8_u (local.get $text)) (i32.const 0x20)) (i32.const 101))
+            (then (return (i32.const 0))))
+          (local.set $text (i32.add (local.get $text) (i32.const 1)))
+          (if (i32.lt_u (local.get $text) (local.get $end))
+            (then
+              (local.set $byte (i32.load8_u (local.get $text)))
+              (if (i32.or (i32.eq (local.get $byte) (i32.const 43)) (i32.eq (local.get $byte) (i32.const 45)))
+                (then
+                  (local.set $explicit_negative (i32.eq (local.get $byte) (i32.const 45)))
+                  (local.set $text (i32.add (local.get $text) (i32.const 1)))))))
+          (block $done
+            (loop $next
+              (br_if $done (i32.ge_u (local.get $text) (local.get $end)))
+              (local.set $byte (i32.sub (i32.load8_u (local.get $text)) (i32.const 48)))
+              (if (i32.gt_u (local.get $byte) (i32.const 9))
+                (then (return (i32.const 0))))
+              (local.set $explicit (i32.add (i32.mul (local.get $explicit) (i32.const 10)) (local.get $byte)))
+              ;; Anything this far out is zero or infinity anyway
+              (if (i32.gt_u (local.get $explicit) (i32.const 100000))
+                (then (local.set $explicit (i32.const 100000))))
+              (local.set $explicit_digits (i32.const 1))
+              (local.set $text (i32.add (local.get $text) (i32.const 1)))
+              (br $next)))
+          (if (i32.eqz (local.get $explicit_digits))
+            (then (return (i32.const 0))))
+          (local.set $exponent
+            (i32.add (local.get $exponent)
+              (select (i32.sub (i32.const 0) (local.get $explicit)) (local.get $explicit)
+                      (local.get $explicit_negative))))))
+      (local.set $number (call $decimal_to_number (local.get $kept) (local.get $exponent) (local.get $sticky))))
+
+    (global.set $parsed
+      (select (f64.neg (local.get $number)) (local.get $number) (local.get $negative)))
+    (i32.const 1))
+
+  ;; The double nearest to $big_r * 10^exponent, where $big_r has `kept`
+  ;; digits and `sticky` says whether digits that were left out were not all
+  ;; zeros. Ties go to the even mantissa.
+  (func $decimal_to_number (param $kept i32) (param $exponent i32) (param $sticky i32) (result f64)
+    (local $power f64)
+    (local $i i32)
+    (local $shift i32)
+    (local $quotient i64)
+    (local $lsb i32)
+    (local $drop i32)
+    (local $mantissa i64)
+    (local $rest i64)
+    (local $half i64)
+    (if (i32.eqz (i32.load (global.get $big_r)))
+      (then (return (f64.const 0))))
+    (if (i32.gt_s (i32.add (local.get $kept) (local.get $exponent)) (i32.const 310))
+      (then (return (f64.const inf))))
+    (if (i32.lt_s (i32.add (local.get $kept) (local.get $exponent)) (i32.const -343))
+      (then (return (f64.const 0))))
+
+    ;; Both the digits and the power of ten are exact doubles, so a single
+    ;; rounding gives the right answer
+    (if (i32.and (i32.and (i32.eqz (local.get $sticky))
+                          (i32.le_u (call $big_bits (global.get $big_r)) (i32.const 53)))
+                 (i32.le_u (i32.add (local.get $exponent) (i32.const 22)) (i32.const 44)))
+      (then
+        (local.set $power (f64.const 1))
+        (local.set $i (select (i32.sub (i32.const 0) (local.get $exponent)) (local.get $exponent)
+                              (i32.lt_s (local.get $exponent) (i32.const 0))))
+        (block $done
+          (loop $next
+            (br_if $done (i32.eqz (local.get $i)))
+            (local.set $power (f64.mul (local.get $power) (f64.const 10)))
+            (local.set $i (i32.sub (local.get $i) (i32.const 1)))
+            (br $next)))
+        (local.set $quotient
+          (i64.or (call $big_get (global.get $big_r) (i32.const 0))
+                  (i64.shl (call $big_get (global.get $big_r) (i32.const 1)) (i64.const 32))))
+        (if (i32.lt_s (local.get $exponent) (i32.const 0))
+          (then (return (f64.div (f64.convert_i64_u (local.get $quotient)) (local.get $power)))))
+        (return (f64.mul (f64.convert_i64_u (local.get $quotient)) (local.get $power)))))
+
+    ;; Otherwise the number is the fraction $big_r / $big_s, scaled by a
+    ;; power of two so that its whole part has 55 or 56 bits
+    (call $big_set (global.get $big_s) (i64.const 1))
+    (if (i32.ge_s (local.get $exponent) (i32.const 0))
+      (then (call $big_mul_pow10 (global.get $big_r) (local.get $exponent)))
+      (else (call $big_mul_pow10 (global.get $big_s) (i32.sub (i32.const 0) (local.get $exponent)))))
+    (local.set $shift
+      (i32.sub (i32.const 55)
+               (i32.sub (call $big_bits (global.get $big_r)) (call $big_bits (global.get $big_s)))))
+    (if (i32.ge_s (local.get $shift) (i32.const 0))
+      (then (call $big_shl (global.get $big_r) (local.get $shift)))
+      (else (call $big_shl (global.get $big_s) (i32.sub (i32.const 0) (local.get $shift)))))
+
+    ;; Long division, one bit at a time
+    (local.set $i (i32.const 57))
+    (loop $next
+      (local.set $i (i32.sub (local.get $i) (i32.const 1)))
+      (call $big_copy (global.get $big_t) (global.get $big_s))
+      (call $big_shl (global.get $big_t) (local.get $i))
+      (if (i32.ge_s (call $big_cmp (global.get $big_r) (global.get $big_t)) (i32.const 0))
+        (then
+          (call $big_sub (global.get $big_r) (global.get $big_t))
+          (local.set $quotient
+            (i64.or (local.get $quotient) (i64.shl (i64.const 1) (i64.extend_i32_u (local.get $i)))))))
+      (br_if $next (local.get $i)))
+    (local.set $sticky (i32.or (local.get $sticky) (i32.ne (i32.load (global.get $big_r)) (i32.const 0))))
+
+    ;; Keeps 53 bits, or fewer for numbers below the normal range
+    (local.set $lsb
+      (i32.sub (i32.sub (i32.sub (i32.const 64) (i32.wrap_i64 (i64.clz (local.get $quotient))))
+                        (i32.const 53))
+               (local.get $shift)))
+    (if (i32.lt_s (local.get $lsb) (i32.const -1074))
+      (then (local.set $lsb (i32.const -1074))))
+    (local.set $drop (i32.add (local.get $lsb) (local.get $shift)))
+    (if (i32.ge_u (local.get $drop) (i32.const 64))
+      (then (return (f64.const 0))))
+    (local.set $mantissa (i64.shr_u (local.get $quotient) (i64.extend_i32_u (local.get $drop))))
+    (local.set $rest
+      (i64.and (local.get $quotient)
+               (i64.sub (i64.shl (i64.const 1) (i64.extend_i32_u (local.get $drop))) (i64.const 1))))
+    (local.set $half (i64.shl (i64.const 1) (i64.extend_i32_u (i32.sub (local.get $drop) (i32.const 1)))))
+    (if (i32.or (i64.gt_u (local.get $rest) (local.get $half))
+                (i32.and (i64.eq (local.get $rest) (local.get $half))
+                         (i32.or (local.get $sticky)
+                                 (i32.wrap_i64 (i64.and (local.get $mantissa) (i64.const 1))))))
+      (then (local.set $mantissa (i64.add (local.get $mantissa) (i64.const 1)))))
+
+    ;; mantissa * 2^lsb, in two steps when 2^lsb is below the normal range
+    (if (i32.lt_s (local.get $lsb) (i32.const -1022))
+      (then
+        (return
+          (f64.mul (f64.mul (f64.convert_i64_u (local.get $mantissa))
+                            (call $power_of_two (i32.add (local.get $lsb) (i32.const 512))))
+                   (call $power_of_two (i32.const -512))))))
+    (f64.mul (f64.convert_i64_u (local.get $mantissa)) (call $power_of_two (local.get $lsb))))
+
+  (func $power_of_two (param $exponent i32) (result f64)
+    (f64.reinterpret_i64
+      (i64.shl (i64.extend_i32_s (i32.add (local.get $exponent) (i32.const 1023))) (i64.const 52))))
+
+  ;; Moves on to the next line of input, failing once input is over
+  (func $next_line (param $line i32) (param $column i32)
+    (local $len i32)
+    (global.set $input_text (call $alloc (i32.const 4096)))
+    (local.set $len (call $host_input (global.get $input_text) (i32.const 4096)))
+    (if (i32.lt_s (local.get $len) (i32.const 0))
+      (then
+        (call $fail (code EmptyCustomInputStack) (message EmptyCustomInputStack)
+          (local.get $line) (local.get $column))))
+    (global.set $input_len (local.get $len))
+    (global.set $input_position (i32.const 0)))
+
+  ;; Skips the spaces at the input position, returning whether anything but
+  ;; spaces is left on the line
+  (func $input_rest (result i32)
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_u (global.get $input_position) (global.get $input_len)))
+        (br_if $done (i32.eqz (call $is_space
+          (i32.load8_u (i32.add (global.get $input_text) (global.get $input_position))))))
+        (global.set $input_position (i32.add (global.get $input_position) (i32.const 1)))
+        (br $next)))
+    (i32.lt_u (global.get $input_position) (global.get $input_len)))
+
+  ;; The next word of input, whichever line it is on
+  (func $input_word (param $line i32) (param $column i32) (result i32)
+    (local $start i32)
+    (block $found
+      (loop $next
+        (br_if $found (call $input_rest))
+        (call $next_line (local.get $line) (local.get $column))
+        (br $next)))
+    (local.set $start (global.get $input_position))
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_u (global.get $input_position) (global.get $input_len)))
+        (br_if $done (call $is_space
+          (i32.load8_u (i32.add (global.get $input_text) (global.get $input_position)))))
+        (global.set $input_position (i32.add (global.get $input_position) (i32.const 1)))
+        (br $next)))
+    (call $string (i32.add (global.get $input_text) (local.get $start))
+                  (i32.sub (global.get $input_position) (local.get $start))))
+
+  ;; The rest of the line the last word was read from, or the whole next
+  ;; line when nothing but spaces is left there
+  (func $input_line (param $line i32) (param $column i32) (result i32)
+    (local $start i32)
+    (if (i32.eqz (call $input_rest))
+      (then (call $next_line (local.get $line) (local.get $column))))
+    (local.set $start (global.get $input_position))
+    (global.set $input_position (global.get $input_len))
+    (call $string (i32.add (global.get $input_text) (local.get $start))
+                  (i32.sub (global.get $input_len) (local.get $start))))
+
+  (func $input_number (param $line i32) (param $column i32) (result i32)
+    (if (i32.eqz (call $parse_number (call $input_word (local.get $line) (local.get $column))))
+      (then
+        (call $fail (code InvalidInputError) (message InvalidInputError)
+          (local.get $line) (local.get $column))))
+    (call $number (global.get $parsed)))
+
+  ;; ------------------------------------------------------------- builtins
+
+  (func $expect_arguments (param $name i32) (param $expected i32) (param $count i32)
+                          (param $line i32) (param $column i32)
+    (local $detail i32)
+    (if (i32.eq (local.get $expected) (local.get $count))
+      (then (return)))
+    (local.set $detail (call $text (string " '")))
+    (call $buffer_string (local.get $detail) (local.get $name))
+    (call $buffer_string (local.get $detail) (string "' expects "))
+    (call $buffer_integer (local.get $detail) (local.get $expected))
+    (call $buffer_string (local.get $detail) (string " argument(s) but got "))
+    (call $buffer_integer (local.get $detail) (local.get $count))
+    (call $buffer_byte (local.get $detail) (i32.const 46))
+    (call $fail_with (code ArgumentCountMismatch) (message ArgumentCountMismatch)
+      (local.get $detail) (local.get $line) (local.get $column)))
+
+  (func $expect_type (param $name i32) (param $value i32) (param $type i32)
+                     (param $line i32) (param $column i32)
+    (local $detail i32)
+    (if (i32.eq (call $type (local.get $value)) (local.get $type))
+      (then (return)))
+    (local.set $detail (call $text (string " '")))
+    (call $buffer_string (local.get $detail) (local.get $name))
+    (call $buffer_string (local.get $detail) (string "' needs a "))
+    (call $buffer_string (local.get $detail)
+      (select (string "list") (select (string "map") (string "string") (i32.eq (local.get $type) (i32.const 4)))
+              (i32.eq (local.get $type) (i32.const 3))))
+    (call $buffer_string (local.get $detail) (string ", not a "))
+    (call $buffer_string (local.get $detail) (call $type_name (local.get $value)))
+    (call $buffer_byte (local.get $detail) (i32.const 46))
+    (call $fail_with (code IncompatibleDataType) (message IncompatibleDataType)
+      (local.get $detail) (local.get $line) (local.get $column)))
+
+  ;; Length in bytes of the UTF-8 character starting with `byte`
+  (func $char_width (param $byte i32) (result i32)
+    (if (i32.lt_u (local.get $byte) (i32.const 0x80)) (then (return (i32.const 1))))
+    (if (i32.lt_u (local.get $byte) (i32.const 0xe0)) (then (return (i32.const 2))))
+    (if (i32.lt_u (local.get $byte) (i32.const 0xf0)) (then (return (i32.const 3))))
+    (i32.const 4))
+
+  ;; The character whose UTF-8 bytes start at `at`
+  (func $decode_char (param $at i32) (result i32)
+    (local $width i32)
+    (local $char i32)
+    (local $i i32)
+    (local.set $width (call $char_width (i32.load8_u (local.get $at))))
+    (if (i32.eq (local.get $width) (i32.const 1))
+      (then (return (i32.load8_u (local.get $at)))))
+    (local.set $char
+      (i32.and (i32.load8_u (local.get $at)) (i32.shr_u (i32.const 0x7f) (local.get $width))))
+    (local.set $i (i32.const 1))
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_u (local.get $i) (local.get $width)))
+        (local.set $char
+          (i32.or (i32.shl (local.get $char) (i32.const 6))
+                  (i32.and (i32.load8_u (i32.add (local.get $at) (local.get $i))) (i32.const 0x3f))))
+        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+        (br $next)))
+    (local.get $char))
+
+  ;; Adds the UTF-8 bytes of a character
+  (func $buffer_char (param $buffer i32) (param $char i32)
+    (local $width i32)
+    (local $at i32)
+    (local $i i32)
+    (local.set $width
+      (select (i32.const 1)
+        (select (i32.const 2)
+          (select (i32.const 3) (i32.const 4) (i32.lt_u (local.get $char) (i32.const 0x10000)))
+          (i32.lt_u (local.get $char) (i32.const 0x800)))
+        (i32.lt_u (local.get $char) (i32.const 0x80))))
+    (local.set $at (call $buffer_reserve (local.get $buffer) (local.get $width)))
+    (if (i32.eq (local.get $width) (i32.const 1))
+      (then
+        (i32.store8 (local.get $at) (local.get $char))
+        (return)))
+    (local.set $i (i32.sub (local.get $width) (i32.const 1)))
+    (block $done
+      (loop $next
+        (br_if $done (i32.eqz (local.get $i)))
+        (i32.store8 (i32.add (local.get $at) (local.get $i))
+          (i32.or (i32.const 0x80) (i32.and (local.get $char) (i32.const 0x3f))))
+        (local.set $char (i32.shr_u (local.get $char) (i32.const 6)))
+        (local.set $i (i32.sub (local.get $i) (i32.const 1)))
+        (br $next)))
+    (i32.store8 (local.get $at)
+      (i32.or (i32.and (i32.shr_u (i32.const 0xff00) (local.get $width)) (i32.const 0xff))
+              (local.get $char))))
+
+  ;; Address of the row of a case table whose run could hold `char`: the last
+  ;; one starting at or before it, or 0. Rows are `size` bytes long.
+  (func $case_row (param $table i32) (param $count i32) (param $size i32) (param $char i32)
+                  (result i32)
+    (local $low i32)
+    (local $high i32)
+    (local $middle i32)
+    (local.set $high (local.get $count))
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_u (local.get $low) (local.get $high)))
+        (local.set $middle (i32.shr_u (i32.add (local.get $low) (local.get $high)) (i32.const 1)))
+        (if (i32.le_u (i32.load (i32.add (local.get $table) (i32.mul (local.get $middle) (local.get $size))))
+                      (local.get $char))
+          (then (local.set $low (i32.add (local.get $middle) (i32.const 1))))
+          (else (local.set $high (local.get $middle))))
+        (br $next)))
+    (if (result i32) (i32.eqz (local.get $low))
+      (then (i32.const 0))
+      (else (i32.add (local.get $table)
+                     (i32.mul (i32.sub (local.get $low) (i32.const 1)) (local.get $size))))))
+
+  ;; Whether `char` is in a table of runs of characters, first and last
+  (func $in_runs (param $table i32) (param $count i32) (param $char i32) (result i32)
+    (local $row i32)
+    (local.set $row (call $case_row (local.get $table) (local.get $count) (i32.const 8) (local.get $char)))
+    (if (result i32) (i32.eqz (local.get $row))
+      (then (i32.const 0))
+      (else (i32.le_u (local.get $char) (i32.load offset=4 (local.get $row))))))
+
+  ;; Whether the first character before byte `at` of `data` that case does not
+  ;; ignore is a cased one
+  (func $cased_before (param $data i32) (param $at i32) (result i32)
+    (local $char i32)
+    (block $done
+      (loop $next
+        (br_if $done (i32.eqz (local.get $at)))
+        (loop $continuation
+          (local.set $at (i32.sub (local.get $at) (i32.const 1)))
+          (br_if $continuation
+            (i32.and (i32.ne (local.get $at) (i32.const 0))
+                     (i32.eq (i32.and (i32.load8_u (i32.add (local.get $data) (local.get $at))) (i32.const 0xc0))
+                             (i32.const 0x80)))))
+        (local.set $char (call $decode_char (i32.add (local.get $data) (local.get $at))))
+        (if (i32.eqz (call $in_runs (global.get $case_ignorable) (global.get $case_ignorable_count)
+                                    (local.get $char)))
+          (then (return (call $in_runs (global.get $cased) (global.get $cased_count) (local.get $char)))))
+        (br $next)))
+    (i32.const 0))
+
+  ;; Whether the first character from `at` on, up to `end`, that case does
+  ;; not ignore is a cased one
+  (func $cased_after (param $at i32) (param $end i32) (result i32)
+    (local $char i32)
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_u (local.get $at) (local.get $end)))
+        (local.set $char (call $decode_char (local.get $at)))
+        (local.set $at (i32.add (local.get $at) (call $char_width (i32.load8_u (local.get $at)))))
+        (if (i32.eqz (call $in_runs (global.get $case_ignorable) (global.get $case_ignorable_count)
+                                    (local.get $char)))
+          (then (return (call $in_runs (global.get $cased) (global.get $cased_count) (local.get $char)))))
+        (br $next)))
+    (i32.const 0))
+
+  ;; Adds a character in upper or lower case, which can be several characters
+  (func $change_char (param $buffer i32) (param $char i32) (param $upper i32)
+    (local $row i32)
+    (local $offset i32)
+    (local.set $row
+      (call $case_row
+        (select (global.get $upper_special) (global.get $lower_special) (local.get $upper))
+        (select (global.get $upper_special_count) (global.get $lower_special_count) (local.get $upper))
+        (i32.const 16) (local.get $char)))
+    (if (i32.ne (local.get $row) (i32.const 0))
+      (then
+        (if (i32.eq (i32.load (local.get $row)) (local.get $char))
+          (then
+            (local.set $offset (i32.const 4))
+            (block $done
+              (loop $next
+                (br_if $done (i32.eq (local.get $offset) (i32.const 16)))
+                (local.set $char (i32.load (i32.add (local.get $row) (local.get $offset))))
+                (br_if $done (i32.eqz (local.get $char)))
+                (call $buffer_char (local.get $buffer) (local.get $char))
+                (local.set $offset (i32.add (local.get $offset) (i32.const 4)))
+                (br $next)))
+            (return)))))
+    (local.set $row
+      (call $case_row
+        (select (global.get $upper_runs) (global.get $lower_runs) (local.get $upper))
+        (select (global.get $upper_runs_count) (global.get $lower_runs_count) (local.get $upper))
+        (i32.const 16) (local.get $char)))
+    (if (i32.ne (local.get $row) (i32.const 0))
+      (then
+        (if (i32.le_u (local.get $char) (i32.load offset=4 (local.get $row)))
+          (then
+            (if (i32.eqz (i32.rem_u (i32.sub (local.get $char) (i32.load (local.get $row)))
+                                    (i32.load offset=12 (local.get $row))))
+              (then (local.set $char (i32.add (local.get $char) (i32.load offset=8 (local.get $row))))))))))
+    (call $buffer_char (local.get $buffer) (local.get $char)))
+
+  ;; A copy of a string in upper or lower case, the way Rust's to_uppercase
+  ;; and to_lowercase do it
+  (func $change_case (param $string i32) (param $upper i32) (result i32)
+    (local $buffer i32)
+    (local $data i32)
+    (local $end i32)
+    (local $at i32)
+    (local $next i32)
+    (local $char i32)
+    (local.set $buffer (call $buffer))
+    (local.set $data (i32.load offset=8 (local.get $string)))
+    (local.set $end (i32.add (local.get $data) (call $len (local.get $string))))
+    (local.set $at (local.get $data))
+    (block $done
+      (loop $each
+        (br_if $done (i32.ge_u (local.get $at) (local.get $end)))
+        (local.set $char (call $decode_char (local.get $at)))
+        (local.set $next (i32.add (local.get $at) (call $char_width (i32.load8_u (local.get $at)))))
+        ;; A capital sigma ending a word becomes the final sigma
+        (if (i32.and (i32.eqz (local.get $upper)) (i32.eq (local.get $char) (i32.const 0x3a3)))
+          (then
+            (if (i32.and (call $cased_before (local.get $data) (i32.sub (local.get $at) (local.get $data)))
+                         (i32.eqz (call $cased_after (local.get $next) (local.get $end))))
+              (then (local.set $char (i32.const 0x3c2))))))
+        (call $change_char (local.get $buffer) (local.get $char) (local.get $upper))
+        (local.set $at (local.get $next))
+        (br $each)))
+    (call $buffer_finish (local.get $buffer)))
+
+  ;; Character position of the first `needle` in `text`, or -1
+  (func $find_text (param $text i32) (param $needle i32) (result f64)
+    (local $start i32)
+    (local $last i32)
+    (local.set $last (i32.sub (call $len (local.get $text)) (call $len (local.get $needle))))
+    (block $done
+      (loop $next
+        (br_if $done (i32.gt_s (local.get $start) (local.get $last)))
+        (if (call $bytes_equal (i32.add (i32.load offset=8 (local.get $text)) (local.get $start))
+                               (i32.load offset=8 (local.get $needle)) (call $len (local.get $needle)))
+          (then
+            (return
+              (f64.convert_i32_u
+                (call $char_count
+                  (call $string (i32.load offset=8 (local.get $text)) (local.get $start)))))))
+        (local.set $start (i32.add (local.get $start) (i32.const 1)))
+        (br $next)))
+    (f64.const -1))
+
+  (func $bytes_equal (param $left i32) (param $right i32) (param $len i32) (result i32)
+    (local $i i32)
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_u (local.get $i) (local.get $len)))
+        (if (i32.ne (i32.load8_u (i32.add (local.get $left) (local.get $i)))
+                    (i32.load8_u (i32.add (local.get $right) (local.get $i))))
+          (then (return (i32.const 0))))
+        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+        (br $next)))
+    (i32.const 1))
+
+  ;; Removes entry or element `position` of a map or list, giving it back
+  (func $remove (param $collection i32) (param $position i32) (result i32)
+    (local $removed i32)
+    (local $size i32)
+    (local.set $removed (call $element (local.get $collection) (local.get $position)))
+    (local.set $size
+      (i32.sub (call $element (local.get $collection) (i32.add (local.get $position) (i32.const 1)))
+               (local.get $removed)))
+    (local.set $removed
+      (if (result i32) (i32.eq (call $type (local.get $collection)) (i32.const 4))
+        (then (i32.load offset=4 (local.get $removed)))
+        (else (i32.load (local.get $removed)))))
+    (memory.copy (call $element (local.get $collection) (local.get $position))
+                 (call $element (local.get $collection) (i32.add (local.get $position) (i32.const 1)))
+                 (i32.mul (local.get $size)
+                          (i32.sub (i32.sub (call $len (local.get $collection)) (local.get $position))
+                                   (i32.const 1))))
+    (i32.store offset=4 (local.get $collection)
+      (i32.sub (call $len (local.get $collection)) (i32.const 1)))
+    (local.get $removed))
+
+  ;; Runs built-in function number `builtin` on the `count` arguments at
+  ;; `args`. The numbers follow the order of the names in the compiler.
+  (func $call_builtin (param $builtin i32) (param $name i32) (param $count i32) (param $args i32)
+                      (param $line i32) (param $column i32) (result i32)
+    (local $first i32)
+    (local $second i32)
+    (local $detail i32)
+    (local $position i32)
+    (local $keys i32)
+    (local $i i32)
+    (call $expect_arguments (local.get $name)
+      (select (i32.const 2) (i32.const 1) (i32.lt_u (i32.sub (local.get $builtin) (i32.const 5)) (i32.const 4)))
+      (local.get $count) (local.get $line) (local.get $column))
+    (local.set $first (i32.load (local.get $args)))
+    (if (i32.eq (local.get $count) (i32.const 2))
+      (then (local.set $second (i32.load offset=4 (local.get $args)))))
+
+    (block $chabiyan
+      (block $hai_kya
+        (block $hatao
+          (block $jodo
+            (block $khojo
+              (block $chhota
+                (block $bada
+                  (block $ank
+                    (block $akshar
+                      (block $lambai
+                        (br_table $lambai $akshar $ank $bada $chhota $khojo $jodo $hatao $hai_kya $chabiyan
+                          (local.get $builtin)))
+                      ;; LAMBAI
+                      (if (i32.ge_u (call $type (local.get $first)) (i32.const 3))
+                        (then (return (call $number (f64.convert_i32_u (call $len (local.get $first)))))))
+                      (call $expect_type (local.get $name) (local.get $first) (i32.const 2)
+                        (local.get $line) (local.get $column))
+                      (return (call $number (f64.convert_i32_u (call $char_count (local.get $first))))))
+                    ;; AKSHAR
+                    (return (call $to_string (local.get $first))))
+                  ;; ANK
+                  (if (i32.eqz (call $type (local.get $first)))
+                    (then (return (local.get $first))))
+                  (if (i32.ne (call $type (local.get $first)) (i32.const 2))
+                    (then
+                      (local.set $detail (call $text (string " A ")))
+                      (call $buffer_string (local.get $detail) (call $type_name (local.get $first)))
+                      (call $buffer_string (local.get $detail) (string " cannot be turned into a number."))
+                      (call $fail_with (code IncompatibleDataType) (message IncompatibleDataType)
+                        (local.get $detail) (local.get $line) (local.get $column))))
+                  (if (call $parse_number (local.get $first))
+                    (then (return (call $number (global.get $parsed)))))
+                  (local.set $detail (call $text (string " \"")))
+                  (call $buffer_string (local.get $detail) (local.get $first))
+                  (call $buffer_string (local.get $detail) (string "\" is not a number."))
+                  (call $fail_with (code IncompatibleDataType) (message IncompatibleDataType)
+                    (local.get $detail) (local.get $line) (local.get $column))
+                  (return (i32.const 0)))
+                ;; BADA
+                (call $expect_type (local.get $name) (local.get $first) (i32.const 2)
+                  (local.get $line) (local.get $column))
+                (return (call $change_case (local.get $first) (i32.const 1))))
+              ;; CHHOTA
+              (call $expect_type (local.get $name) (local.get $first) (i32.const 2)
+                (local.get $line) (local.get $column))
+              (return (call $change_case (local.get $first) (i32.const 0))))
+            ;; KHOJO
+            (call $expect_type (local.get $name) (local.get $first) (i32.const 2)
+              (local.get $line) (local.get $column))
+            (call $expect_type (local.get $name) (local.get $second) (i32.const 2)
+              (local.get $line) (local.get $column))
+            (return (call $number (call $find_text (local.get $first) (local.get $second)))))
+          ;; JODO
+          (call $expect_type (local.get $name) (local.get $first) (i32.const 3)
+            (local.get $line) (local.get $column))
+          (return (call $list_push (local.get $first) (local.get $second))))
+        ;; HATAO
+        (if (i32.eq (call $type (local.get $first)) (i32.const 4))
+          (then
+            (local.set $second (call $map_key (local.get $second) (local.get $line) (local.get $column)))
+            (local.set $position (call $map_find (local.get $first) (local.get $second)))
+            (if (i32.eqz (global.get $found))
+              (then (call $fail_missing_key (local.get $second) (local.get $line) (local.get $column))))
+            (return (call $remove (local.get $first) (local.get $position)))))
+        (call $expect_type (local.get $name) (local.get $first) (i32.const 3)
+          (local.get $line) (local.get $column))
+        (return
+          (call $remove (local.get $first)
+            (call $index_position (local.get $second) (call $len (local.get $first))
+              (local.get $line) (local.get $column)))))
+      ;; HAI_KYA
+      (call $expect_type (local.get $name) (local.get $first) (i32.const 4)
+        (local.get $line) (local.get $column))
+      (drop (call $map_find (local.get $first)
+              (call $map_key (local.get $second) (local.get $line) (local.get $column))))
+      (return (call $bool (global.get $found))))
+    ;; CHABIYAN
+    (call $expect_type (local.get $name) (local.get $first) (i32.const 4)
+      (local.get $line) (local.get $column))
+    (local.set $keys (call $new_list))
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_u (local.get $i) (call $len (local.get $first))))
+        (drop (call $list_push (local.get $keys)
+                (i32.load (call $element (local.get $first) (local.get $i)))))
+        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+        (br $next)))
+    (local.get $keys))
+
+  ;; ------------------------------------------------------------ functions
+
+  ;; Each symbol has a function record: its index in $functions plus one (0
+  ;; while no function has the name), its number of parameters, and where
+  ;; the symbols of its parameters are
+  (func $record (param $symbol i32) (result i32)
+    (i32.add (global.get $function_records) (i32.mul (local.get $symbol) (i32.const 12))))
+
+  ;; Redefinition of functions is allowed, just like variables
+  (func $define (param $symbol i32) (param $index i32) (param $arity i32) (param $parameters i32)
+    (i32.store (call $record (local.get $symbol)) (i32.add (local.get $index) (i32.const 1)))
+    (i32.store offset=4 (call $record (local.get $symbol)) (local.get $arity))
+    (i32.store offset=8 (call $record (local.get $symbol)) (local.get $parameters)))
+
+  ;; Checks a call to a program-defined function before its arguments are
+  ;; worked out. Builtins only check their arguments once they are worked out.
+  (func $prepare_call (param $symbol i32) (param $count i32) (param $line i32) (param $column i32)
+    (if (i32.eqz (i32.load (call $record (local.get $symbol))))
+      (then (return)))
+    (call $expect_arguments (call $name (local.get $symbol))
+      (i32.load offset=4 (call $record (local.get $symbol))) (local.get $count)
+      (local.get $line) (local.get $column))
+    (if (i32.ge_s (global.get $call_depth) (global.get $max_call_depth))
+      (then
+        (call $fail (code MaxCallDepthExceeded) (message MaxCallDepthExceeded)
+          (local.get $line) (local.get $column)))))
+
+  ;; Arguments wait in $arguments until the call they belong to is made
+  (func $push_arg (param $value i32)
+    (call $vector_push (global.get $arguments) (local.get $value)))
+
+  ;; Calls the function named by `symbol` with the last `count` arguments
+  ;; pushed. `builtin` is the number of the builtin with that name, or -1.
+  (func $call (param $symbol i32) (param $count i32) (param $builtin i32) (param $wants_value i32)
+              (param $line i32) (param $column i32) (result i32)
+    (local $first i32)
+    (local $record i32)
+    (local $i i32)
+    (local $result i32)
+    (local $detail i32)
+    (local.set $first (i32.sub (i32.load (global.get $arguments)) (local.get $count)))
+    (local.set $record (call $record (local.get $symbol)))
+    (if (i32.eqz (i32.load (local.get $record)))
+      (then
+        (if (i32.lt_s (local.get $builtin) (i32.const 0))
+          (then
+            (local.set $detail (call $text (string "Undefined function! 'Kaun hai ye ")))
+            (call $buffer_string (local.get $detail) (call $name (local.get $symbol)))
+            (call $buffer_string (local.get $detail) (string "? Kahan se aate hain ye log?'"))
+            (call $fail (code UndefinedFunction) (call $buffer_finish (local.get $detail))
+              (local.get $line) (local.get $column))))
+        (local.set $result
+          (call $call_builtin (local.get $builtin) (call $name (local.get $symbol)) (local.get $count)
+            (call $vector_slot (global.get $arguments) (local.get $first))
+            (local.get $line) (local.get $column)))
+        (i32.store (global.get $arguments) (local.get $first))
+        (return (local.get $result))))
+
+    (call $push_frame)
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_u (local.get $i) (local.get $count)))
+        (call $declare
+          (i32.load (i32.add (i32.load offset=8 (local.get $record)) (i32.shl (local.get $i) (i32.const 2))))
+          (call $vector_get (global.get $arguments) (i32.add (local.get $first) (local.get $i))))
+        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+        (br $next)))
+    (i32.store (global.get $arguments) (local.get $first))
+    (global.set $call_depth (i32.add (global.get $call_depth) (i32.const 1)))
+    (local.set $result
+      (call_indirect $functions (type $function)
+        (i32.sub (i32.load (local.get $record)) (i32.const 1))))
+    (global.set $call_depth (i32.sub (global.get $call_depth) (i32.const 1)))
+    (call $pop_frame)
+
+    (if (i32.eq (local.get $result) (i32.const 8))
+      (then
+        ;; Loops never reach across a function call
+        (call $fail (code LoopControlOutsideLoop) (message LoopControlOutsideLoop)
+          (local.get $line) (local.get $column))))
+    (if (i32.and (local.get $wants_value) (i32.eqz (local.get $result)))
+      (then
+        (local.set $detail (call $text (string " '")))
+        (call $buffer_string (local.get $detail) (call $name (local.get $symbol)))
+        (call $buffer_string (local.get $detail) (string "' did not return anything."))
+        (call $fail_with (code MissingReturnValue) (message MissingReturnValue)
+          (local.get $detail) (local.get $line) (local.get $column))))
+    (local.get $result))
+
+  (func $start
+    (global.set $function_records (call $alloc (i32.mul (global.get $symbol_count) (i32.const 12))))
+    (global.set $expired (call $alloc (global.get $symbol_count)))
+    (global.set $bindings (call $vector))
+    (global.set $scopes (call $vector))
+    (global.set $frames (call $vector))
+    (global.set $arguments (call $vector))
+    (global.set $big_r (call $big_new))
+    (global.set $big_s (call $big_new))
+    (global.set $big_plus (call $big_new))
+    (global.set $big_minus (call $big_new))
+    (global.set $big_t (call $big_new))
+    (call $push_frame))
@@ -0,0 +1,893 @@
+use super::case::CASE_TABLES;
+use super::{RUNTIME_ERRORS, changes_case, input_name};
+use crate::error::ParseError;
+use crate::evaluator::constants::{MAX_CALL_DEPTH, MAX_ITER_COUNT};
+use crate::evaluator::interpreter::InterpreterErrorType::{
+    InvalidLoopStep, LoopControlOutsideLoop, MaxLoopsExceeded, MissingStartSymbol,
+    ReturnOutsideFunction,
+};
+use crate::evaluator::interpreter::{InterpreterError, InterpreterErrorType};
+use crate::lexer::span::Span;
+use crate::parser::ast::{Expression, Ident, Infix, Literal, Prefix, Program, Statement};
+use std::collections::HashMap;
+use std::fmt::Write;
+
+/// Values, variables and builtins of compiled programs
+const RUNTIME: &str = include_str!("runtime.wat");
+
+/// Built-in functions, numbered the way the runtime's $call_builtin expects
+const BUILTINS: [&str; 10] = [
+    "LAMBAI", "AKSHAR", "ANK", "BADA", "CHHOTA", "KHOJO", "JODO", "HATAO", "HAI_KYA", "CHABIYAN",
+];
+
+/// Where the program's static data starts. Below it are the runtime's own
+/// values and scratch space.
+const DATA_START: usize = 64;
+
+/// Addresses of the runtime's `false` and `true`
+const FALSE: usize = 16;
+const TRUE: usize = 24;
+
+/// What a function gives back when it ends without a value, or when it is
+/// left by 'BAS KAR PAGLE' or 'JAANE BHI DO'
+const NOTHING: usize = 0;
+const LOOP_CONTROL: usize = 8;
+
+/// Translates a program to a WebAssembly module in the text format, which
+/// any WebAssembly toolchain turns into a binary module
+/// (`wasm-tools parse program.wat -o program.wasm`).
+///
+/// The module imports `env.print`, `env.input` and `env.error` from the
+/// host and exports its `memory` and a `run` function that runs the
+/// program; the runtime describes the imports in detail. It needs the bulk
+/// memory operations, which current WebAssembly engines support by
+/// default. The compiled program prints what the interpreter would print,
+/// and reports runtime errors with the interpreter's type, message and
+/// location.
+pub fn generate(program: &Program) -> Result<String, InterpreterError> {
+    if program.statements.first().map(Statement::unspanned) != Some(&Statement::ProgramStart) {
+        return Err(InterpreterError::new(MissingStartSymbol));
+    }
+
+    let mut generator = Generator::default();
+    generator.block(&program.statements[1..]);
+    let main = std::mem::take(&mut generator.body);
+    Ok(generator.finish(main))
+}
+
+#[derive(Default)]
+struct Generator {
+    symbols: Vec<String>,
+    symbol_ids: HashMap<String, usize>,
+    /// Strings, numbers and tables that the module starts with, placed from
+    /// DATA_START on
+    data: Vec<u8>,
+    strings: HashMap<String, usize>,
+    numbers: HashMap<u64, usize>,
+    /// Definitions of the WebAssembly functions that the program's functions
+    /// became, in the order of the function table
+    functions: Vec<String>,
+    /// Spans of the statements and expressions being translated, innermost
+    /// last
+    spans: Vec<Span>,
+    body: Body,
+    /// Whether the program calls BADA or CHHOTA
+    changes_case: bool,
+}
+
+/// The WebAssembly function being written: the program itself or one of its
+/// functions
+#[derive(Default)]
+struct Body {
+    code: String,
+    indent: usize,
+    locals: Vec<String>,
+    loops_seen: usize,
+    /// Scopes opened since the start of the function (or the program)
+    scope_depth: usize,
+    /// Each enclosing loop, with the scopes open outside of its body
+    loops: Vec<(usize, usize)>,
+    in_function: bool,
+}
+
+impl Generator {
+    fn finish(mut self, main: Body) -> String {
+        let runtime = self.expand_runtime();
+
+        let mut out = String::new();
+        out += ";; Compiled from a Zen program. Build with: wasm-tools parse program.wat -o program.wasm\n";
+        out += "(module\n";
+        out += &runtime;
+
+        let names: Vec<usize> = std::mem::take(&mut self.symbols)
+            .iter()
+            .map(|name| self.string(name))
+            .collect();
+        let symbol_names = self.table(&names);
+        let case_tables = self.case_tables();
+        let heap = align(DATA_START + self.data.len());
+
+        out += "\n  ;; ---------------------------------------------------------- the program\n\n";
+        let _ = writeln!(
+            out,
+            "  (global $max_iter_count i32 (i32.const {}))",
+            MAX_ITER_COUNT
+        );
+        let _ = writeln!(
+            out,
+            "  (global $max_call_depth i32 (i32.const {}))",
+            MAX_CALL_DEPTH
+        );
+        let _ = writeln!(
+            out,
+            "  (global $symbol_count i32 (i32.const {}))",
+            names.len()
+        );
+        let _ = writeln!(
+            out,
+            "  (global $symbol_names i32 (i32.const {}))",
+            symbol_names
+        );
+        out += &case_tables;
+        let _ = writeln!(out, "  (global $heap (mut i32) (i32.const {}))", heap);
+        let _ = writeln!(out, "  (memory (export \"memory\") {})", heap / 65536 + 1);
+        let _ = writeln!(out, "  (table $functions {} funcref)", self.functions.len());
+        if !self.functions.is_empty() {
+            let elements: Vec<String> = (0..self.functions.len())
+                .map(|id| format!("$function{}", id))
+                .collect();
+            let _ = writeln!(
+                out,
+                "  (elem (table $functions) (i32.const 0) func {})",
+                elements.join(" ")
+            );
+        }
+
+        for function in &self.functions {
+            out += "\n";
+            out += function;
+        }
+
+        out += "\n  (func (export \"run\")\n";
+        out += &declare_locals(&main.locals);
+        out += "    (call $start)\n";
+        out += &main.code;
+        out += "  )\n";
+
+        let _ = writeln!(
+            out,
+            "\n  (data (i32.const {}) \"{}\")",
+            DATA_START,
+            data_string(&self.data)
+        );
+        out += ")\n";
+        out
+    }
+
+    /// The runtime with its static strings resolved to addresses
+    fn expand_runtime(&mut self) -> String {
+        let mut out = String::new();
+        for line in RUNTIME.lines() {
+            if line.trim_start().starts_with(";;") {
+                out += line;
+                out += "\n";
+                continue;
+            }
+
+            let mut rest = line;
+            while let Some(start) = ["(string \"", "(code ", "(message "]
+                .iter()
+                .filter_map(|opening| rest.find(opening))
+                .min()
+            {
+                out += &rest[..start];
+                let (text, len) = runtime_text(&rest[start..]);
+                let _ = write!(out, "(i32.const {})", self.string(&text));
+                rest = &rest[start + len..];
+            }
+            out += rest;
+            out += "\n";
+        }
+        out
+    }
+
+    /// Address of a static string value holding `text`
+    fn string(&mut self, text: &str) -> usize {
+        if let Some(address) = self.strings.get(text) {
+            return *address;
+        }
+        let address = self.reserve(12);
+        self.put_word(address, 2);
+        self.put_word(address + 4, text.len());
+        self.put_word(address + 8, address + 12);
+        self.data.extend_from_slice(text.as_bytes());
+        self.strings.insert(text.to_string(), address);
+        address
+    }
+
+    /// Address of a static number value holding `num`
+    fn number(&mut self, num: f64) -> usize {
+        if let Some(address) = self.numbers.get(&num.to_bits()) {
+            return *address;
+        }
+        let address = self.reserve(16);
+        let offset = address - DATA_START + 8;
+        self.data[offset..offset + 8].copy_from_slice(&num.to_le_bytes());
+        self.numbers.insert(num.to_bits(), address);
+        address
+    }
+
+    /// Address of a static array of 32-bit words
+    fn table(&mut self, words: &[usize]) -> usize {
+        let address = self.reserve(4 * words.len());
+        for (i, word) in words.iter().enumerate() {
+            self.put_word(address + 4 * i, *word);
+        }
+        address
+    }
+
+    /// Places the case tables of the runtime's $change_case, left empty when
+    /// the program never changes case, giving back the globals to find them
+    fn case_tables(&mut self) -> String {
+        let tables = self.changes_case.then(|| &*CASE_TABLES);
+        let mut globals = String::new();
+        self.case_table(&mut globals, "upper_runs", tables.map(|t| &t.upper[..]));
+        self.case_table(
+            &mut globals,
+            "upper_special",
+            tables.map(|t| &t.upper_special[..]),
+        );
+        self.case_table(&mut globals, "lower_runs", tables.map(|t| &t.lower[..]));
+        self.case_table(
+            &mut globals,
+            "lower_special",
+            tables.map(|t| &t.lower_special[..]),
+        );
+        self.case_table(&mut globals, "cased", tables.map(|t| &t.cased[..]));
+        self.case_table(
+            &mut globals,
+            "case_ignorable",
+            tables.map(|t| &t.case_ignorable[..]),
+        );
+        globals
+    }
+
+    fn case_table<const N: usize>(
+        &mut self,
+        globals: &mut String,
+        name: &str,
+        rows: Option<&[[u32; N]]>,
+    ) {
+        let rows = rows.unwrap_or_default();
+        let words: Vec<usize> = rows.iter().flatten().map(|word| *word as usize).collect();
+        let address = self.table(&words);
+        let _ = writeln!(globals, "  (global ${} i32 (i32.const {}))", name, address);
+        let _ = writeln!(
+            globals,
+            "  (global ${}_count i32 (i32.const {}))",
+            name,
+            rows.len()
+        );
+    }
+
+    /// Makes room for `len` zeroed bytes of data, giving back their address
+    fn reserve(&mut self, len: usize) -> usize {
+        self.data.resize(align(self.data.len()), 0);
+        let address = DATA_START + self.data.len();
+        self.data.resize(self.data.len() + len, 0);
+        address
+    }
+
+    fn put_word(&mut self, address: usize, word: usize) {
+        let offset = address - DATA_START;
+        self.data[offset..offset + 4].copy_from_slice(&(word as u32).to_le_bytes());
+    }
+
+    fn symbol(&mut self, name: &Ident) -> usize {
+        if let Some(id) = self.symbol_ids.get(&name.0) {
+            return *id;
+        }
+        self.symbols.push(name.0.clone());
+        self.symbol_ids
+            .insert(name.0.clone(), self.symbols.len() - 1);
+        self.symbols.len() - 1
+    }
+
+    fn line(&mut self, text: &str) {
+        let indent = self.body.indent + 2;
+        let _ = writeln!(self.body.code, "{}{}", "  ".repeat(indent), text);
+    }
+
+    fn open(&mut self, text: &str) {
+        self.line(text);
+        self.body.indent += 1;
+    }
+
+    fn close(&mut self, text: &str) {
+        self.line(text);
+        self.body.indent -= 1;
+    }
+
+    /// Where the innermost statement or expression being translated is, as
+    /// the line and column arguments of the runtime's functions
+    fn at(&self) -> String {
+        let (line, column) = self
+            .spans
+            .last()
+            .map_or((0, 0), |span| (span.line, span.column));
+        format!("(i32.const {}) (i32.const {})", line, column)
+    }
+
+    /// Stops the program with the interpreter's own error message
+    fn fail(&mut self, error_type: InterpreterErrorType, at: &str) {
+        let code = self.string(&error_type.to_string());
+        let message = self.string(&InterpreterError::new(error_type).msg);
+        self.line(&format!(
+            "(call $fail (i32.const {}) (i32.const {}) {})",
+            code, message, at
+        ));
+    }
+
+    fn local(&mut self, name: String, value_type: &str) -> String {
+        self.body
+            .locals
+            .push(format!("(local ${} {})", name, value_type));
+        format!("${}", name)
+    }
+
+    fn block(&mut self, statements: &[Statement]) {
+        for statement in statements {
+            // Nothing after 'KHATAM TATA BYE BYE' runs, in any block
+            if let Statement::ProgramEnd = statement.unspanned() {
+                break;
+            }
+            self.statement(statement);
+        }
+    }
+
+    /// Runs a block body in a fresh scope, dropping its variables afterwards
+    fn scoped_block(&mut self, statements: &[Statement]) {
+        self.line("(call $push_scope)");
+        self.body.scope_depth += 1;
+        self.block(statements);
+        self.body.scope_depth -= 1;
+        self.line("(call $pop_scope)");
+    }
+
+    fn statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::Spanned { span, statement } => {
+                self.spans.push(*span);
+                self.statement(statement);
+                self.spans.pop();
+            }
+            Statement::ProgramStart => {
+                let code = self.string("SyntaxError");
+                let message = self.string(
+                    "Only one 'PARAMPARA PRATISHTA ANUSHASHAN' allowed! 'Ek hi baar bolna kaafi hai.'",
+                );
+                let at = self.at();
+                self.line(&format!(
+                    "(call $fail (i32.const {}) (i32.const {}) {})",
+                    code, message, at
+                ));
+            }
+            Statement::ProgramEnd => {}
+            Statement::Let { name, value } => {
+                let value = self.expression(value);
+                let symbol = self.symbol(name);
+                self.line(&format!("(call $assign (i32.const {}) {})", symbol, value));
+            }
+            Statement::LocalLet { name, value } => {
+                let value = self.expression(value);
+                let symbol = self.symbol(name);
+                self.line(&format!("(call $declare (i32.const {}) {})", symbol, value));
+            }
+            Statement::If {
+                condition,
+                consequence,
+                alternative,
+            } => {
+                let condition = self.expression(condition);
+                self.open(&format!("(if (call $is_true {})", condition));
+                self.open("(then");
+                self.scoped_block(consequence);
+                self.close(")");
+                if let Some(alternative) = alternative {
+                    self.open("(else");
+                    self.scoped_block(alternative);
+                    self.close(")");
+                }
+                self.close(")");
+            }
+            Statement::While { condition, body } => self.while_loop(condition, body),
+            Statement::For {
+                variable,
+                start,
+                end,
+                step,
+                body,
+            } => self.for_loop(variable, start, end, step.as_ref(), body),
+            Statement::Print(expr) => {
+                let value = self.expression(expr);
+                self.line(&format!("(call $print {})", value));
+            }
+            Statement::Function {
+                name,
+                parameters,
+                body,
+            } => self.function(name, parameters, body),
+            Statement::Return(expr) => self.return_statement(expr.as_ref()),
+            Statement::Break | Statement::Continue => {
+                self.loop_control(statement == &Statement::Break)
+            }
+            Statement::IndexLet { left, index, value } => {
+                let left = self.expression(left);
+                let index = self.expression(index);
+                let value = self.expression(value);
+                let at = self.at();
+                self.line(&format!(
+                    "(call $set_element {} {} {} {})",
+                    left, index, value, at
+                ));
+            }
+            Statement::Expression(expr) => {
+                let value = match expr.unspanned() {
+                    // A call used as a statement may return nothing
+                    Expression::Call {
+                        function,
+                        arguments,
+                    } => self.call(function, arguments, false),
+                    _ => self.expression(expr),
+                };
+                self.line(&format!("(drop {})", value));
+            }
+            Statement::Invalid(err) => self.invalid_statement(err),
+        }
+    }
+
+    fn while_loop(&mut self, condition: &Expression, body: &[Statement]) {
+        self.body.loops_seen += 1;
+        let id = self.body.loops_seen;
+        let iterations = self.local(format!("iterations{}", id), "i32");
+        let at = self.at();
+
+        self.line(&format!("(local.set {} (i32.const 0))", iterations));
+        self.open(&format!("(block $break{}", id));
+        self.open(&format!("(loop $next{}", id));
+        self.open(&format!(
+            "(if (i32.gt_s (local.get {}) (global.get $max_iter_count))",
+            iterations
+        ));
+        self.open("(then");
+        self.fail(MaxLoopsExceeded, &at);
+        self.close(")");
+        self.close(")");
+        let condition = self.expression(condition);
+        self.line(&format!(
+            "(br_if $break{} (i32.eqz (call $is_true {})))",
+            id, condition
+        ));
+        self.loop_body(id, body);
+        self.line(&format!(
+            "(local.set {0} (i32.add (local.get {0}) (i32.const 1)))",
+            iterations
+        ));
+        self.line(&format!("(br $next{})", id));
+        self.close(")");
+        self.close(")");
+    }
+
+    fn for_loop(
+        &mut self,
+        variable: &Ident,
+        start: &Expression,
+        end: &Expression,
+        step: Option<&Expression>,
+        body: &[Statement],
+    ) {
+        self.body.loops_seen += 1;
+        let id = self.body.loops_seen;
+        let at = self.at();
+
+        // The loop variable lives in its own scope around the body
+        self.line("(call $push_scope)");
+        self.body.scope_depth += 1;
+        let mut bounds = Vec::new();
+        for (name, bound) in [("start", Some(start)), ("end", Some(end)), ("step", step)] {
+            let local = self.local(format!("{}{}", name, id), "f64");
+            let bound = match bound {
+                Some(bound) => {
+                    let bound = self.expression(bound);
+                    format!("(call $loop_bound {} {})", bound, at)
+                }
+                None => "(f64.const 1)".to_string(),
+            };
+            self.line(&format!("(local.set {} {})", local, bound));
+            bounds.push(local);
+        }
+        let [start, end, step] = [&bounds[0], &bounds[1], &bounds[2]];
+        self.open(&format!("(if (f64.eq (local.get {}) (f64.const 0))", step));
+        self.open("(then");
+        self.fail(InvalidLoopStep, &at);
+        self.close(")");
+        self.close(")");
+
+        // Counting iterations instead of adding up the steps keeps fractional
+        // steps from drifting
+        let iterations = self.local(format!("iterations{}", id), "i32");
+        let current = self.local(format!("current{}", id), "f64");
+        self.line(&format!("(local.set {} (i32.const 0))", iterations));
+        self.open(&format!("(block $break{}", id));
+        self.open(&format!("(loop $next{}", id));
+        self.line(&format!(
+            "(local.set {} (f64.add (local.get {}) (f64.mul (local.get {}) (f64.convert_i32_s (local.get {})))))",
+            current, start, step, iterations
+        ));
+        self.line(&format!(
+            "(br_if $break{} (i32.or (i32.and (f64.gt (local.get {2}) (f64.const 0)) (f64.gt (local.get {1}) (local.get {3}))) (i32.and (f64.lt (local.get {2}) (f64.const 0)) (f64.lt (local.get {1}) (local.get {3})))))",
+            id, current, step, end
+        ));
+        self.open(&format!(
+            "(if (i32.ge_s (local.get {}) (global.get $max_iter_count))",
+            iterations
+        ));
+        self.open("(then");
+        self.fail(MaxLoopsExceeded, &at);
+        self.close(")");
+        self.close(")");
+        let variable = self.symbol(variable);
+        self.line(&format!(
+            "(call $declare (i32.const {}) (call $number (local.get {})))",
+            variable, current
+        ));
+        self.loop_body(id, body);
+        self.line(&format!(
+            "(local.set {0} (i32.add (local.get {0}) (i32.const 1)))",
+            iterations
+        ));
+        self.line(&format!("(br $next{})", id));
+        self.close(")");
+        self.close(")");
+
+        self.body.scope_depth -= 1;
+        self.line("(call $pop_scope)");
+    }
+
+    /// The body of loop `id`. 'JAANE BHI DO' leaves the block around it.
+    fn loop_body(&mut self, id: usize, body: &[Statement]) {
+        self.body.loops.push((id, self.body.scope_depth));
+        self.open(&format!("(block $continue{}", id));
+        self.scoped_block(body);
+        self.close(")");
+        self.body.loops.pop();
+    }
+
+    fn loop_control(&mut self, is_break: bool) {
+        let Some(&(id, loop_scope_depth)) = self.body.loops.last() else {
+            if self.body.in_function {
+                self.pop_scopes(self.body.scope_depth);
+                self.line(&format!("(return (i32.const {}))", LOOP_CONTROL));
+            } else {
+                let at = self.at();
+                self.fail(LoopControlOutsideLoop, &at);
+            }
+            return;
+        };
+
+        self.pop_scopes(self.body.scope_depth - loop_scope_depth);
+        let label = if is_break { "break" } else { "continue" };
+        self.line(&format!("(br ${}{})", label, id));
+    }
+
+    fn return_statement(&mut self, expr: Option<&Expression>) {
+        let value = expr.map(|expr| self.expression(expr));
+
+        if !self.body.in_function {
+            if let Some(value) = value {
+                self.line(&format!("(drop {})", value));
+            }
+            let at = self.at();
+            self.fail(ReturnOutsideFunction, &at);
+            return;
+        }
+
+        // The value is worked out before leaving the blocks, one by one so
+        // that their variables expire properly
+        let value = match value {
+            Some(value) if self.body.scope_depth > 0 => {
+                if !self
+                    .body
+                    .locals
+                    .contains(&"(local $result i32)".to_string())
+                {
+                    self.local("result".to_string(), "i32");
+                }
+                self.line(&format!("(local.set $result {})", value));
+                "(local.get $result)".to_string()
+            }
+            Some(value) => value,
+            None => format!("(i32.const {})", NOTHING),
+        };
+        self.pop_scopes(self.body.scope_depth);
+        self.line(&format!("(return {})", value));
+    }
+
+    fn pop_scopes(&mut self, count: usize) {
+        for _ in 0..count {
+            self.line("(call $pop_scope)");
+        }
+    }
+
+    fn function(&mut self, name: &Ident, parameters: &[Ident], body: &[Statement]) {
+        let id = self.functions.len();
+        // Reserve the slot, so that functions defined inside this one come
+        // after it
+        self.functions.push(String::new());
+        let symbol = self.symbol(name);
+        let parameters: Vec<usize> = parameters
+            .iter()
+            .map(|parameter| self.symbol(parameter))
+            .collect();
+        let parameter_table = self.table(&parameters);
+
+        let outer_body = std::mem::replace(
+            &mut self.body,
+            Body {
+                in_function: true,
+                ..Body::default()
+            },
+        );
+        let outer_spans = std::mem::take(&mut self.spans);
+        self.block(body);
+        self.line(&format!("(return (i32.const {}))", NOTHING));
+        let function_body = std::mem::replace(&mut self.body, outer_body);
+        self.spans = outer_spans;
+
+        self.functions[id] = format!(
+            "  ;; AAO KABHI HAVELI PE {}\n  (func $function{} (type $function)\n{}{}  )\n",
+            name.0,
+            id,
+            declare_locals(&function_body.locals),
+            function_body.code
+        );
+
+        self.line(&format!(
+            "(call $define (i32.const {}) (i32.const {}) (i32.const {}) (i32.const {}))",
+            symbol,
+            id,
+            parameters.len(),
+            parameter_table
+        ));
+    }
+
+    fn invalid_statement(&mut self, err: &ParseError) {
+        let at = match err.span {
+            Some(span) => format!("(i32.const {}) (i32.const {})", span.line, span.column),
+            None => self.at(),
+        };
+        let code = self.string("SyntaxError");
+        let message = self.string(&err.to_string());
+        self.line(&format!(
+            "(call $fail (i32.const {}) (i32.const {}) {})",
+            code, message, at
+        ));
+    }
+
+    /// Translates a call. The function is checked before the arguments are
+    /// worked out, left to right, and pushed for the call to take.
+    fn call(&mut self, function: &Ident, arguments: &[Expression], wants_value: bool) -> String {
+        self.changes_case |= changes_case(function);
+        let symbol = self.symbol(function);
+        let builtin = BUILTINS
+            .iter()
+            .position(|builtin| *builtin == function.0)
+            .map_or(-1, |builtin| builtin as i32);
+        let at = self.at();
+
+        let mut call = format!(
+            "(block (result i32) (call $prepare_call (i32.const {}) (i32.const {}) {})",
+            symbol,
+            arguments.len(),
+            at
+        );
+        for argument in arguments {
+            let value = self.expression(argument);
+            let _ = write!(call, " (call $push_arg {})", value);
+        }
+        let _ = write!(
+            call,
+            " (call $call (i32.const {}) (i32.const {}) (i32.const {}) (i32.const {}) {}))",
+            symbol,
+            arguments.len(),
+            builtin,
+            wants_value as i32,
+            at
+        );
+        call
+    }
+
+    /// Translates an expression into a WebAssembly expression that leaves its
+    /// value on the stack. WebAssembly works out operands left to right, as
+    /// the interpreter does.
+    fn expression(&mut self, expression: &Expression) -> String {
+        match expression {
+            Expression::Spanned { span, expression } => {
+                self.spans.push(*span);
+                let value = self.expression(expression);
+                self.spans.pop();
+                value
+            }
+            Expression::IdentifierExpr(ident) => {
+                let symbol = self.symbol(ident);
+                format!("(call $load (i32.const {}) {})", symbol, self.at())
+            }
+            Expression::LiteralExpr(literal) => {
+                let address = match literal {
+                    Literal::Number(num) => self.number(*num),
+                    Literal::BoolLiteral(bool) => {
+                        if *bool {
+                            TRUE
+                        } else {
+                            FALSE
+                        }
+                    }
+                    Literal::StringLiteral(str) => self.string(str),
+                };
+                format!("(i32.const {})", address)
+            }
+            Expression::PrefixExpr { operator, right } => {
+                let right = self.expression(right);
+                let function = match operator {
+                    Prefix::PrefixPlus => "$unary_plus",
+                    Prefix::PrefixMinus => "$negate",
+                    Prefix::Not => "$not",
+                };
+                format!("(call {} {} {})", function, right, self.at())
+            }
+            Expression::InfixExpr {
+                left,
+                operator,
+                right,
+            } => {
+                let left = self.expression(left);
+                let right = self.expression(right);
+                format!(
+                    "(call {} {} {} {})",
+                    infix_function(operator),
+                    left,
+                    right,
+                    self.at()
+                )
+            }
+            Expression::Call {
+                function,
+                arguments,
+            } => self.call(function, arguments, true),
+            Expression::ListExpr(elements) => {
+                let mut list = "(call $new_list)".to_string();
+                for element in elements {
+                    let value = self.expression(element);
+                    list = format!("(call $list_push {} {})", list, value);
+                }
+                list
+            }
+            Expression::MapExpr(entries) => {
+                let mut map = "(call $new_map)".to_string();
+                for (key, value) in entries {
+                    let key = self.expression(key);
+                    let value = self.expression(value);
+                    map = format!("(call $map_insert {} {} {} {})", map, key, value, self.at());
+                }
+                map
+            }
+            Expression::Index { left, index } => {
+                let left = self.expression(left);
+                let index = self.expression(index);
+                format!("(call $index {} {} {})", left, index, self.at())
+            }
+            Expression::Slice { left, start, end } => {
+                let left = self.expression(left);
+                let mut bound = |bound: &Option<Box<Expression>>| match bound {
+                    Some(bound) => self.expression(bound),
+                    None => format!("(i32.const {})", NOTHING),
+                };
+                let start = bound(start);
+                let end = bound(end);
+                format!("(call $slice {} {} {} {})", left, start, end, self.at())
+            }
+            Expression::Input(kind) => {
+                format!("(call $input_{} {})", input_name(kind), self.at())
+            }
+        }
+    }
+}
+
+fn infix_function(operator: &Infix) -> &'static str {
+    match operator {
+        Infix::Plus => "$add",
+        Infix::Minus => "$subtract",
+        Infix::Multiply => "$multiply",
+        Infix::Divide => "$divide",
+        Infix::Modulo => "$modulo",
+        Infix::Equal => "$equal",
+        Infix::NotEqual => "$not_equal",
+        Infix::GreaterThan => "$greater_than",
+        Infix::GreaterThanEqual => "$greater_than_equal",
+        Infix::LessThan => "$less_than",
+        Infix::LessThanEqual => "$less_than_equal",
+        Infix::LogicalAnd => "$and",
+        Infix::LogicalOr => "$or",
+    }
+}
+
+fn declare_locals(locals: &[String]) -> String {
+    locals
+        .iter()
+        .map(|local| format!("    {}\n", local))
+        .collect()
+}
+
+/// Rounds an address up to the next multiple of 8
+fn align(address: usize) -> usize {
+    address.next_multiple_of(8)
+}
+
+/// Reads one of the runtime's placeholders for a static string:
+/// `(string "text")`, `(code Type)` for the name of an error type or
+/// `(message Type)` for its message. Gives back the text and how long the
+/// placeholder is.
+fn runtime_text(placeholder: &str) -> (String, usize) {
+    if let Some(literal) = placeholder.strip_prefix("(string \"") {
+        let mut text = Vec::new();
+        let mut bytes = literal.bytes().enumerate();
+        while let Some((i, byte)) = bytes.next() {
+            match byte {
+                b'"' => {
+                    let text = String::from_utf8(text).expect("runtime strings are UTF-8");
+                    // The opening, the text, the closing quote and parenthesis
+                    return (text, "(string \"".len() + i + 2);
+                }
+                b'\\' => match bytes.next() {
+                    Some((_, b'n')) => text.push(b'\n'),
+                    Some((_, b't')) => text.push(b'\t'),
+                    Some((_, escaped)) => text.push(escaped),
+                    None => break,
+                },
+                _ => text.push(byte),
+            }
+        }
+        panic!("unterminated string in the runtime: {}", placeholder);
+    }
+
+    let end = placeholder.find(')').expect("placeholders are closed");
+    let (kind, name) = placeholder[1..end]
+        .split_once(' ')
+        .expect("placeholders have a name");
+    let error_type = RUNTIME_ERRORS
+        .iter()
+        .find(|error_type| error_type.to_string() == name);
+    let text = match (kind, error_type) {
+        ("message", Some(error_type)) => InterpreterError::new(error_type.clone()).msg,
+        ("code", _) => name.to_string(),
+        _ => panic!("the runtime has no message for {}", name),
+    };
+    (text, end + 1)
+}
+
+/// Bytes as the contents of a WebAssembly text string
+fn data_string(bytes: &[u8]) -> String {
+    let mut text = String::new();
+    for byte in bytes {
+        match byte {
+            b'"' | b'\\' => {
+                let _ = write!(text, "\\{}", *byte as char);
+            }
+            b' '..=b'~' => text.push(*byte as char),
+            _ => {
+                let _ = write!(text, "\\{:02x}", byte);
+            }
+        }
+    }
+    text
+}
@@ -0,0 +1,198 @@
+use crate::error::{ParseError, ZenError};
+use crate::evaluator::interpreter::InterpreterErrorType;
+use crate::lexer::span::Span;
+use crate::lexer::token_type::TokenType;
+use colored::{Color, Colorize};
+
+/// Renders an error the way rustc does: the message, where it happened, the
+/// offending source line with the span underlined, and a hint on how to fix
+/// it. Colours are only used when `use_color` is set, so the output can also
+/// go to files and pipes.
+///
+/// ```text
+/// error[DivisionByZero]: Division by zero? ...
+///  --> code.zen:4:18
+///   |
+/// 4 |     LAUT KE AAJA X / (A - 5)
+///   |                  ^^^^^^^^^^^
+///   |
+///   = help: make sure the number you divide by can never be 0
+/// ```
+pub fn render_error(error: &ZenError, source: &str, file_name: &str, use_color: bool) -> String {
+    let rendered: Vec<String> = diagnostics(error, source)
+        .iter()
+        .map(|diagnostic| diagnostic.render(source, file_name, use_color))
+        .collect();
+    rendered.join("\n")
+}
+
+/// A single problem to show. Syntax errors can come several at a time.
+pub struct Diagnostic {
+    /// The kind of error, `SyntaxError` or the name of a runtime error
+    pub code: String,
+    pub message: String,
+    /// Where it happened; the end of the file has no span
+    pub span: Option<Span>,
+    pub help: Option<&'static str>,
+}
+
+/// The problems that make up `error`, for tools that show them their own way
+pub fn diagnostics(error: &ZenError, source: &str) -> Vec<Diagnostic> {
+    match error {
+        ZenError::Lex(err) => {
+            let unterminated = source
+                .get(err.span.start..)
+                .is_some_and(|rest| rest.starts_with('"'));
+            vec![Diagnostic {
+                code: "SyntaxError".to_string(),
+                message: err.to_string(),
+                span: Some(err.span),
+                help: Some(if unterminated {
+                    "strings start and end with '\"'"
+                } else {
+                    "remove this character, or put it inside a string"
+                }),
+            }]
+        }
+        ZenError::Parse(errors) => errors
+            .iter()
+            .map(|err| Diagnostic {
+                code: "SyntaxError".to_string(),
+                message: err.to_string(),
+                span: err.span,
+                help: Some(parse_help(err)),
+            })
+            .collect(),
+        ZenError::Runtime(err) => vec![Diagnostic {
+            code: err.error_type.to_string(),
+            message: err.to_string(),
+            span: err.span,
+            help: runtime_help(&err.error_type),
+        }],
+    }
+}
+
+impl Diagnostic {
+    fn render(&self, source: &str, file_name: &str, use_color: bool) -> String {
+        let paint = |text: &str, color: Color| {
+            if use_color {
+                text.color(color).bold().to_string()
+            } else {
+                text.to_string()
+            }
+        };
+        let message = if use_color {
+            self.message.bold().to_string()
+        } else {
+            self.message.clone()
+        };
+
+        let mut rendered = format!(
+            "{}: {}\n",
+            paint(&format!("error[{}]", self.code), Color::Red),
+            message
+        );
+
+        let gutter_width = self.span.map_or(0, |span| span.line.to_string().len());
+        let gutter = paint(&format!("{} |", " ".repeat(gutter_width)), Color::Blue);
+
+        if let Some(span) = self.span {
+            let arrow = format!("{}-->", " ".repeat(gutter_width));
+            rendered += &format!(
+                "{} {}:{}:{}\n",
+                paint(&arrow, Color::Blue),
+                file_name,
+                span.line,
+                span.column
+            );
+
+            let line = source
+                .lines()
+                .nth(span.line.saturating_sub(1))
+                .unwrap_or("");
+            let (padding, width) = underline(line, source, span);
+            rendered += &format!("{}\n", gutter);
+            let row = format!(
+                "{} {}",
+                paint(&format!("{} |", span.line), Color::Blue),
+                line
+            );
+            rendered += &format!("{}\n", row.trim_end());
+            rendered += &format!(
+                "{} {}{}\n",
+                gutter,
+                padding,
+                paint(&"^".repeat(width), Color::Red)
+            );
+        }
+
+        if let Some(help) = self.help {
+            if self.span.is_some() {
+                rendered += &format!("{}\n", gutter);
+            }
+            let equals = format!("{} =", " ".repeat(gutter_width));
+            rendered += &format!(
+                "{} {}: {}\n",
+                paint(&equals, Color::Blue),
+                paint("help", Color::Cyan),
+                help
+            );
+        }
+        rendered
+    }
+}
+
+/// Whitespace that lines the carets up under the span, and how many carets
+/// to draw. A span running over several lines is underlined up to the end of
+/// its first line.
+fn underline(line: &str, source: &str, span: Span) -> (String, usize) {
+    // Tabs are kept, so that the carets line up however wide a tab is shown
+    let padding = line
+        .chars()
+        .take(span.column.saturating_sub(1))
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+
+    let spanned = source.get(span.start..span.end).unwrap_or("");
+    let first_line = spanned.lines().next().unwrap_or("");
+    (padding, first_line.chars().count().max(1))
+}
+
+fn parse_help(err: &ParseError) -> &'static str {
+    if err.found == TokenType::Eof && err.expected.contains(&TokenType::EndProgram.describe()) {
+        "every program has to end with 'KHATAM TATA BYE BYE'"
+    } else {
+        "look for a missing or misspelt word just before this point"
+    }
+}
+
+fn runtime_help(error_type: &InterpreterErrorType) -> Option<&'static str> {
+    use InterpreterErrorType::*;
+
+    let help = match error_type {
+        MissingStartSymbol => "start the program with 'PARAMPARA PRATISHTA ANUSHASHAN'",
+        UndefinedVariable => "give the variable a value with 'BOLE TOH' before using it",
+        VariableOutOfScope => "this variable only lived inside the block that created it",
+        DivisionByZero => "make sure the number you divide by can never be 0",
+        IncompatibleDataType => "check the types of the values used here",
+        UndefinedFunction => "define it first with 'AAO KABHI HAVELI PE'",
+        ArgumentCountMismatch => "pass as many arguments as the function has parameters",
+        MissingReturnValue => "make the function 'LAUT KE AAJA' with a value",
+        ReturnOutsideFunction => "'LAUT KE AAJA' only works inside a function",
+        MaxLoopsExceeded => "check that the loop condition eventually becomes false",
+        MaxCallDepthExceeded => "check that the recursion has a case where it stops",
+        IndexOutOfBounds => "positions start at 0 and end one before the length",
+        MissingKey => "use HAI_KYA to check whether the key is there first",
+        InvalidLoopStep => "use a step other than 0 with 'KADAM'",
+        LoopControlOutsideLoop => "use it inside 'JAB TAK HAI JAAN' or 'EK DO TEEN'",
+        MaxStepsExceeded => "the program took too many steps; look for work that never ends",
+        MaxOutputExceeded => "print less, for example only after a loop is done",
+        MaxVariablesExceeded => {
+            "the program keeps too many variables alive at once; reuse variables or raise the limit"
+        }
+        MaxTimeExceeded => "the program took too long; look for work that never ends",
+        InvalidInputError | EmptyCustomInputStack => "check the input given to the program",
+        SyntaxError | DeadlyError | UnknownParserError | ExecutionCancelled => return None,
+    };
+    Some(help)
+}
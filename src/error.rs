@@ -0,0 +1,108 @@
+use crate::evaluator::interpreter::InterpreterError;
+use crate::lexer::span::Span;
+use crate::lexer::token_type::TokenType;
+use std::fmt;
+
+/// Anything that can go wrong with a Zen program, split by the phase that
+/// found the problem. Parsing carries on after a mistake, so it can report
+/// several at once.
+#[derive(Debug)]
+pub enum ZenError {
+    Lex(LexError),
+    Parse(Vec<ParseError>),
+    Runtime(InterpreterError),
+}
+
+/// Source text that is not part of the language at all
+#[derive(Debug, PartialEq)]
+pub struct LexError {
+    pub msg: String,
+    pub span: Span,
+}
+
+/// Tokens that do not form a valid program. Points at the furthest token the
+/// parser could reach, along with what it would have accepted there.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ParseError {
+    pub expected: Vec<String>,
+    pub found: TokenType,
+    pub span: Option<Span>,
+}
+
+impl LexError {
+    pub fn new_illegal_character(found: char, span: Span) -> Self {
+        let msg = match found {
+            '"' => "This string never ends, close it with another '\"'! 'Picture abhi baaki hai mere dost.'".to_string(),
+            other => format!("'{}' is not a part of Zen! 'Tum kaun ho? Main tumhe nahi jaanta.'", other),
+        };
+        LexError { msg, span }
+    }
+}
+
+impl ZenError {
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            ZenError::Lex(err) => Some(err.span),
+            ZenError::Parse(errors) => errors.first().and_then(|err| err.span),
+            ZenError::Runtime(err) => err.span,
+        }
+    }
+}
+
+impl fmt::Display for ZenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ZenError::Lex(err) => write!(f, "{}", err),
+            ZenError::Parse(errors) => {
+                let messages: Vec<String> = errors.iter().map(ParseError::to_string).collect();
+                write!(f, "{}", messages.join("\n"))
+            }
+            ZenError::Runtime(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.msg)
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Mogambo dukhi hua! ")?;
+        match self.expected.as_slice() {
+            [] => write!(f, "Did not expect {}", self.found.describe()),
+            [only] => write!(f, "Expected {} but found {}", only, self.found.describe()),
+            [init @ .., last] => write!(
+                f,
+                "Expected {} or {} but found {}",
+                init.join(", "),
+                last,
+                self.found.describe()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ZenError {}
+impl std::error::Error for LexError {}
+impl std::error::Error for ParseError {}
+
+impl From<LexError> for ZenError {
+    fn from(err: LexError) -> Self {
+        ZenError::Lex(err)
+    }
+}
+
+impl From<Vec<ParseError>> for ZenError {
+    fn from(errors: Vec<ParseError>) -> Self {
+        ZenError::Parse(errors)
+    }
+}
+
+impl From<InterpreterError> for ZenError {
+    fn from(err: InterpreterError) -> Self {
+        ZenError::Runtime(err)
+    }
+}
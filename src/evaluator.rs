@@ -1,2 +1,9 @@
-mod constants;
+mod builtins;
+mod bytecode;
+mod compiler;
+pub mod config;
+pub(crate) mod constants;
+mod environment;
 pub mod interpreter;
+pub mod io;
+pub mod value;
@@ -0,0 +1,182 @@
+use crate::evaluator::interpreter::InterpreterErrorType::{
+    ArgumentCountMismatch, IncompatibleDataType, MissingKey,
+};
+use crate::evaluator::interpreter::{InterpreterError, index_position, map_key};
+use crate::evaluator::value::{MapKey, Value};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+type Builtin = fn(&str, Vec<Value>) -> Result<Value, InterpreterError>;
+
+/// Runs the built-in function called `name`, if there is one.
+///
+/// User-defined functions take priority, so this is only consulted for names
+/// that the program did not define itself.
+pub(crate) fn call_builtin(
+    name: &str,
+    arguments: Vec<Value>,
+) -> Option<Result<Value, InterpreterError>> {
+    let builtin: Builtin = match name {
+        "LAMBAI" => lambai,
+        "AKSHAR" => akshar,
+        "ANK" => ank,
+        "BADA" => bada,
+        "CHHOTA" => chhota,
+        "KHOJO" => khojo,
+        "JODO" => jodo,
+        "HATAO" => hatao,
+        "HAI_KYA" => hai_kya,
+        "CHABIYAN" => chabiyan,
+        _ => return None,
+    };
+    Some(builtin(name, arguments))
+}
+
+fn expect_arguments<const N: usize>(
+    name: &str,
+    arguments: Vec<Value>,
+) -> Result<[Value; N], InterpreterError> {
+    let count = arguments.len();
+    <[Value; N]>::try_from(arguments).map_err(|_| {
+        InterpreterError::new_from_append_error(
+            &format!(" '{}' expects {} argument(s) but got {}.", name, N, count),
+            ArgumentCountMismatch,
+        )
+    })
+}
+
+fn expect_string(name: &str, mut value: Value) -> Result<String, InterpreterError> {
+    match &mut value {
+        Value::Str(str) => Ok(std::mem::take(str)),
+        other => Err(InterpreterError::new_from_append_error(
+            &format!(" '{}' needs a string, not a {}.", name, other.type_name()),
+            IncompatibleDataType,
+        )),
+    }
+}
+
+fn expect_map(
+    name: &str,
+    value: Value,
+) -> Result<Rc<RefCell<BTreeMap<MapKey, Value>>>, InterpreterError> {
+    match &value {
+        Value::Map(map) => Ok(Rc::clone(map)),
+        other => Err(InterpreterError::new_from_append_error(
+            &format!(" '{}' needs a map, not a {}.", name, other.type_name()),
+            IncompatibleDataType,
+        )),
+    }
+}
+
+fn expect_list(name: &str, value: Value) -> Result<Rc<RefCell<Vec<Value>>>, InterpreterError> {
+    match &value {
+        Value::List(list) => Ok(Rc::clone(list)),
+        other => Err(InterpreterError::new_from_append_error(
+            &format!(" '{}' needs a list, not a {}.", name, other.type_name()),
+            IncompatibleDataType,
+        )),
+    }
+}
+
+/// Length of a string (in characters), of a list or of a map
+fn lambai(name: &str, arguments: Vec<Value>) -> Result<Value, InterpreterError> {
+    let [value] = expect_arguments(name, arguments)?;
+    let len = match &value {
+        Value::List(list) => list.borrow().len(),
+        Value::Map(map) => map.borrow().len(),
+        _ => expect_string(name, value)?.chars().count(),
+    };
+    Ok(Value::Number(len as f64))
+}
+
+/// Converts any value to its printed form
+fn akshar(name: &str, arguments: Vec<Value>) -> Result<Value, InterpreterError> {
+    let [value] = expect_arguments(name, arguments)?;
+    Ok(Value::Str(value.to_string()))
+}
+
+/// Converts a string (or a number) to a number
+fn ank(name: &str, arguments: Vec<Value>) -> Result<Value, InterpreterError> {
+    let [value] = expect_arguments(name, arguments)?;
+    match &value {
+        Value::Number(num) => Ok(Value::Number(*num)),
+        Value::Str(str) => match str.trim().parse::<f64>() {
+            Ok(num) => Ok(Value::Number(num)),
+            Err(_) => Err(InterpreterError::new_from_append_error(
+                &format!(" \"{}\" is not a number.", str),
+                IncompatibleDataType,
+            )),
+        },
+        other => Err(InterpreterError::new_from_append_error(
+            &format!(" A {} cannot be turned into a number.", other.type_name()),
+            IncompatibleDataType,
+        )),
+    }
+}
+
+fn bada(name: &str, arguments: Vec<Value>) -> Result<Value, InterpreterError> {
+    let [value] = expect_arguments(name, arguments)?;
+    Ok(Value::Str(expect_string(name, value)?.to_uppercase()))
+}
+
+fn chhota(name: &str, arguments: Vec<Value>) -> Result<Value, InterpreterError> {
+    let [value] = expect_arguments(name, arguments)?;
+    Ok(Value::Str(expect_string(name, value)?.to_lowercase()))
+}
+
+/// Character position of the first occurrence of a substring, or -1
+fn khojo(name: &str, arguments: Vec<Value>) -> Result<Value, InterpreterError> {
+    let [haystack, needle] = expect_arguments(name, arguments)?;
+    let haystack = expect_string(name, haystack)?;
+    let needle = expect_string(name, needle)?;
+
+    let position = match haystack.find(&needle) {
+        Some(byte_offset) => haystack[..byte_offset].chars().count() as f64,
+        None => -1_f64,
+    };
+    Ok(Value::Number(position))
+}
+
+/// Appends a value to the end of a list, giving back the same list
+fn jodo(name: &str, arguments: Vec<Value>) -> Result<Value, InterpreterError> {
+    let [list, value] = expect_arguments(name, arguments)?;
+    let list = expect_list(name, list)?;
+    list.borrow_mut().push(value);
+    Ok(Value::List(list))
+}
+
+/// Removes the element at a position of a list (or a key of a map), giving
+/// back the removed value
+fn hatao(name: &str, arguments: Vec<Value>) -> Result<Value, InterpreterError> {
+    let [container, index] = expect_arguments(name, arguments)?;
+    if let Value::Map(map) = &container {
+        let key = map_key(&index)?;
+        return map.borrow_mut().remove(&key).ok_or_else(|| {
+            InterpreterError::new_from_append_error(
+                &format!(" There is no key {} in this map.", key),
+                MissingKey,
+            )
+        });
+    }
+    let list = expect_list(name, container)?;
+    let mut list = list.borrow_mut();
+    let position = index_position(&index, list.len())?;
+    Ok(list.remove(position))
+}
+
+/// Whether a map has the given key
+fn hai_kya(name: &str, arguments: Vec<Value>) -> Result<Value, InterpreterError> {
+    let [map, key] = expect_arguments(name, arguments)?;
+    let map = expect_map(name, map)?;
+    let has_key = map.borrow().contains_key(&map_key(&key)?);
+    Ok(Value::Bool(has_key))
+}
+
+/// The keys of a map as a new list, in sorted order
+fn chabiyan(name: &str, arguments: Vec<Value>) -> Result<Value, InterpreterError> {
+    let [map] = expect_arguments(name, arguments)?;
+    let map = expect_map(name, map)?;
+    let keys = map.borrow().keys().map(Value::from).collect();
+    Ok(Value::new_list(keys))
+}
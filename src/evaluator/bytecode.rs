@@ -0,0 +1,147 @@
+use crate::evaluator::interpreter::InterpreterError;
+use crate::evaluator::value::Value;
+use crate::lexer::span::Span;
+use crate::parser::ast::{Infix, InputKind, Prefix};
+use std::collections::{HashMap, HashSet};
+use std::hash::{BuildHasherDefault, Hasher};
+use std::rc::Rc;
+
+/// A variable or function name, interned so that lookups do not need to
+/// hash and compare whole strings
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub(crate) struct Symbol(u32);
+
+/// Symbols are small distinct numbers already, so they are used as their own
+/// hash instead of going through SipHash on every variable lookup
+#[derive(Default)]
+pub(crate) struct SymbolHasher(u64);
+
+impl Hasher for SymbolHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = (self.0 << 8) | u64::from(*byte);
+        }
+    }
+
+    fn write_u32(&mut self, n: u32) {
+        self.0 = u64::from(n);
+    }
+}
+
+pub(crate) type SymbolMap<V> = HashMap<Symbol, V, BuildHasherDefault<SymbolHasher>>;
+pub(crate) type SymbolSet = HashSet<Symbol, BuildHasherDefault<SymbolHasher>>;
+
+/// Maps names to symbols and back. Kept by the interpreter, so that the
+/// same name means the same symbol across programs run one after another.
+#[derive(Default)]
+pub(crate) struct Symbols {
+    ids: HashMap<String, Symbol>,
+    names: Vec<String>,
+}
+
+impl Symbols {
+    pub(crate) fn intern(&mut self, name: &str) -> Symbol {
+        if let Some(symbol) = self.ids.get(name) {
+            return *symbol;
+        }
+        let symbol = Symbol(self.names.len() as u32);
+        self.ids.insert(name.to_string(), symbol);
+        self.names.push(name.to_string());
+        symbol
+    }
+
+    pub(crate) fn name(&self, symbol: Symbol) -> &str {
+        &self.names[symbol.0 as usize]
+    }
+}
+
+/// One step of the virtual machine. Operands are taken from the top of the
+/// value stack and results are pushed back onto it.
+#[derive(Debug)]
+pub(crate) enum Instruction {
+    /// Pushes a constant of the chunk
+    Constant(usize),
+    Load(Symbol),
+    /// Pops a value into the nearest variable of that name, like 'BOLE TOH'
+    Store(Symbol),
+    /// Pops a value into a new variable of the innermost scope, like 'APNA'
+    Declare(Symbol),
+    Pop,
+    Prefix(Prefix),
+    Infix(Infix),
+    /// Collects that many values into a list
+    MakeList(usize),
+    NewMap,
+    /// Pops a key and a value and adds them to the map below them
+    InsertEntry,
+    Index,
+    Slice {
+        start: bool,
+        end: bool,
+    },
+    /// Pops a container, an index and a value, and updates the container
+    SetElement,
+    Input(InputKind),
+    Print,
+    Jump(usize),
+    /// Pops a value and jumps unless it is exactly `true`
+    JumpUnlessTrue(usize),
+    PushScope,
+    PopScope,
+    /// Makes a function of the chunk callable by its name
+    DefineFunction(usize),
+    /// Checks a call to a program-defined function before its arguments are
+    /// worked out
+    PrepareCall {
+        function: Symbol,
+        arguments: usize,
+    },
+    Call {
+        function: Symbol,
+        arguments: usize,
+        wants_value: bool,
+    },
+    Return {
+        has_value: bool,
+    },
+    /// 'BAS KAR PAGLE' or 'JAANE BHI DO' that would leave a function
+    LoopControlInFunction,
+    /// Fails with an error of the chunk
+    Fail(usize),
+    /// Pushes the iteration count of a 'JAB TAK HAI JAAN' loop
+    WhileStart,
+    /// Counts an iteration, failing when there were too many
+    WhileStep,
+    /// Checks that the value on top is a number
+    CheckLoopBound,
+    /// Checks the step below the top and pushes the iteration count of an
+    /// 'EK DO TEEN' loop
+    ForStart,
+    /// Sets the loop variable for the next iteration, or jumps out when done
+    ForNext {
+        variable: Symbol,
+        exit: usize,
+    },
+    ForStep,
+}
+
+/// Compiled code along with what it refers to. `spans[i]` tells where in the
+/// source `code[i]` comes from, for error messages.
+#[derive(Default)]
+pub(crate) struct Chunk {
+    pub(crate) code: Vec<Instruction>,
+    pub(crate) spans: Vec<Option<Span>>,
+    pub(crate) constants: Vec<Value>,
+    pub(crate) functions: Vec<Rc<Function>>,
+    pub(crate) errors: Vec<InterpreterError>,
+}
+
+pub(crate) struct Function {
+    pub(crate) name: Symbol,
+    pub(crate) parameters: Vec<Symbol>,
+    pub(crate) chunk: Rc<Chunk>,
+}
@@ -0,0 +1,439 @@
+use crate::error::ParseError;
+use crate::evaluator::bytecode::{Chunk, Function, Instruction, Symbols};
+use crate::evaluator::interpreter::InterpreterError;
+use crate::evaluator::interpreter::InterpreterErrorType::{
+    LoopControlOutsideLoop, ReturnOutsideFunction, SyntaxError,
+};
+use crate::evaluator::value::Value;
+use crate::lexer::span::Span;
+use crate::parser::ast::{Expression, Ident, Statement};
+use std::rc::Rc;
+
+/// Turns the statements of a program into bytecode for the interpreter.
+///
+/// Scopes, loops and calls work exactly as they do in the language
+/// definition; the compiler only decides up front which scopes to close and
+/// which loop values to drop when a statement jumps out of a block.
+pub(crate) struct Compiler<'a> {
+    symbols: &'a mut Symbols,
+    chunk: Chunk,
+    /// Spans of the statements and expressions being compiled, innermost last
+    spans: Vec<Span>,
+    /// Scopes opened since the start of the function (or the program)
+    scope_depth: usize,
+    /// Values kept on the stack by the loops being compiled
+    stack_depth: usize,
+    loops: Vec<LoopContext>,
+    in_function: bool,
+}
+
+struct LoopContext {
+    /// Scopes open outside of the loop body
+    scope_depth: usize,
+    /// Stack values that belong to this loop or to enclosing ones
+    stack_depth: usize,
+    breaks: Vec<usize>,
+    continues: Vec<usize>,
+}
+
+impl<'a> Compiler<'a> {
+    pub(crate) fn new(symbols: &'a mut Symbols) -> Self {
+        Self {
+            symbols,
+            chunk: Chunk::default(),
+            spans: Vec::new(),
+            scope_depth: 0,
+            stack_depth: 0,
+            loops: Vec::new(),
+            in_function: false,
+        }
+    }
+
+    pub(crate) fn compile_program(mut self, statements: &[Statement]) -> Chunk {
+        self.compile_block(statements);
+        self.chunk
+    }
+
+    fn compile_function(
+        &mut self,
+        name: &Ident,
+        parameters: &[Ident],
+        body: &[Statement],
+    ) -> Function {
+        let mut compiler = Compiler::new(self.symbols);
+        compiler.in_function = true;
+        compiler.compile_block(body);
+        compiler.emit(Instruction::Return { has_value: false });
+        let chunk = Rc::new(compiler.chunk);
+
+        Function {
+            name: self.symbols.intern(&name.0),
+            parameters: parameters
+                .iter()
+                .map(|parameter| self.symbols.intern(&parameter.0))
+                .collect(),
+            chunk,
+        }
+    }
+
+    fn compile_block(&mut self, statements: &[Statement]) {
+        for statement in statements {
+            // Nothing after 'KHATAM TATA BYE BYE' runs, in any block
+            if let Statement::ProgramEnd = statement.unspanned() {
+                break;
+            }
+            self.compile_statement(statement);
+        }
+    }
+
+    fn compile_scoped_block(&mut self, statements: &[Statement]) {
+        self.emit(Instruction::PushScope);
+        self.scope_depth += 1;
+        self.compile_block(statements);
+        self.scope_depth -= 1;
+        self.emit(Instruction::PopScope);
+    }
+
+    fn compile_statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::Spanned { span, statement } => {
+                self.spans.push(*span);
+                self.compile_statement(statement);
+                self.spans.pop();
+            }
+            Statement::ProgramStart => {
+                let error = InterpreterError::new_from_custom_error(
+                    "Only one 'PARAMPARA PRATISHTA ANUSHASHAN' allowed! 'Ek hi baar bolna kaafi hai.'",
+                    SyntaxError,
+                );
+                self.emit_error(error);
+            }
+            Statement::ProgramEnd => {}
+            Statement::Let { name, value } => {
+                self.compile_expression(value);
+                let symbol = self.symbols.intern(&name.0);
+                self.emit(Instruction::Store(symbol));
+            }
+            Statement::LocalLet { name, value } => {
+                self.compile_expression(value);
+                let symbol = self.symbols.intern(&name.0);
+                self.emit(Instruction::Declare(symbol));
+            }
+            Statement::If {
+                condition,
+                consequence,
+                alternative,
+            } => {
+                self.compile_expression(condition);
+                let to_alternative = self.emit(Instruction::JumpUnlessTrue(0));
+                self.compile_scoped_block(consequence);
+                match alternative {
+                    Some(alternative) => {
+                        let to_end = self.emit(Instruction::Jump(0));
+                        self.patch_jump(to_alternative);
+                        self.compile_scoped_block(alternative);
+                        self.patch_jump(to_end);
+                    }
+                    None => self.patch_jump(to_alternative),
+                }
+            }
+            Statement::While { condition, body } => self.compile_while_loop(condition, body),
+            Statement::For {
+                variable,
+                start,
+                end,
+                step,
+                body,
+            } => self.compile_for_loop(variable, start, end, step.as_ref(), body),
+            Statement::Print(expr) => {
+                self.compile_expression(expr);
+                self.emit(Instruction::Print);
+            }
+            Statement::Function {
+                name,
+                parameters,
+                body,
+            } => {
+                let function = self.compile_function(name, parameters, body);
+                self.chunk.functions.push(Rc::new(function));
+                self.emit(Instruction::DefineFunction(self.chunk.functions.len() - 1));
+            }
+            Statement::Return(expr) => self.compile_return(expr.as_ref()),
+            Statement::Break | Statement::Continue => {
+                self.compile_loop_control(statement == &Statement::Break)
+            }
+            Statement::IndexLet { left, index, value } => {
+                self.compile_expression(left);
+                self.compile_expression(index);
+                self.compile_expression(value);
+                self.emit(Instruction::SetElement);
+            }
+            Statement::Expression(expr) => match expr.unspanned() {
+                // A call used as a statement may return nothing
+                Expression::Call {
+                    function,
+                    arguments,
+                } => self.compile_call(function, arguments, false),
+                _ => {
+                    self.compile_expression(expr);
+                    self.emit(Instruction::Pop);
+                }
+            },
+            Statement::Invalid(err) => self.emit_error(invalid_statement(err)),
+        }
+    }
+
+    // Loop layout: the loop's own values sit on the stack for as long as it
+    // runs, and every iteration of the body gets a fresh scope
+    fn compile_while_loop(&mut self, condition: &Expression, body: &[Statement]) {
+        self.emit(Instruction::WhileStart);
+        self.stack_depth += 1;
+
+        let top = self.chunk.code.len();
+        self.compile_expression(condition);
+        let to_exit = self.emit(Instruction::JumpUnlessTrue(0));
+        self.compile_loop_body(body);
+        let continue_target = self.chunk.code.len();
+        self.emit(Instruction::WhileStep);
+        self.emit(Instruction::Jump(top));
+
+        self.patch_jump(to_exit);
+        self.finish_loop(continue_target);
+        self.emit(Instruction::Pop);
+        self.stack_depth -= 1;
+    }
+
+    fn compile_for_loop(
+        &mut self,
+        variable: &Ident,
+        start: &Expression,
+        end: &Expression,
+        step: Option<&Expression>,
+        body: &[Statement],
+    ) {
+        // The loop variable lives in its own scope around the body
+        self.emit(Instruction::PushScope);
+        self.scope_depth += 1;
+
+        for bound in [Some(start), Some(end), step] {
+            match bound {
+                Some(bound) => {
+                    self.compile_expression(bound);
+                    self.emit(Instruction::CheckLoopBound);
+                }
+                None => self.emit_constant(Value::Number(1_f64)),
+            }
+        }
+        self.emit(Instruction::ForStart);
+        self.stack_depth += 4;
+
+        let top = self.chunk.code.len();
+        let variable = self.symbols.intern(&variable.0);
+        let to_exit = self.emit(Instruction::ForNext { variable, exit: 0 });
+        self.compile_loop_body(body);
+        let continue_target = self.chunk.code.len();
+        self.emit(Instruction::ForStep);
+        self.emit(Instruction::Jump(top));
+
+        self.patch_jump(to_exit);
+        self.finish_loop(continue_target);
+        for _ in 0..4 {
+            self.emit(Instruction::Pop);
+        }
+        self.stack_depth -= 4;
+        self.scope_depth -= 1;
+        self.emit(Instruction::PopScope);
+    }
+
+    fn compile_loop_body(&mut self, body: &[Statement]) {
+        self.loops.push(LoopContext {
+            scope_depth: self.scope_depth,
+            stack_depth: self.stack_depth,
+            breaks: Vec::new(),
+            continues: Vec::new(),
+        });
+        self.compile_scoped_block(body);
+    }
+
+    /// Points the loop's 'BAS KAR PAGLE' jumps here and its 'JAANE BHI DO'
+    /// jumps at `continue_target`
+    fn finish_loop(&mut self, continue_target: usize) {
+        let context = self.loops.pop().expect("loop context pushed for the body");
+        for jump in context.breaks {
+            self.patch_jump(jump);
+        }
+        for jump in context.continues {
+            self.set_jump_target(jump, continue_target);
+        }
+    }
+
+    fn compile_loop_control(&mut self, is_break: bool) {
+        let Some(context) = self.loops.last() else {
+            if self.in_function {
+                self.emit(Instruction::LoopControlInFunction);
+            } else {
+                self.emit_error(InterpreterError::new(LoopControlOutsideLoop));
+            }
+            return;
+        };
+
+        let scopes = self.scope_depth - context.scope_depth;
+        let values = self.stack_depth - context.stack_depth;
+        for _ in 0..scopes {
+            self.emit(Instruction::PopScope);
+        }
+        for _ in 0..values {
+            self.emit(Instruction::Pop);
+        }
+
+        let jump = self.emit(Instruction::Jump(0));
+        let context = self.loops.last_mut().expect("checked above");
+        if is_break {
+            context.breaks.push(jump);
+        } else {
+            context.continues.push(jump);
+        }
+    }
+
+    fn compile_return(&mut self, expr: Option<&Expression>) {
+        if let Some(expr) = expr {
+            self.compile_expression(expr);
+        }
+
+        if !self.in_function {
+            if expr.is_some() {
+                self.emit(Instruction::Pop);
+            }
+            self.emit_error(InterpreterError::new(ReturnOutsideFunction));
+            return;
+        }
+
+        // Leaving the blocks one by one lets their variables expire properly
+        for _ in 0..self.scope_depth {
+            self.emit(Instruction::PopScope);
+        }
+        self.emit(Instruction::Return {
+            has_value: expr.is_some(),
+        });
+    }
+
+    fn compile_call(&mut self, function: &Ident, arguments: &[Expression], wants_value: bool) {
+        let function = self.symbols.intern(&function.0);
+        self.emit(Instruction::PrepareCall {
+            function,
+            arguments: arguments.len(),
+        });
+        for argument in arguments {
+            self.compile_expression(argument);
+        }
+        self.emit(Instruction::Call {
+            function,
+            arguments: arguments.len(),
+            wants_value,
+        });
+    }
+
+    fn compile_expression(&mut self, expression: &Expression) {
+        match expression {
+            Expression::Spanned { span, expression } => {
+                self.spans.push(*span);
+                self.compile_expression(expression);
+                self.spans.pop();
+            }
+            Expression::IdentifierExpr(ident) => {
+                let symbol = self.symbols.intern(&ident.0);
+                self.emit(Instruction::Load(symbol));
+            }
+            Expression::LiteralExpr(literal) => self.emit_constant(Value::from(literal)),
+            Expression::PrefixExpr { operator, right } => {
+                self.compile_expression(right);
+                self.emit(Instruction::Prefix(operator.clone()));
+            }
+            Expression::InfixExpr {
+                left,
+                operator,
+                right,
+            } => {
+                self.compile_expression(left);
+                self.compile_expression(right);
+                self.emit(Instruction::Infix(operator.clone()));
+            }
+            Expression::Call {
+                function,
+                arguments,
+            } => self.compile_call(function, arguments, true),
+            Expression::ListExpr(elements) => {
+                for element in elements {
+                    self.compile_expression(element);
+                }
+                self.emit(Instruction::MakeList(elements.len()));
+            }
+            Expression::MapExpr(entries) => {
+                self.emit(Instruction::NewMap);
+                for (key, value) in entries {
+                    self.compile_expression(key);
+                    self.compile_expression(value);
+                    self.emit(Instruction::InsertEntry);
+                }
+            }
+            Expression::Index { left, index } => {
+                self.compile_expression(left);
+                self.compile_expression(index);
+                self.emit(Instruction::Index);
+            }
+            Expression::Slice { left, start, end } => {
+                self.compile_expression(left);
+                for bound in [start, end].into_iter().flatten() {
+                    self.compile_expression(bound);
+                }
+                self.emit(Instruction::Slice {
+                    start: start.is_some(),
+                    end: end.is_some(),
+                });
+            }
+            Expression::Input(kind) => {
+                self.emit(Instruction::Input(*kind));
+            }
+        }
+    }
+
+    /// Adds an instruction, returning its position
+    fn emit(&mut self, instruction: Instruction) -> usize {
+        self.chunk.code.push(instruction);
+        self.chunk.spans.push(self.spans.last().copied());
+        self.chunk.code.len() - 1
+    }
+
+    fn emit_constant(&mut self, value: Value) {
+        self.chunk.constants.push(value);
+        self.emit(Instruction::Constant(self.chunk.constants.len() - 1));
+    }
+
+    fn emit_error(&mut self, error: InterpreterError) {
+        self.chunk.errors.push(error);
+        self.emit(Instruction::Fail(self.chunk.errors.len() - 1));
+    }
+
+    /// Makes the jump at `position` land on the next instruction
+    fn patch_jump(&mut self, position: usize) {
+        self.set_jump_target(position, self.chunk.code.len());
+    }
+
+    fn set_jump_target(&mut self, position: usize, target: usize) {
+        match &mut self.chunk.code[position] {
+            Instruction::Jump(to)
+            | Instruction::JumpUnlessTrue(to)
+            | Instruction::ForNext { exit: to, .. } => *to = target,
+            other => unreachable!("{:?} is not a jump", other),
+        }
+    }
+}
+
+/// A statement the parser could not make sense of only fails once it is run
+fn invalid_statement(err: &ParseError) -> InterpreterError {
+    let error = InterpreterError::new_from_custom_error(&err.to_string(), SyntaxError);
+    match err.span {
+        Some(span) => error.with_span(span),
+        None => error,
+    }
+}
@@ -0,0 +1,56 @@
+use crate::evaluator::constants::{MAX_CALL_DEPTH, MAX_ITER_COUNT};
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::time::Duration;
+
+/// Limits on what a program may do while it runs, each with its own error
+/// type once it is crossed. The defaults are the interpreter's usual limits;
+/// the ones that are `None` are off unless set, for example by a playground
+/// running code it does not trust:
+///
+/// ```
+/// use zen::evaluator::config::InterpreterConfig;
+/// use zen::evaluator::interpreter::Interpreter;
+///
+/// let config = InterpreterConfig {
+///     max_steps: Some(1_000_000),
+///     max_output_bytes: Some(64 * 1024),
+///     ..InterpreterConfig::default()
+/// };
+/// let interpreter = Interpreter::new_with_config("", false, config);
+/// ```
+#[derive(Debug, Clone)]
+pub struct InterpreterConfig {
+    /// How many times a single loop may run (MaxLoopsExceeded)
+    pub max_iterations: usize,
+    /// How deeply function calls may nest (MaxCallDepthExceeded)
+    pub max_call_depth: usize,
+    /// How many instructions a run may take in total, across all loops and
+    /// calls (MaxStepsExceeded)
+    pub max_steps: Option<usize>,
+    /// How many bytes a run may print, newlines included
+    /// (MaxOutputExceeded)
+    pub max_output_bytes: Option<usize>,
+    /// How many variables may be alive at once, counting every scope of
+    /// every call (MaxVariablesExceeded)
+    pub max_variables: Option<usize>,
+    /// How long a run may take on the wall clock (MaxTimeExceeded)
+    pub timeout: Option<Duration>,
+    /// Stops the program once set to true, for example from another thread
+    /// when the user hits stop (ExecutionCancelled)
+    pub cancel: Option<Arc<AtomicBool>>,
+}
+
+impl Default for InterpreterConfig {
+    fn default() -> Self {
+        Self {
+            max_iterations: MAX_ITER_COUNT as usize,
+            max_call_depth: MAX_CALL_DEPTH,
+            max_steps: None,
+            max_output_bytes: None,
+            max_variables: None,
+            timeout: None,
+            cancel: None,
+        }
+    }
+}
@@ -1 +1,5 @@
 pub(crate) const MAX_ITER_COUNT: i32 = 100000;
+pub(crate) const MAX_CALL_DEPTH: usize = 200;
+/// How many instructions run between checks of the timeout and the
+/// cancellation flag
+pub(crate) const INTERRUPT_CHECK_INTERVAL: usize = 1024;
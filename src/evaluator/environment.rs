@@ -0,0 +1,123 @@
+use crate::evaluator::bytecode::{Symbol, SymbolMap, SymbolSet};
+use crate::evaluator::value::Value;
+
+pub(crate) type Scope = SymbolMap<Value>;
+
+/// Variable storage of a running program.
+///
+/// Every function call gets its own frame, which is a stack of block scopes
+/// (innermost last). The very first scope of the first frame is the global
+/// scope. Lookups walk the current frame outwards and finally fall back to the
+/// globals, so a function never sees its caller's locals. Assignments only
+/// ever touch the current frame, so a function can read globals but writing
+/// to one from inside a function creates a local instead.
+pub(crate) struct Environment {
+    frames: Vec<Vec<Scope>>,
+    // Names that lived in a scope which has already ended, for better errors
+    expired: SymbolSet,
+    /// Variables alive in all frames together
+    len: usize,
+}
+
+impl Default for Environment {
+    fn default() -> Self {
+        Self {
+            frames: vec![vec![Scope::default()]],
+            expired: Default::default(),
+            len: 0,
+        }
+    }
+}
+
+impl Environment {
+    pub(crate) fn get(&self, name: Symbol) -> Option<Value> {
+        self.current_frame()
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(&name))
+            .or_else(|| self.frames[0][0].get(&name))
+            .cloned()
+    }
+
+    /// Updates the nearest binding of `name` in the current frame, or creates
+    /// it in the innermost scope when there is none.
+    pub(crate) fn assign(&mut self, name: Symbol, value: Value) {
+        let frame = self.current_frame_mut();
+        match frame
+            .iter_mut()
+            .rev()
+            .find_map(|scope| scope.get_mut(&name))
+        {
+            Some(slot) => *slot = value,
+            None => self.declare(name, value),
+        }
+    }
+
+    /// Creates `name` in the innermost scope, shadowing any outer binding.
+    pub(crate) fn declare(&mut self, name: Symbol, value: Value) {
+        if let Some(scope) = self.current_frame_mut().last_mut() {
+            if scope.insert(name, value).is_none() {
+                self.len += 1;
+            }
+        }
+    }
+
+    pub(crate) fn has_expired(&self, name: Symbol) -> bool {
+        self.expired.contains(&name)
+    }
+
+    pub(crate) fn push_scope(&mut self) {
+        self.current_frame_mut().push(Scope::default());
+    }
+
+    pub(crate) fn pop_scope(&mut self) {
+        let frame = self.current_frame_mut();
+        // The outermost scope of a frame lives as long as the frame itself
+        if frame.len() > 1 {
+            if let Some(scope) = frame.pop() {
+                self.len -= scope.len();
+                self.expired.extend(scope.into_keys());
+            }
+        }
+    }
+
+    pub(crate) fn push_frame(&mut self, parameters: Scope) {
+        self.len += parameters.len();
+        self.frames.push(vec![parameters]);
+    }
+
+    pub(crate) fn pop_frame(&mut self) {
+        if self.frames.len() > 1 {
+            if let Some(frame) = self.frames.pop() {
+                self.len -= frame.iter().map(Scope::len).sum::<usize>();
+            }
+        }
+    }
+
+    /// Variables of the global scope, in no particular order
+    pub(crate) fn globals(&self) -> impl Iterator<Item = (&Symbol, &Value)> {
+        self.frames[0][0].iter()
+    }
+
+    /// Drops every frame and scope but the global one, as when a program
+    /// stops halfway through a function or block
+    pub(crate) fn unwind(&mut self) {
+        self.frames.truncate(1);
+        self.frames[0].truncate(1);
+        self.len = self.frames[0][0].len();
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    fn current_frame(&self) -> &Vec<Scope> {
+        self.frames.last().expect("global frame is never popped")
+    }
+
+    fn current_frame_mut(&mut self) -> &mut Vec<Scope> {
+        self.frames
+            .last_mut()
+            .expect("global frame is never popped")
+    }
+}
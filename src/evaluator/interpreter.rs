@@ -1,21 +1,55 @@
-use crate::evaluator::constants::MAX_ITER_COUNT;
+use crate::evaluator::builtins::call_builtin;
+use crate::evaluator::bytecode::{Chunk, Function, Instruction, Symbol, SymbolMap, Symbols};
+use crate::evaluator::compiler::Compiler;
+use crate::evaluator::config::InterpreterConfig;
+use crate::evaluator::constants::INTERRUPT_CHECK_INTERVAL;
+use crate::evaluator::environment::Environment;
 use crate::evaluator::interpreter::InterpreterErrorType::{
-    DeadlyError, DivisionByZero, EmptyCustomInputStack, IncompatibleDataType, InvalidInputError,
-    MaxLoopsExceeded, MissingStartSymbol, SyntaxError, UndefinedVariable, UnknownParserError,
+    ArgumentCountMismatch, DeadlyError, DivisionByZero, EmptyCustomInputStack, ExecutionCancelled,
+    IncompatibleDataType, IndexOutOfBounds, InvalidInputError, InvalidLoopStep,
+    LoopControlOutsideLoop, MaxCallDepthExceeded, MaxLoopsExceeded, MaxOutputExceeded,
+    MaxStepsExceeded, MaxTimeExceeded, MaxVariablesExceeded, MissingKey, MissingReturnValue,
+    MissingStartSymbol, ReturnOutsideFunction, SyntaxError, UndefinedFunction, UndefinedVariable,
+    UnknownParserError, VariableOutOfScope,
 };
-use crate::parser::ast::{Expression, Ident, Infix, Literal, Prefix, Program, Statement};
+use crate::evaluator::io::{
+    InputCursor, InputSource, MemoryInput, MemoryOutput, OutputSink, StdInput, StdOutput,
+};
+use crate::evaluator::value::{MapKey, Value};
+use crate::lexer::span::Span;
+use crate::parser::ast::{Infix, InputKind, Prefix, Program, Statement};
 use lazy_static::lazy_static;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt;
+use std::rc::Rc;
+use std::sync::atomic::Ordering;
+use std::time::Instant;
 
 pub struct Interpreter {
-    output: String,
-    variable_stack: HashMap<String, f64>,
-    input: String,
-    is_on_console: bool,
+    variable_stack: Environment,
+    functions: SymbolMap<Rc<Function>>,
+    symbols: Symbols,
+    input: InputCursor,
+    output: Box<dyn OutputSink>,
+    /// What `run_code` gives back, when the output goes to memory
+    captured: Option<MemoryOutput>,
+    config: InterpreterConfig,
+    /// Bytes printed so far in the current run
+    output_bytes: usize,
+}
+
+/// A function being run, or the program itself at the bottom
+struct CallFrame {
+    chunk: Rc<Chunk>,
+    /// The next instruction to run
+    ip: usize,
+    /// Where the values of this call start on the value stack
+    stack_base: usize,
+    function: Option<Symbol>,
+    wants_value: bool,
 }
 
-#[derive(Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub enum InterpreterErrorType {
     SyntaxError,
     MissingStartSymbol,
@@ -27,6 +61,21 @@ pub enum InterpreterErrorType {
     EmptyCustomInputStack,
     UnknownParserError,
     MaxLoopsExceeded,
+    UndefinedFunction,
+    ArgumentCountMismatch,
+    MissingReturnValue,
+    ReturnOutsideFunction,
+    MaxCallDepthExceeded,
+    VariableOutOfScope,
+    IndexOutOfBounds,
+    MissingKey,
+    InvalidLoopStep,
+    LoopControlOutsideLoop,
+    MaxStepsExceeded,
+    MaxOutputExceeded,
+    MaxVariablesExceeded,
+    MaxTimeExceeded,
+    ExecutionCancelled,
 }
 
 lazy_static! {
@@ -44,23 +93,88 @@ lazy_static! {
             "Incompatible data types! 'Ek chutki datatype ki keemat, tum kya jaano Ramesh babu.'",
         );
         m.insert(DeadlyError, "Oh no! Something went really wrong. 'Babuji ne kaha ERROR chhod do, sab ne kaha ERROR chhod do, lekin kisi ne yeh nahi bataya ki error kaise chhodte hain.'");
-        m.insert(InvalidInputError, "Expected numeral types as input only! 'Number chahiye, number! Number k alawa kuch nhi.'");
+        m.insert(
+            InvalidInputError,
+            "Expected a number as input! 'Number chahiye, number! Number k alawa kuch nhi.'",
+        );
         m.insert(
             EmptyCustomInputStack,
-            "Empty input stack! 'Uncle Ji, Uncle Ji, thoda data deejiye.'",
+            "No input left to read! 'Uncle Ji, Uncle Ji, thoda data deejiye.'",
         );
         m.insert(UnknownParserError, "Parsing error due to invalid syntax ! 'Mogambo dukhi hua... kyunki kuch toh gadbad hai ??'");
         m.insert(
             MaxLoopsExceeded,
             "Maximum iterations exceeded! Arre bas bhi kro bhai!",
         );
+        m.insert(
+            UndefinedFunction,
+            "Undefined function! 'Kahan se aate hain ye log?'",
+        );
+        m.insert(
+            ArgumentCountMismatch,
+            "Wrong number of arguments! 'Kitne aadmi the? Ginti toh sahi karo!'",
+        );
+        m.insert(
+            MissingReturnValue,
+            "Function returned nothing! 'Khaali haath laut aaye?'",
+        );
+        m.insert(
+            ReturnOutsideFunction,
+            "'LAUT KE AAJA' used outside a function! 'Jaana kahan hai, pehle aaye toh sahi.'",
+        );
+        m.insert(
+            MaxCallDepthExceeded,
+            "Maximum call depth exceeded! 'Tareekh pe tareekh, tareekh pe tareekh...'",
+        );
+        m.insert(
+            VariableOutOfScope,
+            "Variable used outside its scope! 'Jo bhi hai, bas yahi ek pal hai.'",
+        );
+        m.insert(
+            IndexOutOfBounds,
+            "Index out of bounds! 'Itni lambi chhalaang? Aukaat mein reh ke index karo.'",
+        );
+        m.insert(
+            MissingKey,
+            "Key not found in map! 'Chabi kho gayi, taala kaise khulega?'",
+        );
+        m.insert(
+            InvalidLoopStep,
+            "Loop step cannot be zero! 'Ek kadam bhi na chale, toh manzil kaise milegi?'",
+        );
+        m.insert(
+            LoopControlOutsideLoop,
+            "'BAS KAR PAGLE' or 'JAANE BHI DO' used outside a loop! 'Rukna kahan hai, jab chal hi nahi rahe?'",
+        );
+        m.insert(
+            MaxStepsExceeded,
+            "Maximum steps exceeded! 'Picture abhi baaki hai? Nahi, ab bas.'",
+        );
+        m.insert(
+            MaxOutputExceeded,
+            "Maximum output exceeded! 'Bas karo, kitna bologe? Ek baar jo maine commitment kar di...'",
+        );
+        m.insert(
+            MaxVariablesExceeded,
+            "Too many variables! 'Itne saare naam, itni saari yaadein. Ab aur jagah nahi.'",
+        );
+        m.insert(
+            MaxTimeExceeded,
+            "Time limit exceeded! 'Tareekh pe tareekh milti rahi, par time khatam ho gaya.'",
+        );
+        m.insert(
+            ExecutionCancelled,
+            "Program stopped! 'Cut! Cut! Cut!' The run was cancelled.",
+        );
         m
     };
 }
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct InterpreterError {
     pub msg: String,
     pub error_type: InterpreterErrorType,
+    /// Where in the source the error happened, when known
+    pub span: Option<Span>,
 }
 
 impl fmt::Display for InterpreterErrorType {
@@ -71,18 +185,28 @@ impl fmt::Display for InterpreterErrorType {
     }
 }
 
+impl fmt::Display for InterpreterError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.msg)
+    }
+}
+
+impl std::error::Error for InterpreterError {}
+
 impl InterpreterError {
     pub fn new(err_type: InterpreterErrorType) -> Self {
         let msg = ERROR_MESSAGES.get(&err_type).unwrap_or(&"Unknown Error");
         InterpreterError {
             msg: msg.to_string(),
             error_type: err_type,
+            span: None,
         }
     }
     pub fn new_from_custom_error(msg: &str, err_type: InterpreterErrorType) -> Self {
         InterpreterError {
             msg: msg.to_string(),
             error_type: err_type,
+            span: None,
         }
     }
     pub fn new_from_append_error(msg_append: &str, err_type: InterpreterErrorType) -> Self {
@@ -90,8 +214,16 @@ impl InterpreterError {
         InterpreterError {
             msg: msg.to_string() + msg_append,
             error_type: err_type,
+            span: None,
         }
     }
+
+    /// Records where the error happened, unless a more precise location is
+    /// already known
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span.get_or_insert(span);
+        self
+    }
 }
 
 impl Default for Interpreter {
@@ -102,436 +234,844 @@ impl Default for Interpreter {
 
 impl Interpreter {
     pub fn new(input: &str, is_on_console: bool) -> Self {
+        Self::new_with_config(input, is_on_console, InterpreterConfig::default())
+    }
+
+    /// Same as `new`, but with the limits of `config` instead of the default
+    /// ones
+    pub fn new_with_config(input: &str, is_on_console: bool, config: InterpreterConfig) -> Self {
+        // Input given up front is used even on the console
+        let input_source: Box<dyn InputSource> = if input.is_empty() && is_on_console {
+            Box::new(StdInput)
+        } else {
+            Box::new(MemoryInput::new(input))
+        };
+        if is_on_console {
+            return Self::new_with_io(input_source, Box::new(StdOutput), config);
+        }
+
+        let captured = MemoryOutput::default();
+        let mut interpreter = Self::new_with_io(input_source, Box::new(captured.clone()), config);
+        interpreter.captured = Some(captured);
+        interpreter
+    }
+
+    /// An interpreter that reads and prints through the given input and
+    /// output, such as a `CallbackOutput` streaming to a browser. `run_code`
+    /// then gives back an empty string.
+    pub fn new_with_io(
+        input: Box<dyn InputSource>,
+        output: Box<dyn OutputSink>,
+        config: InterpreterConfig,
+    ) -> Self {
         Self {
-            output: "".to_string(),
             variable_stack: Default::default(),
-            input: input.to_string(),
-            is_on_console,
+            functions: Default::default(),
+            symbols: Default::default(),
+            input: InputCursor::new(input),
+            output,
+            captured: None,
+            config,
+            output_bytes: 0,
         }
     }
-    pub fn run_code(&mut self, program_ast: Program) -> Result<String, InterpreterError> {
-        let mut program_ast_iter = program_ast.statements.iter();
 
-        if program_ast_iter.next() != Some(&Statement::ProgramStart) {
+    pub fn run_code(&mut self, program_ast: Program) -> Result<String, InterpreterError> {
+        if program_ast.statements.first().map(Statement::unspanned)
+            != Some(&Statement::ProgramStart)
+        {
             return Err(InterpreterError::new(MissingStartSymbol));
         }
 
-        for statement in program_ast_iter {
-            match statement {
-                Statement::ProgramStart => {
-                    return Err(InterpreterError::new_from_custom_error(
-                        "Only one 'PARAMPARA PRATISHTA ANUSHASHAN' allowed! 'Ek hi baar bolna kaafi hai.'",
-                        SyntaxError,
-                    ));
+        let chunk = Compiler::new(&mut self.symbols).compile_program(&program_ast.statements[1..]);
+        self.execute(Rc::new(chunk))?;
+
+        Ok(self
+            .captured
+            .as_ref()
+            .map(MemoryOutput::text)
+            .unwrap_or_default())
+    }
+
+    /// Runs statements on top of everything run before, as the REPL does:
+    /// variables and functions carry over from one call to the next. Gives
+    /// back only what these statements printed. After an error the program
+    /// is back at the top level, with its globals as the error left them.
+    pub fn run_statements(&mut self, statements: &[Statement]) -> Result<String, InterpreterError> {
+        let chunk = Compiler::new(&mut self.symbols).compile_program(statements);
+        let result = self.execute(Rc::new(chunk));
+        if result.is_err() {
+            self.variable_stack.unwind();
+        }
+        let output = self
+            .captured
+            .as_ref()
+            .map(MemoryOutput::take)
+            .unwrap_or_default();
+        result.map(|_| output)
+    }
+
+    /// The global variables and their values, sorted by name
+    pub fn globals(&self) -> Vec<(String, Value)> {
+        let mut globals: Vec<(String, Value)> = self
+            .variable_stack
+            .globals()
+            .map(|(name, value)| (self.symbols.name(*name).to_string(), value.clone()))
+            .collect();
+        globals.sort_by(|a, b| a.0.cmp(&b.0));
+        globals
+    }
+
+    fn execute(&mut self, chunk: Rc<Chunk>) -> Result<(), InterpreterError> {
+        let mut current = CallFrame {
+            chunk,
+            ip: 0,
+            stack_base: 0,
+            function: None,
+            wants_value: false,
+        };
+        let mut callers = Vec::new();
+        let mut stack = Vec::new();
+        let mut steps = 0;
+        self.output_bytes = 0;
+        let deadline = self.config.timeout.map(|timeout| Instant::now() + timeout);
+
+        loop {
+            steps += 1;
+            if self
+                .config
+                .max_steps
+                .is_some_and(|max_steps| steps > max_steps)
+            {
+                let err = InterpreterError::new(MaxStepsExceeded);
+                return Err(locate(err, &current, &callers));
+            }
+            // Looking at the clock costs more than a step, so it is only
+            // done every now and then
+            if steps % INTERRUPT_CHECK_INTERVAL == 0 {
+                if let Err(err) = self.check_interrupted(deadline) {
+                    return Err(locate(err, &current, &callers));
                 }
-                Statement::ProgramEnd => {
-                    break;
+            }
+            match self.step(&mut current, &mut callers, &mut stack) {
+                Ok(true) => {}
+                Ok(false) => return Ok(()),
+                Err(err) => return Err(locate(err, &current, &callers)),
+            }
+        }
+    }
+
+    /// Runs the next instruction, returning whether there is anything left
+    /// to run
+    fn step(
+        &mut self,
+        current: &mut CallFrame,
+        callers: &mut Vec<CallFrame>,
+        stack: &mut Vec<Value>,
+    ) -> Result<bool, InterpreterError> {
+        let Some(instruction) = current.chunk.code.get(current.ip) else {
+            return Ok(false);
+        };
+        current.ip += 1;
+
+        match instruction {
+            Instruction::Constant(index) => stack.push(current.chunk.constants[*index].clone()),
+            Instruction::Load(name) => stack.push(self.get_value_of(*name)?),
+            Instruction::Store(name) => {
+                // Redefinition of variables is allowed, even with a different type
+                let value = pop(stack);
+                self.variable_stack.assign(*name, value);
+                self.check_variable_count()?;
+            }
+            Instruction::Declare(name) => {
+                let value = pop(stack);
+                self.variable_stack.declare(*name, value);
+                self.check_variable_count()?;
+            }
+            Instruction::Pop => {
+                pop(stack);
+            }
+            Instruction::Prefix(operator) => {
+                let right = pop(stack);
+                stack.push(evaluate_prefix(operator, right)?);
+            }
+            Instruction::Infix(operator) => {
+                let right = pop(stack);
+                let left = pop(stack);
+                stack.push(evaluate_infix(operator, left, right)?);
+            }
+            Instruction::MakeList(len) => {
+                let elements = stack.split_off(stack.len() - len);
+                stack.push(Value::new_list(elements));
+            }
+            Instruction::NewMap => stack.push(Value::new_map(BTreeMap::new())),
+            Instruction::InsertEntry => {
+                let value = pop(stack);
+                let key = map_key(&pop(stack))?;
+                if let Some(Value::Map(map)) = stack.last() {
+                    map.borrow_mut().insert(key, value);
                 }
-                Statement::Let { name, value } => {
-                    self.set_value_in_stack(name, value)?;
+            }
+            Instruction::Index => {
+                let index = pop(stack);
+                let left = pop(stack);
+                stack.push(index_value(left, index)?);
+            }
+            Instruction::Slice { start, end } => {
+                let end = end.then(|| pop(stack));
+                let start = start.then(|| pop(stack));
+                let left = pop(stack);
+                stack.push(slice_value(left, start, end)?);
+            }
+            Instruction::SetElement => {
+                let value = pop(stack);
+                let index = pop(stack);
+                let target = pop(stack);
+                set_element(target, index, value)?;
+            }
+            Instruction::Input(kind) => stack.push(self.take_input(kind)?),
+            Instruction::Print => {
+                let value = pop(stack);
+                self.print_value(value)?;
+            }
+            Instruction::Jump(target) => current.ip = *target,
+            Instruction::JumpUnlessTrue(target) => {
+                if pop(stack) != Value::Bool(true) {
+                    current.ip = *target;
                 }
-                Statement::If {
-                    condition,
-                    consequence,
-                    alternative,
-                } => {
-                    let condition_truth_val = self.evaluate_expression(condition)?;
-                    if condition_truth_val == Literal::BoolLiteral(true) {
-                        let mut consequence = consequence.clone();
-                        consequence.insert(0, Statement::ProgramStart);
-                        consequence.push(Statement::ProgramEnd);
-
-                        self.run_code(Program {
-                            statements: consequence,
-                        })?;
-                    } else if let Some(statements) = alternative {
-                        let mut consequence = statements.clone();
-                        consequence.insert(0, Statement::ProgramStart);
-                        consequence.push(Statement::ProgramEnd);
-
-                        self.run_code(Program {
-                            statements: consequence,
-                        })?;
+            }
+            Instruction::PushScope => self.variable_stack.push_scope(),
+            Instruction::PopScope => self.variable_stack.pop_scope(),
+            Instruction::DefineFunction(index) => {
+                // Redefinition of functions is allowed, just like variables
+                let function = Rc::clone(&current.chunk.functions[*index]);
+                self.functions.insert(function.name, function);
+            }
+            Instruction::PrepareCall {
+                function,
+                arguments,
+            } => {
+                // Builtins only check their arguments once they are worked out
+                if let Some(definition) = self.functions.get(function) {
+                    if definition.parameters.len() != *arguments {
+                        return Err(InterpreterError::new_from_append_error(
+                            &format!(
+                                " '{}' expects {} argument(s) but got {}.",
+                                self.symbols.name(*function),
+                                definition.parameters.len(),
+                                arguments
+                            ),
+                            ArgumentCountMismatch,
+                        ));
                     }
-                }
-                Statement::While { condition, body } => {
-                    let mut iter_count = 0;
-                    loop {
-                        let condition_expr = self.evaluate_expression(condition)?;
-                        if condition_expr != Literal::BoolLiteral(true) {
-                            break;
-                        }
-
-                        let mut body = body.clone();
-                        body.insert(0, Statement::ProgramStart);
-                        body.push(Statement::ProgramEnd);
-
-                        self.run_code(Program { statements: body })?;
-                        iter_count += 1;
-
-                        if iter_count > MAX_ITER_COUNT {
-                            return Err(InterpreterError::new(MaxLoopsExceeded));
-                        }
+                    if callers.len() >= self.config.max_call_depth {
+                        return Err(InterpreterError::new(MaxCallDepthExceeded));
                     }
                 }
-                Statement::Print(expr) => {
-                    let value = self.evaluate_expression(expr)?;
-                    if self.is_on_console {
-                        match value {
-                            Literal::Number(num) => {
-                                println!("{}", num);
-                            }
-                            Literal::BoolLiteral(bool) => {
-                                println!("{}", bool);
-                            }
-                            Literal::StringLiteral(str) => {
-                                println!("{}", str);
-                            }
-                        }
-                    } else {
-                        match value {
-                            Literal::Number(num) => {
-                                self.output.push_str(&num.to_string());
-                            }
-                            Literal::BoolLiteral(bool) => {
-                                self.output.push_str(&bool.to_string());
-                            }
-                            Literal::StringLiteral(str) => {
-                                self.output.push_str(&str);
+            }
+            Instruction::Call {
+                function,
+                arguments,
+                wants_value,
+            } => {
+                let (function, wants_value) = (*function, *wants_value);
+                let values = stack.split_off(stack.len() - arguments);
+
+                let Some(definition) = self.functions.get(&function).cloned() else {
+                    return match call_builtin(self.symbols.name(function), values) {
+                        Some(result) => {
+                            let value = result?;
+                            if wants_value {
+                                stack.push(value);
                             }
+                            Ok(true)
                         }
-                        self.output.push('\n');
+                        None => Err(InterpreterError::new_from_custom_error(
+                            &format!(
+                                "Undefined function! 'Kaun hai ye {}? Kahan se aate hain ye log?'",
+                                self.symbols.name(function)
+                            ),
+                            UndefinedFunction,
+                        )),
+                    };
+                };
+
+                let parameters = definition.parameters.iter().copied().zip(values).collect();
+                self.variable_stack.push_frame(parameters);
+                self.check_variable_count()?;
+                let callee = CallFrame {
+                    chunk: Rc::clone(&definition.chunk),
+                    ip: 0,
+                    stack_base: stack.len(),
+                    function: Some(function),
+                    wants_value,
+                };
+                callers.push(std::mem::replace(current, callee));
+            }
+            Instruction::Return { has_value } => {
+                let value = has_value.then(|| pop(stack));
+                let callee = self.leave_function(current, callers, stack);
+                match (value, callee.wants_value) {
+                    (Some(value), true) => stack.push(value),
+                    (None, true) => {
+                        let name = callee.function.map_or("", |name| self.symbols.name(name));
+                        return Err(InterpreterError::new_from_append_error(
+                            &format!(" '{}' did not return anything.", name),
+                            MissingReturnValue,
+                        ));
                     }
+                    (_, false) => {}
+                }
+            }
+            Instruction::LoopControlInFunction => {
+                // Loops never reach across a function call
+                self.leave_function(current, callers, stack);
+                return Err(InterpreterError::new(LoopControlOutsideLoop));
+            }
+            Instruction::Fail(index) => return Err(current.chunk.errors[*index].clone()),
+            Instruction::WhileStart => stack.push(Value::Number(0_f64)),
+            Instruction::WhileStep => {
+                let iter_count = count_iteration(stack);
+                if iter_count > self.config.max_iterations as f64 {
+                    return Err(InterpreterError::new(MaxLoopsExceeded));
+                }
+            }
+            Instruction::CheckLoopBound => {
+                if let Some(other) = stack
+                    .last()
+                    .filter(|value| !matches!(value, Value::Number(_)))
+                {
+                    return Err(InterpreterError::new_from_append_error(
+                        &format!(" Loop bounds must be numbers, not a {}.", other.type_name()),
+                        IncompatibleDataType,
+                    ));
                 }
-                Statement::Expression(expr) => {
-                    self.evaluate_expression(expr)?;
+            }
+            Instruction::ForStart => {
+                if stack.last() == Some(&Value::Number(0_f64)) {
+                    return Err(InterpreterError::new(InvalidLoopStep));
                 }
+                stack.push(Value::Number(0_f64));
+            }
+            Instruction::ForNext { variable, exit } => {
+                let [start, end, step, iter_count] = loop_numbers(stack);
+                // Counting iterations instead of adding up the steps keeps
+                // fractional steps from drifting
+                let current_value = start + step * iter_count;
+                if (step > 0_f64 && current_value > end) || (step < 0_f64 && current_value < end) {
+                    current.ip = *exit;
+                } else if iter_count >= self.config.max_iterations as f64 {
+                    return Err(InterpreterError::new(MaxLoopsExceeded));
+                } else {
+                    self.variable_stack
+                        .declare(*variable, Value::Number(current_value));
+                    self.check_variable_count()?;
+                }
+            }
+            Instruction::ForStep => {
+                count_iteration(stack);
             }
         }
 
-        Ok(self.output.clone())
+        Ok(true)
     }
 
-    fn evaluate_expression(
+    /// Drops the frame of the function being left and goes back to its
+    /// caller, returning the frame that was left
+    fn leave_function(
         &mut self,
-        expression: &Expression,
-    ) -> Result<Literal, InterpreterError> {
-        match expression {
-            Expression::IdentifierExpr(ident) => self.get_value_of(ident),
-            Expression::LiteralExpr(literal) => Ok(literal.clone()),
-            Expression::PrefixExpr { .. } => self.evaluate_prefix_expression(expression),
-            Expression::InfixExpr { .. } => self.evaluate_infix_expression(expression),
-            Expression::Input => self.take_input_from_stdin(),
-        }
+        current: &mut CallFrame,
+        callers: &mut Vec<CallFrame>,
+        stack: &mut Vec<Value>,
+    ) -> CallFrame {
+        stack.truncate(current.stack_base);
+        self.variable_stack.pop_frame();
+        let caller = callers.pop().expect("functions are always called");
+        std::mem::replace(current, caller)
     }
 
-    fn take_input_from_stdin(&mut self) -> Result<Literal, InterpreterError> {
-        // TODO: Implement taking input from user, with possible account for string based input!
-        let mut value = String::new();
-        if self.input.is_empty() && self.is_on_console {
-            std::io::stdin()
-                .read_line(&mut value)
-                .expect("Failed to read line");
-
-            let value: f64 = match value.trim().parse() {
-                Ok(num) => num,
-                Err(_) => {
-                    return Err(InterpreterError::new(
-                        InterpreterErrorType::InvalidInputError,
-                    ))
-                }
-            };
+    fn print_value(&mut self, value: Value) -> Result<(), InterpreterError> {
+        let text = value.to_string();
+        // Nothing of a line goes out once it would cross the limit
+        self.output_bytes += text.len() + 1;
+        if self
+            .config
+            .max_output_bytes
+            .is_some_and(|max_output_bytes| self.output_bytes > max_output_bytes)
+        {
+            return Err(InterpreterError::new(MaxOutputExceeded));
+        }
 
-            Ok(Literal::Number(value))
-        } else {
-            let mut value = self.input.split_whitespace().collect::<Vec<&str>>();
+        self.output.write_line(&text);
+        Ok(())
+    }
 
-            if value.is_empty() {
-                return Err(InterpreterError::new(
-                    InterpreterErrorType::EmptyCustomInputStack,
-                ));
-            }
+    /// Fails once the run is past its deadline or has been cancelled
+    fn check_interrupted(&self, deadline: Option<Instant>) -> Result<(), InterpreterError> {
+        let cancelled = self
+            .config
+            .cancel
+            .as_ref()
+            .is_some_and(|cancel| cancel.load(Ordering::Relaxed));
+        if cancelled {
+            return Err(InterpreterError::new(ExecutionCancelled));
+        }
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            return Err(InterpreterError::new(MaxTimeExceeded));
+        }
+        Ok(())
+    }
 
-            let num_value: f64 = match value[0].trim().parse() {
-                Ok(num) => num,
-                Err(_) => {
-                    return Err(InterpreterError::new(
-                        InterpreterErrorType::InvalidInputError,
-                    ))
-                }
-            };
-            value.remove(0);
-            self.input = value.join(" ");
+    fn check_variable_count(&self) -> Result<(), InterpreterError> {
+        match self.config.max_variables {
+            Some(max_variables) if self.variable_stack.len() > max_variables => {
+                Err(InterpreterError::new(MaxVariablesExceeded))
+            }
+            _ => Ok(()),
+        }
+    }
 
-            Ok(Literal::Number(num_value))
+    fn take_input(&mut self, kind: &InputKind) -> Result<Value, InterpreterError> {
+        let text = self.input.read(kind)?;
+        match kind {
+            InputKind::Number => match text.parse() {
+                Ok(num) => Ok(Value::Number(num)),
+                Err(_) => Err(InterpreterError::new(
+                    InterpreterErrorType::InvalidInputError,
+                )),
+            },
+            InputKind::Word | InputKind::Line => Ok(Value::Str(text)),
         }
     }
 
-    fn get_value_of(&self, ident: &Ident) -> Result<Literal, InterpreterError> {
-        match self.variable_stack.get(&ident.0) {
-            Some(t) => Ok(Literal::Number(*t)),
+    fn get_value_of(&self, name: Symbol) -> Result<Value, InterpreterError> {
+        match self.variable_stack.get(name) {
+            Some(t) => Ok(t),
+            None if self.variable_stack.has_expired(name) => {
+                Err(InterpreterError::new_from_append_error(
+                    &format!(
+                        " '{}' only lived inside a block that has already ended.",
+                        self.symbols.name(name)
+                    ),
+                    VariableOutOfScope,
+                ))
+            }
             None => Err(InterpreterError::new_from_custom_error(
                 &format!(
                     "Undefined variable! 'Tumhara value kya hai, {}? , Batao bhi Basanti !'",
-                    ident.0
+                    self.symbols.name(name)
                 ),
                 UndefinedVariable,
             )),
         }
     }
+}
 
-    fn set_value_in_stack(
-        &mut self,
-        ident: &Ident,
-        value: &Expression,
-    ) -> Result<(), InterpreterError> {
-        let num_val = self.evaluate_expression(value)?;
-        // Redefinition of variables is allowed
-        if let Literal::Number(num) = num_val {
-            self.variable_stack.insert(ident.0.clone(), num);
-            return Ok(());
-        }
-        Err(InterpreterError::new(IncompatibleDataType))
+/// Points an error at the instruction that failed. Instructions without a
+/// location of their own are blamed on the call that led to them, and so
+/// are limits that trip before a function has run anything.
+fn locate(err: InterpreterError, current: &CallFrame, callers: &[CallFrame]) -> InterpreterError {
+    let span = std::iter::once(current)
+        .chain(callers.iter().rev())
+        .find_map(|frame| frame.chunk.spans[frame.ip.checked_sub(1)?]);
+    match span {
+        Some(span) => err.with_span(span),
+        None => err,
     }
+}
 
-    fn evaluate_prefix_expression(
-        &mut self,
-        expression: &Expression,
-    ) -> Result<Literal, InterpreterError> {
-        if let Expression::PrefixExpr { operator, right } = expression {
-            let right = self.evaluate_expression(right)?;
-
-            return match operator {
-                Prefix::PrefixPlus => {
-                    if let Literal::Number(num_right) = right {
-                        return Ok(Literal::Number(num_right));
-                    }
+fn pop(stack: &mut Vec<Value>) -> Value {
+    stack.pop().expect("compiled code keeps the stack balanced")
+}
 
-                    Err(InterpreterError::new_from_append_error(
-                        "Only numeral types allowed with unary addition!",
-                        IncompatibleDataType,
-                    ))
-                }
-                Prefix::PrefixMinus => {
-                    if let Literal::Number(num_right) = right {
-                        return Ok(Literal::Number(-1_f64 * num_right));
-                    }
+/// Adds one to the iteration count on top of the stack, returning it
+fn count_iteration(stack: &mut [Value]) -> f64 {
+    match stack.last_mut() {
+        Some(Value::Number(iter_count)) => {
+            *iter_count += 1_f64;
+            *iter_count
+        }
+        _ => unreachable!("loops keep their iteration count on the stack"),
+    }
+}
 
-                    Err(InterpreterError::new_from_append_error(
-                        "Only numeral types allowed with unary negation!",
-                        IncompatibleDataType,
-                    ))
+/// Start, end, step and iteration count of the innermost 'EK DO TEEN' loop
+fn loop_numbers(stack: &[Value]) -> [f64; 4] {
+    let mut numbers = [0_f64; 4];
+    for (number, value) in numbers.iter_mut().zip(&stack[stack.len() - 4..]) {
+        match value {
+            Value::Number(num) => *number = *num,
+            _ => unreachable!("loop bounds are checked when the loop starts"),
+        }
+    }
+    numbers
+}
+
+fn index_value(left: Value, index: Value) -> Result<Value, InterpreterError> {
+    match &left {
+        Value::Str(str) => {
+            let position = index_position(&index, str.chars().count())?;
+            Ok(Value::Str(str.chars().skip(position).take(1).collect()))
+        }
+        Value::List(list) => {
+            let list = list.borrow();
+            let position = index_position(&index, list.len())?;
+            Ok(list[position].clone())
+        }
+        Value::Map(map) => {
+            let key = map_key(&index)?;
+            map.borrow().get(&key).cloned().ok_or_else(|| {
+                InterpreterError::new_from_append_error(
+                    &format!(" There is no key {} in this map.", key),
+                    MissingKey,
+                )
+            })
+        }
+        other => Err(InterpreterError::new_from_append_error(
+            &format!(" A {} cannot be indexed.", other.type_name()),
+            IncompatibleDataType,
+        )),
+    }
+}
+
+fn slice_value(
+    left: Value,
+    start: Option<Value>,
+    end: Option<Value>,
+) -> Result<Value, InterpreterError> {
+    match &left {
+        Value::Str(str) => {
+            let (start, end) = slice_bounds(start.as_ref(), end.as_ref(), str.chars().count())?;
+            Ok(Value::Str(
+                str.chars().skip(start).take(end - start).collect(),
+            ))
+        }
+        Value::List(list) => {
+            let list = list.borrow();
+            let (start, end) = slice_bounds(start.as_ref(), end.as_ref(), list.len())?;
+            Ok(Value::new_list(list[start..end].to_vec()))
+        }
+        other => Err(InterpreterError::new_from_append_error(
+            &format!(" A {} cannot be sliced.", other.type_name()),
+            IncompatibleDataType,
+        )),
+    }
+}
+
+fn set_element(target: Value, index: Value, value: Value) -> Result<(), InterpreterError> {
+    match &target {
+        Value::List(list) => {
+            let mut list = list.borrow_mut();
+            let position = index_position(&index, list.len())?;
+            list[position] = value;
+        }
+        Value::Map(map) => {
+            map.borrow_mut().insert(map_key(&index)?, value);
+        }
+        other => {
+            return Err(InterpreterError::new_from_append_error(
+                &format!(" Cannot change an element of a {}.", other.type_name()),
+                IncompatibleDataType,
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn evaluate_prefix(operator: &Prefix, right: Value) -> Result<Value, InterpreterError> {
+    match operator {
+        Prefix::PrefixPlus => {
+            if let Value::Number(num_right) = right {
+                return Ok(Value::Number(num_right));
+            }
+
+            Err(InterpreterError::new_from_append_error(
+                "Only numeral types allowed with unary addition!",
+                IncompatibleDataType,
+            ))
+        }
+        Prefix::PrefixMinus => {
+            if let Value::Number(num_right) = right {
+                return Ok(Value::Number(-num_right));
+            }
+
+            Err(InterpreterError::new_from_append_error(
+                "Only numeral types allowed with unary negation!",
+                IncompatibleDataType,
+            ))
+        }
+        Prefix::Not => {
+            if let Value::Bool(bool_right) = right {
+                return Ok(Value::Bool(!bool_right));
+            }
+
+            Err(InterpreterError::new_from_custom_error(
+                "Only boolean types allowed with not! 'Yeh Kya Ho gya hai duniyan ko ??'",
+                IncompatibleDataType,
+            ))
+        }
+    }
+}
+
+fn evaluate_infix(operator: &Infix, left: Value, right: Value) -> Result<Value, InterpreterError> {
+    match operator {
+        Infix::Plus => match (&left, &right) {
+            (Value::Number(num_left), Value::Number(num_right)) => {
+                Ok(Value::Number(num_left + num_right))
+            }
+            // Joining with a string converts the other side to text
+            (Value::Str(str_left), right) => Ok(Value::Str(format!("{}{}", str_left, right))),
+            (left, Value::Str(str_right)) => Ok(Value::Str(format!("{}{}", left, str_right))),
+            _ => Err(InterpreterError::new_from_append_error(
+                "Only numeral or string types allowed with addition! ",
+                IncompatibleDataType,
+            )),
+        },
+        Infix::Minus => {
+            if let Value::Number(num_left) = left {
+                if let Value::Number(num_right) = right {
+                    return Ok(Value::Number(num_left - num_right));
                 }
-                Prefix::Not => {
-                    if let Literal::BoolLiteral(bool_right) = right {
-                        return Ok(Literal::BoolLiteral(!bool_right));
+            }
+            Err(InterpreterError::new_from_append_error(
+                "Only numeral types allowed with subtraction! 'Number chahiye, number!",
+                IncompatibleDataType,
+            ))
+        }
+        Infix::Multiply => {
+            if let Value::Number(num_left) = left {
+                if let Value::Number(num_right) = right {
+                    return Ok(Value::Number(num_left * num_right));
+                }
+            }
+            Err(InterpreterError::new_from_append_error(
+                "Only numeral types allowed with multiplication!",
+                IncompatibleDataType,
+            ))
+        }
+        Infix::Divide => {
+            if let Value::Number(num_left) = left {
+                if let Value::Number(num_right) = right {
+                    if num_right == 0_f64 {
+                        return Err(InterpreterError::new(DivisionByZero));
                     }
-
-                    Err(InterpreterError::new_from_custom_error(
-                        "Only boolean types allowed with not! 'Yeh Kya Ho gya hai duniyan ko ??'",
-                        IncompatibleDataType,
-                    ))
+                    return Ok(Value::Number(num_left / num_right));
                 }
-            };
+            }
+            Err(InterpreterError::new_from_append_error(
+                "Only numeral types allowed with division!",
+                IncompatibleDataType,
+            ))
         }
-        Err(InterpreterError::new(DeadlyError))
-    }
-    fn evaluate_infix_expression(
-        &mut self,
-        expression: &Expression,
-    ) -> Result<Literal, InterpreterError> {
-        if let Expression::InfixExpr {
-            left,
-            operator,
-            right,
-        } = expression
-        {
-            let left = self.evaluate_expression(left)?;
-            let right = self.evaluate_expression(right)?;
-
-            return match operator {
-                Infix::Plus => {
-                    if let Literal::Number(num_left) = left {
-                        if let Literal::Number(num_right) = right {
-                            return Ok(Literal::Number(num_left + num_right));
-                        }
+        Infix::Modulo => {
+            if let Value::Number(num_left) = left {
+                if let Value::Number(num_right) = right {
+                    return Ok(Value::Number(num_left % num_right));
+                }
+            }
+            Err(InterpreterError::new_from_append_error(
+                "Only numeral types allowed with modulus! ",
+                IncompatibleDataType,
+            ))
+        }
+
+        Infix::Equal => {
+            match &left {
+                Value::Number(num_left) => {
+                    if let Value::Number(num_right) = &right {
+                        return Ok(Value::Bool(num_left == num_right));
                     }
-                    Err(InterpreterError::new_from_append_error(
-                        "Only numeral types allowed with addition! ",
-                        IncompatibleDataType,
-                    ))
                 }
-                Infix::Minus => {
-                    if let Literal::Number(num_left) = left {
-                        if let Literal::Number(num_right) = right {
-                            return Ok(Literal::Number(num_left - num_right));
-                        }
+                Value::Bool(bool_left) => {
+                    if let Value::Bool(bool_right) = &right {
+                        return Ok(Value::Bool(bool_left == bool_right));
                     }
-                    Err(InterpreterError::new_from_append_error(
-                        "Only numeral types allowed with subtraction! 'Number chahiye, number!",
-                        IncompatibleDataType,
-                    ))
                 }
-                Infix::Multiply => {
-                    if let Literal::Number(num_left) = left {
-                        if let Literal::Number(num_right) = right {
-                            return Ok(Literal::Number(num_left * num_right));
-                        }
+                Value::Str(str_left) => {
+                    if let Value::Str(str_right) = &right {
+                        return Ok(Value::Bool(str_left == str_right));
                     }
-                    Err(InterpreterError::new_from_append_error(
-                        "Only numeral types allowed with multiplication!",
-                        IncompatibleDataType,
-                    ))
                 }
-                Infix::Divide => {
-                    if let Literal::Number(num_left) = left {
-                        if let Literal::Number(num_right) = right {
-                            if num_right == 0_f64 {
-                                return Err(InterpreterError::new(DivisionByZero));
-                            }
-                            return Ok(Literal::Number(num_left / num_right));
-                        }
+                Value::List(_) => {
+                    if let Value::List(_) = &right {
+                        return Ok(Value::Bool(left == right));
                     }
-                    Err(InterpreterError::new_from_append_error(
-                        "Only numeral types allowed with division!",
-                        IncompatibleDataType,
-                    ))
                 }
-                Infix::Modulo => {
-                    if let Literal::Number(num_left) = left {
-                        if let Literal::Number(num_right) = right {
-                            return Ok(Literal::Number(num_left % num_right));
-                        }
+                Value::Map(_) => {
+                    if let Value::Map(_) = &right {
+                        return Ok(Value::Bool(left == right));
                     }
-                    Err(InterpreterError::new_from_append_error(
-                        "Only numeral types allowed with modulus! ",
-                        IncompatibleDataType,
-                    ))
                 }
+            }
 
-                Infix::Equal => {
-                    match left {
-                        Literal::Number(num_left) => {
-                            if let Literal::Number(num_right) = right {
-                                return Ok(Literal::BoolLiteral(num_left == num_right));
-                            }
-                        }
-                        Literal::BoolLiteral(bool_left) => {
-                            if let Literal::BoolLiteral(bool_right) = right {
-                                return Ok(Literal::BoolLiteral(bool_left == bool_right));
-                            }
-                        }
-                        Literal::StringLiteral(str_left) => {
-                            if let Literal::StringLiteral(str_right) = right {
-                                return Ok(Literal::BoolLiteral(str_left == str_right));
-                            }
-                        }
+            Err(InterpreterError::new_from_custom_error(
+                "Comparison with only homogeneous data types is allowed! 'Ek chutki datatype ki keemat, tum kya jaano Ramesh babu.'",
+                IncompatibleDataType,
+            ))
+        }
+        Infix::NotEqual => {
+            match &left {
+                Value::Number(num_left) => {
+                    if let Value::Number(num_right) = &right {
+                        return Ok(Value::Bool(num_left != num_right));
                     }
-
-                    Err(InterpreterError::new_from_custom_error(
-                        "Comparison with only homogeneous data types is allowed! 'Ek chutki datatype ki keemat, tum kya jaano Ramesh babu.'",
-                        IncompatibleDataType,
-                    ))
                 }
-                Infix::NotEqual => {
-                    match left {
-                        Literal::Number(num_left) => {
-                            if let Literal::Number(num_right) = right {
-                                return Ok(Literal::BoolLiteral(num_left != num_right));
-                            }
-                        }
-                        Literal::BoolLiteral(bool_left) => {
-                            if let Literal::BoolLiteral(bool_right) = right {
-                                return Ok(Literal::BoolLiteral(bool_left != bool_right));
-                            }
-                        }
-                        Literal::StringLiteral(str_left) => {
-                            if let Literal::StringLiteral(str_right) = right {
-                                return Ok(Literal::BoolLiteral(str_left != str_right));
-                            }
-                        }
+                Value::Bool(bool_left) => {
+                    if let Value::Bool(bool_right) = &right {
+                        return Ok(Value::Bool(bool_left != bool_right));
                     }
-
-                    Err(InterpreterError::new_from_append_error(
-                        "Comparison with only homogeneous data types is allowed!",
-                        IncompatibleDataType,
-                    ))
                 }
-                Infix::GreaterThan => {
-                    if let Literal::Number(num_left) = left {
-                        if let Literal::Number(num_right) = right {
-                            return Ok(Literal::BoolLiteral(num_left > num_right));
-                        }
+                Value::Str(str_left) => {
+                    if let Value::Str(str_right) = &right {
+                        return Ok(Value::Bool(str_left != str_right));
                     }
-
-                    Err(InterpreterError::new_from_append_error(
-                        "Comparison with only numeral data types is allowed!",
-                        IncompatibleDataType,
-                    ))
                 }
-                Infix::GreaterThanEqual => {
-                    if let Literal::Number(num_left) = left {
-                        if let Literal::Number(num_right) = right {
-                            return Ok(Literal::BoolLiteral(num_left >= num_right));
-                        }
+                Value::List(_) => {
+                    if let Value::List(_) = &right {
+                        return Ok(Value::Bool(left != right));
                     }
-
-                    Err(InterpreterError::new_from_append_error(
-                        "Comparison with only numeral data types is allowed! ",
-                        IncompatibleDataType,
-                    ))
                 }
-                Infix::LessThan => {
-                    if let Literal::Number(num_left) = left {
-                        if let Literal::Number(num_right) = right {
-                            return Ok(Literal::BoolLiteral(num_left < num_right));
-                        }
+                Value::Map(_) => {
+                    if let Value::Map(_) = &right {
+                        return Ok(Value::Bool(left != right));
                     }
+                }
+            }
 
-                    Err(InterpreterError::new_from_append_error(
-                        "Comparison with only numeral data types is allowed! ",
-                        IncompatibleDataType,
-                    ))
+            Err(InterpreterError::new_from_append_error(
+                "Comparison with only homogeneous data types is allowed!",
+                IncompatibleDataType,
+            ))
+        }
+        Infix::GreaterThan => {
+            if let Value::Number(num_left) = left {
+                if let Value::Number(num_right) = right {
+                    return Ok(Value::Bool(num_left > num_right));
                 }
-                Infix::LessThanEqual => {
-                    if let Literal::Number(num_left) = left {
-                        if let Literal::Number(num_right) = right {
-                            return Ok(Literal::BoolLiteral(num_left <= num_right));
-                        }
-                    }
+            }
 
-                    Err(InterpreterError::new_from_append_error(
-                        "Comparison with only numeral data types is allowed! ",
-                        IncompatibleDataType,
-                    ))
+            Err(InterpreterError::new_from_append_error(
+                "Comparison with only numeral data types is allowed!",
+                IncompatibleDataType,
+            ))
+        }
+        Infix::GreaterThanEqual => {
+            if let Value::Number(num_left) = left {
+                if let Value::Number(num_right) = right {
+                    return Ok(Value::Bool(num_left >= num_right));
                 }
+            }
 
-                Infix::LogicalAnd => {
-                    if let Literal::BoolLiteral(bool_left) = left {
-                        if let Literal::BoolLiteral(bool_right) = right {
-                            return Ok(Literal::BoolLiteral(bool_left && bool_right));
-                        }
-                    }
+            Err(InterpreterError::new_from_append_error(
+                "Comparison with only numeral data types is allowed! ",
+                IncompatibleDataType,
+            ))
+        }
+        Infix::LessThan => {
+            if let Value::Number(num_left) = left {
+                if let Value::Number(num_right) = right {
+                    return Ok(Value::Bool(num_left < num_right));
+                }
+            }
 
-                    Err(InterpreterError::new_from_append_error(
-                        "Comparison with only boolean data types is allowed! ",
-                        IncompatibleDataType,
-                    ))
+            Err(InterpreterError::new_from_append_error(
+                "Comparison with only numeral data types is allowed! ",
+                IncompatibleDataType,
+            ))
+        }
+        Infix::LessThanEqual => {
+            if let Value::Number(num_left) = left {
+                if let Value::Number(num_right) = right {
+                    return Ok(Value::Bool(num_left <= num_right));
                 }
-                Infix::LogicalOr => {
-                    if let Literal::BoolLiteral(bool_left) = left {
-                        if let Literal::BoolLiteral(bool_right) = right {
-                            return Ok(Literal::BoolLiteral(bool_left || bool_right));
-                        }
-                    }
+            }
 
-                    Err(InterpreterError::new_from_append_error(
-                        "Comparison with only boolean data types is allowed!",
-                        IncompatibleDataType,
-                    ))
+            Err(InterpreterError::new_from_append_error(
+                "Comparison with only numeral data types is allowed! ",
+                IncompatibleDataType,
+            ))
+        }
+
+        Infix::LogicalAnd => {
+            if let Value::Bool(bool_left) = left {
+                if let Value::Bool(bool_right) = right {
+                    return Ok(Value::Bool(bool_left && bool_right));
+                }
+            }
+
+            Err(InterpreterError::new_from_append_error(
+                "Comparison with only boolean data types is allowed! ",
+                IncompatibleDataType,
+            ))
+        }
+        Infix::LogicalOr => {
+            if let Value::Bool(bool_left) = left {
+                if let Value::Bool(bool_right) = right {
+                    return Ok(Value::Bool(bool_left || bool_right));
                 }
-            };
+            }
+
+            Err(InterpreterError::new_from_append_error(
+                "Comparison with only boolean data types is allowed!",
+                IncompatibleDataType,
+            ))
         }
-        Err(InterpreterError::new(DeadlyError))
     }
 }
+
+fn whole_number(value: &Value) -> Result<f64, InterpreterError> {
+    match value {
+        Value::Number(num) if num.fract() == 0_f64 => Ok(*num),
+        other => Err(InterpreterError::new_from_append_error(
+            &format!(" Positions must be whole numbers, not {}.", other),
+            IncompatibleDataType,
+        )),
+    }
+}
+
+/// Checks that `index` points at an element of a sequence of `len` elements
+pub(crate) fn index_position(index: &Value, len: usize) -> Result<usize, InterpreterError> {
+    let position = whole_number(index)?;
+    if position < 0_f64 || position >= len as f64 {
+        return Err(InterpreterError::new_from_append_error(
+            &format!(" Position {} asked, but length is only {}.", position, len),
+            IndexOutOfBounds,
+        ));
+    }
+    Ok(position as usize)
+}
+
+/// Turns a value into a map key, rejecting types that cannot be keys
+pub(crate) fn map_key(key: &Value) -> Result<MapKey, InterpreterError> {
+    MapKey::from_value(key).ok_or_else(|| {
+        InterpreterError::new_from_append_error(
+            &format!(" A {} cannot be used as a map key.", key.type_name()),
+            IncompatibleDataType,
+        )
+    })
+}
+
+/// Resolves the optional bounds of a slice, clamping them to the sequence
+fn slice_bounds(
+    start: Option<&Value>,
+    end: Option<&Value>,
+    len: usize,
+) -> Result<(usize, usize), InterpreterError> {
+    let bound = |value: Option<&Value>, default: usize| match value {
+        Some(value) => {
+            let position = whole_number(value)?;
+            if position < 0_f64 {
+                return Err(InterpreterError::new_from_append_error(
+                    &format!(" Slice bounds cannot be negative, got {}.", position),
+                    IndexOutOfBounds,
+                ));
+            }
+            Ok((position as usize).min(len))
+        }
+        None => Ok(default),
+    };
+
+    let start = bound(start, 0)?;
+    let end = bound(end, len)?;
+    Ok((start, end.max(start)))
+}
@@ -0,0 +1,211 @@
+use crate::evaluator::interpreter::{InterpreterError, InterpreterErrorType};
+use crate::parser::ast::InputKind;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Where 'INPUT LE LE RE BABA' and the other input forms read from.
+///
+/// Each call gives the next line of input without its line ending, or `None`
+/// once there is no input left. The interpreter splits the lines into words
+/// itself, so every source reads the same way.
+pub trait InputSource {
+    fn read_line(&mut self) -> Option<String>;
+}
+
+/// Where 'PRINT BASANTI PRINT' writes to, one line at a time. The line comes
+/// without its newline.
+pub trait OutputSink {
+    fn write_line(&mut self, line: &str);
+}
+
+/// A position in the input, moving forward as the three input forms read it:
+///
+/// - a number or a word is the next word, whichever line it is on
+/// - a line is the rest of the line the last word was read from, or the whole
+///   next line when nothing but spaces is left there
+pub(crate) struct InputCursor {
+    source: Box<dyn InputSource>,
+    line: String,
+    position: usize,
+}
+
+impl InputCursor {
+    pub(crate) fn new(source: Box<dyn InputSource>) -> Self {
+        Self {
+            source,
+            line: String::new(),
+            position: 0,
+        }
+    }
+
+    pub(crate) fn read(&mut self, kind: &InputKind) -> Result<String, InterpreterError> {
+        let text = match kind {
+            InputKind::Number | InputKind::Word => self.word(),
+            InputKind::Line => self.line(),
+        };
+        text.ok_or_else(|| InterpreterError::new(InterpreterErrorType::EmptyCustomInputStack))
+    }
+
+    fn rest(&self) -> &str {
+        self.line[self.position..].trim_start_matches(is_space)
+    }
+
+    fn next_line(&mut self) -> Option<()> {
+        self.line = self.source.read_line()?;
+        self.position = 0;
+        Some(())
+    }
+
+    fn word(&mut self) -> Option<String> {
+        while self.rest().is_empty() {
+            self.next_line()?;
+        }
+        let start = self.line.len() - self.rest().len();
+        let end = self.line[start..]
+            .find(is_space)
+            .map_or(self.line.len(), |len| start + len);
+        self.position = end;
+        Some(self.line[start..end].to_string())
+    }
+
+    fn line(&mut self) -> Option<String> {
+        if self.rest().is_empty() {
+            self.next_line()?;
+            // Nothing is left of the line once it is handed out whole
+            return Some(std::mem::take(&mut self.line));
+        }
+        let line = self.rest().to_string();
+        self.position = self.line.len();
+        Some(line)
+    }
+}
+
+/// Reads the lines of the console
+#[derive(Default)]
+pub struct StdInput;
+
+impl InputSource for StdInput {
+    fn read_line(&mut self) -> Option<String> {
+        let mut line = String::new();
+        let read = std::io::stdin()
+            .read_line(&mut line)
+            .expect("Failed to read line");
+        if read == 0 {
+            return None;
+        }
+        Some(without_line_ending(&line).to_string())
+    }
+}
+
+/// Prints every line on the console
+#[derive(Default)]
+pub struct StdOutput;
+
+impl OutputSink for StdOutput {
+    fn write_line(&mut self, line: &str) {
+        println!("{}", line);
+    }
+}
+
+/// Input given up front, read line by line just like the console. Only the
+/// position of the next line moves, so reading all of the input takes time
+/// in proportion to its length, however many values it holds.
+pub struct MemoryInput {
+    input: String,
+    position: usize,
+}
+
+impl MemoryInput {
+    pub fn new(input: &str) -> Self {
+        Self {
+            input: input.to_string(),
+            position: 0,
+        }
+    }
+}
+
+impl InputSource for MemoryInput {
+    fn read_line(&mut self) -> Option<String> {
+        let rest = &self.input[self.position..];
+        if rest.is_empty() {
+            return None;
+        }
+        let len = rest.find('\n').map_or(rest.len(), |end| end + 1);
+        self.position += len;
+        Some(without_line_ending(&rest[..len]).to_string())
+    }
+}
+
+/// The spaces between words, the same ASCII ones the compiled runtimes know
+fn is_space(c: char) -> bool {
+    matches!(c, ' ' | '\t'..='\r')
+}
+
+fn without_line_ending(line: &str) -> &str {
+    let line = line.strip_suffix('\n').unwrap_or(line);
+    line.strip_suffix('\r').unwrap_or(line)
+}
+
+/// Collects the printed lines in memory. Clones share the same text, so one
+/// clone can go to the interpreter while another reads what was printed.
+#[derive(Default, Clone)]
+pub struct MemoryOutput {
+    text: Rc<RefCell<String>>,
+}
+
+impl MemoryOutput {
+    /// Everything printed so far, each line ending with a newline
+    pub fn text(&self) -> String {
+        self.text.borrow().clone()
+    }
+
+    /// Same as `text`, but also forgets the text, so that the next call only
+    /// gives what is printed after this one
+    pub fn take(&self) -> String {
+        std::mem::take(&mut self.text.borrow_mut())
+    }
+}
+
+impl OutputSink for MemoryOutput {
+    fn write_line(&mut self, line: &str) {
+        let mut text = self.text.borrow_mut();
+        text.push_str(line);
+        text.push('\n');
+    }
+}
+
+/// Asks a function for every line of input, e.g. one that waits for a
+/// message from a browser
+pub struct CallbackInput<F: FnMut() -> Option<String>> {
+    callback: F,
+}
+
+impl<F: FnMut() -> Option<String>> CallbackInput<F> {
+    pub fn new(callback: F) -> Self {
+        Self { callback }
+    }
+}
+
+impl<F: FnMut() -> Option<String>> InputSource for CallbackInput<F> {
+    fn read_line(&mut self) -> Option<String> {
+        (self.callback)()
+    }
+}
+
+/// Hands every printed line to a function, e.g. one that streams it to a
+/// websocket
+pub struct CallbackOutput<F: FnMut(&str)> {
+    callback: F,
+}
+
+impl<F: FnMut(&str)> CallbackOutput<F> {
+    pub fn new(callback: F) -> Self {
+        Self { callback }
+    }
+}
+
+impl<F: FnMut(&str)> OutputSink for CallbackOutput<F> {
+    fn write_line(&mut self, line: &str) {
+        (self.callback)(line)
+    }
+}
@@ -0,0 +1,309 @@
+use crate::parser::ast::Literal;
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::rc::Rc;
+
+/// A runtime value, as held by variables and produced by expressions.
+///
+/// Lists and maps are shared by reference, so a list passed to a function or
+/// stored in a second variable is the same list, not a copy. That also lets
+/// a list or map contain itself, so printing and comparing keep track of
+/// the containers they are already inside of. Nesting can go deeper than
+/// the native stack, so printing, comparing and dropping never recurse.
+#[derive(Debug, Clone)]
+pub enum Value {
+    Number(f64),
+    Bool(bool),
+    Str(String),
+    List(Rc<RefCell<Vec<Value>>>),
+    Map(Rc<RefCell<BTreeMap<MapKey, Value>>>),
+}
+
+/// What is left to write of a value inside a collection
+enum Pending {
+    Value(Value),
+    Text(&'static str),
+    Key(MapKey),
+    /// The end of a container, after which it can be met again without
+    /// being a cycle
+    Close(*const (), &'static str),
+}
+
+/// A key of a map. Only numbers and strings can be used as keys; numbers sort
+/// before strings, which keeps iteration over the keys predictable.
+#[derive(Debug, Clone)]
+pub enum MapKey {
+    Number(f64),
+    Str(String),
+}
+
+impl Value {
+    /// Name of the value's type, as shown to the user in error messages
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Number(_) => "number",
+            Value::Bool(_) => "boolean",
+            Value::Str(_) => "string",
+            Value::List(_) => "list",
+            Value::Map(_) => "map",
+        }
+    }
+
+    pub fn new_map(entries: BTreeMap<MapKey, Value>) -> Self {
+        Value::Map(Rc::new(RefCell::new(entries)))
+    }
+
+    pub fn new_list(elements: Vec<Value>) -> Self {
+        Value::List(Rc::new(RefCell::new(elements)))
+    }
+
+    /// Where a list or map lives, to recognise it when it is met again
+    fn container(&self) -> Option<*const ()> {
+        match self {
+            Value::List(list) => Some(Rc::as_ptr(list) as *const ()),
+            Value::Map(map) => Some(Rc::as_ptr(map) as *const ()),
+            _ => None,
+        }
+    }
+
+    /// How the value is written inside a collection, where strings are
+    /// quoted. A container met again inside itself is a cycle, written as
+    /// `[...]` or `{...}`. What is left to write is kept on a stack of its
+    /// own.
+    fn fmt_nested(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut open = HashSet::new();
+        let mut pending = vec![Pending::Value(self.clone())];
+        while let Some(next) = pending.pop() {
+            let value = match next {
+                Pending::Value(value) => value,
+                Pending::Text(text) => {
+                    write!(f, "{}", text)?;
+                    continue;
+                }
+                Pending::Key(key) => {
+                    write!(f, "{}: ", key)?;
+                    continue;
+                }
+                Pending::Close(container, text) => {
+                    open.remove(&container);
+                    write!(f, "{}", text)?;
+                    continue;
+                }
+            };
+
+            let Some(container) = value.container() else {
+                match &value {
+                    Value::Str(str) => write!(f, "{:?}", str)?,
+                    other => write!(f, "{}", other)?,
+                }
+                continue;
+            };
+            if !open.insert(container) {
+                match value {
+                    Value::Map(_) => write!(f, "{{...}}")?,
+                    _ => write!(f, "[...]")?,
+                }
+                continue;
+            }
+            match &value {
+                Value::List(list) => {
+                    write!(f, "[")?;
+                    pending.push(Pending::Close(container, "]"));
+                    for (i, element) in list.borrow().iter().enumerate().rev() {
+                        pending.push(Pending::Value(element.clone()));
+                        if i > 0 {
+                            pending.push(Pending::Text(", "));
+                        }
+                    }
+                }
+                Value::Map(map) => {
+                    write!(f, "{{")?;
+                    pending.push(Pending::Close(container, "}"));
+                    for (i, (key, value)) in map.borrow().iter().enumerate().rev() {
+                        pending.push(Pending::Value(value.clone()));
+                        pending.push(Pending::Key(key.clone()));
+                        if i > 0 {
+                            pending.push(Pending::Text(", "));
+                        }
+                    }
+                }
+                _ => unreachable!("only lists and maps are containers"),
+            }
+        }
+        Ok(())
+    }
+
+    /// Compares two values without recursing, keeping the pairs of
+    /// containers still to compare on a stack. A pair met again, as in a
+    /// cycle, is taken as equal: whatever is in it is compared already.
+    fn eq_nested(&self, other: &Value) -> bool {
+        let mut compared = HashSet::new();
+        let mut pending = Vec::new();
+        if !self.eq_or_defer(other, &mut pending) {
+            return false;
+        }
+        while let Some((left, right)) = pending.pop() {
+            let pair = (left.container(), right.container());
+            if pair.0 == pair.1 || !compared.insert(pair) {
+                continue;
+            }
+            let equal = match (&left, &right) {
+                (Value::List(left), Value::List(right)) => {
+                    let (left, right) = (left.borrow(), right.borrow());
+                    left.len() == right.len()
+                        && left
+                            .iter()
+                            .zip(right.iter())
+                            .all(|(left, right)| left.eq_or_defer(right, &mut pending))
+                }
+                (Value::Map(left), Value::Map(right)) => {
+                    let (left, right) = (left.borrow(), right.borrow());
+                    left.len() == right.len()
+                        && left.iter().zip(right.iter()).all(
+                            |((left_key, left), (right_key, right))| {
+                                left_key == right_key && left.eq_or_defer(right, &mut pending)
+                            },
+                        )
+                }
+                _ => false,
+            };
+            if !equal {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Compares two values that are not both containers, and leaves two
+    /// containers on `pending` for later
+    fn eq_or_defer(&self, other: &Value, pending: &mut Vec<(Value, Value)>) -> bool {
+        if self.container().is_some() && other.container().is_some() {
+            pending.push((self.clone(), other.clone()));
+            return true;
+        }
+        self.eq_scalar(other)
+    }
+
+    /// Moves the elements of a list or map nothing else shares into
+    /// `orphans`, leaving it empty
+    fn give_up_elements(&mut self, orphans: &mut Vec<Value>) {
+        match self {
+            Value::List(list) => {
+                if let Some(list) = Rc::get_mut(list) {
+                    orphans.append(list.get_mut());
+                }
+            }
+            Value::Map(map) => {
+                if let Some(map) = Rc::get_mut(map) {
+                    orphans.extend(std::mem::take(map.get_mut()).into_values());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn eq_scalar(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::Number(left), Value::Number(right)) => left == right,
+            (Value::Bool(left), Value::Bool(right)) => left == right,
+            (Value::Str(left), Value::Str(right)) => left == right,
+            _ => false,
+        }
+    }
+}
+
+/// Lists and maps let go of their elements one at a time, instead of
+/// recursing through every nested one on the native stack
+impl Drop for Value {
+    fn drop(&mut self) {
+        let mut orphans = Vec::new();
+        self.give_up_elements(&mut orphans);
+        while let Some(mut orphan) = orphans.pop() {
+            orphan.give_up_elements(&mut orphans);
+        }
+    }
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        self.eq_nested(other)
+    }
+}
+
+impl From<&Literal> for Value {
+    fn from(literal: &Literal) -> Self {
+        match literal {
+            Literal::Number(num) => Value::Number(*num),
+            Literal::BoolLiteral(bool) => Value::Bool(*bool),
+            Literal::StringLiteral(str) => Value::Str(str.clone()),
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Number(num) => write!(f, "{}", num),
+            Value::Bool(bool) => write!(f, "{}", bool),
+            Value::Str(str) => write!(f, "{}", str),
+            container => container.fmt_nested(f),
+        }
+    }
+}
+
+impl MapKey {
+    /// Turns a value into a key, if its type can be used as one
+    pub fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            // -0 and 0 are the same key
+            Value::Number(num) => Some(MapKey::Number(num + 0_f64)),
+            Value::Str(str) => Some(MapKey::Str(str.clone())),
+            _ => None,
+        }
+    }
+}
+
+impl From<&MapKey> for Value {
+    fn from(key: &MapKey) -> Self {
+        match key {
+            MapKey::Number(num) => Value::Number(*num),
+            MapKey::Str(str) => Value::Str(str.clone()),
+        }
+    }
+}
+
+impl Ord for MapKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (MapKey::Number(left), MapKey::Number(right)) => left.total_cmp(right),
+            (MapKey::Number(_), MapKey::Str(_)) => Ordering::Less,
+            (MapKey::Str(_), MapKey::Number(_)) => Ordering::Greater,
+            (MapKey::Str(left), MapKey::Str(right)) => left.cmp(right),
+        }
+    }
+}
+
+impl PartialOrd for MapKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for MapKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for MapKey {}
+
+impl fmt::Display for MapKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MapKey::Number(num) => write!(f, "{}", num),
+            MapKey::Str(str) => write!(f, "{:?}", str),
+        }
+    }
+}
@@ -0,0 +1,498 @@
+use crate::lexer::lexer_util::{Comment, Lexer};
+use crate::lexer::span::Span;
+use crate::lexer::token_type::TokenType;
+use crate::parser::ast::{
+    Expression, Ident, Infix, InputKind, Literal, Precedence, Prefix, Program, Statement,
+};
+
+const INDENT: &str = "    ";
+
+/// Writes a program back as canonical Zen source: keywords spelled out with
+/// single spaces, blocks indented by four spaces, one space around infix
+/// operators and only the parentheses the meaning needs. Comments stay on
+/// the line of the statement they were written next to, and single blank
+/// lines between statements are kept.
+///
+/// `program` is parsed from `source` with spans, which is how the comments
+/// of the source find their place. Formatting the output again gives the
+/// same text.
+pub fn format_program(program: &Program, source: &str) -> String {
+    let comments = Lexer::lex_comments(source.as_bytes());
+    let else_keywords = match Lexer::lex_tokens_with_spans(source.as_bytes()) {
+        Ok((_, (tokens, spans))) => tokens
+            .iter()
+            .zip(spans)
+            .filter(|(token, _)| matches!(token, TokenType::ElseIf | TokenType::Else))
+            .map(|(_, span)| span.start)
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    let line_starts = std::iter::once(0)
+        .chain(source.match_indices('\n').map(|(offset, _)| offset + 1))
+        .collect();
+    let mut formatter = Formatter {
+        out: String::new(),
+        depth: 0,
+        comments: &comments,
+        line_starts,
+        else_keywords,
+        last_line: None,
+    };
+    formatter.block(&program.statements);
+    formatter.comments_before(usize::MAX, false);
+    formatter.out
+}
+
+struct Formatter<'a> {
+    out: String,
+    depth: usize,
+    /// Comments not written yet
+    comments: &'a [Comment],
+    /// Byte offset where each source line starts
+    line_starts: Vec<usize>,
+    /// Byte offset of every 'WARNA AGAR' and 'NHI TOH', which the program
+    /// has no spans for
+    else_keywords: Vec<usize>,
+    /// Source line of the last thing written, or `None` at the start of a
+    /// block, where blank lines are left out
+    last_line: Option<usize>,
+}
+
+impl Formatter<'_> {
+    /// The source line of a byte offset
+    fn line_of(&self, offset: usize) -> usize {
+        self.line_starts.partition_point(|&start| start <= offset)
+    }
+
+    fn block(&mut self, statements: &[Statement]) {
+        for statement in statements {
+            self.statement(statement, None);
+        }
+    }
+
+    /// Writes a line, after a blank one if the source had one before `line`
+    fn line(&mut self, text: &str, line: Option<usize>) {
+        if let (Some(last), Some(line)) = (self.last_line, line) {
+            if line > last + 1 {
+                self.out.push('\n');
+            }
+        }
+        for _ in 0..self.depth {
+            self.out.push_str(INDENT);
+        }
+        self.out.push_str(text);
+        self.out.push('\n');
+        self.last_line = line.or(self.last_line);
+    }
+
+    /// Writes the comments that come before byte `offset` on lines of their
+    /// own. Comments `moved` from where they were keep no blank line before
+    /// them.
+    fn comments_before(&mut self, offset: usize, moved: bool) {
+        while let Some((comment, rest)) = self.comments.split_first() {
+            if comment.span.start >= offset {
+                break;
+            }
+            self.comments = rest;
+            let line = (!moved).then_some(comment.span.line);
+            self.line(&format!("@{}", comment.text), line);
+        }
+    }
+
+    /// The comment right after byte `offset` on the same source line, if
+    /// any, to go at the end of the written line
+    fn trailing_comment(&mut self, offset: usize) -> String {
+        let line = self.line_of(offset);
+        match self.comments.split_first() {
+            Some((comment, rest)) if comment.span.start >= offset && comment.span.line == line => {
+                self.comments = rest;
+                format!(" @{}", comment.text)
+            }
+            _ => String::new(),
+        }
+    }
+
+    /// Writes `text`, which starts in the source at `start` and ends at byte
+    /// `end`, with the comment that follows it on the same line
+    fn line_with_comment(&mut self, text: String, start: Option<Span>, end: Option<usize>) {
+        let comment = match end {
+            Some(end) => self.trailing_comment(end),
+            None => String::new(),
+        };
+        self.line(&(text + &comment), start.map(|span| span.line));
+        if let Some(end) = end {
+            self.last_line = Some(self.line_of(end));
+        }
+    }
+
+    fn statement(&mut self, statement: &Statement, span: Option<Span>) {
+        let (statement, span) = match statement {
+            Statement::Spanned { span, statement } => {
+                return self.statement(statement, Some(*span));
+            }
+            statement => (statement, span),
+        };
+
+        // Where the first line of the statement ends in the source
+        let header_end = match statement {
+            Statement::If { condition, .. } | Statement::While { condition, .. } => {
+                expression_span(condition).map(|span| span.end)
+            }
+            Statement::For { end, step, .. } => step
+                .as_ref()
+                .and_then(expression_span)
+                .or(expression_span(end))
+                .map(|span| span.end),
+            Statement::Function { .. } => span.map(|span| span.start),
+            _ => span.map(|span| span.end),
+        };
+        if let Some(span) = span {
+            self.comments_before(span.start, false);
+        }
+        if let Some(header_end) = header_end {
+            // Comments between the lines of a statement go above it
+            self.comments_before(header_end, true);
+        }
+
+        match statement {
+            Statement::If {
+                condition,
+                consequence,
+                alternative,
+            } => {
+                let header = format!(
+                    "{} {} {}",
+                    TokenType::If,
+                    expression(condition),
+                    TokenType::Then
+                );
+                self.line_with_comment(header, span, header_end);
+                self.if_rest(consequence, alternative.as_deref(), header_end);
+                self.end_block(TokenType::EndIf, span);
+            }
+            Statement::While { condition, body } => {
+                let header = format!(
+                    "{} {} {}",
+                    TokenType::While,
+                    expression(condition),
+                    TokenType::Do
+                );
+                self.line_with_comment(header, span, header_end);
+                self.body(body);
+                self.end_block(TokenType::EndWhile, span);
+            }
+            Statement::For {
+                variable,
+                start,
+                end,
+                step,
+                body,
+            } => {
+                let mut header = format!(
+                    "{} {} {} {} {} {} {}",
+                    TokenType::For,
+                    variable.0,
+                    TokenType::Assign,
+                    expression(start),
+                    TokenType::From,
+                    expression(end),
+                    TokenType::To
+                );
+                if let Some(step) = step {
+                    header += &format!(" {} {}", TokenType::Step, expression(step));
+                }
+                self.line_with_comment(header, span, header_end);
+                self.body(body);
+                self.end_block(TokenType::EndWhile, span);
+            }
+            Statement::Function {
+                name,
+                parameters,
+                body,
+            } => {
+                let parameters: Vec<&str> =
+                    parameters.iter().map(|Ident(name)| name.as_str()).collect();
+                let header = format!(
+                    "{} {}({})",
+                    TokenType::FunctionStart,
+                    name.0,
+                    parameters.join(", ")
+                );
+                self.line_with_comment(header, span, header_end);
+                self.body(body);
+                self.end_block(TokenType::FunctionEnd, span);
+            }
+            statement => {
+                let text = simple_statement(statement);
+                self.line_with_comment(text, span, header_end);
+            }
+        }
+    }
+
+    fn body(&mut self, statements: &[Statement]) {
+        self.depth += 1;
+        self.last_line = None;
+        self.block(statements);
+        self.depth -= 1;
+    }
+
+    /// The branches of an 'AGAR' after its condition, which ends at byte
+    /// `header_end`, up to its 'BAS ITNA HI'
+    fn if_rest(
+        &mut self,
+        consequence: &[Statement],
+        alternative: Option<&[Statement]>,
+        header_end: Option<usize>,
+    ) {
+        self.body(consequence);
+        let Some(alternative) = alternative else {
+            return;
+        };
+
+        // Where 'WARNA AGAR' or 'NHI TOH' is in the source
+        let keyword = consequence
+            .last()
+            .and_then(statement_span)
+            .map(|span| span.end)
+            .or(header_end)
+            .and_then(|after| self.else_keyword(after));
+        if let Some(keyword) = keyword {
+            self.inner_comments(keyword);
+        }
+
+        match alternative {
+            // 'WARNA AGAR' is parsed into a lone condition without a span
+            [
+                Statement::If {
+                    condition,
+                    consequence,
+                    alternative,
+                },
+            ] => {
+                let condition_end = expression_span(condition).map(|span| span.end);
+                if let Some(condition_end) = condition_end {
+                    self.comments_before(condition_end, true);
+                }
+                let header = format!(
+                    "{} {} {}",
+                    TokenType::ElseIf,
+                    expression(condition),
+                    TokenType::Then
+                );
+                self.line_with_comment(header, None, condition_end);
+                self.if_rest(consequence, alternative.as_deref(), condition_end);
+            }
+            alternative => {
+                self.line_with_comment(TokenType::Else.to_string(), None, keyword);
+                self.body(alternative);
+            }
+        }
+    }
+
+    /// The first 'WARNA AGAR' or 'NHI TOH' at or after byte `offset`
+    fn else_keyword(&self, offset: usize) -> Option<usize> {
+        let index = self.else_keywords.partition_point(|&start| start < offset);
+        self.else_keywords.get(index).copied()
+    }
+
+    /// Writes the comments before `offset` inside the block being written,
+    /// indented like its statements
+    fn inner_comments(&mut self, offset: usize) {
+        self.depth += 1;
+        self.comments_before(offset, false);
+        self.depth -= 1;
+    }
+
+    /// Closes a block that ends at the end of `span`, keeping the comments
+    /// written inside it and the one after its last keyword
+    fn end_block(&mut self, keyword: TokenType, span: Option<Span>) {
+        match span {
+            Some(span) => {
+                self.inner_comments(span.end);
+                self.line_with_comment(keyword.to_string(), None, Some(span.end));
+            }
+            None => self.line(&keyword.to_string(), None),
+        }
+    }
+}
+
+fn simple_statement(statement: &Statement) -> String {
+    match statement {
+        Statement::ProgramStart => TokenType::StartProgram.to_string(),
+        Statement::ProgramEnd => TokenType::EndProgram.to_string(),
+        Statement::Let { name, value } => {
+            format!("{} {} {}", name.0, TokenType::Assign, expression(value))
+        }
+        Statement::LocalLet { name, value } => format!(
+            "{} {} {} {}",
+            TokenType::Local,
+            name.0,
+            TokenType::Assign,
+            expression(value)
+        ),
+        Statement::IndexLet { left, index, value } => format!(
+            "{}[{}] {} {}",
+            operand(left, Precedence::PIndex, false, true),
+            expression(index),
+            TokenType::Assign,
+            expression(value)
+        ),
+        Statement::Print(value) => format!("{} {}", TokenType::Print, expression(value)),
+        Statement::Return(Some(value)) => format!("{} {}", TokenType::Return, expression(value)),
+        Statement::Return(None) => TokenType::Return.to_string(),
+        Statement::Break => TokenType::Break.to_string(),
+        Statement::Continue => TokenType::Continue.to_string(),
+        Statement::Expression(value) => expression(value),
+        Statement::Invalid(err) => unreachable!("a program with syntax errors: {}", err),
+        Statement::Spanned { statement, .. } => simple_statement(statement),
+        Statement::If { .. }
+        | Statement::While { .. }
+        | Statement::For { .. }
+        | Statement::Function { .. } => unreachable!("blocks are written line by line"),
+    }
+}
+
+/// An expression that nothing follows on its line
+fn expression(expr: &Expression) -> String {
+    operand(expr, Precedence::PLowest, false, false)
+}
+
+/// Writes `expr` as an operand that binds at least as tightly as
+/// `outer` (more tightly on the `right` of an operator). With `open_tail`,
+/// more operators follow it, which a prefix operator would swallow, since it
+/// applies to everything after it.
+fn operand(expr: &Expression, outer: Precedence, right: bool, open_tail: bool) -> String {
+    match expr {
+        Expression::Spanned { expression, .. } => operand(expression, outer, right, open_tail),
+        Expression::InfixExpr {
+            left,
+            operator,
+            right: right_operand,
+        } => {
+            let precedence = precedence(operator);
+            let wrapped = precedence < outer || (right && precedence == outer);
+            let text = format!(
+                "{} {} {}",
+                operand(left, precedence.clone(), false, true),
+                infix_token(operator),
+                operand(right_operand, precedence, true, open_tail && !wrapped)
+            );
+            if wrapped { format!("({})", text) } else { text }
+        }
+        Expression::PrefixExpr { operator, right } => {
+            let text = format!(
+                "{}{}",
+                prefix_token(operator),
+                operand(right, Precedence::PIndex, false, false)
+            );
+            if open_tail {
+                format!("({})", text)
+            } else {
+                text
+            }
+        }
+        Expression::IdentifierExpr(Ident(name)) => name.clone(),
+        Expression::LiteralExpr(literal) => literal_token(literal).to_string(),
+        Expression::ListExpr(elements) => format!("[{}]", list(elements)),
+        Expression::MapExpr(entries) => {
+            let entries: Vec<String> = entries
+                .iter()
+                .map(|(key, value)| format!("{}: {}", expression(key), expression(value)))
+                .collect();
+            format!("{{{}}}", entries.join(", "))
+        }
+        Expression::Call {
+            function,
+            arguments,
+        } => format!("{}({})", function.0, list(arguments)),
+        Expression::Index { left, index } => format!(
+            "{}[{}]",
+            operand(left, Precedence::PIndex, false, true),
+            expression(index)
+        ),
+        Expression::Slice { left, start, end } => {
+            let bound = |bound: &Option<Box<Expression>>| {
+                bound.as_deref().map(expression).unwrap_or_default()
+            };
+            format!(
+                "{}[{}:{}]",
+                operand(left, Precedence::PIndex, false, true),
+                bound(start),
+                bound(end)
+            )
+        }
+        Expression::Input(kind) => match kind {
+            InputKind::Number => TokenType::Input,
+            InputKind::Word => TokenType::InputWord,
+            InputKind::Line => TokenType::InputLine,
+        }
+        .to_string(),
+    }
+}
+
+fn list(elements: &[Expression]) -> String {
+    let elements: Vec<String> = elements.iter().map(expression).collect();
+    elements.join(", ")
+}
+
+/// How tightly the parser binds an operator, as in `infix_op`
+fn precedence(operator: &Infix) -> Precedence {
+    match operator {
+        Infix::LogicalOr => Precedence::PLogicalOr,
+        Infix::LogicalAnd => Precedence::PLogicalAnd,
+        Infix::Equal | Infix::NotEqual => Precedence::PEquals,
+        Infix::GreaterThanEqual | Infix::LessThanEqual | Infix::GreaterThan | Infix::LessThan => {
+            Precedence::PLessGreater
+        }
+        Infix::Plus | Infix::Minus => Precedence::PSum,
+        Infix::Multiply | Infix::Divide | Infix::Modulo => Precedence::PProduct,
+    }
+}
+
+fn infix_token(operator: &Infix) -> TokenType {
+    match operator {
+        Infix::Plus => TokenType::Plus,
+        Infix::Minus => TokenType::Minus,
+        Infix::Divide => TokenType::Divide,
+        Infix::Multiply => TokenType::Multiply,
+        Infix::Modulo => TokenType::Modulo,
+        Infix::Equal => TokenType::Equal,
+        Infix::NotEqual => TokenType::NotEqual,
+        Infix::GreaterThanEqual => TokenType::GreaterThanEqual,
+        Infix::LessThanEqual => TokenType::LessThanEqual,
+        Infix::GreaterThan => TokenType::GreaterThan,
+        Infix::LessThan => TokenType::LessThan,
+        Infix::LogicalAnd => TokenType::LogicalAnd,
+        Infix::LogicalOr => TokenType::LogicalOr,
+    }
+}
+
+fn prefix_token(operator: &Prefix) -> TokenType {
+    match operator {
+        Prefix::PrefixPlus => TokenType::Plus,
+        Prefix::PrefixMinus => TokenType::Minus,
+        Prefix::Not => TokenType::Not,
+    }
+}
+
+fn literal_token(literal: &Literal) -> TokenType {
+    match literal {
+        Literal::Number(number) => TokenType::Number(*number),
+        Literal::BoolLiteral(bool) => TokenType::BooleanLiteral(*bool),
+        Literal::StringLiteral(string) => TokenType::StringLiteral(string.clone()),
+    }
+}
+
+fn statement_span(statement: &Statement) -> Option<Span> {
+    match statement {
+        Statement::Spanned { span, .. } => Some(*span),
+        _ => None,
+    }
+}
+
+fn expression_span(expression: &Expression) -> Option<Span> {
+    match expression {
+        Expression::Spanned { span, .. } => Some(*span),
+        _ => None,
+    }
+}
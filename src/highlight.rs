@@ -0,0 +1,54 @@
+use crate::lexer::lexer_util::{ClassifiedToken, Lexer};
+use crate::lexer::token_type::TokenClass;
+use serde_json::json;
+
+/// The classified tokens of a program as a JSON array, one object per token
+/// with its class, byte range and where it starts:
+///
+/// ```text
+/// [{"class":"keyword","start":0,"end":30,"line":1,"column":1}, ...]
+/// ```
+pub fn to_json(source: &str) -> String {
+    let tokens: Vec<_> = Lexer::lex_classified(source.as_bytes())
+        .iter()
+        .map(|token| {
+            json!({
+                "class": token.class.name(),
+                "start": token.span.start,
+                "end": token.span.end,
+                "line": token.span.line,
+                "column": token.span.column,
+            })
+        })
+        .collect();
+    serde_json::Value::Array(tokens).to_string()
+}
+
+/// The program itself, coloured with ANSI escape codes for a terminal.
+/// The colours are always written, so they survive a pipe into `less -R`.
+pub fn to_ansi(source: &str) -> String {
+    let mut out = String::new();
+    let mut written = 0;
+    for ClassifiedToken { class, span } in Lexer::lex_classified(source.as_bytes()) {
+        out += &source[written..span.start];
+        out += &format!(
+            "\x1b[{}m{}\x1b[0m",
+            ansi_code(class),
+            &source[span.start..span.end]
+        );
+        written = span.end;
+    }
+    out += &source[written..];
+    out
+}
+
+fn ansi_code(class: TokenClass) -> &'static str {
+    match class {
+        TokenClass::Keyword => "1;35",
+        TokenClass::Identifier => "36",
+        TokenClass::Number => "33",
+        TokenClass::String => "32",
+        TokenClass::Comment => "90",
+        TokenClass::Operator => "1",
+    }
+}
@@ -1,3 +1,4 @@
 pub mod lexer_util;
+pub mod span;
 pub mod token_type;
 pub mod tokens;
@@ -6,14 +6,16 @@ use nom::bytes::complete::{tag, take};
 use nom::character::complete::{
     alpha1, alphanumeric1, char, digit1, multispace0, multispace1, space0,
 };
-use nom::combinator::{map, map_res, opt, recognize, value};
+use nom::combinator::{map, map_res, not, opt, recognize, value};
 use nom::multi::many0;
-use nom::sequence::pair;
 use nom::sequence::{delimited, preceded};
-use nom::IResult;
-use nom::{branch, bytes, AsBytes};
+use nom::sequence::{pair, tuple};
+use nom::{AsBytes, branch, bytes};
+use nom::{Err, IResult};
 
-use crate::lexer::token_type::TokenType;
+use crate::error::LexError;
+use crate::lexer::span::Span;
+use crate::lexer::token_type::{TokenClass, TokenType};
 
 macro_rules! syntax {
     ($func_name: ident, $tag_string: literal, $output_token: expr_2021) => {
@@ -25,10 +27,172 @@ macro_rules! syntax {
 
 pub struct Lexer;
 
+/// A comment, from '@' to the end of its line. The parser only sees a line
+/// break where it was; the formatter puts it back.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comment {
+    /// What follows the '@', without the line ending
+    pub text: String,
+    pub span: Span,
+}
+
+/// A token with what kind of source it is. Its span is the byte range to
+/// highlight, so a multi-word keyword like 'JAB TAK HAI JAAN' is one piece.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClassifiedToken {
+    pub class: TokenClass,
+    pub span: Span,
+}
+
 impl Lexer {
     pub fn lex_tokens(bytes: &[u8]) -> IResult<&[u8], Vec<TokenType>> {
-        lex_tokens(bytes)
-            .map(|(slice, result)| (slice, [&result[..], &vec![TokenType::Eof][..]].concat()))
+        Self::lex_tokens_with_spans(bytes).map(|(slice, (tokens, _))| (slice, tokens))
+    }
+
+    /// Same as `lex_tokens`, but also gives the source span of every token
+    pub fn lex_tokens_with_spans(bytes: &[u8]) -> IResult<&[u8], (Vec<TokenType>, Vec<Span>)> {
+        let mut tokens = Vec::new();
+        let mut spans = Vec::new();
+        let mut cursor = LineCursor::default();
+        let mut input = bytes;
+
+        loop {
+            let (token_start, _) = space0(input)?;
+            let (remaining, token) = match lex_token(token_start) {
+                Ok(result) => result,
+                Err(Err::Error(_)) => break,
+                Err(e) => return Err(e),
+            };
+            let start = bytes.len() - token_start.len();
+            let end = bytes.len() - remaining.len();
+            let (start, end) = trim_whitespace(bytes, start, end);
+            spans.push(cursor.span(bytes, start, end));
+            tokens.push(token);
+            input = remaining;
+        }
+
+        let end = bytes.len() - input.len();
+        spans.push(cursor.span(bytes, end, end));
+        tokens.push(TokenType::Eof);
+        Ok((input, (tokens, spans)))
+    }
+
+    /// The comments of a program, in source order
+    pub fn lex_comments(bytes: &[u8]) -> Vec<Comment> {
+        let Ok((_, (tokens, spans))) = Self::lex_tokens_with_spans(bytes) else {
+            return Vec::new();
+        };
+        tokens
+            .iter()
+            .zip(spans)
+            .filter(|(token, span)| {
+                **token == TokenType::EndOfStatement && bytes.get(span.start) == Some(&b'@')
+            })
+            .map(|(_, span)| Comment {
+                text: String::from_utf8_lossy(&bytes[span.start + 1..span.end])
+                    .trim_end()
+                    .to_string(),
+                span,
+            })
+            .collect()
+    }
+
+    /// Every token of a program that is worth highlighting, comments
+    /// included, in source order. Characters that are not Zen are left out
+    /// instead of failing, so half-written programs highlight too.
+    pub fn lex_classified(bytes: &[u8]) -> Vec<ClassifiedToken> {
+        let Ok((_, (tokens, spans))) = Self::lex_tokens_with_spans(bytes) else {
+            return Vec::new();
+        };
+        tokens
+            .iter()
+            .zip(spans)
+            .filter_map(|(token, span)| {
+                let class = match token {
+                    TokenType::EndOfStatement if bytes.get(span.start) == Some(&b'@') => {
+                        TokenClass::Comment
+                    }
+                    token => token.class()?,
+                };
+                Some(ClassifiedToken { class, span })
+            })
+            .collect()
+    }
+
+    /// Lexes a whole program, refusing anything that is not a Zen token
+    pub fn lex_program(bytes: &[u8]) -> Result<(Vec<TokenType>, Vec<Span>), LexError> {
+        // Every byte lexes to some token, so only whitespace can be left over
+        let (_, (tokens, spans)) = Self::lex_tokens_with_spans(bytes).map_err(|_| LexError {
+            msg: "Kuch toh gadbad hai, Daya! The program could not be read.".to_string(),
+            span: Span::default(),
+        })?;
+
+        match tokens.iter().position(|token| *token == TokenType::Illegal) {
+            Some(position) => {
+                let span = spans[position];
+                let found = String::from_utf8_lossy(&bytes[span.start..])
+                    .chars()
+                    .next()
+                    .unwrap_or_default();
+                Err(LexError::new_illegal_character(found, span))
+            }
+            None => Ok((tokens, spans)),
+        }
+    }
+}
+
+/// Keywords swallow the whitespace around them, which should not count as
+/// part of their span. Tokens made only of whitespace (line breaks) are kept.
+fn trim_whitespace(bytes: &[u8], start: usize, end: usize) -> (usize, usize) {
+    let token = &bytes[start..end];
+    match token.iter().position(|b| !b.is_ascii_whitespace()) {
+        Some(leading) => {
+            let trailing = token
+                .iter()
+                .rev()
+                .position(|b| !b.is_ascii_whitespace())
+                .unwrap_or(0);
+            (start + leading, end - trailing)
+        }
+        None => (start, end),
+    }
+}
+
+/// Keeps track of line and column while walking forward through the source
+struct LineCursor {
+    offset: usize,
+    line: usize,
+    column: usize,
+}
+
+impl Default for LineCursor {
+    fn default() -> Self {
+        Self {
+            offset: 0,
+            line: 1,
+            column: 1,
+        }
+    }
+}
+
+impl LineCursor {
+    fn span(&mut self, bytes: &[u8], start: usize, end: usize) -> Span {
+        for &byte in &bytes[self.offset..start] {
+            if byte == b'\n' {
+                self.line += 1;
+                self.column = 1;
+            } else if byte & 0xC0 != 0x80 {
+                // Continuation bytes of a multi-byte character are not columns
+                self.column += 1;
+            }
+        }
+        self.offset = start;
+        Span {
+            start,
+            end,
+            line: self.line,
+            column: self.column,
+        }
     }
 }
 
@@ -147,6 +311,68 @@ fn do_keyword(input: &[u8]) -> IResult<&[u8], TokenType> {
 
 syntax! {end_while_keyword, "JAHAN", TokenType::EndWhile}
 
+fn for_keyword(input: &[u8]) -> IResult<&[u8], TokenType> {
+    let (input, _) = multispace0(input)?;
+    let (input, _) = tag("EK")(input)?;
+    let (input, _) = multispace1(input)?;
+    let (input, _) = tag("DO")(input)?;
+    let (input, _) = multispace1(input)?;
+    let (input, _) = tag("TEEN")(input)?;
+    let (input, _) = multispace1(input)?;
+    Ok((input, TokenType::For))
+}
+
+// Single word keywords must not swallow the start of a longer identifier
+fn word_end(input: &[u8]) -> IResult<&[u8], ()> {
+    not(alt((alphanumeric1, tag("_"))))(input)
+}
+
+fn from_keyword(input: &[u8]) -> IResult<&[u8], TokenType> {
+    let (input, _) = multispace0(input)?;
+    let (input, _) = tag("SE")(input)?;
+    let (input, _) = word_end(input)?;
+    let (input, _) = multispace0(input)?;
+    Ok((input, TokenType::From))
+}
+
+fn to_keyword(input: &[u8]) -> IResult<&[u8], TokenType> {
+    let (input, _) = multispace0(input)?;
+    let (input, _) = tag("TAK")(input)?;
+    let (input, _) = word_end(input)?;
+    let (input, _) = multispace0(input)?;
+    Ok((input, TokenType::To))
+}
+
+fn step_keyword(input: &[u8]) -> IResult<&[u8], TokenType> {
+    let (input, _) = multispace0(input)?;
+    let (input, _) = tag("KADAM")(input)?;
+    let (input, _) = word_end(input)?;
+    let (input, _) = multispace0(input)?;
+    Ok((input, TokenType::Step))
+}
+
+fn break_keyword(input: &[u8]) -> IResult<&[u8], TokenType> {
+    let (input, _) = multispace0(input)?;
+    let (input, _) = tag("BAS")(input)?;
+    let (input, _) = multispace1(input)?;
+    let (input, _) = tag("KAR")(input)?;
+    let (input, _) = multispace1(input)?;
+    let (input, _) = tag("PAGLE")(input)?;
+    let (input, _) = space0(input)?;
+    Ok((input, TokenType::Break))
+}
+
+fn continue_keyword(input: &[u8]) -> IResult<&[u8], TokenType> {
+    let (input, _) = multispace0(input)?;
+    let (input, _) = tag("JAANE")(input)?;
+    let (input, _) = multispace1(input)?;
+    let (input, _) = tag("BHI")(input)?;
+    let (input, _) = multispace1(input)?;
+    let (input, _) = tag("DO")(input)?;
+    let (input, _) = space0(input)?;
+    Ok((input, TokenType::Continue))
+}
+
 fn print_keyword(input: &[u8]) -> IResult<&[u8], TokenType> {
     let (input, _) = multispace0(input)?;
     let (input, _) = tag("PRINT")(input)?;
@@ -160,7 +386,14 @@ fn print_keyword(input: &[u8]) -> IResult<&[u8], TokenType> {
 
 fn input_keyword(input: &[u8]) -> IResult<&[u8], TokenType> {
     let (input, _) = multispace0(input)?;
-    let (input, _) = tag("INPUT")(input)?;
+    let (input, token) = alt((
+        value(TokenType::Input, tag("INPUT")),
+        value(TokenType::InputWord, tag("SHABD")),
+        value(
+            TokenType::InputLine,
+            tuple((tag("PURI"), multispace1, tag("LINE"))),
+        ),
+    ))(input)?;
     let (input, _) = multispace1(input)?;
     let (input, _) = tag("LE")(input)?;
     let (input, _) = multispace1(input)?;
@@ -170,7 +403,48 @@ fn input_keyword(input: &[u8]) -> IResult<&[u8], TokenType> {
     let (input, _) = multispace1(input)?;
     let (input, _) = tag("BABA")(input)?;
     let (input, _) = multispace0(input)?;
-    Ok((input, TokenType::Input))
+    Ok((input, token))
+}
+
+fn function_start_keyword(input: &[u8]) -> IResult<&[u8], TokenType> {
+    let (input, _) = multispace0(input)?;
+    let (input, _) = tag("AAO")(input)?;
+    let (input, _) = multispace1(input)?;
+    let (input, _) = tag("KABHI")(input)?;
+    let (input, _) = multispace1(input)?;
+    let (input, _) = tag("HAVELI")(input)?;
+    let (input, _) = multispace1(input)?;
+    let (input, _) = tag("PE")(input)?;
+    let (input, _) = multispace0(input)?;
+    Ok((input, TokenType::FunctionStart))
+}
+
+fn function_end_keyword(input: &[u8]) -> IResult<&[u8], TokenType> {
+    let (input, _) = multispace0(input)?;
+    let (input, _) = tag("PHIR")(input)?;
+    let (input, _) = multispace1(input)?;
+    let (input, _) = tag("MILENGE")(input)?;
+    let (input, _) = multispace0(input)?;
+    Ok((input, TokenType::FunctionEnd))
+}
+
+fn return_keyword(input: &[u8]) -> IResult<&[u8], TokenType> {
+    let (input, _) = multispace0(input)?;
+    let (input, _) = tag("LAUT")(input)?;
+    let (input, _) = multispace1(input)?;
+    let (input, _) = tag("KE")(input)?;
+    let (input, _) = multispace1(input)?;
+    let (input, _) = tag("AAJA")(input)?;
+    // Only horizontal spaces, so that a bare return keeps its line break
+    let (input, _) = space0(input)?;
+    Ok((input, TokenType::Return))
+}
+
+fn local_keyword(input: &[u8]) -> IResult<&[u8], TokenType> {
+    let (input, _) = multispace0(input)?;
+    let (input, _) = tag("APNA")(input)?;
+    let (input, _) = multispace1(input)?;
+    Ok((input, TokenType::Local))
 }
 
 fn assign_operator(input: &[u8]) -> IResult<&[u8], TokenType> {
@@ -191,18 +465,49 @@ pub fn lex_keyword(input: &[u8]) -> IResult<&[u8], TokenType> {
         else_if_keyword,
         else_keyword,
         end_if_keyword,
-        while_keyword,
-        end_while_keyword,
+        lex_loop_keyword,
         print_keyword,
         input_keyword,
+        function_start_keyword,
+        function_end_keyword,
+        return_keyword,
+        local_keyword,
+    ))(input)
+}
+
+fn lex_loop_keyword(input: &[u8]) -> IResult<&[u8], TokenType> {
+    alt((
+        while_keyword,
+        end_while_keyword,
+        for_keyword,
+        from_keyword,
+        to_keyword,
+        step_keyword,
+        break_keyword,
+        continue_keyword,
     ))(input)
 }
 
 syntax! {left_paren_punctuation, "(", TokenType::LeftParen}
 syntax! {right_paren_punctuation, ")", TokenType::RightParen}
+syntax! {comma_punctuation, ",", TokenType::Comma}
+syntax! {left_bracket_punctuation, "[", TokenType::LeftBracket}
+syntax! {right_bracket_punctuation, "]", TokenType::RightBracket}
+syntax! {colon_punctuation, ":", TokenType::Colon}
+syntax! {left_brace_punctuation, "{", TokenType::LeftBrace}
+syntax! {right_brace_punctuation, "}", TokenType::RightBrace}
 
 pub fn lex_punctuations(input: &[u8]) -> IResult<&[u8], TokenType> {
-    alt((left_paren_punctuation, right_paren_punctuation))(input)
+    alt((
+        left_paren_punctuation,
+        right_paren_punctuation,
+        comma_punctuation,
+        left_bracket_punctuation,
+        right_bracket_punctuation,
+        colon_punctuation,
+        left_brace_punctuation,
+        right_brace_punctuation,
+    ))(input)
 }
 // Strings
 fn pis(input: &[u8]) -> IResult<&[u8], Vec<u8>> {
@@ -273,8 +578,9 @@ fn lex_comment(input: &[u8]) -> IResult<&[u8], TokenType> {
     map(
         delimited(
             bytes::complete::tag(b"@"),
-            bytes::complete::take_until("\n"),
-            bytes::complete::tag(b"\n"),
+            bytes::complete::take_till(|byte| byte == b'\n'),
+            // The last line of a file may end without a line break
+            opt(bytes::complete::tag(b"\n")),
         ),
         |_| TokenType::EndOfStatement, // Ignore the comment
     )(input)
@@ -0,0 +1,27 @@
+use std::fmt;
+
+/// Where a piece of source code lives: a byte range plus the line and column
+/// (both starting at 1) of its first character.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Span {
+    /// Span running from the start of `self` to the end of `other`
+    pub fn to(self, other: Span) -> Span {
+        Span {
+            end: self.end.max(other.end),
+            ..self
+        }
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
@@ -1,3 +1,5 @@
+use std::fmt;
+
 #[derive(PartialEq, Debug, Clone)]
 pub enum TokenType {
     // Special tokens
@@ -43,10 +45,172 @@ pub enum TokenType {
     While,
     Do,
     EndWhile,
+    For,
+    From,
+    To,
+    Step,
+    Break,
+    Continue,
     Print,
     Input,
+    InputWord,
+    InputLine,
+    FunctionStart,
+    FunctionEnd,
+    Return,
+    Local,
 
     // Punctuation
     LeftParen,
     RightParen,
+    Comma,
+    LeftBracket,
+    RightBracket,
+    Colon,
+    LeftBrace,
+    RightBrace,
+}
+
+impl fmt::Display for TokenType {
+    /// Writes the token the way it is spelled in Zen source code
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let text = match self {
+            TokenType::Illegal => "<illegal>",
+            TokenType::Eof => "<end of file>",
+            TokenType::EndOfStatement => "\n",
+            TokenType::Identifier(name) => name,
+            TokenType::Number(num) => return write!(f, "{}", num),
+            TokenType::StringLiteral(str) => {
+                let escaped = str.replace('\\', "\\\\").replace('"', "\\\"");
+                return write!(f, "\"{}\"", escaped);
+            }
+            TokenType::BooleanLiteral(bool) => return write!(f, "{}", bool),
+            TokenType::Assign => "BOLE TOH",
+            TokenType::Plus => "+",
+            TokenType::Minus => "-",
+            TokenType::Multiply => "*",
+            TokenType::Divide => "/",
+            TokenType::Modulo => "%",
+            TokenType::GreaterThan => ">",
+            TokenType::GreaterThanEqual => ">=",
+            TokenType::LessThan => "<",
+            TokenType::LessThanEqual => "<=",
+            TokenType::Equal => "==",
+            TokenType::NotEqual => "!=",
+            TokenType::Not => "!",
+            TokenType::LogicalAnd => "&&",
+            TokenType::LogicalOr => "||",
+            TokenType::StartProgram => "PARAMPARA PRATISHTA ANUSHASHAN",
+            TokenType::EndProgram => "KHATAM TATA BYE BYE",
+            TokenType::If => "AGAR",
+            TokenType::Then => "TAB",
+            TokenType::ElseIf => "WARNA AGAR",
+            TokenType::Else => "NHI TOH",
+            TokenType::EndIf => "BAS ITNA HI",
+            TokenType::While => "JAB TAK HAI JAAN",
+            TokenType::Do => "TAB TAK",
+            TokenType::EndWhile => "JAHAN",
+            TokenType::For => "EK DO TEEN",
+            TokenType::From => "SE",
+            TokenType::To => "TAK",
+            TokenType::Step => "KADAM",
+            TokenType::Break => "BAS KAR PAGLE",
+            TokenType::Continue => "JAANE BHI DO",
+            TokenType::Print => "PRINT BASANTI PRINT",
+            TokenType::Input => "INPUT LE LE RE BABA",
+            TokenType::InputWord => "SHABD LE LE RE BABA",
+            TokenType::InputLine => "PURI LINE LE LE RE BABA",
+            TokenType::FunctionStart => "AAO KABHI HAVELI PE",
+            TokenType::FunctionEnd => "PHIR MILENGE",
+            TokenType::Return => "LAUT KE AAJA",
+            TokenType::Local => "APNA",
+            TokenType::LeftParen => "(",
+            TokenType::RightParen => ")",
+            TokenType::Comma => ",",
+            TokenType::LeftBracket => "[",
+            TokenType::RightBracket => "]",
+            TokenType::Colon => ":",
+            TokenType::LeftBrace => "{",
+            TokenType::RightBrace => "}",
+        };
+        write!(f, "{}", text)
+    }
+}
+
+impl TokenType {
+    /// How the token is called in error messages
+    pub fn describe(&self) -> String {
+        match self {
+            TokenType::Illegal => "an illegal character".to_string(),
+            TokenType::Eof => "the end of the file".to_string(),
+            TokenType::EndOfStatement => "the end of the line".to_string(),
+            TokenType::Identifier(name) => format!("the name '{}'", name),
+            token => format!("'{}'", token),
+        }
+    }
+}
+
+/// The kind of a piece of source code, for highlighting
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum TokenClass {
+    Keyword,
+    Identifier,
+    Number,
+    String,
+    Comment,
+    Operator,
+}
+
+impl TokenClass {
+    /// The name used for the class in JSON output
+    pub fn name(&self) -> &'static str {
+        match self {
+            TokenClass::Keyword => "keyword",
+            TokenClass::Identifier => "identifier",
+            TokenClass::Number => "number",
+            TokenClass::String => "string",
+            TokenClass::Comment => "comment",
+            TokenClass::Operator => "operator",
+        }
+    }
+}
+
+impl TokenType {
+    /// How the token is highlighted. Comments lex to the end of a
+    /// statement, so they are told apart by `Lexer::lex_classified`.
+    pub fn class(&self) -> Option<TokenClass> {
+        let class = match self {
+            TokenType::Illegal | TokenType::Eof | TokenType::EndOfStatement => return None,
+            TokenType::Identifier(_) => TokenClass::Identifier,
+            TokenType::Number(_) => TokenClass::Number,
+            TokenType::StringLiteral(_) => TokenClass::String,
+            // Operators and punctuation are all made of symbols
+            TokenType::Plus
+            | TokenType::Minus
+            | TokenType::Multiply
+            | TokenType::Divide
+            | TokenType::Modulo
+            | TokenType::GreaterThan
+            | TokenType::GreaterThanEqual
+            | TokenType::LessThan
+            | TokenType::LessThanEqual
+            | TokenType::Equal
+            | TokenType::NotEqual
+            | TokenType::Not
+            | TokenType::LogicalAnd
+            | TokenType::LogicalOr
+            | TokenType::LeftParen
+            | TokenType::RightParen
+            | TokenType::Comma
+            | TokenType::LeftBracket
+            | TokenType::RightBracket
+            | TokenType::Colon
+            | TokenType::LeftBrace
+            | TokenType::RightBrace => TokenClass::Operator,
+            // Everything else is spelled in words, 'BOLE TOH' and 'true'
+            // included
+            _ => TokenClass::Keyword,
+        };
+        Some(class)
+    }
 }
@@ -2,6 +2,7 @@ use nom::*;
 use std::iter::Enumerate;
 use std::ops::{Range, RangeFrom, RangeFull, RangeTo};
 
+use crate::lexer::span::Span;
 use crate::lexer::token_type::TokenType;
 
 #[derive(Clone, Copy, PartialEq, Debug)]
@@ -10,14 +11,30 @@ pub struct Tokens<'a> {
     pub tok: &'a [TokenType],
     pub start: usize,
     pub end: usize,
+    /// Source span of each token, or empty when positions are not tracked
+    pub spans: &'a [Span],
 }
 
 impl<'a> Tokens<'a> {
     pub fn new(vec: &'a [TokenType]) -> Self {
+        Self::new_with_spans(vec, &[])
+    }
+
+    pub fn new_with_spans(vec: &'a [TokenType], spans: &'a [Span]) -> Self {
+        debug_assert!(spans.is_empty() || spans.len() == vec.len());
         Tokens {
             tok: vec,
             start: 0,
             end: vec.len(),
+            spans,
+        }
+    }
+
+    fn slice_spans(&self, range: Range<usize>) -> &'a [Span] {
+        if self.spans.is_empty() {
+            self.spans
+        } else {
+            &self.spans[range]
         }
     }
 }
@@ -36,6 +53,7 @@ impl InputTake for Tokens<'_> {
             tok: &self.tok[0..count],
             start: 0,
             end: count,
+            spans: self.slice_spans(0..count),
         }
     }
 
@@ -46,11 +64,13 @@ impl InputTake for Tokens<'_> {
             tok: prefix,
             start: 0,
             end: prefix.len(),
+            spans: self.slice_spans(0..count),
         };
         let second = Tokens {
             tok: suffix,
             start: 0,
             end: suffix.len(),
+            spans: self.slice_spans(count..self.tok.len()),
         };
         (second, first)
     }
@@ -70,6 +90,7 @@ impl Slice<Range<usize>> for Tokens<'_> {
             tok: self.tok.slice(range.clone()),
             start: self.start + range.start,
             end: self.start + range.end,
+            spans: self.slice_spans(range.clone()),
         }
     }
 }
@@ -95,6 +116,7 @@ impl Slice<RangeFull> for Tokens<'_> {
             tok: self.tok,
             start: self.start,
             end: self.end,
+            spans: self.spans,
         }
     }
 }
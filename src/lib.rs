@@ -1,35 +1,51 @@
-use crate::evaluator::interpreter::InterpreterErrorType::UnknownParserError;
-use crate::evaluator::interpreter::{Interpreter, InterpreterError};
+use crate::error::ZenError;
+use crate::evaluator::interpreter::Interpreter;
 use crate::lexer::lexer_util::Lexer;
 use crate::lexer::tokens::Tokens;
-use crate::parser::ast::Statement;
 use crate::parser::parser_util::Parser;
 
+pub mod codegen;
+pub mod diagnostics;
+pub mod error;
 pub mod evaluator;
+pub mod formatter;
+pub mod highlight;
 pub mod lexer;
+pub mod lsp;
 pub mod parser;
+pub mod repl;
 
-/// Just returns the same code as Ok!
-/// TODO implement later
-pub fn compile(mut code: String) -> Result<String, String> {
-    code.push_str("\nBhag yha se!!");
-    Ok(code)
+/// Compiles a program to C source, which builds with any C99 compiler:
+/// `cc program.c -lm`
+pub fn compile(code: String) -> Result<String, ZenError> {
+    let (tokens, spans) = Lexer::lex_program(code.as_bytes())?;
+    let program = Parser::parse_program(Tokens::new_with_spans(&tokens, &spans))?;
+
+    Ok(codegen::c::generate(&program)?)
+}
+
+/// Compiles a program to a WebAssembly module in the text format, which
+/// builds with any WebAssembly toolchain:
+/// `wasm-tools parse program.wat -o program.wasm`
+pub fn compile_to_wat(code: String) -> Result<String, ZenError> {
+    let (tokens, spans) = Lexer::lex_program(code.as_bytes())?;
+    let program = Parser::parse_program(Tokens::new_with_spans(&tokens, &spans))?;
+
+    Ok(codegen::wasm::generate(&program)?)
 }
 
-pub fn run_program(
-    code: String,
-    input: &str,
-    is_on_console: bool,
-) -> Result<String, InterpreterError> {
-    let (_, r) = Lexer::lex_tokens(code.as_bytes()).unwrap();
-    let tokens = Tokens::new(&r);
-    let (_, result) = Parser::parse_tokens(tokens).unwrap();
-
-    if !r.is_empty()
-        && (result.statements.is_empty() || !result.statements.contains(&Statement::ProgramEnd))
-    {
-        return Err(InterpreterError::new(UnknownParserError));
-    }
-
-    Interpreter::new(input, is_on_console).run_code(result)
+/// Formats a program the canonical way, keeping its comments (see
+/// `formatter::format_program`)
+pub fn format_code(code: String) -> Result<String, ZenError> {
+    let (tokens, spans) = Lexer::lex_program(code.as_bytes())?;
+    let program = Parser::parse_program(Tokens::new_with_spans(&tokens, &spans))?;
+
+    Ok(formatter::format_program(&program, &code))
+}
+
+pub fn run_program(code: String, input: &str, is_on_console: bool) -> Result<String, ZenError> {
+    let (tokens, spans) = Lexer::lex_program(code.as_bytes())?;
+    let program = Parser::parse_program(Tokens::new_with_spans(&tokens, &spans))?;
+
+    Ok(Interpreter::new(input, is_on_console).run_code(program)?)
 }
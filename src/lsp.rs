@@ -0,0 +1,666 @@
+use crate::diagnostics::diagnostics;
+use crate::error::ZenError;
+use crate::lexer::lexer_util::Lexer;
+use crate::lexer::span::Span;
+use crate::lexer::token_type::{TokenClass, TokenType};
+use crate::lexer::tokens::Tokens;
+use crate::parser::parser_util::Parser;
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+
+/// The semantic token types, in the order of `TokenClass`
+const TOKEN_TYPES: [&str; 6] = [
+    "keyword", "variable", "number", "string", "comment", "operator",
+];
+
+/// Every keyword with what it does, for completion and hover
+const KEYWORDS: &[(&str, &str)] = &[
+    ("PARAMPARA PRATISHTA ANUSHASHAN", "Starts the program"),
+    ("KHATAM TATA BYE BYE", "Ends the program"),
+    ("BOLE TOH", "Gives a variable a value: `A BOLE TOH 1`"),
+    ("PRINT BASANTI PRINT", "Prints a value on its own line"),
+    ("INPUT LE LE RE BABA", "Reads a number from the input"),
+    ("SHABD LE LE RE BABA", "Reads a word from the input"),
+    (
+        "PURI LINE LE LE RE BABA",
+        "Reads the rest of the input line",
+    ),
+    (
+        "AGAR",
+        "Runs a block if a condition holds: `AGAR A > 1 TAB`",
+    ),
+    ("TAB", "Ends the condition of `AGAR` and `WARNA AGAR`"),
+    (
+        "WARNA AGAR",
+        "Checks another condition when the ones before failed",
+    ),
+    ("NHI TOH", "Runs a block when every condition failed"),
+    ("BAS ITNA HI", "Ends `AGAR`"),
+    (
+        "JAB TAK HAI JAAN",
+        "Repeats a block while a condition holds: `JAB TAK HAI JAAN A > 1 TAB TAK`",
+    ),
+    ("TAB TAK", "Ends the condition of `JAB TAK HAI JAAN`"),
+    (
+        "EK DO TEEN",
+        "Counts through a range: `EK DO TEEN I BOLE TOH 1 SE 10 TAK`",
+    ),
+    ("SE", "Where `EK DO TEEN` starts counting"),
+    ("TAK", "Where `EK DO TEEN` stops counting"),
+    ("KADAM", "How far `EK DO TEEN` counts at a time"),
+    ("JAHAN", "Ends `JAB TAK HAI JAAN` and `EK DO TEEN`"),
+    ("BAS KAR PAGLE", "Leaves the loop right away"),
+    ("JAANE BHI DO", "Skips to the next round of the loop"),
+    (
+        "AAO KABHI HAVELI PE",
+        "Defines a function: `AAO KABHI HAVELI PE jod(A, B)`",
+    ),
+    ("PHIR MILENGE", "Ends the function"),
+    ("LAUT KE AAJA", "Returns a value from the function"),
+    (
+        "APNA",
+        "Makes a new variable in the current block, hiding any outer one with the same name: `APNA A BOLE TOH 1`",
+    ),
+];
+
+/// Serves one editor over a pair of streams until it asks to exit, and
+/// gives the exit code: 0 when the editor shut the server down first, as
+/// the protocol asks, 1 otherwise.
+pub fn run(mut input: impl BufRead, mut output: impl Write) -> io::Result<i32> {
+    let mut server = Server::default();
+    while let Some(body) = read_message(&mut input)? {
+        let replies = match serde_json::from_str(&body) {
+            Ok(message) => server.handle(&message),
+            Err(err) => vec![error_response(Value::Null, PARSE_ERROR, &err.to_string())],
+        };
+        for reply in replies {
+            write_message(&mut output, &reply)?;
+        }
+        if let Some(code) = server.exit_code {
+            return Ok(code);
+        }
+    }
+    // The editor went away without saying goodbye
+    Ok(1)
+}
+
+/// Reads the body of the next message, `None` once the input is closed
+pub fn read_message(input: &mut impl BufRead) -> io::Result<Option<String>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                length = value.trim().parse().ok();
+            }
+        }
+    }
+
+    let Some(length) = length else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "message without a Content-Length header",
+        ));
+    };
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+    String::from_utf8(body)
+        .map(Some)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+pub fn write_message(output: &mut impl Write, message: &Value) -> io::Result<()> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()
+}
+
+/// A language server for Zen programs: diagnostics, keyword completion,
+/// hover, go to definition and document symbols.
+///
+/// Documents are synced whole on every change. Everything but the
+/// diagnostics is worked out from the tokens alone, so it keeps working
+/// while a program is half written and does not parse.
+#[derive(Default)]
+pub struct Server {
+    /// The text of every open document, by URI
+    documents: HashMap<String, String>,
+    shutting_down: bool,
+    /// Set once the editor sent `exit`
+    exit_code: Option<i32>,
+}
+
+impl Server {
+    /// Handles one message from the editor, giving the messages to send
+    /// back: the response to a request, and any notifications
+    pub fn handle(&mut self, message: &Value) -> Vec<Value> {
+        let method = message["method"].as_str().unwrap_or("");
+        let params = &message["params"];
+        let Some(id) = message.get("id").cloned() else {
+            return self.notify(method, params);
+        };
+        if method.is_empty() {
+            // A response to a request of ours, and we never send any
+            return Vec::new();
+        }
+        if self.shutting_down {
+            return vec![error_response(
+                id,
+                INVALID_REQUEST,
+                "the server is shutting down",
+            )];
+        }
+
+        let result = match method {
+            "initialize" => json!({
+                "capabilities": {
+                    "textDocumentSync": { "openClose": true, "change": 1 },
+                    "completionProvider": {},
+                    "hoverProvider": true,
+                    "definitionProvider": true,
+                    "documentSymbolProvider": true,
+                    "semanticTokensProvider": {
+                        "legend": { "tokenTypes": TOKEN_TYPES, "tokenModifiers": [] },
+                        "full": true,
+                    },
+                },
+                "serverInfo": { "name": "zen-lsp", "version": env!("CARGO_PKG_VERSION") },
+            }),
+            "shutdown" => {
+                self.shutting_down = true;
+                Value::Null
+            }
+            "textDocument/completion" => self
+                .position(params)
+                .map_or(Value::Null, |(text, offset)| completion(text, offset)),
+            "textDocument/hover" => self
+                .position(params)
+                .and_then(|(text, offset)| hover(text, offset))
+                .unwrap_or(Value::Null),
+            "textDocument/definition" => self
+                .position(params)
+                .and_then(|(text, offset)| {
+                    let index = Index::new(text);
+                    let definition = index.definition_at(offset)?;
+                    Some(json!({
+                        "uri": params["textDocument"]["uri"],
+                        "range": range(text, definition.span),
+                    }))
+                })
+                .unwrap_or(Value::Null),
+            "textDocument/documentSymbol" => self
+                .text(params)
+                .map_or(Value::Null, |text| Index::new(text).symbols(text)),
+            "textDocument/semanticTokens/full" => {
+                self.text(params).map_or(Value::Null, semantic_tokens)
+            }
+            _ => {
+                let message = format!("unknown method '{}'", method);
+                return vec![error_response(id, METHOD_NOT_FOUND, &message)];
+            }
+        };
+        vec![json!({ "jsonrpc": "2.0", "id": id, "result": result })]
+    }
+
+    fn notify(&mut self, method: &str, params: &Value) -> Vec<Value> {
+        let uri = params["textDocument"]["uri"]
+            .as_str()
+            .unwrap_or("")
+            .to_string();
+        match method {
+            "exit" => {
+                self.exit_code = Some(if self.shutting_down { 0 } else { 1 });
+                Vec::new()
+            }
+            "textDocument/didOpen" => {
+                let text = params["textDocument"]["text"].as_str().unwrap_or("");
+                self.open(uri, text.to_string())
+            }
+            "textDocument/didChange" => {
+                // With full sync the last change holds the whole text
+                let changes = params["contentChanges"].as_array();
+                match changes.and_then(|changes| changes.last()?["text"].as_str()) {
+                    Some(text) => self.open(uri, text.to_string()),
+                    None => Vec::new(),
+                }
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+                vec![publish_diagnostics(&uri, Vec::new())]
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    fn open(&mut self, uri: String, text: String) -> Vec<Value> {
+        let found = check(&text);
+        let notification = publish_diagnostics(&uri, found);
+        self.documents.insert(uri, text);
+        vec![notification]
+    }
+
+    fn text(&self, params: &Value) -> Option<&str> {
+        let uri = params["textDocument"]["uri"].as_str()?;
+        self.documents.get(uri).map(String::as_str)
+    }
+
+    /// The document a request is about, and the byte offset of its position
+    fn position(&self, params: &Value) -> Option<(&str, usize)> {
+        let text = self.text(params)?;
+        Some((text, offset(text, &params["position"])))
+    }
+}
+
+fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+fn publish_diagnostics(uri: &str, found: Vec<Value>) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/publishDiagnostics",
+        "params": { "uri": uri, "diagnostics": found },
+    })
+}
+
+/// The syntax errors in a program, as the same diagnostics `zen check` shows
+fn check(text: &str) -> Vec<Value> {
+    let parse = || -> Result<(), ZenError> {
+        let (tokens, spans) = Lexer::lex_program(text.as_bytes())?;
+        Parser::parse_program(Tokens::new_with_spans(&tokens, &spans))?;
+        Ok(())
+    };
+    let Err(error) = parse() else {
+        return Vec::new();
+    };
+
+    diagnostics(&error, text)
+        .into_iter()
+        .map(|diagnostic| {
+            // Errors at the end of the file have no span
+            let span = diagnostic.span.unwrap_or(Span {
+                start: text.len(),
+                end: text.len(),
+                ..Span::default()
+            });
+            let message = match diagnostic.help {
+                Some(help) => format!("{}\nhelp: {}", diagnostic.message, help),
+                None => diagnostic.message,
+            };
+            json!({
+                "range": range(text, span),
+                "severity": 1,
+                "code": diagnostic.code,
+                "source": "zen",
+                "message": message,
+            })
+        })
+        .collect()
+}
+
+/// Every keyword, plus the names the program defines. A keyword replaces
+/// as much of it as was typed already, over several words if need be, so
+/// `JAB TA` completes to `JAB TAK HAI JAAN`.
+fn completion(text: &str, offset: usize) -> Value {
+    let line_start = text[..offset].rfind('\n').map_or(0, |newline| newline + 1);
+    let typed = &text[line_start..offset];
+    // Where the words typed so far on this line start
+    let word_starts: Vec<usize> = std::iter::once(0)
+        .chain(
+            typed
+                .char_indices()
+                .filter(|(_, c)| c.is_whitespace())
+                .map(|(at, c)| at + c.len_utf8()),
+        )
+        .collect();
+    let current_word = *word_starts.last().unwrap_or(&0);
+
+    let mut items: Vec<Value> = KEYWORDS
+        .iter()
+        .map(|(keyword, detail)| {
+            let start = word_starts
+                .iter()
+                .copied()
+                .find(|&start| {
+                    let prefix = typed[start..].to_uppercase();
+                    !prefix.is_empty() && keyword.starts_with(&prefix)
+                })
+                .unwrap_or(current_word);
+            let replaced = Span {
+                start: line_start + start,
+                end: offset,
+                ..Span::default()
+            };
+            json!({
+                "label": keyword,
+                "kind": 14,
+                "detail": detail,
+                "textEdit": { "range": range(text, replaced), "newText": keyword },
+            })
+        })
+        .collect();
+
+    let mut seen = Vec::new();
+    for definition in Index::new(text).definitions {
+        if seen.contains(&definition.name) {
+            continue;
+        }
+        let (kind, detail) = match definition.kind {
+            DefinitionKind::Function => (3, "function"),
+            _ => (6, "variable"),
+        };
+        items.push(json!({ "label": definition.name, "kind": kind, "detail": detail }));
+        seen.push(definition.name);
+    }
+    Value::Array(items)
+}
+
+/// The classified tokens, so that editors can colour multi-word keywords
+/// as one. Each token is five numbers: its line and start relative to the
+/// token before, its length, its type and no modifiers.
+fn semantic_tokens(text: &str) -> Value {
+    let mut data = Vec::new();
+    let (mut last_line, mut last_start) = (0, 0);
+    for token in Lexer::lex_classified(text.as_bytes()) {
+        let start = position(text, token.span.start);
+        let line = start["line"].as_u64().unwrap_or(0);
+        let character = start["character"].as_u64().unwrap_or(0);
+        // A token may only cover one line
+        let spelled = &text[token.span.start..token.span.end];
+        let length = spelled.lines().next().unwrap_or("").encode_utf16().count();
+        let token_type = match token.class {
+            TokenClass::Keyword => 0,
+            TokenClass::Identifier => 1,
+            TokenClass::Number => 2,
+            TokenClass::String => 3,
+            TokenClass::Comment => 4,
+            TokenClass::Operator => 5,
+        };
+
+        let delta_start = if line == last_line {
+            character - last_start
+        } else {
+            character
+        };
+        data.extend([line - last_line, delta_start, length as u64, token_type, 0]);
+        (last_line, last_start) = (line, character);
+    }
+    json!({ "data": data })
+}
+
+/// What a keyword does, or where a name got its value
+fn hover(text: &str, offset: usize) -> Option<Value> {
+    let index = Index::new(text);
+    let (token, span) = index.token_at(offset)?;
+    let contents = match token {
+        TokenType::Identifier(name) => {
+            let definition = index.resolve(name, span.start)?;
+            let line = definition.span.line;
+            let what = match &definition.kind {
+                DefinitionKind::Variable => {
+                    format!("variable, first given a value on line {}", line)
+                }
+                DefinitionKind::LoopVariable => {
+                    format!("loop variable of the loop on line {}", line)
+                }
+                DefinitionKind::Parameter(function) => format!("parameter of `{}`", function),
+                DefinitionKind::Function => format!("function, defined on line {}", line),
+            };
+            let source = text.lines().nth(line - 1).unwrap_or("").trim();
+            format!("```zen\n{}\n```\n`{}`: {}", source, name, what)
+        }
+        keyword => {
+            let keyword = keyword.to_string();
+            let (_, detail) = KEYWORDS.iter().find(|(known, _)| *known == keyword)?;
+            format!("```zen\n{}\n```\n{}", keyword, detail)
+        }
+    };
+    Some(json!({
+        "contents": { "kind": "markdown", "value": contents },
+        "range": range(text, *span),
+    }))
+}
+
+/// The LSP position of a byte offset: a line and a column counted in UTF-16
+/// code units, both from 0
+fn position(text: &str, offset: usize) -> Value {
+    let offset = offset.min(text.len());
+    let line_start = text[..offset].rfind('\n').map_or(0, |newline| newline + 1);
+    json!({
+        "line": text[..line_start].matches('\n').count(),
+        "character": text[line_start..offset].encode_utf16().count(),
+    })
+}
+
+/// The byte offset of an LSP position, clamped to the end of its line
+fn offset(text: &str, position: &Value) -> usize {
+    let line = position["line"].as_u64().unwrap_or(0) as usize;
+    let character = position["character"].as_u64().unwrap_or(0) as usize;
+    let line_start = match line {
+        0 => 0,
+        _ => match text.match_indices('\n').nth(line - 1) {
+            Some((newline, _)) => newline + 1,
+            None => return text.len(),
+        },
+    };
+
+    let mut units = 0;
+    for (at, c) in text[line_start..].char_indices() {
+        if units >= character || c == '\n' {
+            return line_start + at;
+        }
+        units += c.len_utf16();
+    }
+    text.len()
+}
+
+fn range(text: &str, span: Span) -> Value {
+    json!({ "start": position(text, span.start), "end": position(text, span.end) })
+}
+
+enum DefinitionKind {
+    Variable,
+    LoopVariable,
+    /// Names the function it belongs to
+    Parameter(String),
+    Function,
+}
+
+/// A place where a name gets its value
+struct Definition {
+    name: String,
+    kind: DefinitionKind,
+    /// Where the name itself is written
+    span: Span,
+    /// The function it lives in, an index into `Index::functions`
+    function: Option<usize>,
+}
+
+struct Function {
+    /// Its name, an index into `Index::definitions`
+    definition: usize,
+    /// From 'AAO KABHI HAVELI PE' to 'PHIR MILENGE', or to the end of the
+    /// document while it is not closed yet
+    span: Span,
+}
+
+/// The names of a document: where each one is defined and which function
+/// it belongs to
+struct Index {
+    tokens: Vec<(TokenType, Span)>,
+    definitions: Vec<Definition>,
+    functions: Vec<Function>,
+}
+
+impl Index {
+    fn new(text: &str) -> Self {
+        let (tokens, spans) = Lexer::lex_tokens_with_spans(text.as_bytes())
+            .map(|(_, lexed)| lexed)
+            .unwrap_or_default();
+        let mut definitions = Vec::new();
+        let mut functions: Vec<Function> = Vec::new();
+        // The functions the tokens so far are in, innermost last
+        let mut open: Vec<usize> = Vec::new();
+
+        for (at, token) in tokens.iter().enumerate() {
+            let function = open.last().copied();
+            match (token, tokens.get(at + 1)) {
+                (TokenType::FunctionStart, Some(TokenType::Identifier(name))) => {
+                    definitions.push(Definition {
+                        name: name.clone(),
+                        kind: DefinitionKind::Function,
+                        span: spans[at + 1],
+                        function,
+                    });
+                    functions.push(Function {
+                        definition: definitions.len() - 1,
+                        span: Span {
+                            end: text.len(),
+                            ..spans[at]
+                        },
+                    });
+                    open.push(functions.len() - 1);
+
+                    // The parameters, `(A, B)`
+                    let mut next = at + 3;
+                    while tokens.get(at + 2) == Some(&TokenType::LeftParen) {
+                        let Some(TokenType::Identifier(parameter)) = tokens.get(next) else {
+                            break;
+                        };
+                        definitions.push(Definition {
+                            name: parameter.clone(),
+                            kind: DefinitionKind::Parameter(name.clone()),
+                            span: spans[next],
+                            function: open.last().copied(),
+                        });
+                        if tokens.get(next + 1) != Some(&TokenType::Comma) {
+                            break;
+                        }
+                        next += 2;
+                    }
+                }
+                (TokenType::FunctionEnd, _) => {
+                    if let Some(closed) = open.pop() {
+                        functions[closed].span.end = spans[at].end;
+                    }
+                }
+                (TokenType::Identifier(name), Some(TokenType::Assign)) => {
+                    let kind = match at.checked_sub(1).map(|before| &tokens[before]) {
+                        Some(TokenType::For) => DefinitionKind::LoopVariable,
+                        _ => DefinitionKind::Variable,
+                    };
+                    definitions.push(Definition {
+                        name: name.clone(),
+                        kind,
+                        span: spans[at],
+                        function,
+                    });
+                }
+                _ => {}
+            }
+        }
+
+        Index {
+            tokens: tokens.into_iter().zip(spans).collect(),
+            definitions,
+            functions,
+        }
+    }
+
+    /// The word at `offset`, or just before it
+    fn token_at(&self, offset: usize) -> Option<&(TokenType, Span)> {
+        self.tokens.iter().find(|(token, span)| {
+            span.start <= offset
+                && offset <= span.end
+                && !matches!(token, TokenType::EndOfStatement | TokenType::Eof)
+        })
+    }
+
+    fn definition_at(&self, offset: usize) -> Option<&Definition> {
+        match self.token_at(offset)? {
+            (TokenType::Identifier(name), span) => self.resolve(name, span.start),
+            _ => None,
+        }
+    }
+
+    /// Where `name`, used at `offset`, was first defined: inside the
+    /// function around it if it was, at the top of the program otherwise
+    fn resolve(&self, name: &str, offset: usize) -> Option<&Definition> {
+        let function = self
+            .functions
+            .iter()
+            .rposition(|function| function.span.start <= offset && offset <= function.span.end);
+        let named = || {
+            self.definitions
+                .iter()
+                .filter(|definition| definition.name == name)
+        };
+
+        named()
+            .find(|definition| function.is_some() && definition.function == function)
+            .or_else(|| named().find(|definition| definition.function.is_none()))
+    }
+
+    /// The outline of the document: its functions, with their parameters
+    /// and variables inside, and the variables of the program itself
+    fn symbols(&self, text: &str) -> Value {
+        Value::Array(self.symbols_in(text, None))
+    }
+
+    fn symbols_in(&self, text: &str, function: Option<usize>) -> Vec<Value> {
+        let mut symbols = Vec::new();
+        let mut seen = Vec::new();
+        for (index, definition) in self.definitions.iter().enumerate() {
+            if definition.function != function {
+                continue;
+            }
+            if let DefinitionKind::Function = definition.kind {
+                let Some(inner) = self
+                    .functions
+                    .iter()
+                    .position(|inner| inner.definition == index)
+                else {
+                    continue;
+                };
+                let parameters: Vec<&str> = self
+                    .definitions
+                    .iter()
+                    .filter(|parameter| {
+                        parameter.function == Some(inner)
+                            && matches!(parameter.kind, DefinitionKind::Parameter(_))
+                    })
+                    .map(|parameter| parameter.name.as_str())
+                    .collect();
+                symbols.push(json!({
+                    "name": definition.name,
+                    "detail": format!("({})", parameters.join(", ")),
+                    "kind": 12,
+                    "range": range(text, self.functions[inner].span),
+                    "selectionRange": range(text, definition.span),
+                    "children": self.symbols_in(text, Some(inner)),
+                }));
+            } else if !seen.contains(&&definition.name) {
+                seen.push(&definition.name);
+                symbols.push(json!({
+                    "name": definition.name,
+                    "kind": 13,
+                    "range": range(text, definition.span),
+                    "selectionRange": range(text, definition.span),
+                }));
+            }
+        }
+        symbols
+    }
+}
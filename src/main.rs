@@ -1,55 +1,321 @@
-use colored::Colorize;
-use std::process;
-use std::{env, fs, io, io::Write};
-use zen::run_program;
+use clap::error::ErrorKind;
+use clap::{CommandFactory, Parser as _, Subcommand, ValueEnum};
+use rustyline::DefaultEditor;
+use rustyline::error::ReadlineError;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use std::{env, fs, io, process};
+use zen::diagnostics::render_error;
+use zen::error::ZenError;
+use zen::evaluator::config::InterpreterConfig;
+use zen::evaluator::interpreter::Interpreter;
+use zen::lexer::lexer_util::Lexer;
+use zen::lexer::tokens::Tokens;
+use zen::parser::ast::Program;
+use zen::parser::parser_util::Parser;
+use zen::repl::{Repl, Step};
+use zen::{compile, compile_to_wat, format_code, highlight};
 
-pub mod lexer;
-pub mod parser;
-use crate::lexer::lexer_util::Lexer;
-use crate::lexer::tokens::Tokens;
-use crate::parser::parser_util::Parser;
+/// The file could not be read or written. Clap itself exits with 2 on bad
+/// arguments.
+const EXIT_IO: i32 = 1;
+const EXIT_LEX: i32 = 3;
+const EXIT_PARSE: i32 = 4;
+const EXIT_RUNTIME: i32 = 5;
+/// `zen fmt --check` found a file that is not formatted
+const EXIT_UNFORMATTED: i32 = 6;
+
+const EXIT_CODES: &str = "Exit codes:
+  0  success
+  1  the file could not be read or written
+  2  bad command line arguments
+  3  the program has characters that are not part of Zen
+  4  the program has syntax errors
+  5  the program failed while running
+  6  `zen fmt --check` found the program not formatted";
+
+/// Zen, the Bollywood programming language. Without a command or a file,
+/// zen starts an interactive REPL.
+#[derive(clap::Parser)]
+#[command(
+    name = "zen",
+    version,
+    after_help = EXIT_CODES
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// Program to run, same as `zen run FILE`
+    file: Option<PathBuf>,
+    /// Never colour error messages, even on a terminal
+    #[arg(long, global = true)]
+    no_color: bool,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Run a program
+    Run {
+        file: PathBuf,
+        /// Text that the program reads its input from, instead of the terminal
+        #[arg(long, value_name = "TEXT")]
+        input: Option<String>,
+        /// How many times a single loop may run before the program is stopped
+        #[arg(long, value_name = "COUNT")]
+        max_loops: Option<usize>,
+        /// How many steps the whole program may take before it is stopped
+        #[arg(long, value_name = "COUNT")]
+        max_steps: Option<usize>,
+        /// How many bytes the program may print before it is stopped
+        #[arg(long, value_name = "BYTES")]
+        max_output: Option<usize>,
+        /// How many milliseconds the program may run before it is stopped
+        #[arg(long, value_name = "MS")]
+        timeout: Option<u64>,
+    },
+    /// Check a program for mistakes without running it
+    Check { file: PathBuf },
+    /// Show the tokens a program is made of
+    Tokens { file: PathBuf },
+    /// Show the syntax tree of a program
+    Ast { file: PathBuf },
+    /// Compile a program to C or to a WebAssembly text module
+    Compile {
+        file: PathBuf,
+        #[arg(long, value_enum, default_value_t = Format::C)]
+        format: Format,
+        /// Where to write the output, instead of stdout
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+    /// Show a program highlighted, or its classified tokens as JSON
+    Highlight {
+        file: PathBuf,
+        #[arg(long, value_enum, default_value_t = HighlightFormat::Ansi)]
+        format: HighlightFormat,
+    },
+    /// Format a program the canonical way, keeping its comments
+    Fmt {
+        file: PathBuf,
+        /// Write the formatted program back to the file, instead of stdout
+        #[arg(short, long)]
+        write: bool,
+        /// Only check whether the file is formatted, exiting with 6 if not
+        #[arg(long, conflicts_with = "write")]
+        check: bool,
+    },
+}
+
+#[derive(Clone, ValueEnum)]
+enum Format {
+    /// C99 source, built with `cc program.c -lm`
+    C,
+    /// WebAssembly text, built with `wasm-tools parse program.wat -o program.wasm`
+    Wat,
+}
+
+#[derive(Clone, ValueEnum)]
+enum HighlightFormat {
+    /// The program coloured for a terminal
+    Ansi,
+    /// Every token with its class and byte range, for editors and web pages
+    Json,
+}
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
-    let mut contents = String::new();
+    let cli = Cli::parse();
+    let use_color = !cli.no_color && io::stderr().is_terminal();
 
-    if args.len() > 1 {
-        let filename = &args[1];
-        contents = match fs::read_to_string(filename) {
-            Ok(content) => content,
-            Err(e) => {
-                eprintln!("Error reading the file {}\nError {}", filename, e);
-                process::exit(1);
+    let command = match (cli.command, cli.file) {
+        // Checked here rather than by clap, which would also keep the global
+        // --no-color from coming before a command
+        (Some(_), Some(file)) => Cli::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                format!(
+                    "the file '{}' cannot be used with a command",
+                    file.display()
+                ),
+            )
+            .exit(),
+        (Some(command), None) => command,
+        (None, Some(file)) => Command::Run {
+            file,
+            input: None,
+            max_loops: None,
+            max_steps: None,
+            max_output: None,
+            timeout: None,
+        },
+        (None, None) => return run_repl(use_color),
+    };
+
+    let file = match &command {
+        Command::Run { file, .. }
+        | Command::Check { file }
+        | Command::Tokens { file }
+        | Command::Ast { file }
+        | Command::Compile { file, .. }
+        | Command::Highlight { file, .. }
+        | Command::Fmt { file, .. } => file.clone(),
+    };
+    let file_name = file.display().to_string();
+    let source = match fs::read_to_string(&file) {
+        Ok(content) => content,
+        Err(e) => {
+            eprintln!("Error reading the file {}\nError {}", file_name, e);
+            process::exit(EXIT_IO);
+        }
+    };
+
+    if let Err(e) = run_command(command, &source) {
+        report_error(&e, &source, &file_name, use_color);
+        process::exit(exit_code(&e));
+    }
+}
+
+fn run_command(command: Command, source: &str) -> Result<(), ZenError> {
+    match command {
+        Command::Run {
+            input,
+            max_loops,
+            max_steps,
+            max_output,
+            timeout,
+            ..
+        } => {
+            let program = parse(source)?;
+            let mut config = InterpreterConfig {
+                max_steps,
+                max_output_bytes: max_output,
+                timeout: timeout.map(Duration::from_millis),
+                ..InterpreterConfig::default()
+            };
+            if let Some(max_loops) = max_loops {
+                config.max_iterations = max_loops;
             }
-        };
+            let input = input.unwrap_or_default();
+            Interpreter::new_with_config(&input, true, config).run_code(program)?;
+        }
+        Command::Check { .. } => {
+            parse(source)?;
+            println!("No mistakes found. 'All is well!'");
+        }
+        Command::Tokens { .. } => {
+            let (tokens, spans) = Lexer::lex_program(source.as_bytes())?;
+            for (token, span) in tokens.iter().zip(&spans) {
+                println!("{}:{}\t{:?}", span.line, span.column, token);
+            }
+        }
+        Command::Ast { .. } => println!("{:#?}", parse(source)?),
+        Command::Compile { format, output, .. } => {
+            let compiled = match format {
+                Format::C => compile(source.to_string())?,
+                Format::Wat => compile_to_wat(source.to_string())?,
+            };
+            match output {
+                Some(path) => write_output(&path, &compiled),
+                None => print!("{}", compiled),
+            }
+        }
+        Command::Highlight { format, .. } => match format {
+            HighlightFormat::Ansi => print!("{}", highlight::to_ansi(source)),
+            HighlightFormat::Json => println!("{}", highlight::to_json(source)),
+        },
+        Command::Fmt { file, write, check } => {
+            let formatted = format_code(source.to_string())?;
+            if check {
+                if formatted != source {
+                    eprintln!(
+                        "{} is not formatted, run `zen fmt -w` on it",
+                        file.display()
+                    );
+                    process::exit(EXIT_UNFORMATTED);
+                }
+            } else if write {
+                write_output(&file, &formatted);
+            } else {
+                print!("{}", formatted);
+            }
+        }
+    }
+    Ok(())
+}
 
-        let runnable = run_program(contents, "", true);
-        match runnable {
-            Ok(output) => {
-                println!("{}", output);
+fn parse(source: &str) -> Result<Program, ZenError> {
+    let (tokens, spans) = Lexer::lex_program(source.as_bytes())?;
+    Ok(Parser::parse_program(Tokens::new_with_spans(
+        &tokens, &spans,
+    ))?)
+}
+
+fn write_output(path: &Path, contents: &str) {
+    if let Err(e) = fs::write(path, contents) {
+        eprintln!("Error writing the file {}\nError {}", path.display(), e);
+        process::exit(EXIT_IO);
+    }
+}
+
+fn exit_code(e: &ZenError) -> i32 {
+    match e {
+        ZenError::Lex(_) => EXIT_LEX,
+        ZenError::Parse(_) => EXIT_PARSE,
+        ZenError::Runtime(_) => EXIT_RUNTIME,
+    }
+}
+
+/// Reads statements from the terminal and runs them as they are completed,
+/// keeping their variables and functions for the next ones
+fn run_repl(use_color: bool) {
+    let mut editor = match DefaultEditor::new() {
+        Ok(editor) => editor,
+        Err(e) => {
+            eprintln!("Could not open the terminal for the REPL\nError {}", e);
+            process::exit(1);
+        }
+    };
+    let history = env::var_os("HOME").map(|home| PathBuf::from(home).join(".zen_history"));
+    if let Some(history) = &history {
+        // There is no history yet on the first run
+        let _ = editor.load_history(history);
+    }
+
+    println!("Welcome To Zen world!");
+    println!("Type Zen statements to run them, or :help for the commands.");
+    let mut repl = Repl::new(true);
+    loop {
+        let prompt = if repl.is_pending() { "... " } else { "> " };
+        let line = match editor.readline(prompt) {
+            Ok(line) => line,
+            // Ctrl-C drops the block being typed, or else the line
+            Err(ReadlineError::Interrupted) => {
+                repl.clear_pending();
+                continue;
             }
+            Err(ReadlineError::Eof) => break,
             Err(e) => {
-                println!(
-                    "{}\nMessage: {}\nError Type: {}",
-                    "Runtime Error occurred!".red(),
-                    e.msg.blue(),
-                    e.error_type.to_string().yellow()
-                );
+                eprintln!("Could not read the line\nError {}", e);
+                break;
             }
+        };
+        if !line.trim().is_empty() {
+            let _ = editor.add_history_entry(line.as_str());
         }
-    } else {
-        println!("Welcome To Zen world!");
-        println!("Generate AST from code right here!");
-        print!("> ");
-        std::io::stdout().flush().unwrap();
-        io::stdin()
-            .read_line(&mut contents)
-            .expect("Failed to read line");
 
-        let (_, r) = Lexer::lex_tokens(contents.as_bytes()).unwrap();
-        let tokens = Tokens::new(&r);
-        let (_, result) = Parser::parse_tokens(tokens).unwrap();
+        match repl.feed(&line) {
+            Step::More => {}
+            Step::Output(output) => print!("{}", output),
+            Step::Error { error, source } => report_error(&error, &source, "<repl>", use_color),
+            Step::Quit => break,
+        }
+    }
 
-        println!("Here is your AST:\n {:#?}", result);
+    if let Some(history) = &history {
+        let _ = editor.save_history(history);
     }
 }
+
+fn report_error(e: &ZenError, source: &str, file_name: &str, use_color: bool) {
+    eprint!("{}", render_error(e, source, file_name, use_color));
+}
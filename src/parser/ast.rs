@@ -1,3 +1,6 @@
+use crate::error::ParseError;
+use crate::lexer::span::Span;
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct Program {
     pub statements: Vec<Statement>,
@@ -11,6 +14,10 @@ pub enum Statement {
         name: Ident,
         value: Expression,
     },
+    LocalLet {
+        name: Ident,
+        value: Expression,
+    },
     If {
         condition: Box<Expression>,
         consequence: Vec<Statement>,
@@ -20,8 +27,36 @@ pub enum Statement {
         condition: Box<Expression>,
         body: Vec<Statement>,
     },
+    For {
+        variable: Ident,
+        start: Expression,
+        end: Expression,
+        step: Option<Expression>,
+        body: Vec<Statement>,
+    },
     Print(Box<Expression>),
+    Function {
+        name: Ident,
+        parameters: Vec<Ident>,
+        body: Vec<Statement>,
+    },
+    Return(Option<Expression>),
+    Break,
+    Continue,
+    IndexLet {
+        left: Expression,
+        index: Expression,
+        value: Expression,
+    },
     Expression(Expression),
+    /// A statement along with where it was written in the source
+    Spanned {
+        span: Span,
+        statement: Box<Statement>,
+    },
+    /// Stands in for a statement that did not parse, so that parsing can
+    /// carry on and report every mistake in the program
+    Invalid(ParseError),
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -37,7 +72,54 @@ pub enum Expression {
         operator: Infix,
         right: Box<Expression>,
     },
-    Input,
+    ListExpr(Vec<Expression>),
+    MapExpr(Vec<(Expression, Expression)>),
+    Call {
+        function: Ident,
+        arguments: Vec<Expression>,
+    },
+    Index {
+        left: Box<Expression>,
+        index: Box<Expression>,
+    },
+    Slice {
+        left: Box<Expression>,
+        start: Option<Box<Expression>>,
+        end: Option<Box<Expression>>,
+    },
+    Input(InputKind),
+    /// An expression along with where it was written in the source
+    Spanned {
+        span: Span,
+        expression: Box<Expression>,
+    },
+}
+
+impl Statement {
+    /// The statement itself, looking through any source span around it
+    pub fn unspanned(&self) -> &Statement {
+        match self {
+            Statement::Spanned { statement, .. } => statement.unspanned(),
+            statement => statement,
+        }
+    }
+}
+
+impl Expression {
+    /// The expression itself, looking through any source span around it
+    pub fn unspanned(&self) -> &Expression {
+        match self {
+            Expression::Spanned { expression, .. } => expression.unspanned(),
+            expression => expression,
+        }
+    }
+
+    pub fn into_unspanned(self) -> Expression {
+        match self {
+            Expression::Spanned { expression, .. } => expression.into_unspanned(),
+            expression => expression,
+        }
+    }
 }
 #[derive(PartialEq, Debug, Clone)]
 pub enum Literal {
@@ -49,6 +131,17 @@ pub enum Literal {
 #[derive(PartialEq, Debug, Eq, Clone)]
 pub struct Ident(pub String);
 
+/// What an input expression reads
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum InputKind {
+    /// 'INPUT LE LE RE BABA', the next word as a number
+    Number,
+    /// 'SHABD LE LE RE BABA', the next word as a string
+    Word,
+    /// 'PURI LINE LE LE RE BABA', the rest of the line as a string
+    Line,
+}
+
 #[derive(PartialEq, Debug, Clone)]
 pub enum Prefix {
     PrefixPlus,
@@ -84,4 +177,5 @@ pub enum Precedence {
     PProduct,
     PLParen,
     PRParen,
+    PIndex,
 }
@@ -1,45 +1,123 @@
 use nom::branch::alt;
-use nom::error::{Error, ErrorKind};
-use nom::multi::many0;
+use nom::error::{ErrorKind, ParseError as _};
+use nom::multi::{many0, separated_list0};
 use nom::sequence::{preceded, tuple};
 use nom::{error_position, IResult};
 
 use super::ast::*;
+use crate::error::ParseError;
+use crate::lexer::span::Span;
 use crate::lexer::token_type::TokenType;
 use crate::lexer::tokens::Tokens;
 use nom::bytes::complete::take;
-use nom::combinator::{map, opt, peek, verify};
-use nom::Err;
+use nom::combinator::{map, opt, peek, value};
+use nom::{Err, Finish, Slice};
 
+use std::cmp::Ordering;
 use std::result::Result::*;
+
+/// Why the tokens did not parse: the position the parser got stuck at and
+/// everything it would have accepted there
+#[derive(Clone, PartialEq, Debug)]
+pub struct ParserError<'a> {
+    pub input: Tokens<'a>,
+    pub expected: Vec<String>,
+}
+
+pub type ParseResult<'a, O> = IResult<Tokens<'a>, O, ParserError<'a>>;
+
+impl<'a> ParserError<'a> {
+    fn expected(input: Tokens<'a>, what: impl Into<String>) -> Self {
+        ParserError {
+            input,
+            expected: vec![what.into()],
+        }
+    }
+
+    /// Detaches the error from the tokens, remembering what was found instead
+    pub fn into_parse_error(self) -> ParseError {
+        ParseError {
+            expected: self.expected,
+            found: self.input.tok.first().cloned().unwrap_or(TokenType::Eof),
+            span: self.input.spans.first().copied(),
+        }
+    }
+}
+
+impl<'a> nom::error::ParseError<Tokens<'a>> for ParserError<'a> {
+    fn from_error_kind(input: Tokens<'a>, _kind: ErrorKind) -> Self {
+        ParserError {
+            input,
+            expected: Vec::new(),
+        }
+    }
+
+    fn append(_input: Tokens<'a>, _kind: ErrorKind, other: Self) -> Self {
+        other
+    }
+
+    /// Of two failed alternatives, the one that got further into the tokens
+    /// tells more about the mistake
+    fn or(mut self, other: Self) -> Self {
+        match self.input.tok.len().cmp(&other.input.tok.len()) {
+            Ordering::Less => self,
+            Ordering::Greater => other,
+            Ordering::Equal => {
+                for expected in other.expected {
+                    if !self.expected.contains(&expected) {
+                        self.expected.push(expected);
+                    }
+                }
+                self
+            }
+        }
+    }
+}
+
+/// Runs `parser`, reporting just `what` when it fails without getting past
+/// the first token, instead of every alternative it tried
+fn expecting<'a, O>(
+    what: &'static str,
+    mut parser: impl FnMut(Tokens<'a>) -> ParseResult<'a, O>,
+) -> impl FnMut(Tokens<'a>) -> ParseResult<'a, O> {
+    move |input: Tokens<'a>| {
+        parser(input).map_err(|err| match err {
+            Err::Error(e) if e.input.tok.len() == input.tok.len() => {
+                Err::Error(ParserError::expected(input, what))
+            }
+            err => err,
+        })
+    }
+}
+
 macro_rules! tag_token (
     ($func_name:ident, $tag: expr_2021) => (
-        fn $func_name(tokens: Tokens) -> IResult<Tokens, Tokens> {
-            verify(take(1usize), |t: &Tokens| t.tok[0] == $tag)(tokens)
+        fn $func_name(tokens: Tokens) -> ParseResult<Tokens> {
+            tag_token($tag)(tokens)
         }
     )
   );
-fn parse_literal(input: Tokens) -> IResult<Tokens, Literal> {
+fn parse_literal(input: Tokens) -> ParseResult<Literal> {
     let (i1, t1) = take(1usize)(input)?;
     if t1.tok.is_empty() {
-        Err(Err::Error(Error::new(input, ErrorKind::Tag)))
+        Err(Err::Error(ParserError::expected(input, "a value")))
     } else {
         match t1.tok[0].clone() {
             TokenType::Number(name) => Ok((i1, Literal::Number(name))),
             TokenType::StringLiteral(s) => Ok((i1, Literal::StringLiteral(s))),
             TokenType::BooleanLiteral(b) => Ok((i1, Literal::BoolLiteral(b))),
-            _ => Err(Err::Error(Error::new(input, ErrorKind::Tag))),
+            _ => Err(Err::Error(ParserError::expected(input, "a value"))),
         }
     }
 }
-fn parse_ident(input: Tokens) -> IResult<Tokens, Ident> {
+fn parse_ident(input: Tokens) -> ParseResult<Ident> {
     let (i1, t1) = take(1usize)(input)?;
     if t1.tok.is_empty() {
-        Err(Err::Error(Error::new(input, ErrorKind::Tag)))
+        Err(Err::Error(ParserError::expected(input, "a name")))
     } else {
         match t1.tok[0].clone() {
             TokenType::Identifier(name) => Ok((i1, Ident(name))),
-            _ => Err(Err::Error(Error::new(input, ErrorKind::Tag))),
+            _ => Err(Err::Error(ParserError::expected(input, "a name"))),
         }
     }
 }
@@ -50,6 +128,12 @@ tag_token!(if_tag, TokenType::If);
 tag_token!(then_tag, TokenType::Then);
 tag_token!(else_tag, TokenType::Else);
 tag_token!(elseif_tag, TokenType::ElseIf);
+tag_token!(comma_tag, TokenType::Comma);
+tag_token!(lbracket_tag, TokenType::LeftBracket);
+tag_token!(rbracket_tag, TokenType::RightBracket);
+tag_token!(colon_tag, TokenType::Colon);
+tag_token!(lbrace_tag, TokenType::LeftBrace);
+tag_token!(rbrace_tag, TokenType::RightBrace);
 
 fn infix_op(t: &TokenType) -> (Precedence, Option<Infix>) {
     match *t {
@@ -66,72 +150,243 @@ fn infix_op(t: &TokenType) -> (Precedence, Option<Infix>) {
         TokenType::Modulo => (Precedence::PProduct, Some(Infix::Modulo)),
         TokenType::LogicalAnd => (Precedence::PLogicalAnd, Some(Infix::LogicalAnd)),
         TokenType::LogicalOr => (Precedence::PLogicalOr, Some(Infix::LogicalOr)),
+        TokenType::LeftBracket => (Precedence::PIndex, None),
         _ => (Precedence::PLowest, None),
     }
 }
 
+/// Span of the tokens consumed between `start` and `rest`, leaving out line
+/// breaks at the end. `None` when the tokens carry no spans.
+fn consumed_span(start: Tokens, rest: Tokens) -> Option<Span> {
+    let consumed = start.tok.len() - rest.tok.len();
+    let last = start.tok[..consumed]
+        .iter()
+        .rposition(|token| *token != TokenType::EndOfStatement)?;
+    let first = start.spans.first()?;
+    Some(first.to(start.spans[last]))
+}
+
+fn spanned_expr(start: Tokens, rest: Tokens, expression: Expression) -> Expression {
+    match consumed_span(start, rest) {
+        // A parenthesised expression already knows where it is
+        Some(_) if matches!(expression, Expression::Spanned { .. }) => expression,
+        Some(span) => Expression::Spanned {
+            span,
+            expression: Box::new(expression),
+        },
+        None => expression,
+    }
+}
+
 pub struct Parser;
 
 impl Parser {
-    pub fn parse_tokens(tokens: Tokens) -> IResult<Tokens, Program> {
+    pub fn parse_tokens(tokens: Tokens) -> ParseResult<Program> {
         parse_program(tokens)
     }
+
+    /// Parses a complete program, which has to end with 'KHATAM TATA BYE BYE'.
+    /// Gives back every syntax error in the program, in source order.
+    pub fn parse_program(tokens: Tokens) -> Result<Program, Vec<ParseError>> {
+        let (remaining_tokens, program) = Self::parse_all(tokens)?;
+
+        let has_end = program
+            .statements
+            .iter()
+            .any(|statement| statement.unspanned() == &Statement::ProgramEnd);
+        if !has_end {
+            let error = ParserError::expected(remaining_tokens, TokenType::EndProgram.describe());
+            return Err(vec![error.into_parse_error()]);
+        }
+        Ok(program)
+    }
+
+    /// Parses statements that are not a whole program, such as a line typed
+    /// into the REPL, so 'KHATAM TATA BYE BYE' is not needed at the end
+    pub fn parse_statements(tokens: Tokens) -> Result<Vec<Statement>, Vec<ParseError>> {
+        Self::parse_all(tokens).map(|(_, program)| program.statements)
+    }
+
+    fn parse_all(tokens: Tokens) -> Result<(Tokens, Program), Vec<ParseError>> {
+        let (remaining_tokens, program) = parse_program(tokens)
+            .finish()
+            .map_err(|err| vec![err.into_parse_error()])?;
+
+        let mut errors = Vec::new();
+        collect_invalid(&program.statements, &mut errors);
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        Ok((remaining_tokens, program))
+    }
+}
+
+fn collect_invalid(statements: &[Statement], errors: &mut Vec<ParseError>) {
+    for statement in statements {
+        match statement.unspanned() {
+            Statement::Invalid(err) => errors.push(err.clone()),
+            Statement::If {
+                consequence,
+                alternative,
+                ..
+            } => {
+                collect_invalid(consequence, errors);
+                collect_invalid(alternative.as_deref().unwrap_or_default(), errors);
+            }
+            Statement::While { body, .. }
+            | Statement::For { body, .. }
+            | Statement::Function { body, .. } => collect_invalid(body, errors),
+            _ => {}
+        }
+    }
 }
 
-fn parse_program(input: Tokens) -> IResult<Tokens, Program> {
-    let (remaining_tokens, statements) = many0(parse_statement)(input)?;
+fn parse_program(input: Tokens) -> ParseResult<Program> {
+    let (remaining_tokens, statements) = parse_block(&[])(input)?;
     Ok((remaining_tokens, Program { statements }))
 }
 
-fn parse_statement(input: Tokens) -> IResult<Tokens, Statement> {
-    let (remaining_tokens, statement) = alt((
-        parse_program_start,
-        parse_program_end,
-        parse_let_statement,
-        parse_if_statement,
-        parse_while_statement,
-        parse_print_statement,
-        parse_expression_statement,
-    ))(input)?;
+/// Statements up to one of the `terminators` (which is left for the caller)
+/// or the end of the file. A statement that does not parse is kept as
+/// `Statement::Invalid` and skipped, so that the rest of the block still gets
+/// parsed and checked.
+fn parse_block<'a>(
+    terminators: &'static [TokenType],
+) -> impl Fn(Tokens<'a>) -> ParseResult<'a, Vec<Statement>> {
+    move |mut input: Tokens<'a>| {
+        let mut statements = Vec::new();
+        loop {
+            let (remaining_tokens, _) = many0(tag_token(TokenType::EndOfStatement))(input)?;
+            input = remaining_tokens;
+            match input.tok.first() {
+                None | Some(TokenType::Eof) => return Ok((input, statements)),
+                Some(token) if terminators.contains(token) => return Ok((input, statements)),
+                _ => {}
+            }
+
+            match parse_statement(input) {
+                Ok((remaining_tokens, statement)) => {
+                    statements.push(statement);
+                    input = remaining_tokens;
+                }
+                Err(Err::Error(err)) => {
+                    let expected = terminators.iter().map(TokenType::describe).collect();
+                    let err = ParserError { input, expected }.or(err);
+                    statements.push(Statement::Invalid(err.into_parse_error()));
+                    input = skip_statement(input, terminators);
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+}
+
+/// Skips past a statement that did not parse: up to the end of its line, or
+/// past the end of the block it opened ('BAS ITNA HI', 'JAHAN' or 'PHIR
+/// MILENGE'). Stops early at a token that ends the enclosing block.
+///
+/// Keywords like 'BOLE TOH' take the line ending after them along, so the
+/// statement also ends wherever the next line starts.
+fn skip_statement<'a>(input: Tokens<'a>, terminators: &[TokenType]) -> Tokens<'a> {
+    let line = |position: usize| input.spans.get(position).map(|span| span.line);
+    let mut depth = 0;
+    for (position, token) in input.tok.iter().enumerate() {
+        if depth == 0 && position > 0 && line(position) > line(position - 1) {
+            return input.slice(position..);
+        }
+        match token {
+            TokenType::Eof => return input.slice(position..),
+            TokenType::EndOfStatement if depth == 0 => return input.slice(position + 1..),
+            TokenType::If | TokenType::While | TokenType::For | TokenType::FunctionStart => {
+                depth += 1
+            }
+            TokenType::EndIf | TokenType::EndWhile | TokenType::FunctionEnd => {
+                match depth {
+                    // Ends the enclosing block, unless it is a stray one right
+                    // at the start
+                    0 if position > 0 => return input.slice(position..),
+                    0 | 1 => return input.slice(position + 1..),
+                    _ => depth -= 1,
+                }
+            }
+            token if depth == 0 && position > 0 && terminators.contains(token) => {
+                return input.slice(position..);
+            }
+            _ => {}
+        }
+    }
+    input.slice(input.tok.len()..)
+}
+
+fn parse_statement(input: Tokens) -> ParseResult<Statement> {
+    let (remaining_tokens, statement) = expecting(
+        "a statement",
+        alt((
+            parse_program_start,
+            parse_program_end,
+            parse_let_statement,
+            parse_local_let_statement,
+            parse_index_let_statement,
+            parse_if_statement,
+            parse_while_statement,
+            parse_for_statement,
+            parse_print_statement,
+            parse_function_statement,
+            parse_return_statement,
+            parse_loop_control_statement,
+            parse_expression_statement,
+        )),
+    )(input)?;
+
+    let statement = match consumed_span(input, remaining_tokens) {
+        Some(span) => Statement::Spanned {
+            span,
+            statement: Box::new(statement),
+        },
+        None => statement,
+    };
 
     let remaining_tokens = match opt(many0(tag_token(TokenType::EndOfStatement)))(remaining_tokens)
-    { Ok((remaining_tokens, _)) => {
-        remaining_tokens
-    } _ => {
-        remaining_tokens
-    }};
+    {
+        Ok((remaining_tokens, _)) => remaining_tokens,
+        _ => remaining_tokens,
+    };
 
     Ok((remaining_tokens, statement))
 }
 
-fn parse_expression_statement(input: Tokens) -> IResult<Tokens, Statement> {
-    map(parse_expr, Statement::Expression)(input)
+fn parse_expression_statement(input: Tokens) -> ParseResult<Statement> {
+    let (remaining_tokens, expr) = parse_expr(input)?;
+    // An expression followed by 'BOLE TOH' is an assignment that went wrong,
+    // which the assignment parsers explain better
+    if assign_tag(remaining_tokens).is_ok() {
+        return Err(Err::Error(error_position!(
+            remaining_tokens,
+            ErrorKind::Tag
+        )));
+    }
+    Ok((remaining_tokens, Statement::Expression(expr)))
 }
 
-fn parse_program_start(input: Tokens) -> IResult<Tokens, Statement> {
+fn parse_program_start(input: Tokens) -> ParseResult<Statement> {
     map(tag_token(TokenType::StartProgram), |_| {
         Statement::ProgramStart
     })(input)
 }
 
-fn parse_program_end(input: Tokens) -> IResult<Tokens, Statement> {
+fn parse_program_end(input: Tokens) -> ParseResult<Statement> {
     map(tag_token(TokenType::EndProgram), |_| Statement::ProgramEnd)(input)
 }
-fn tag_token(token: TokenType) -> impl Fn(Tokens) -> IResult<Tokens, Tokens> {
-    move |input: Tokens| {
-        let (remaining_tokens, first_token) = take(1usize)(input)?;
-        if first_token.tok[0] == token {
+fn tag_token(token: TokenType) -> impl Fn(Tokens) -> ParseResult<Tokens> {
+    move |input: Tokens| match take::<_, _, ParserError>(1usize)(input) {
+        Ok((remaining_tokens, first_token)) if first_token.tok[0] == token => {
             Ok((remaining_tokens, first_token))
-        } else {
-            Err(nom::Err::Error(nom::error::Error {
-                input,
-                code: nom::error::ErrorKind::Tag,
-            }))
         }
+        _ => Err(Err::Error(ParserError::expected(input, token.describe()))),
     }
 }
 
-fn parse_let_statement(input: Tokens) -> IResult<Tokens, Statement> {
+fn parse_let_statement(input: Tokens) -> ParseResult<Statement> {
     map(
         tuple((
             opt(many0(tag_token(TokenType::EndOfStatement))),
@@ -146,7 +401,40 @@ fn parse_let_statement(input: Tokens) -> IResult<Tokens, Statement> {
     )(input)
 }
 
-fn parse_while_statement(input: Tokens) -> IResult<Tokens, Statement> {
+fn parse_local_let_statement(input: Tokens) -> ParseResult<Statement> {
+    map(
+        tuple((
+            tag_token(TokenType::Local),
+            parse_ident,
+            assign_tag,
+            parse_expr,
+        )),
+        |(_, ident, _, expr)| Statement::LocalLet {
+            name: ident,
+            value: expr,
+        },
+    )(input)
+}
+
+fn parse_index_let_statement(input: Tokens) -> ParseResult<Statement> {
+    let (i1, target) = parse_expr(input)?;
+    let (i2, _) = assign_tag(i1)?;
+    let (i3, value) = parse_expr(i2)?;
+
+    match target.into_unspanned() {
+        Expression::Index { left, index } => Ok((
+            i3,
+            Statement::IndexLet {
+                left: *left,
+                index: *index,
+                value,
+            },
+        )),
+        _ => Err(Err::Error(error_position!(input, ErrorKind::Tag))),
+    }
+}
+
+fn parse_while_statement(input: Tokens) -> ParseResult<Statement> {
     map(
         tuple((
             tag_token(TokenType::While),
@@ -154,38 +442,94 @@ fn parse_while_statement(input: Tokens) -> IResult<Tokens, Statement> {
             parse_expr,
             opt(many0(tag_token(TokenType::EndOfStatement))),
             tag_token(TokenType::Do),
-            opt(many0(tag_token(TokenType::EndOfStatement))),
-            many0(parse_statement),
-            opt(many0(tag_token(TokenType::EndOfStatement))),
+            parse_block(&[TokenType::EndWhile]),
             tag_token(TokenType::EndWhile),
         )),
-        |(_, _, condition, _, _, _, body, _, _)| Statement::While {
+        |(_, _, condition, _, _, body, _)| Statement::While {
             condition: Box::new(condition),
             body,
         },
     )(input)
 }
 
-fn parse_print_statement(input: Tokens) -> IResult<Tokens, Statement> {
+fn parse_for_statement(input: Tokens) -> ParseResult<Statement> {
+    map(
+        tuple((
+            tag_token(TokenType::For),
+            parse_ident,
+            tag_token(TokenType::Assign),
+            parse_expr,
+            tag_token(TokenType::From),
+            parse_expr,
+            tag_token(TokenType::To),
+            opt(preceded(tag_token(TokenType::Step), parse_expr)),
+            parse_block(&[TokenType::EndWhile]),
+            tag_token(TokenType::EndWhile),
+        )),
+        |(_, variable, _, start, _, end, _, step, body, _)| Statement::For {
+            variable,
+            start,
+            end,
+            step,
+            body,
+        },
+    )(input)
+}
+
+fn parse_loop_control_statement(input: Tokens) -> ParseResult<Statement> {
+    alt((
+        map(tag_token(TokenType::Break), |_| Statement::Break),
+        map(tag_token(TokenType::Continue), |_| Statement::Continue),
+    ))(input)
+}
+
+fn parse_print_statement(input: Tokens) -> ParseResult<Statement> {
     map(preceded(tag_token(TokenType::Print), parse_expr), |expr| {
         Statement::Print(Box::new(expr))
     })(input)
 }
 
-fn parse_expr(input: Tokens) -> IResult<Tokens, Expression> {
+fn parse_function_statement(input: Tokens) -> ParseResult<Statement> {
+    map(
+        tuple((
+            tag_token(TokenType::FunctionStart),
+            parse_ident,
+            lparen_tag,
+            separated_list0(comma_tag, parse_ident),
+            rparen_tag,
+            parse_block(&[TokenType::FunctionEnd]),
+            tag_token(TokenType::FunctionEnd),
+        )),
+        |(_, name, _, parameters, _, body, _)| Statement::Function {
+            name,
+            parameters,
+            body,
+        },
+    )(input)
+}
+
+fn parse_return_statement(input: Tokens) -> ParseResult<Statement> {
+    map(
+        preceded(tag_token(TokenType::Return), opt(parse_expr)),
+        Statement::Return,
+    )(input)
+}
+
+fn parse_expr(input: Tokens) -> ParseResult<Expression> {
     parse_pratt_expr(input, Precedence::PLowest)
 }
 
-fn parse_pratt_expr(input: Tokens, precedence: Precedence) -> IResult<Tokens, Expression> {
+fn parse_pratt_expr(input: Tokens, precedence: Precedence) -> ParseResult<Expression> {
     let (i1, left) = parse_atom_expr(input)?;
-    go_parse_pratt_expr(i1, precedence, left)
+    go_parse_pratt_expr(input, i1, precedence, left)
 }
 
-fn go_parse_pratt_expr(
-    input: Tokens,
+fn go_parse_pratt_expr<'a>(
+    start: Tokens<'a>,
+    input: Tokens<'a>,
     precedence: Precedence,
     left: Expression,
-) -> IResult<Tokens, Expression> {
+) -> ParseResult<'a, Expression> {
     let (i1, t1) = take(1usize)(input)?;
 
     if t1.tok.is_empty() {
@@ -195,15 +539,19 @@ fn go_parse_pratt_expr(
         let p = infix_op(preview);
         match p {
             (ref peek_precedence, _) if precedence < *peek_precedence => {
-                let (i2, left2) = parse_infix_expr(input, left)?;
-                go_parse_pratt_expr(i2, precedence, left2)
+                let (i2, left2) = match preview {
+                    TokenType::LeftBracket => parse_index_expr(input, left)?,
+                    _ => parse_infix_expr(input, left)?,
+                };
+                let left2 = spanned_expr(start, i2, left2);
+                go_parse_pratt_expr(start, i2, precedence, left2)
             }
             _ => Ok((input, left)),
         }
     }
 }
 
-fn parse_infix_expr(input: Tokens, left: Expression) -> IResult<Tokens, Expression> {
+fn parse_infix_expr(input: Tokens, left: Expression) -> ParseResult<Expression> {
     let (i1, t1) = take(1usize)(input)?;
     if t1.tok.is_empty() {
         Err(Err::Error(error_position!(input, ErrorKind::Tag)))
@@ -227,38 +575,128 @@ fn parse_infix_expr(input: Tokens, left: Expression) -> IResult<Tokens, Expressi
     }
 }
 
-fn parse_atom_expr(input: Tokens) -> IResult<Tokens, Expression> {
-    let (input, expr) = alt((
-        parse_literal_expr,
-        parse_identifier_expr,
-        parse_prefix_expr,
-        parse_paren_expr,
-        parse_input_expr,
-    ))(input)?;
+fn parse_index_expr(input: Tokens, left: Expression) -> ParseResult<Expression> {
+    let (i1, _) = lbracket_tag(input)?;
+    let (i2, start) = opt(parse_expr)(i1)?;
+    let (i3, colon) = opt(colon_tag)(i2)?;
+
+    match (start, colon) {
+        (Some(index), None) => {
+            let (i4, _) = rbracket_tag(i3)?;
+            Ok((
+                i4,
+                Expression::Index {
+                    left: Box::new(left),
+                    index: Box::new(index),
+                },
+            ))
+        }
+        (start, Some(_)) => {
+            let (i4, end) = opt(parse_expr)(i3)?;
+            let (i5, _) = rbracket_tag(i4)?;
+            Ok((
+                i5,
+                Expression::Slice {
+                    left: Box::new(left),
+                    start: start.map(Box::new),
+                    end: end.map(Box::new),
+                },
+            ))
+        }
+        (None, None) => Err(Err::Error(error_position!(input, ErrorKind::Tag))),
+    }
+}
+
+fn parse_atom_expr(start: Tokens) -> ParseResult<Expression> {
+    let (input, expr) = expecting(
+        "an expression",
+        alt((
+            parse_literal_expr,
+            parse_call_expr,
+            parse_identifier_expr,
+            parse_prefix_expr,
+            parse_paren_expr,
+            parse_list_expr,
+            parse_map_expr,
+            parse_input_expr,
+        )),
+    )(start)?;
     let (remaining_input, _) = opt(many0(tag_token(TokenType::EndOfStatement)))(input)?;
 
-    Ok((remaining_input, expr))
+    Ok((remaining_input, spanned_expr(start, input, expr)))
 }
-fn parse_paren_expr(input: Tokens) -> IResult<Tokens, Expression> {
+fn parse_paren_expr(input: Tokens) -> ParseResult<Expression> {
     let (i1, _) = lparen_tag(input)?;
     let (i2, expr) = parse_expr(i1)?;
     let (i3, _) = rparen_tag(i2)?;
     Ok((i3, expr))
 }
-fn parse_literal_expr(input: Tokens) -> IResult<Tokens, Expression> {
+fn parse_list_expr(input: Tokens) -> ParseResult<Expression> {
+    map(
+        tuple((
+            lbracket_tag,
+            separated_list0(
+                comma_tag,
+                preceded(opt(many0(tag_token(TokenType::EndOfStatement))), parse_expr),
+            ),
+            opt(many0(tag_token(TokenType::EndOfStatement))),
+            rbracket_tag,
+        )),
+        |(_, elements, _, _)| Expression::ListExpr(elements),
+    )(input)
+}
+fn parse_map_expr(input: Tokens) -> ParseResult<Expression> {
+    map(
+        tuple((
+            lbrace_tag,
+            separated_list0(
+                comma_tag,
+                map(
+                    tuple((
+                        opt(many0(tag_token(TokenType::EndOfStatement))),
+                        parse_expr,
+                        colon_tag,
+                        parse_expr,
+                    )),
+                    |(_, key, _, value)| (key, value),
+                ),
+            ),
+            opt(many0(tag_token(TokenType::EndOfStatement))),
+            rbrace_tag,
+        )),
+        |(_, entries, _, _)| Expression::MapExpr(entries),
+    )(input)
+}
+fn parse_literal_expr(input: Tokens) -> ParseResult<Expression> {
     let (i1, lit) = parse_literal(input)?;
     let (_i2, next) = peek(take(1usize))(i1)?;
     if next.tok.is_empty() || !matches!(next.tok[0], TokenType::Identifier(_)) {
         Ok((i1, Expression::LiteralExpr(lit)))
     } else {
-        Err(Err::Error(Error::new(input, ErrorKind::Tag)))
+        // The mistake is the name after the value, not the value
+        Err(Err::Error(ParserError::expected(i1, "an operator")))
     }
 }
-fn parse_identifier_expr(input: Tokens) -> IResult<Tokens, Expression> {
+fn parse_call_expr(input: Tokens) -> ParseResult<Expression> {
+    map(
+        tuple((
+            parse_ident,
+            lparen_tag,
+            separated_list0(comma_tag, parse_expr),
+            rparen_tag,
+        )),
+        |(function, _, arguments, _)| Expression::Call {
+            function,
+            arguments,
+        },
+    )(input)
+}
+
+fn parse_identifier_expr(input: Tokens) -> ParseResult<Expression> {
     map(parse_ident, Expression::IdentifierExpr)(input)
 }
 
-fn parse_prefix_expr(input: Tokens) -> IResult<Tokens, Expression> {
+fn parse_prefix_expr(input: Tokens) -> ParseResult<Expression> {
     map(
         tuple((parse_prefix_operator, parse_expr)),
         |(operator, right)| Expression::PrefixExpr {
@@ -268,11 +706,24 @@ fn parse_prefix_expr(input: Tokens) -> IResult<Tokens, Expression> {
     )(input)
 }
 
-fn parse_input_expr(input: Tokens) -> IResult<Tokens, Expression> {
-    map(tag_token(TokenType::Input), |_| Expression::Input)(input)
+fn parse_input_expr(input: Tokens) -> ParseResult<Expression> {
+    alt((
+        value(
+            Expression::Input(InputKind::Number),
+            tag_token(TokenType::Input),
+        ),
+        value(
+            Expression::Input(InputKind::Word),
+            tag_token(TokenType::InputWord),
+        ),
+        value(
+            Expression::Input(InputKind::Line),
+            tag_token(TokenType::InputLine),
+        ),
+    ))(input)
 }
 
-fn parse_if_statement(input: Tokens) -> IResult<Tokens, Statement> {
+fn parse_if_statement(input: Tokens) -> ParseResult<Statement> {
     map(
         tuple((
             if_tag,
@@ -280,61 +731,59 @@ fn parse_if_statement(input: Tokens) -> IResult<Tokens, Statement> {
             parse_expr,
             opt(many0(tag_token(TokenType::EndOfStatement))),
             then_tag,
-            opt(many0(tag_token(TokenType::EndOfStatement))),
-            many0(parse_statement),
-            opt(many0(tag_token(TokenType::EndOfStatement))),
-            opt(parse_else_elif),
-            opt(many0(tag_token(TokenType::EndOfStatement))),
-            opt(parse_else),
+            parse_block(&[TokenType::ElseIf, TokenType::Else, TokenType::EndIf]),
+            parse_alternative,
             tag_token(TokenType::EndIf),
         )),
-        |(_, _, condition, _, _, _, consequence, _, elif, _, else_, _)| Statement::If {
+        |(_, _, condition, _, _, consequence, alternative, _)| Statement::If {
             condition: Box::new(condition),
             consequence,
-            alternative: else_.or(elif),
+            alternative,
         },
     )(input)
 }
-fn parse_else_elif(input: Tokens) -> IResult<Tokens, Vec<Statement>> {
+
+/// Whatever follows the body of an 'AGAR' or 'NHI TOH AGAR': another
+/// condition, a last 'NHI TOH', or nothing at all
+fn parse_alternative(input: Tokens) -> ParseResult<Option<Vec<Statement>>> {
+    match input.tok.first() {
+        Some(TokenType::ElseIf) => map(parse_else_elif, Some)(input),
+        Some(TokenType::Else) => map(parse_else, Some)(input),
+        _ => Ok((input, None)),
+    }
+}
+
+fn parse_else_elif(input: Tokens) -> ParseResult<Vec<Statement>> {
     map(
         tuple((
             elseif_tag,
             opt(many0(tag_token(TokenType::EndOfStatement))),
             parse_expr,
             opt(many0(tag_token(TokenType::EndOfStatement))),
-            tag_token(TokenType::Then),
-            opt(many0(tag_token(TokenType::EndOfStatement))),
-            many0(parse_statement),
-            opt(many0(tag_token(TokenType::EndOfStatement))),
-            opt(parse_else_elif),
-            opt(many0(tag_token(TokenType::EndOfStatement))),
-            opt(parse_else),
+            then_tag,
+            parse_block(&[TokenType::ElseIf, TokenType::Else, TokenType::EndIf]),
+            parse_alternative,
         )),
-        |(_, _, condition, _, _, _, consequence, _, elif, _, else_)| {
+        |(_, _, condition, _, _, consequence, alternative)| {
             vec![Statement::If {
                 condition: Box::new(condition),
                 consequence,
-                alternative: elif.or(else_),
+                alternative,
             }]
         },
     )(input)
 }
 
-fn parse_else(input: Tokens) -> IResult<Tokens, Vec<Statement>> {
-    map(preceded(else_tag, many0(parse_statement)), |statements| {
-        statements
-    })(input)
+fn parse_else(input: Tokens) -> ParseResult<Vec<Statement>> {
+    preceded(else_tag, parse_block(&[TokenType::EndIf]))(input)
 }
 
-fn parse_prefix_operator(input: Tokens) -> IResult<Tokens, Prefix> {
+fn parse_prefix_operator(input: Tokens) -> ParseResult<Prefix> {
     let (remaining_tokens, token) = take(1usize)(input)?;
     match token.tok[0] {
         TokenType::Plus => Ok((remaining_tokens, Prefix::PrefixPlus)),
         TokenType::Minus => Ok((remaining_tokens, Prefix::PrefixMinus)),
         TokenType::Not => Ok((remaining_tokens, Prefix::Not)),
-        _ => Err(nom::Err::Error(nom::error::Error {
-            input,
-            code: nom::error::ErrorKind::Tag,
-        })),
+        _ => Err(Err::Error(error_position!(input, ErrorKind::Tag))),
     }
 }
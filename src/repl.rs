@@ -0,0 +1,186 @@
+use crate::error::ZenError;
+use crate::evaluator::interpreter::Interpreter;
+use crate::lexer::lexer_util::Lexer;
+use crate::lexer::token_type::TokenType;
+use crate::lexer::tokens::Tokens;
+use crate::parser::parser_util::Parser;
+
+const HELP: &str = "Type Zen statements to run them right away. A block keeps reading lines until it is closed with 'BAS ITNA HI', 'JAHAN' or 'PHIR MILENGE'.
+Commands:
+  :vars     show the global variables
+  :reset    forget every variable and function
+  :ast      show or hide the AST of what you type
+  :tokens   show or hide the tokens of what you type
+  :help     show this help
+  :quit     leave, 'Picture abhi baaki hai mere dost'";
+
+/// An interactive session, fed one line at a time.
+///
+/// Statements run as soon as they are complete, all on the same interpreter,
+/// so variables and functions stay around for the lines that follow. Lines
+/// starting with `:` are commands for the session itself (see `:help`).
+pub struct Repl {
+    interpreter: Interpreter,
+    is_on_console: bool,
+    /// Lines of a block that is not closed yet
+    pending: String,
+    show_ast: bool,
+    show_tokens: bool,
+}
+
+/// What became of a line fed to the REPL
+#[derive(Debug)]
+pub enum Step {
+    /// A block is still open, so the statement goes on in the next line
+    More,
+    /// What the statements printed, or what a command had to say
+    Output(String),
+    /// The statements did not lex, parse or run. Spans in the error point
+    /// into `source`, the statements as typed.
+    Error {
+        error: ZenError,
+        source: String,
+    },
+    Quit,
+}
+
+impl Repl {
+    /// With `is_on_console`, programs print and read input straight on the
+    /// console, as with `Interpreter::new`. Otherwise whatever they print
+    /// comes back in `Step::Output`.
+    pub fn new(is_on_console: bool) -> Self {
+        Self {
+            interpreter: Interpreter::new("", is_on_console),
+            is_on_console,
+            pending: String::new(),
+            show_ast: false,
+            show_tokens: false,
+        }
+    }
+
+    /// Whether the lines fed so far leave a block open
+    pub fn is_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// Forgets the lines of a block that is not closed yet
+    pub fn clear_pending(&mut self) {
+        self.pending.clear();
+    }
+
+    pub fn feed(&mut self, line: &str) -> Step {
+        if !self.is_pending() {
+            if let Some(command) = line.trim().strip_prefix(':') {
+                return self.command(command.trim());
+            }
+            if line.trim().is_empty() {
+                return Step::Output(String::new());
+            }
+        }
+
+        self.pending.push_str(line);
+        self.pending.push('\n');
+        let source = self.pending.clone();
+
+        let (tokens, spans) = match Lexer::lex_program(source.as_bytes()) {
+            Ok(lexed) => lexed,
+            Err(err) => return self.fail(ZenError::Lex(err), source),
+        };
+        if open_blocks(&tokens) > 0 {
+            return Step::More;
+        }
+        self.pending.clear();
+
+        let mut output = String::new();
+        if self.show_tokens {
+            let shown: Vec<&TokenType> = tokens
+                .iter()
+                .filter(|token| **token != TokenType::Eof)
+                .collect();
+            self.show(&mut output, format!("Here are your tokens:\n {:?}", shown));
+        }
+        let statements = match Parser::parse_statements(Tokens::new_with_spans(&tokens, &spans)) {
+            Ok(statements) => statements,
+            Err(errors) => return self.fail(ZenError::Parse(errors), source),
+        };
+        if self.show_ast {
+            self.show(
+                &mut output,
+                format!("Here is your AST:\n {:#?}", statements),
+            );
+        }
+
+        match self.interpreter.run_statements(&statements) {
+            Ok(printed) => Step::Output(output + &printed),
+            Err(err) => self.fail(ZenError::Runtime(err), source),
+        }
+    }
+
+    fn command(&mut self, command: &str) -> Step {
+        let reply = match command {
+            "vars" => {
+                let globals = self.interpreter.globals();
+                if globals.is_empty() {
+                    "No variables yet. 'Khali haath aaye the, khali haath jaoge.'".to_string()
+                } else {
+                    let lines: Vec<String> = globals
+                        .iter()
+                        .map(|(name, value)| format!("{} = {}", name, value))
+                        .collect();
+                    lines.join("\n")
+                }
+            }
+            "reset" => {
+                self.interpreter = Interpreter::new("", self.is_on_console);
+                "Everything is forgotten. 'Main kaun hoon? Main kahan hoon?'".to_string()
+            }
+            "ast" => {
+                self.show_ast = !self.show_ast;
+                format!("AST display {}", on_off(self.show_ast))
+            }
+            "tokens" => {
+                self.show_tokens = !self.show_tokens;
+                format!("Token display {}", on_off(self.show_tokens))
+            }
+            "help" => HELP.to_string(),
+            "quit" | "exit" => return Step::Quit,
+            other => format!(
+                "Unknown command ':{}'. Type :help to see the commands.",
+                other
+            ),
+        };
+        Step::Output(reply + "\n")
+    }
+
+    /// Shows `text` before the statements run: on the console right away,
+    /// since the statements print straight to it, or else in `output`
+    fn show(&self, output: &mut String, text: String) {
+        if self.is_on_console {
+            println!("{}", text);
+        } else {
+            output.push_str(&text);
+            output.push('\n');
+        }
+    }
+
+    fn fail(&mut self, error: ZenError, source: String) -> Step {
+        self.pending.clear();
+        Step::Error { error, source }
+    }
+}
+
+/// How many blocks the tokens open without closing them again
+fn open_blocks(tokens: &[TokenType]) -> i32 {
+    tokens
+        .iter()
+        .map(|token| match token {
+            TokenType::If | TokenType::While | TokenType::For | TokenType::FunctionStart => 1,
+            TokenType::EndIf | TokenType::EndWhile | TokenType::FunctionEnd => -1,
+            _ => 0,
+        })
+        .sum()
+}
+
+fn on_off(on: bool) -> &'static str {
+    if on { "on" } else { "off" }
+}
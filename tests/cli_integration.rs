@@ -0,0 +1,151 @@
+use std::process::{Command, Output};
+use std::{env, fs, process};
+
+/// Writes `code` to a file and runs the zen binary on it with `args`
+fn zen(name: &str, code: &str, args: &[&str]) -> Output {
+    let dir = env::temp_dir().join(format!("zen-cli-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(format!("{}.zen", name));
+    fs::write(&path, code).unwrap();
+
+    Command::new(env!("CARGO_BIN_EXE_zen"))
+        .args(args)
+        .arg(&path)
+        .output()
+        .unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).to_string()
+}
+
+const GINTI: &str = "PARAMPARA PRATISHTA ANUSHASHAN
+N BOLE TOH INPUT LE LE RE BABA
+EK DO TEEN I BOLE TOH 1 SE N TAK
+    PRINT BASANTI PRINT I
+JAHAN
+KHATAM TATA BYE BYE";
+
+#[test]
+fn test_cli_runs_programs() {
+    let output = zen("ginti", GINTI, &["run", "--input", "3"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "1\n2\n3\n");
+
+    let output = zen(
+        "ginti_limit",
+        GINTI,
+        &["run", "--input", "3", "--max-loops", "2"],
+    );
+    assert_eq!(output.status.code(), Some(5));
+    assert_eq!(stdout(&output), "1\n2\n");
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("error[MaxLoopsExceeded]"));
+}
+
+#[test]
+fn test_cli_global_no_color() {
+    let code = "PARAMPARA PRATISHTA ANUSHASHAN\nPRINT BASANTI PRINT 1\nKHATAM TATA BYE BYE";
+    // --no-color goes anywhere, before or after the command
+    for args in [
+        &["--no-color", "run"][..],
+        &["run", "--no-color"],
+        &["--no-color", "check"],
+        &["--no-color"],
+    ] {
+        let output = zen("no_color", code, args);
+        assert_eq!(output.status.code(), Some(0), "{:?}", args);
+    }
+
+    // A file of its own cannot come with a command
+    let output = zen("no_color", code, &["ginti.zen", "check"]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn test_cli_exit_codes() {
+    let output = zen(
+        "lex",
+        "PARAMPARA PRATISHTA ANUSHASHAN\nA BOLE TOH $\n",
+        &["--no-color"],
+    );
+    assert_eq!(output.status.code(), Some(3));
+
+    let output = zen(
+        "parse",
+        "PARAMPARA PRATISHTA ANUSHASHAN\nA BOLE TOH (1\nKHATAM TATA BYE BYE",
+        &["check"],
+    );
+    assert_eq!(output.status.code(), Some(4));
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("error[SyntaxError]"));
+
+    let output = zen(
+        "runtime",
+        "PARAMPARA PRATISHTA ANUSHASHAN\nPRINT BASANTI PRINT 1 / 0\nKHATAM TATA BYE BYE",
+        &["run"],
+    );
+    assert_eq!(output.status.code(), Some(5));
+
+    let output = Command::new(env!("CARGO_BIN_EXE_zen"))
+        .args(["run", "nahi-hai.zen"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn test_cli_inspects_and_compiles() {
+    let code = "PARAMPARA PRATISHTA ANUSHASHAN\nPRINT BASANTI PRINT 1\nKHATAM TATA BYE BYE";
+
+    let output = zen("check", code, &["check"]);
+    assert_eq!(output.status.code(), Some(0));
+
+    let output = zen("tokens", code, &["tokens"]);
+    assert!(stdout(&output).starts_with("1:1\tStartProgram\n2:1\tPrint\n2:21\tNumber(1.0)\n"));
+
+    let output = zen("ast", code, &["ast"]);
+    assert!(stdout(&output).starts_with("Program {"));
+
+    let output = zen("c", code, &["compile"]);
+    assert!(stdout(&output).contains("int main(void)"));
+
+    let output = zen("wat", code, &["compile", "--format", "wat"]);
+    assert!(stdout(&output).contains("(module"));
+}
+
+#[test]
+fn test_cli_formats_programs() {
+    let code = "PARAMPARA PRATISHTA ANUSHASHAN\nA  BOLE TOH 1+2\nKHATAM TATA BYE BYE";
+    let formatted = "PARAMPARA PRATISHTA ANUSHASHAN\nA BOLE TOH 1 + 2\nKHATAM TATA BYE BYE\n";
+
+    let output = zen("fmt", code, &["fmt"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), formatted);
+
+    let output = zen("fmt_check", code, &["fmt", "--check"]);
+    assert_eq!(output.status.code(), Some(6));
+
+    let output = zen("fmt_done", formatted, &["fmt", "--check"]);
+    assert_eq!(output.status.code(), Some(0));
+}
+
+#[test]
+fn test_cli_highlights_programs() {
+    let code = "PARAMPARA PRATISHTA ANUSHASHAN\nPRINT BASANTI PRINT 1 @ ek\nKHATAM TATA BYE BYE";
+
+    let output = zen("highlight", code, &["highlight"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(stdout(&output).starts_with(
+        "\x1b[1;35mPARAMPARA PRATISHTA ANUSHASHAN\x1b[0m\n\x1b[1;35mPRINT BASANTI PRINT\x1b[0m \x1b[33m1\x1b[0m \x1b[90m@ ek\x1b[0m\n"
+    ));
+
+    let output = zen("highlight_json", code, &["highlight", "--format", "json"]);
+    assert!(
+        stdout(&output).starts_with(
+            "[{\"class\":\"keyword\",\"column\":1,\"end\":30,\"line\":1,\"start\":0},"
+        )
+    );
+    assert!(
+        stdout(&output)
+            .contains("{\"class\":\"comment\",\"column\":23,\"end\":57,\"line\":2,\"start\":53}")
+    );
+}
@@ -0,0 +1,283 @@
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+use std::{env, fs, process};
+use zen::error::ZenError;
+use zen::{compile, run_program};
+
+/// Compiles `code` to C, builds it with the system C compiler (`$CC`, or
+/// `cc`) and runs it with `stdin` as its input
+fn run_compiled(name: &str, code: &str, stdin: &str) -> Output {
+    let c_source = compile(code.to_string()).unwrap();
+
+    let dir = env::temp_dir().join(format!("zen-compiler-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let source_path = dir.join(format!("{}.c", name));
+    let binary_path: PathBuf = dir.join(name);
+    fs::write(&source_path, c_source).unwrap();
+
+    let compiler = env::var("CC").unwrap_or_else(|_| "cc".to_string());
+    let build = Command::new(compiler)
+        .args(["-std=c99", "-Wall", "-Wextra", "-o"])
+        .arg(&binary_path)
+        .arg(&source_path)
+        .arg("-lm")
+        .output()
+        .expect("a C compiler is needed to test compiled programs");
+    assert!(
+        build.status.success(),
+        "{}",
+        String::from_utf8_lossy(&build.stderr)
+    );
+    assert!(
+        build.stderr.is_empty(),
+        "warnings building {}:\n{}",
+        name,
+        String::from_utf8_lossy(&build.stderr)
+    );
+
+    let mut child = Command::new(&binary_path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn assert_same_output(name: &str, code: &str) {
+    let expected = run_program(code.to_string(), "", false).unwrap();
+    let output = run_compiled(name, code, "");
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(String::from_utf8_lossy(&output.stdout), expected);
+}
+
+/// The compiled program must fail with the interpreter's error, reported on
+/// stderr after whatever it printed before failing
+fn assert_same_error(name: &str, code: &str, printed: &str) {
+    let Err(ZenError::Runtime(err)) = run_program(code.to_string(), "", false) else {
+        panic!("expected a runtime error");
+    };
+    let mut expected = format!("error[{}]: {}\n", err.error_type, err);
+    if let Some(span) = err.span {
+        expected += &format!(" --> {}:{}\n", span.line, span.column);
+    }
+
+    let output = run_compiled(name, code, "");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), printed);
+    assert_eq!(String::from_utf8_lossy(&output.stderr), expected);
+}
+
+#[test]
+fn test_compiled_numbers_and_strings() {
+    let input = r#"PARAMPARA PRATISHTA ANUSHASHAN
+PRINT BASANTI PRINT 0.1 + 0.2
+PRINT BASANTI PRINT 1 / 3
+PRINT BASANTI PRINT 10 / 4 * 2
+PRINT BASANTI PRINT 7 % 3 - 2.5
+PRINT BASANTI PRINT -0
+PRINT BASANTI PRINT 100000000000000000000000 * 10
+PRINT BASANTI PRINT 1 / 1000000000
+PRINT BASANTI PRINT "Mogambo" + " khush hua " + 2 + true
+PRINT BASANTI PRINT 1 < 2 && !(2 >= 3) || false
+PRINT BASANTI PRINT "abc" == "abc"
+PRINT BASANTI PRINT [1, 2] != [1, 2]
+KHATAM TATA BYE BYE"#;
+    assert_same_output("numbers_and_strings", input);
+}
+
+#[test]
+fn test_compiled_loops_and_functions() {
+    let input = "PARAMPARA PRATISHTA ANUSHASHAN
+AAO KABHI HAVELI PE fib(N)
+    AGAR N < 2 TAB
+        LAUT KE AAJA N
+    BAS ITNA HI
+    LAUT KE AAJA fib(N - 1) + fib(N - 2)
+PHIR MILENGE
+AAO KABHI HAVELI PE pehla_bada(LIST, LIMIT)
+    EK DO TEEN I BOLE TOH 0 SE LAMBAI(LIST) - 1 TAK
+        AGAR LIST[I] > LIMIT TAB
+            LAUT KE AAJA I
+        BAS ITNA HI
+    JAHAN
+    LAUT KE AAJA -1
+PHIR MILENGE
+PRINT BASANTI PRINT fib(15)
+PRINT BASANTI PRINT pehla_bada([3, 8, 1, 9], 5)
+K BOLE TOH 0
+JAB TAK HAI JAAN K < 10 TAB TAK
+    K BOLE TOH K + 1
+    AGAR K % 2 == 0 TAB
+        JAANE BHI DO
+    WARNA AGAR K > 7 TAB
+        BAS KAR PAGLE
+    BAS ITNA HI
+    PRINT BASANTI PRINT K
+JAHAN
+EK DO TEEN X BOLE TOH 1 SE 0 TAK KADAM -0.25
+    PRINT BASANTI PRINT X
+JAHAN
+KHATAM TATA BYE BYE";
+    assert_same_output("loops_and_functions", input);
+}
+
+#[test]
+fn test_compiled_scopes() {
+    let input = "PARAMPARA PRATISHTA ANUSHASHAN
+A BOLE TOH 1
+AAO KABHI HAVELI PE badlo()
+    PRINT BASANTI PRINT A
+    A BOLE TOH 2
+    LAUT KE AAJA A
+PHIR MILENGE
+PRINT BASANTI PRINT badlo()
+PRINT BASANTI PRINT A
+AGAR true TAB
+    APNA A BOLE TOH 3
+    PRINT BASANTI PRINT A
+BAS ITNA HI
+PRINT BASANTI PRINT A
+KHATAM TATA BYE BYE";
+    assert_same_output("scopes", input);
+}
+
+#[test]
+fn test_compiled_collections() {
+    let input = r#"PARAMPARA PRATISHTA ANUSHASHAN
+L BOLE TOH [1, "do", [3]]
+JODO(L, 4)
+L[0] BOLE TOH 10
+PRINT BASANTI PRINT L
+PRINT BASANTI PRINT L[1:]
+PRINT BASANTI PRINT L[:1]
+PRINT BASANTI PRINT HATAO(L, 2)
+M BOLE TOH {"b": 2, 1: "ek", "a": [1]}
+M["c"] BOLE TOH "ch\"a"
+PRINT BASANTI PRINT M
+PRINT BASANTI PRINT CHABIYAN(M)
+PRINT BASANTI PRINT HAI_KYA(M, "z")
+PRINT BASANTI PRINT HATAO(M, 1)
+PRINT BASANTI PRINT LAMBAI(M)
+S BOLE TOH "Basanti"
+PRINT BASANTI PRINT S[2] + S[1:4] + S[10:]
+PRINT BASANTI PRINT BADA(S) + CHHOTA("NACHO")
+PRINT BASANTI PRINT KHOJO(S, "nti")
+PRINT BASANTI PRINT ANK(" 4.5 ") * 2
+PRINT BASANTI PRINT AKSHAR(L) + "!"
+KHATAM TATA BYE BYE"#;
+    assert_same_output("collections", input);
+}
+
+#[test]
+fn test_compiled_unicode_case() {
+    // Letters that grow, titlecase letters, and 'Σ' that lowers differently
+    // at the end of a word
+    let input = r#"PARAMPARA PRATISHTA ANUSHASHAN
+PRINT BASANTI PRINT BADA("héllo straße ﬁ ŉ ǅ ÿ 𐐨")
+PRINT BASANTI PRINT CHHOTA("ΟΔΟΣ ΟΔΟΣ. Σ İ ǅ ΑΣ'Σ ÀÉÎ")
+KHATAM TATA BYE BYE"#;
+    assert_same_output("unicode_case", input);
+}
+
+#[test]
+fn test_compiled_input() {
+    let input = "PARAMPARA PRATISHTA ANUSHASHAN
+A BOLE TOH INPUT LE LE RE BABA
+B BOLE TOH INPUT LE LE RE BABA
+PRINT BASANTI PRINT A * B
+KHATAM TATA BYE BYE";
+    let output = run_compiled("input", input, "6\n 7 \n");
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "42\n");
+
+    let output = run_compiled("bad_input", input, "chhe\n");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("error[InvalidInputError]"));
+
+    let input = "PARAMPARA PRATISHTA ANUSHASHAN
+N BOLE TOH INPUT LE LE RE BABA
+NAAM BOLE TOH SHABD LE LE RE BABA
+BAAKI BOLE TOH PURI LINE LE LE RE BABA
+AGLI BOLE TOH PURI LINE LE LE RE BABA
+PRINT BASANTI PRINT [N, NAAM, BAAKI, AGLI]
+KHATAM TATA BYE BYE";
+    let stdin = "21 Gabbar Singh  Thakur\n  Ramgarh \n";
+    let expected = run_program(input.to_string(), stdin, false).unwrap();
+    let output = run_compiled("word_input", input, stdin);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), expected);
+
+    let output = run_compiled("no_input", input, "7 Jai\n");
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("error[EmptyCustomInputStack]"));
+}
+
+#[test]
+fn test_compiled_runtime_errors() {
+    let input = "PARAMPARA PRATISHTA ANUSHASHAN
+A BOLE TOH 5
+AAO KABHI HAVELI PE bhaag(X)
+    LAUT KE AAJA X / (A - 5)
+PHIR MILENGE
+PRINT BASANTI PRINT 1
+PRINT BASANTI PRINT 1 + bhaag(2)
+KHATAM TATA BYE BYE";
+    assert_same_error("division_by_zero", input, "1\n");
+
+    let input = "PARAMPARA PRATISHTA ANUSHASHAN
+AAO KABHI HAVELI PE kuch_nahi()
+    PRINT BASANTI PRINT 0
+PHIR MILENGE
+X BOLE TOH kuch_nahi()
+KHATAM TATA BYE BYE";
+    assert_same_error("missing_return_value", input, "0\n");
+
+    let input = "PARAMPARA PRATISHTA ANUSHASHAN
+AAO KABHI HAVELI PE hamesha(N)
+    LAUT KE AAJA hamesha(N + 1)
+PHIR MILENGE
+hamesha(0)
+KHATAM TATA BYE BYE";
+    assert_same_error("call_depth", input, "");
+
+    let input = "PARAMPARA PRATISHTA ANUSHASHAN
+AGAR true TAB
+    APNA Y BOLE TOH 3
+BAS ITNA HI
+PRINT BASANTI PRINT [1, 2][Y]
+KHATAM TATA BYE BYE";
+    assert_same_error("out_of_scope", input, "");
+
+    let input = "PARAMPARA PRATISHTA ANUSHASHAN
+AAO KABHI HAVELI PE ruko()
+    BAS KAR PAGLE
+PHIR MILENGE
+EK DO TEEN I BOLE TOH 1 SE 3 TAK
+    ruko()
+JAHAN
+KHATAM TATA BYE BYE";
+    assert_same_error("loop_control", input, "");
+
+    let input = "PARAMPARA PRATISHTA ANUSHASHAN
+LAUT KE AAJA 1
+KHATAM TATA BYE BYE";
+    assert_same_error("return_outside_function", input, "");
+}
+
+#[test]
+fn test_compile_reports_syntax_errors() {
+    let input = "PARAMPARA PRATISHTA ANUSHASHAN\nA BOLE TOH (1\nKHATAM TATA BYE BYE";
+    assert!(matches!(
+        compile(input.to_string()),
+        Err(ZenError::Parse(_))
+    ));
+}
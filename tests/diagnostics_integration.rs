@@ -0,0 +1,52 @@
+use zen::diagnostics::render_error;
+use zen::run_program;
+
+#[test]
+fn test_render_runtime_error() {
+    let input = "PARAMPARA PRATISHTA ANUSHASHAN
+A BOLE TOH 5
+AAO KABHI HAVELI PE bhaag(X)
+\tLAUT KE AAJA X / (A - 5)
+PHIR MILENGE
+PRINT BASANTI PRINT 1 + bhaag(2)
+KHATAM TATA BYE BYE";
+    let err = run_program(input.to_string(), "", false).unwrap_err();
+
+    let expected = "error[DivisionByZero]: Division by zero? 'Ye zero hai, ye divide karne ka sign hai, aur ye divide by zero ka darr.'
+ --> haveli.zen:4:15
+  |
+4 | \tLAUT KE AAJA X / (A - 5)
+  | \t             ^^^^^^^^^^^
+  |
+  = help: make sure the number you divide by can never be 0
+";
+    assert_eq!(render_error(&err, input, "haveli.zen", false), expected);
+}
+
+#[test]
+fn test_render_syntax_errors() {
+    let input = "PARAMPARA PRATISHTA ANUSHASHAN\nPRINT BASANTI PRINT 1\n";
+    let err = run_program(input.to_string(), "", false).unwrap_err();
+    let expected = "error[SyntaxError]: Mogambo dukhi hua! Expected 'KHATAM TATA BYE BYE' but found the end of the file
+ --> adhura.zen:3:1
+  |
+3 |
+  | ^
+  |
+  = help: every program has to end with 'KHATAM TATA BYE BYE'
+";
+    assert_eq!(render_error(&err, input, "adhura.zen", false), expected);
+
+    let input = "PARAMPARA PRATISHTA ANUSHASHAN\nPRINT BASANTI PRINT 10 # 3\nKHATAM TATA BYE BYE";
+    let err = run_program(input.to_string(), "", false).unwrap_err();
+    let rendered = render_error(&err, input, "hash.zen", false);
+    assert!(rendered.contains("2 | PRINT BASANTI PRINT 10 # 3\n  |                        ^\n"));
+    assert!(rendered.contains("help: remove this character"));
+
+    let input =
+        "PARAMPARA PRATISHTA ANUSHASHAN\nA BOLE TOH (1\nB BOLE TOH 2 *\nKHATAM TATA BYE BYE";
+    let err = run_program(input.to_string(), "", false).unwrap_err();
+    let rendered = render_error(&err, input, "do.zen", false);
+    assert_eq!(rendered.matches("error[SyntaxError]").count(), 2);
+    assert!(rendered.contains("\n\nerror[SyntaxError]"));
+}
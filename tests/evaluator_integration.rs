@@ -1,6 +1,16 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+use zen::error::ZenError;
 use zen::evaluator;
-use zen::evaluator::interpreter::InterpreterErrorType;
+use zen::evaluator::config::InterpreterConfig;
+use zen::evaluator::interpreter::{Interpreter, InterpreterErrorType};
+use zen::evaluator::io::{CallbackInput, CallbackOutput, MemoryInput, MemoryOutput};
 use zen::lexer::lexer_util::Lexer;
+use zen::lexer::token_type::TokenType;
 use zen::lexer::tokens::Tokens;
 use zen::parser::ast::Program;
 use zen::parser::parser_util::Parser;
@@ -221,3 +231,1169 @@ fn max_iteration_exceeded() {
         .run_code(assert_input_with_program(input))
         .is_err_and(|err| { err.error_type == InterpreterErrorType::MaxLoopsExceeded }));
 }
+
+#[test]
+fn test_evaluator_function_call() {
+    let input = "PARAMPARA PRATISHTA ANUSHASHAN
+        AAO KABHI HAVELI PE jodo(a, b)
+            LAUT KE AAJA a + b
+        PHIR MILENGE
+        PRINT BASANTI PRINT jodo(2, 3) * jodo(1, 1)
+        KHATAM TATA BYE BYE"
+        .as_bytes();
+    assert_eq!(
+        evaluator::interpreter::Interpreter::new("", false)
+            .run_code(assert_input_with_program(input))
+            .unwrap()
+            .trim_end(),
+        "10"
+    );
+}
+
+#[test]
+fn test_evaluator_recursive_function() {
+    let input = "PARAMPARA PRATISHTA ANUSHASHAN
+        AAO KABHI HAVELI PE factorial(n)
+            AGAR n <= 1 TAB
+                LAUT KE AAJA 1
+            BAS ITNA HI
+            LAUT KE AAJA n * factorial(n - 1)
+        PHIR MILENGE
+        PRINT BASANTI PRINT factorial(5)
+        KHATAM TATA BYE BYE"
+        .as_bytes();
+    assert_eq!(
+        evaluator::interpreter::Interpreter::new("", false)
+            .run_code(assert_input_with_program(input))
+            .unwrap()
+            .trim_end(),
+        "120"
+    );
+}
+
+#[test]
+fn test_evaluator_function_as_statement() {
+    let input = "PARAMPARA PRATISHTA ANUSHASHAN
+        AAO KABHI HAVELI PE bolo(n)
+            PRINT BASANTI PRINT n
+        PHIR MILENGE
+        A BOLE TOH 7
+        bolo(A)
+        bolo(A + 1)
+        PRINT BASANTI PRINT A
+        KHATAM TATA BYE BYE"
+        .as_bytes();
+    assert_eq!(
+        evaluator::interpreter::Interpreter::new("", false)
+            .run_code(assert_input_with_program(input))
+            .unwrap()
+            .trim_end(),
+        "7\n8\n7"
+    );
+}
+
+#[test]
+fn test_evaluator_function_errors() {
+    let cases = [
+        (
+            "PRINT BASANTI PRINT gayab(1)",
+            InterpreterErrorType::UndefinedFunction,
+        ),
+        (
+            "AAO KABHI HAVELI PE f(a)\nLAUT KE AAJA a\nPHIR MILENGE\nPRINT BASANTI PRINT f(1, 2)",
+            InterpreterErrorType::ArgumentCountMismatch,
+        ),
+        (
+            "AAO KABHI HAVELI PE f()\nLAUT KE AAJA\nPHIR MILENGE\nPRINT BASANTI PRINT f()",
+            InterpreterErrorType::MissingReturnValue,
+        ),
+        (
+            "LAUT KE AAJA 1",
+            InterpreterErrorType::ReturnOutsideFunction,
+        ),
+        (
+            "AAO KABHI HAVELI PE f(n)\nLAUT KE AAJA f(n + 1)\nPHIR MILENGE\nf(1)",
+            InterpreterErrorType::MaxCallDepthExceeded,
+        ),
+    ];
+
+    for (body, error_type) in cases {
+        let input = format!(
+            "PARAMPARA PRATISHTA ANUSHASHAN\n{}\nKHATAM TATA BYE BYE",
+            body
+        );
+        assert!(
+            evaluator::interpreter::Interpreter::new("", false)
+                .run_code(assert_input_with_program(input.as_bytes()))
+                .is_err_and(|err| err.error_type == error_type),
+            "{} should fail with {}",
+            body,
+            error_type
+        );
+    }
+}
+
+#[test]
+fn test_evaluator_block_variables_do_not_leak() {
+    let input = "PARAMPARA PRATISHTA ANUSHASHAN
+        A BOLE TOH 2
+        JAB TAK HAI JAAN A > 0 TAB TAK
+            B BOLE TOH A * 10
+            A BOLE TOH A - 1
+        JAHAN
+        PRINT BASANTI PRINT B
+        KHATAM TATA BYE BYE"
+        .as_bytes();
+    assert!(
+        evaluator::interpreter::Interpreter::new("", false)
+            .run_code(assert_input_with_program(input))
+            .is_err_and(|err| err.error_type == InterpreterErrorType::VariableOutOfScope)
+    );
+}
+
+#[test]
+fn test_evaluator_shadowing_with_local() {
+    let input = "PARAMPARA PRATISHTA ANUSHASHAN
+        A BOLE TOH 1
+        AGAR A == 1 TAB
+            APNA A BOLE TOH 100
+            PRINT BASANTI PRINT A
+        BAS ITNA HI
+        PRINT BASANTI PRINT A
+        KHATAM TATA BYE BYE"
+        .as_bytes();
+    assert_eq!(
+        evaluator::interpreter::Interpreter::new("", false)
+            .run_code(assert_input_with_program(input))
+            .unwrap()
+            .trim_end(),
+        "100\n1"
+    );
+}
+
+#[test]
+fn test_evaluator_nested_loops_keep_their_own_variables() {
+    let input = "PARAMPARA PRATISHTA ANUSHASHAN
+        I BOLE TOH 0
+        JAB TAK HAI JAAN I < 2 TAB TAK
+            J BOLE TOH 0
+            JAB TAK HAI JAAN J < 2 TAB TAK
+                PRINT BASANTI PRINT I * 10 + J
+                J BOLE TOH J + 1
+            JAHAN
+            I BOLE TOH I + 1
+        JAHAN
+        KHATAM TATA BYE BYE"
+        .as_bytes();
+    assert_eq!(
+        evaluator::interpreter::Interpreter::new("", false)
+            .run_code(assert_input_with_program(input))
+            .unwrap()
+            .trim_end(),
+        "0\n1\n10\n11"
+    );
+}
+
+#[test]
+fn test_evaluator_function_scope() {
+    let input = "PARAMPARA PRATISHTA ANUSHASHAN
+        AAO KABHI HAVELI PE badlo(n)
+            I BOLE TOH n * 2
+            LAUT KE AAJA I + OFFSET
+        PHIR MILENGE
+        OFFSET BOLE TOH 1
+        I BOLE TOH 5
+        PRINT BASANTI PRINT badlo(I)
+        PRINT BASANTI PRINT I
+        KHATAM TATA BYE BYE"
+        .as_bytes();
+    assert_eq!(
+        evaluator::interpreter::Interpreter::new("", false)
+            .run_code(assert_input_with_program(input))
+            .unwrap()
+            .trim_end(),
+        "11\n5"
+    );
+}
+
+#[test]
+fn test_evaluator_string_and_bool_variables() {
+    let input = "PARAMPARA PRATISHTA ANUSHASHAN
+        NAAM BOLE TOH \"Basanti\"
+        MILA BOLE TOH false
+        I BOLE TOH 3
+        JAB TAK HAI JAAN I > 0 TAB TAK
+            AGAR I == 2 TAB
+                MILA BOLE TOH true
+            BAS ITNA HI
+            I BOLE TOH I - 1
+        JAHAN
+        PRINT BASANTI PRINT NAAM
+        PRINT BASANTI PRINT MILA
+        PRINT BASANTI PRINT NAAM == \"Basanti\" && !MILA
+        KHATAM TATA BYE BYE"
+        .as_bytes();
+    assert_eq!(
+        evaluator::interpreter::Interpreter::new("", false)
+            .run_code(assert_input_with_program(input))
+            .unwrap()
+            .trim_end(),
+        "Basanti\ntrue\nfalse"
+    );
+}
+
+#[test]
+fn test_evaluator_variable_changes_type() {
+    let input = "PARAMPARA PRATISHTA ANUSHASHAN
+        A BOLE TOH 10
+        A BOLE TOH \"das\"
+        PRINT BASANTI PRINT A
+        PRINT BASANTI PRINT A * 2
+        KHATAM TATA BYE BYE"
+        .as_bytes();
+    assert!(
+        evaluator::interpreter::Interpreter::new("", false)
+            .run_code(assert_input_with_program(input))
+            .is_err_and(|err| err.error_type == InterpreterErrorType::IncompatibleDataType)
+    );
+}
+
+#[test]
+fn test_evaluator_function_with_string_argument() {
+    let input = "PARAMPARA PRATISHTA ANUSHASHAN
+        AAO KABHI HAVELI PE naam_batao(naam, sahi)
+            AGAR sahi TAB
+                LAUT KE AAJA naam
+            BAS ITNA HI
+            LAUT KE AAJA \"Gabbar\"
+        PHIR MILENGE
+        PRINT BASANTI PRINT naam_batao(\"Veeru\", true)
+        PRINT BASANTI PRINT naam_batao(\"Veeru\", false)
+        KHATAM TATA BYE BYE"
+        .as_bytes();
+    assert_eq!(
+        evaluator::interpreter::Interpreter::new("", false)
+            .run_code(assert_input_with_program(input))
+            .unwrap()
+            .trim_end(),
+        "Veeru\nGabbar"
+    );
+}
+
+#[test]
+fn test_evaluator_string_operations() {
+    let input = "PARAMPARA PRATISHTA ANUSHASHAN
+        NAAM BOLE TOH \"Basanti\"
+        PRINT BASANTI PRINT \"Naam: \" + NAAM
+        PRINT BASANTI PRINT \"Score: \" + 42 + \", pass: \" + true
+        PRINT BASANTI PRINT LAMBAI(NAAM)
+        PRINT BASANTI PRINT NAAM[0] + NAAM[LAMBAI(NAAM) - 1]
+        PRINT BASANTI PRINT NAAM[1:4]
+        PRINT BASANTI PRINT NAAM[:3] + \"|\" + NAAM[3:] + \"|\" + NAAM[5:100]
+        KHATAM TATA BYE BYE"
+        .as_bytes();
+    assert_eq!(
+        evaluator::interpreter::Interpreter::new("", false)
+            .run_code(assert_input_with_program(input))
+            .unwrap()
+            .trim_end(),
+        "Naam: Basanti\nScore: 42, pass: true\n7\nBi\nasa\nBas|anti|ti"
+    );
+}
+
+#[test]
+fn test_evaluator_string_builtins() {
+    let input = "PARAMPARA PRATISHTA ANUSHASHAN
+        PRINT BASANTI PRINT ANK(\"12.5\") * 2
+        PRINT BASANTI PRINT AKSHAR(7) + AKSHAR(3)
+        PRINT BASANTI PRINT BADA(\"gabbar\") + CHHOTA(\"SINGH\")
+        PRINT BASANTI PRINT KHOJO(\"Sholay\", \"lay\")
+        PRINT BASANTI PRINT KHOJO(\"Sholay\", \"xyz\")
+        KHATAM TATA BYE BYE"
+        .as_bytes();
+    assert_eq!(
+        evaluator::interpreter::Interpreter::new("", false)
+            .run_code(assert_input_with_program(input))
+            .unwrap()
+            .trim_end(),
+        "25\n73\nGABBARsingh\n3\n-1"
+    );
+}
+
+#[test]
+fn test_evaluator_string_errors() {
+    let cases = [
+        (
+            "PRINT BASANTI PRINT \"abc\"[3]",
+            InterpreterErrorType::IndexOutOfBounds,
+        ),
+        (
+            "PRINT BASANTI PRINT \"abc\"[1.5]",
+            InterpreterErrorType::IncompatibleDataType,
+        ),
+        (
+            "PRINT BASANTI PRINT 123[0]",
+            InterpreterErrorType::IncompatibleDataType,
+        ),
+        (
+            "PRINT BASANTI PRINT ANK(\"das\")",
+            InterpreterErrorType::IncompatibleDataType,
+        ),
+        (
+            "PRINT BASANTI PRINT LAMBAI(\"a\", \"b\")",
+            InterpreterErrorType::ArgumentCountMismatch,
+        ),
+        (
+            "PRINT BASANTI PRINT true + false",
+            InterpreterErrorType::IncompatibleDataType,
+        ),
+    ];
+
+    for (body, error_type) in cases {
+        let input = format!(
+            "PARAMPARA PRATISHTA ANUSHASHAN\n{}\nKHATAM TATA BYE BYE",
+            body
+        );
+        assert!(
+            evaluator::interpreter::Interpreter::new("", false)
+                .run_code(assert_input_with_program(input.as_bytes()))
+                .is_err_and(|err| err.error_type == error_type),
+            "{} should fail with {}",
+            body,
+            error_type
+        );
+    }
+}
+
+#[test]
+fn test_evaluator_lists() {
+    let input = "PARAMPARA PRATISHTA ANUSHASHAN
+        A BOLE TOH [10, 20, 30]
+        A[1] BOLE TOH A[1] + 5
+        JODO(A, \"chalis\")
+        PRINT BASANTI PRINT A
+        PRINT BASANTI PRINT LAMBAI(A)
+        PRINT BASANTI PRINT HATAO(A, 0)
+        PRINT BASANTI PRINT A[1:]
+        PRINT BASANTI PRINT A == [25, 30, \"chalis\"]
+        KHATAM TATA BYE BYE"
+        .as_bytes();
+    assert_eq!(
+        evaluator::interpreter::Interpreter::new("", false)
+            .run_code(assert_input_with_program(input))
+            .unwrap()
+            .trim_end(),
+        "[10, 25, 30, \"chalis\"]\n4\n10\n[30, \"chalis\"]\ntrue"
+    );
+}
+
+#[test]
+fn test_evaluator_list_of_inputs() {
+    let input = "PARAMPARA PRATISHTA ANUSHASHAN
+        N BOLE TOH INPUT LE LE RE BABA
+        SERIES BOLE TOH []
+        JAB TAK HAI JAAN LAMBAI(SERIES) < N TAB TAK
+            JODO(SERIES, INPUT LE LE RE BABA)
+        JAHAN
+        I BOLE TOH 0
+        TOTAL BOLE TOH 0
+        JAB TAK HAI JAAN I < N TAB TAK
+            TOTAL BOLE TOH TOTAL + SERIES[I]
+            I BOLE TOH I + 1
+        JAHAN
+        PRINT BASANTI PRINT TOTAL
+        KHATAM TATA BYE BYE"
+        .as_bytes();
+    assert_eq!(
+        evaluator::interpreter::Interpreter::new("3 4 5 6", false)
+            .run_code(assert_input_with_program(input))
+            .unwrap()
+            .trim_end(),
+        "15"
+    );
+}
+
+#[test]
+fn test_evaluator_lists_are_shared() {
+    let input = "PARAMPARA PRATISHTA ANUSHASHAN
+        AAO KABHI HAVELI PE badlo(L)
+            L[0] BOLE TOH \"badla\"
+        PHIR MILENGE
+        A BOLE TOH [1, [2, 3]]
+        B BOLE TOH A
+        badlo(B)
+        A[1][0] BOLE TOH 20
+        PRINT BASANTI PRINT A
+        KHATAM TATA BYE BYE"
+        .as_bytes();
+    assert_eq!(
+        evaluator::interpreter::Interpreter::new("", false)
+            .run_code(assert_input_with_program(input))
+            .unwrap()
+            .trim_end(),
+        "[\"badla\", [20, 3]]"
+    );
+}
+
+#[test]
+fn test_evaluator_self_containing_values() {
+    let input = "PARAMPARA PRATISHTA ANUSHASHAN
+        L BOLE TOH [1, 2]
+        JODO(L, L)
+        PRINT BASANTI PRINT L
+        PRINT BASANTI PRINT L == L
+        K BOLE TOH [0]
+        K[0] BOLE TOH K
+        J BOLE TOH [0]
+        J[0] BOLE TOH J
+        PRINT BASANTI PRINT [K, K]
+        PRINT BASANTI PRINT K == J
+        PRINT BASANTI PRINT K != L
+        M BOLE TOH {\"a\": 1}
+        M[\"x\"] BOLE TOH M
+        N BOLE TOH {\"a\": 1}
+        N[\"x\"] BOLE TOH N
+        PRINT BASANTI PRINT M
+        PRINT BASANTI PRINT M == N
+        M[\"l\"] BOLE TOH L
+        PRINT BASANTI PRINT M == N
+        KHATAM TATA BYE BYE"
+        .as_bytes();
+    assert_eq!(
+        evaluator::interpreter::Interpreter::new("", false)
+            .run_code(assert_input_with_program(input))
+            .unwrap()
+            .trim_end(),
+        "[1, 2, [...]]\ntrue\n[[[...]], [[...]]]\ntrue\ntrue\n{\"a\": 1, \"x\": {...}}\ntrue\nfalse"
+    );
+}
+
+#[test]
+fn test_evaluator_deeply_nested_values() {
+    // Far deeper than the native stack could follow; the values are printed,
+    // compared and dropped all the same
+    let input = "PARAMPARA PRATISHTA ANUSHASHAN
+        L BOLE TOH []
+        M BOLE TOH {}
+        EK DO TEEN I BOLE TOH 1 SE 100000 TAK
+            L BOLE TOH [L]
+            M BOLE TOH {\"m\": M}
+        JAHAN
+        K BOLE TOH []
+        EK DO TEEN I BOLE TOH 1 SE 100000 TAK
+            K BOLE TOH [K]
+        JAHAN
+        S BOLE TOH AKSHAR(L)
+        PRINT BASANTI PRINT LAMBAI(S)
+        PRINT BASANTI PRINT S[:3] + S[199999:]
+        PRINT BASANTI PRINT LAMBAI(AKSHAR(M))
+        PRINT BASANTI PRINT L == L
+        PRINT BASANTI PRINT L == K
+        PRINT BASANTI PRINT [L] == K
+        KHATAM TATA BYE BYE"
+        .as_bytes();
+    assert_eq!(
+        evaluator::interpreter::Interpreter::new("", false)
+            .run_code(assert_input_with_program(input))
+            .unwrap()
+            .trim_end(),
+        "200002\n[[[]]]\n700002\ntrue\ntrue\nfalse"
+    );
+}
+
+#[test]
+fn test_evaluator_list_errors() {
+    let cases = [
+        (
+            "A BOLE TOH [1]\nPRINT BASANTI PRINT A[1]",
+            InterpreterErrorType::IndexOutOfBounds,
+        ),
+        (
+            "A BOLE TOH [1]\nA[5] BOLE TOH 2",
+            InterpreterErrorType::IndexOutOfBounds,
+        ),
+        (
+            "A BOLE TOH \"abc\"\nA[0] BOLE TOH \"z\"",
+            InterpreterErrorType::IncompatibleDataType,
+        ),
+        ("JODO(5, 1)", InterpreterErrorType::IncompatibleDataType),
+        ("HATAO([], 0)", InterpreterErrorType::IndexOutOfBounds),
+    ];
+
+    for (body, error_type) in cases {
+        let input = format!(
+            "PARAMPARA PRATISHTA ANUSHASHAN\n{}\nKHATAM TATA BYE BYE",
+            body
+        );
+        assert!(
+            evaluator::interpreter::Interpreter::new("", false)
+                .run_code(assert_input_with_program(input.as_bytes()))
+                .is_err_and(|err| err.error_type == error_type),
+            "{} should fail with {}",
+            body,
+            error_type
+        );
+    }
+}
+
+#[test]
+fn test_evaluator_maps() {
+    let input = "PARAMPARA PRATISHTA ANUSHASHAN
+        M BOLE TOH {\"jai\": 1, 2: \"veeru\"}
+        M[\"jai\"] BOLE TOH M[\"jai\"] + 5
+        M[\"basanti\"] BOLE TOH [true]
+        PRINT BASANTI PRINT M
+        PRINT BASANTI PRINT HAI_KYA(M, \"jai\")
+        PRINT BASANTI PRINT HAI_KYA(M, \"gabbar\")
+        PRINT BASANTI PRINT HATAO(M, 2)
+        PRINT BASANTI PRINT CHABIYAN(M)
+        PRINT BASANTI PRINT LAMBAI(M)
+        PRINT BASANTI PRINT {} == {}
+        KHATAM TATA BYE BYE"
+        .as_bytes();
+    assert_eq!(
+        evaluator::interpreter::Interpreter::new("", false)
+            .run_code(assert_input_with_program(input))
+            .unwrap()
+            .trim_end(),
+        "{2: \"veeru\", \"basanti\": [true], \"jai\": 6}\ntrue\nfalse\nveeru\n[\"basanti\", \"jai\"]\n2\ntrue"
+    );
+}
+
+#[test]
+fn test_evaluator_word_count() {
+    let input = "PARAMPARA PRATISHTA ANUSHASHAN
+        SHABD BOLE TOH [\"kitne\", \"aadmi\", \"the\", \"kitne\"]
+        GINTI BOLE TOH {}
+        I BOLE TOH 0
+        JAB TAK HAI JAAN I < LAMBAI(SHABD) TAB TAK
+            AGAR HAI_KYA(GINTI, SHABD[I]) TAB
+                GINTI[SHABD[I]] BOLE TOH GINTI[SHABD[I]] + 1
+            NHI TOH
+                GINTI[SHABD[I]] BOLE TOH 1
+            BAS ITNA HI
+            I BOLE TOH I + 1
+        JAHAN
+        CHABI BOLE TOH CHABIYAN(GINTI)
+        I BOLE TOH 0
+        JAB TAK HAI JAAN I < LAMBAI(CHABI) TAB TAK
+            PRINT BASANTI PRINT CHABI[I] + \" \" + GINTI[CHABI[I]]
+            I BOLE TOH I + 1
+        JAHAN
+        KHATAM TATA BYE BYE"
+        .as_bytes();
+    assert_eq!(
+        evaluator::interpreter::Interpreter::new("", false)
+            .run_code(assert_input_with_program(input))
+            .unwrap()
+            .trim_end(),
+        "aadmi 1\nkitne 2\nthe 1"
+    );
+}
+
+#[test]
+fn test_evaluator_map_errors() {
+    let cases = [
+        (
+            "M BOLE TOH {\"a\": 1}\nPRINT BASANTI PRINT M[\"b\"]",
+            InterpreterErrorType::MissingKey,
+        ),
+        ("HATAO({}, 1)", InterpreterErrorType::MissingKey),
+        (
+            "M BOLE TOH {[1]: 1}",
+            InterpreterErrorType::IncompatibleDataType,
+        ),
+        (
+            "M BOLE TOH {}\nM[true] BOLE TOH 1",
+            InterpreterErrorType::IncompatibleDataType,
+        ),
+        ("HAI_KYA([], 0)", InterpreterErrorType::IncompatibleDataType),
+    ];
+
+    for (body, error_type) in cases {
+        let input = format!(
+            "PARAMPARA PRATISHTA ANUSHASHAN\n{}\nKHATAM TATA BYE BYE",
+            body
+        );
+        assert!(
+            evaluator::interpreter::Interpreter::new("", false)
+                .run_code(assert_input_with_program(input.as_bytes()))
+                .is_err_and(|err| err.error_type == error_type),
+            "{} should fail with {}",
+            body,
+            error_type
+        );
+    }
+}
+
+#[test]
+fn test_evaluator_for_loop() {
+    let input = "PARAMPARA PRATISHTA ANUSHASHAN
+        TOTAL BOLE TOH 0
+        EK DO TEEN I BOLE TOH 1 SE 4 TAK
+            TOTAL BOLE TOH TOTAL + I
+        JAHAN
+        PRINT BASANTI PRINT TOTAL
+        EK DO TEEN I BOLE TOH 10 SE 0 TAK KADAM -5
+            PRINT BASANTI PRINT I
+        JAHAN
+        EK DO TEEN I BOLE TOH 0 SE 1 TAK KADAM 0.5
+            EK DO TEEN J BOLE TOH 1 SE 2 TAK
+                PRINT BASANTI PRINT I + J
+            JAHAN
+        JAHAN
+        EK DO TEEN I BOLE TOH 5 SE 1 TAK
+            PRINT BASANTI PRINT \"kabhi nahi\"
+        JAHAN
+        KHATAM TATA BYE BYE"
+        .as_bytes();
+    assert_eq!(
+        evaluator::interpreter::Interpreter::new("", false)
+            .run_code(assert_input_with_program(input))
+            .unwrap()
+            .trim_end(),
+        "10\n10\n5\n0\n1\n2\n1.5\n2.5\n2\n3"
+    );
+}
+
+#[test]
+fn test_evaluator_for_loop_return() {
+    let input = "PARAMPARA PRATISHTA ANUSHASHAN
+        AAO KABHI HAVELI PE khojo_pehla(L, X)
+            EK DO TEEN I BOLE TOH 0 SE LAMBAI(L) - 1 TAK
+                AGAR L[I] == X TAB
+                    LAUT KE AAJA I
+                BAS ITNA HI
+            JAHAN
+            LAUT KE AAJA -1
+        PHIR MILENGE
+        PRINT BASANTI PRINT khojo_pehla([4, 8, 15, 16], 15)
+        PRINT BASANTI PRINT khojo_pehla([4, 8], 23)
+        KHATAM TATA BYE BYE"
+        .as_bytes();
+    assert_eq!(
+        evaluator::interpreter::Interpreter::new("", false)
+            .run_code(assert_input_with_program(input))
+            .unwrap()
+            .trim_end(),
+        "2\n-1"
+    );
+}
+
+#[test]
+fn test_evaluator_for_loop_errors() {
+    let cases = [
+        (
+            "EK DO TEEN I BOLE TOH 1 SE 5 TAK KADAM 0\nJAHAN",
+            InterpreterErrorType::InvalidLoopStep,
+        ),
+        (
+            "EK DO TEEN I BOLE TOH 1 SE \"das\" TAK\nJAHAN",
+            InterpreterErrorType::IncompatibleDataType,
+        ),
+        (
+            "EK DO TEEN I BOLE TOH 0 SE 1000000 TAK\nJAHAN",
+            InterpreterErrorType::MaxLoopsExceeded,
+        ),
+        (
+            "EK DO TEEN I BOLE TOH 1 SE 2 TAK\nJAHAN\nPRINT BASANTI PRINT I",
+            InterpreterErrorType::VariableOutOfScope,
+        ),
+    ];
+
+    for (body, error_type) in cases {
+        let input = format!(
+            "PARAMPARA PRATISHTA ANUSHASHAN\n{}\nKHATAM TATA BYE BYE",
+            body
+        );
+        assert!(
+            evaluator::interpreter::Interpreter::new("", false)
+                .run_code(assert_input_with_program(input.as_bytes()))
+                .is_err_and(|err| err.error_type == error_type),
+            "{} should fail with {}",
+            body,
+            error_type
+        );
+    }
+}
+
+#[test]
+fn test_evaluator_break_and_continue() {
+    let input = "PARAMPARA PRATISHTA ANUSHASHAN
+        EK DO TEEN I BOLE TOH 1 SE 10 TAK
+            AGAR I % 2 == 0 TAB
+                JAANE BHI DO
+            BAS ITNA HI
+            AGAR I > 5 TAB
+                BAS KAR PAGLE
+            BAS ITNA HI
+            PRINT BASANTI PRINT I
+        JAHAN
+        A BOLE TOH 0
+        JAB TAK HAI JAAN true TAB TAK
+            A BOLE TOH A + 1
+            EK DO TEEN J BOLE TOH 1 SE 3 TAK
+                AGAR J == 2 TAB
+                    BAS KAR PAGLE
+                BAS ITNA HI
+                PRINT BASANTI PRINT A * 10 + J
+            JAHAN
+            AGAR A == 2 TAB
+                BAS KAR PAGLE
+            BAS ITNA HI
+        JAHAN
+        KHATAM TATA BYE BYE"
+        .as_bytes();
+    assert_eq!(
+        evaluator::interpreter::Interpreter::new("", false)
+            .run_code(assert_input_with_program(input))
+            .unwrap()
+            .trim_end(),
+        "1\n3\n5\n11\n21"
+    );
+}
+
+#[test]
+fn test_evaluator_loop_control_outside_loop() {
+    // Each case with where the error is reported: at the statement, or at
+    // the call of the function that tried to leave a loop
+    let cases = [
+        ("BAS KAR PAGLE", (2, 1)),
+        ("AGAR true TAB\nJAANE BHI DO\nBAS ITNA HI", (3, 1)),
+        (
+            "AAO KABHI HAVELI PE ruko()\nBAS KAR PAGLE\nPHIR MILENGE\nJAB TAK HAI JAAN true TAB TAK\nruko()\nJAHAN",
+            (6, 1),
+        ),
+    ];
+
+    for (body, location) in cases {
+        let input = format!(
+            "PARAMPARA PRATISHTA ANUSHASHAN\n{}\nKHATAM TATA BYE BYE",
+            body
+        );
+        let Err(ZenError::Runtime(err)) = zen::run_program(input, "", false) else {
+            panic!("{} should fail", body);
+        };
+        assert_eq!(err.error_type, InterpreterErrorType::LoopControlOutsideLoop);
+        let span = err.span.unwrap();
+        assert_eq!((span.line, span.column), location, "{}", body);
+    }
+}
+
+#[test]
+fn test_evaluator_error_location() {
+    let input = "PARAMPARA PRATISHTA ANUSHASHAN
+A BOLE TOH 5
+AAO KABHI HAVELI PE bhaag(X)
+    LAUT KE AAJA X / (A - 5)
+PHIR MILENGE
+PRINT BASANTI PRINT 1 + bhaag(2)
+KHATAM TATA BYE BYE";
+    let Err(ZenError::Runtime(err)) = zen::run_program(input.to_string(), "", false) else {
+        panic!("expected a runtime error");
+    };
+    assert_eq!(err.error_type, InterpreterErrorType::DivisionByZero);
+    let span = err.span.unwrap();
+    assert_eq!((span.line, span.column), (4, 18));
+    assert_eq!(&input[span.start..span.end], "X / (A - 5)");
+
+    let input = "PARAMPARA PRATISHTA ANUSHASHAN\nPRINT BASANTI PRINT  GABBAR\nKHATAM TATA BYE BYE";
+    let err = zen::run_program(input.to_string(), "", false).unwrap_err();
+    let span = err.span().unwrap();
+    assert_eq!((span.line, span.column), (2, 22));
+    assert_eq!(&input[span.start..span.end], "GABBAR");
+}
+
+#[test]
+fn test_evaluator_syntax_errors() {
+    let input = "PARAMPARA PRATISHTA ANUSHASHAN
+AGAR 1 < 2 TAB
+    PRINT BASANTI PRINT 1 +
+BAS ITNA HI
+KHATAM TATA BYE BYE";
+    let Err(ZenError::Parse(errors)) = zen::run_program(input.to_string(), "", false) else {
+        panic!("expected a parse error");
+    };
+    let [err] = &errors[..] else {
+        panic!("expected a single parse error, got {:?}", errors);
+    };
+    assert_eq!(err.expected, vec!["an expression".to_string()]);
+    assert_eq!(err.found, TokenType::EndOfStatement);
+    assert_eq!(err.span.map(|span| span.line), Some(3));
+
+    let input = "PARAMPARA PRATISHTA ANUSHASHAN
+JAB TAK HAI JAAN 1 < 2 TAB TAK
+    PRINT BASANTI PRINT 1
+KHATAM TATA BYE BYE";
+    let Err(ZenError::Parse(errors)) = zen::run_program(input.to_string(), "", false) else {
+        panic!("expected a parse error");
+    };
+    let [err] = &errors[..] else {
+        panic!("expected a single parse error, got {:?}", errors);
+    };
+    assert_eq!(err.expected, vec!["'JAHAN'".to_string()]);
+    assert_eq!(err.found, TokenType::Eof);
+
+    let input = "PARAMPARA PRATISHTA ANUSHASHAN\nPRINT BASANTI PRINT 1\n";
+    let Err(ZenError::Parse(errors)) = zen::run_program(input.to_string(), "", false) else {
+        panic!("expected a parse error");
+    };
+    let [err] = &errors[..] else {
+        panic!("expected a single parse error, got {:?}", errors);
+    };
+    assert_eq!(err.expected, vec!["'KHATAM TATA BYE BYE'".to_string()]);
+    assert_eq!(err.found, TokenType::Eof);
+    assert!(err.to_string().starts_with("Mogambo dukhi hua!"));
+}
+
+#[test]
+fn test_evaluator_lex_errors() {
+    let input = "PARAMPARA PRATISHTA ANUSHASHAN\nA BOLE TOH 5 $ 2\nKHATAM TATA BYE BYE";
+    let Err(ZenError::Lex(err)) = zen::run_program(input.to_string(), "", false) else {
+        panic!("expected a lex error");
+    };
+    assert_eq!((err.span.line, err.span.column), (2, 14));
+    assert!(err.msg.contains("'$'"));
+
+    let input = "PARAMPARA PRATISHTA ANUSHASHAN\nPRINT BASANTI PRINT \"adhura\nKHATAM TATA BYE BYE";
+    let Err(ZenError::Lex(err)) = zen::run_program(input.to_string(), "", false) else {
+        panic!("expected a lex error");
+    };
+    assert_eq!((err.span.line, err.span.column), (2, 21));
+    assert!(err.msg.contains("never ends"));
+}
+
+#[test]
+fn test_evaluator_configured_limits() {
+    let nested_loops = "EK DO TEEN I BOLE TOH 1 SE 1000 TAK
+EK DO TEEN J BOLE TOH 1 SE 1000 TAK
+JAHAN
+JAHAN";
+    let recursion = "AAO KABHI HAVELI PE f(n)
+AGAR n > 0 TAB
+LAUT KE AAJA f(n - 1)
+BAS ITNA HI
+LAUT KE AAJA 0
+PHIR MILENGE
+PRINT BASANTI PRINT f(20)";
+    let cases = [
+        (
+            "EK DO TEEN I BOLE TOH 1 SE 11 TAK\nJAHAN",
+            InterpreterConfig {
+                max_iterations: 10,
+                ..InterpreterConfig::default()
+            },
+            InterpreterErrorType::MaxLoopsExceeded,
+        ),
+        (
+            nested_loops,
+            InterpreterConfig {
+                max_steps: Some(10_000),
+                ..InterpreterConfig::default()
+            },
+            InterpreterErrorType::MaxStepsExceeded,
+        ),
+        (
+            "EK DO TEEN I BOLE TOH 1 SE 20 TAK\nPRINT BASANTI PRINT \"Mogambo khush hua\"\nJAHAN",
+            InterpreterConfig {
+                max_output_bytes: Some(100),
+                ..InterpreterConfig::default()
+            },
+            InterpreterErrorType::MaxOutputExceeded,
+        ),
+        (
+            recursion,
+            InterpreterConfig {
+                max_variables: Some(10),
+                ..InterpreterConfig::default()
+            },
+            InterpreterErrorType::MaxVariablesExceeded,
+        ),
+        (
+            recursion,
+            InterpreterConfig {
+                max_call_depth: 10,
+                ..InterpreterConfig::default()
+            },
+            InterpreterErrorType::MaxCallDepthExceeded,
+        ),
+    ];
+
+    for (body, config, error_type) in cases {
+        let input = format!(
+            "PARAMPARA PRATISHTA ANUSHASHAN\n{}\nKHATAM TATA BYE BYE",
+            body
+        );
+        assert!(
+            Interpreter::new_with_config("", false, config.clone())
+                .run_code(assert_input_with_program(input.as_bytes()))
+                .is_err_and(|err| err.error_type == error_type),
+            "{} should fail with {}",
+            body,
+            error_type
+        );
+
+        // Each program stays within the default limits
+        assert!(
+            Interpreter::new("", false)
+                .run_code(assert_input_with_program(input.as_bytes()))
+                .is_ok(),
+            "{} should run with the default limits",
+            body
+        );
+    }
+
+    // Output stops before the line that crosses the limit
+    let input = "PARAMPARA PRATISHTA ANUSHASHAN\nPRINT BASANTI PRINT 12\nPRINT BASANTI PRINT 34\nKHATAM TATA BYE BYE";
+    let config = InterpreterConfig {
+        max_output_bytes: Some(5),
+        ..InterpreterConfig::default()
+    };
+    let (tokens, spans) = Lexer::lex_program(input.as_bytes()).unwrap();
+    let program = Parser::parse_program(Tokens::new_with_spans(&tokens, &spans)).unwrap();
+    let err = Interpreter::new_with_config("", false, config)
+        .run_code(program)
+        .unwrap_err();
+    assert_eq!(err.error_type, InterpreterErrorType::MaxOutputExceeded);
+    assert_eq!(err.span.map(|span| span.line), Some(3));
+}
+
+#[test]
+fn test_evaluator_step_limit_before_any_instruction() {
+    let input = "PARAMPARA PRATISHTA ANUSHASHAN
+AAO KABHI HAVELI PE f()
+    LAUT KE AAJA 1
+PHIR MILENGE
+EK DO TEEN I BOLE TOH 1 SE 3 TAK
+    PRINT BASANTI PRINT f()
+JAHAN
+KHATAM TATA BYE BYE";
+
+    // Every limit, including none at all and ones that trip on the first
+    // instruction of a call
+    let mut tripped_in_call = false;
+    for max_steps in 0..200 {
+        let config = InterpreterConfig {
+            max_steps: Some(max_steps),
+            ..InterpreterConfig::default()
+        };
+        let (tokens, spans) = Lexer::lex_program(input.as_bytes()).unwrap();
+        let program = Parser::parse_program(Tokens::new_with_spans(&tokens, &spans)).unwrap();
+        match Interpreter::new_with_config("", false, config).run_code(program) {
+            Ok(_) => break,
+            Err(err) => {
+                assert_eq!(err.error_type, InterpreterErrorType::MaxStepsExceeded);
+                assert_eq!(err.span.is_none(), max_steps == 0, "{} steps", max_steps);
+                tripped_in_call |= err.span.is_some_and(|span| span.line == 6);
+            }
+        }
+    }
+    assert!(tripped_in_call);
+}
+
+#[test]
+fn test_evaluator_timeout_and_cancellation() {
+    // Would take 10^10 steps if nothing stopped it
+    let input = "PARAMPARA PRATISHTA ANUSHASHAN
+        EK DO TEEN I BOLE TOH 1 SE 100000 TAK
+            EK DO TEEN J BOLE TOH 1 SE 100000 TAK
+            JAHAN
+        JAHAN
+        KHATAM TATA BYE BYE";
+
+    let config = InterpreterConfig {
+        timeout: Some(Duration::from_millis(50)),
+        ..InterpreterConfig::default()
+    };
+    let started = Instant::now();
+    let err = Interpreter::new_with_config("", false, config)
+        .run_code(assert_input_with_program(input.as_bytes()))
+        .unwrap_err();
+    assert_eq!(err.error_type, InterpreterErrorType::MaxTimeExceeded);
+    assert!(started.elapsed() < Duration::from_secs(5));
+
+    let cancel = Arc::new(AtomicBool::new(false));
+    let config = InterpreterConfig {
+        cancel: Some(Arc::clone(&cancel)),
+        ..InterpreterConfig::default()
+    };
+    let program = thread::spawn(move || {
+        Interpreter::new_with_config("", false, config)
+            .run_code(assert_input_with_program(input.as_bytes()))
+            .map_err(|err| err.error_type)
+    });
+    thread::sleep(Duration::from_millis(50));
+    cancel.store(true, Ordering::Relaxed);
+    assert_eq!(
+        program.join().unwrap(),
+        Err(InterpreterErrorType::ExecutionCancelled)
+    );
+}
+
+#[test]
+fn test_evaluator_interrupted_in_a_call() {
+    // The clock is only looked at every so often, so padding the program
+    // moves that check onto every instruction of the loop in turn,
+    // including the first one of the call
+    for padding in 0..8 {
+        let input = format!(
+            "PARAMPARA PRATISHTA ANUSHASHAN
+AAO KABHI HAVELI PE f()
+PHIR MILENGE
+X BOLE TOH 1
+{}JAB TAK HAI JAAN true TAB TAK
+    f()
+JAHAN
+KHATAM TATA BYE BYE",
+            "X BOLE TOH -1\n".repeat(padding)
+        );
+
+        let config = InterpreterConfig {
+            timeout: Some(Duration::ZERO),
+            ..InterpreterConfig::default()
+        };
+        let err = Interpreter::new_with_config("", false, config)
+            .run_code(assert_input_with_program(input.as_bytes()))
+            .unwrap_err();
+        assert_eq!(err.error_type, InterpreterErrorType::MaxTimeExceeded);
+
+        let config = InterpreterConfig {
+            cancel: Some(Arc::new(AtomicBool::new(true))),
+            ..InterpreterConfig::default()
+        };
+        let err = Interpreter::new_with_config("", false, config)
+            .run_code(assert_input_with_program(input.as_bytes()))
+            .unwrap_err();
+        assert_eq!(err.error_type, InterpreterErrorType::ExecutionCancelled);
+    }
+}
+
+#[test]
+fn test_evaluator_custom_io() {
+    let input = "PARAMPARA PRATISHTA ANUSHASHAN
+        A BOLE TOH INPUT LE LE RE BABA
+        B BOLE TOH INPUT LE LE RE BABA
+        PRINT BASANTI PRINT A + B
+        PRINT BASANTI PRINT A * B
+        KHATAM TATA BYE BYE";
+
+    // Input asked for one piece at a time, output streamed line by line
+    let mut answers = vec!["7".to_string(), " 6 ".to_string()].into_iter();
+    let lines = Rc::new(RefCell::new(Vec::new()));
+    let streamed = Rc::clone(&lines);
+    let mut interpreter = Interpreter::new_with_io(
+        Box::new(CallbackInput::new(move || answers.next())),
+        Box::new(CallbackOutput::new(move |line: &str| {
+            streamed.borrow_mut().push(line.to_string())
+        })),
+        InterpreterConfig::default(),
+    );
+    let output = interpreter
+        .run_code(assert_input_with_program(input.as_bytes()))
+        .unwrap();
+    assert_eq!(output, "");
+    assert_eq!(*lines.borrow(), vec!["13", "42"]);
+
+    let captured = MemoryOutput::default();
+    let mut interpreter = Interpreter::new_with_io(
+        Box::new(MemoryInput::new("2\n\n 3")),
+        Box::new(captured.clone()),
+        InterpreterConfig::default(),
+    );
+    interpreter
+        .run_code(assert_input_with_program(input.as_bytes()))
+        .unwrap();
+    assert_eq!(captured.text(), "5\n6\n");
+
+    let mut interpreter = Interpreter::new_with_io(
+        Box::new(MemoryInput::new("2")),
+        Box::new(MemoryOutput::default()),
+        InterpreterConfig::default(),
+    );
+    let err = interpreter
+        .run_code(assert_input_with_program(input.as_bytes()))
+        .unwrap_err();
+    assert_eq!(err.error_type, InterpreterErrorType::EmptyCustomInputStack);
+}
+
+#[test]
+fn test_evaluator_word_and_line_input() {
+    let input = "PARAMPARA PRATISHTA ANUSHASHAN
+        N BOLE TOH INPUT LE LE RE BABA
+        NAAM BOLE TOH SHABD LE LE RE BABA
+        BAAKI BOLE TOH PURI LINE LE LE RE BABA
+        AGLI BOLE TOH PURI LINE LE LE RE BABA
+        PRINT BASANTI PRINT N * 2
+        PRINT BASANTI PRINT NAAM
+        PRINT BASANTI PRINT BAAKI
+        PRINT BASANTI PRINT AGLI
+        KHATAM TATA BYE BYE";
+    let run = |stdin: &str| {
+        Interpreter::new(stdin, false).run_code(assert_input_with_program(input.as_bytes()))
+    };
+
+    assert_eq!(
+        run("21 Gabbar Singh  Thakur\n  Ramgarh ke sholay \r\n").unwrap(),
+        "42\nGabbar\nSingh  Thakur\n  Ramgarh ke sholay \n"
+    );
+    // A line after the last word of a line is the whole next line
+    assert_eq!(
+        run("\n7 Jai \nVeeru aur Jai\n\n").unwrap(),
+        "14\nJai\nVeeru aur Jai\n\n"
+    );
+    assert_eq!(
+        run("7 Jai\nVeeru").unwrap_err().error_type,
+        InterpreterErrorType::EmptyCustomInputStack
+    );
+    assert_eq!(
+        run("Gabbar 7").unwrap_err().error_type,
+        InterpreterErrorType::InvalidInputError
+    );
+
+    // Lines from any other source read the same way
+    let mut lines = vec!["21 Gabbar Singh  Thakur", "  Ramgarh ke sholay "].into_iter();
+    let captured = MemoryOutput::default();
+    Interpreter::new_with_io(
+        Box::new(CallbackInput::new(move || lines.next().map(str::to_string))),
+        Box::new(captured.clone()),
+        InterpreterConfig::default(),
+    )
+    .run_code(assert_input_with_program(input.as_bytes()))
+    .unwrap();
+    assert_eq!(
+        captured.text(),
+        "42\nGabbar\nSingh  Thakur\n  Ramgarh ke sholay \n"
+    );
+}
+
+#[test]
+fn test_evaluator_large_input() {
+    let input = "PARAMPARA PRATISHTA ANUSHASHAN
+        N BOLE TOH INPUT LE LE RE BABA
+        TOTAL BOLE TOH 0
+        EK DO TEEN I BOLE TOH 1 SE N TAK
+            TOTAL BOLE TOH TOTAL + INPUT LE LE RE BABA
+        JAHAN
+        PRINT BASANTI PRINT TOTAL
+        PRINT BASANTI PRINT SHABD LE LE RE BABA
+        KHATAM TATA BYE BYE";
+    let count: u64 = 50_000;
+
+    // One value per line, and all of them on a single line
+    let mut per_line = format!("{}\n", count);
+    let mut one_line = format!("{}\n", count);
+    for i in 1..=count {
+        per_line += &format!("{}\n", i);
+        one_line += &format!("{} ", i);
+    }
+    per_line += "bas\n";
+    one_line += "bas\n";
+
+    let expected = format!("{}\nbas\n", count * (count + 1) / 2);
+    for stdin in [per_line, one_line] {
+        let output = Interpreter::new(&stdin, false)
+            .run_code(assert_input_with_program(input.as_bytes()))
+            .unwrap();
+        assert_eq!(output, expected);
+    }
+}
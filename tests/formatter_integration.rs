@@ -0,0 +1,180 @@
+use zen::lexer::lexer_util::Lexer;
+use zen::lexer::tokens::Tokens;
+use zen::parser::ast::Program;
+use zen::parser::parser_util::Parser;
+use zen::{format_code, run_program};
+
+/// The program without any spans, to compare what two sources mean
+fn ast(code: &str) -> Program {
+    let (tokens, _) = Lexer::lex_program(code.as_bytes()).unwrap();
+    Parser::parse_program(Tokens::new(&tokens)).unwrap()
+}
+
+/// Formats `code`, checking that the result means the same, prints the
+/// same and stays as it is when formatted again
+fn assert_round_trip(code: &str, input: &str) -> String {
+    let formatted = format_code(code.to_string()).unwrap();
+    assert_eq!(ast(&formatted), ast(code), "{}", formatted);
+    assert_eq!(
+        run_program(formatted.clone(), input, false).map_err(|e| e.to_string()),
+        run_program(code.to_string(), input, false).map_err(|e| e.to_string()),
+    );
+    assert_eq!(format_code(formatted.clone()).unwrap(), formatted);
+    formatted
+}
+
+#[test]
+fn test_format_canonical_layout() {
+    let code = "PARAMPARA PRATISHTA ANUSHASHAN
+N   BOLE  TOH INPUT LE LE RE BABA
+  JAB TAK HAI JAAN N>0&&N<20 TAB TAK
+PRINT   BASANTI PRINT N*(2+3)
+        N BOLE TOH N-1
+JAHAN
+AGAR N==0 TAB
+PRINT BASANTI PRINT \"khatam\"
+WARNA AGAR N == 1 TAB
+  JAANE BHI DO
+NHI TOH
+ L BOLE TOH [1,2,   3]
+ L[0] BOLE TOH {\"a\":1}
+BAS ITNA HI
+AAO KABHI HAVELI PE jod(A,B)
+LAUT KE AAJA A+B
+PHIR MILENGE
+EK DO TEEN I BOLE TOH 1 SE 5 TAK KADAM 2
+PRINT BASANTI PRINT jod(I,SHABD LE LE RE BABA)
+JAHAN
+KHATAM TATA BYE BYE";
+
+    assert_eq!(
+        format_code(code.to_string()).unwrap(),
+        "PARAMPARA PRATISHTA ANUSHASHAN
+N BOLE TOH INPUT LE LE RE BABA
+JAB TAK HAI JAAN N > 0 && N < 20 TAB TAK
+    PRINT BASANTI PRINT N * (2 + 3)
+    N BOLE TOH N - 1
+JAHAN
+AGAR N == 0 TAB
+    PRINT BASANTI PRINT \"khatam\"
+WARNA AGAR N == 1 TAB
+    JAANE BHI DO
+NHI TOH
+    L BOLE TOH [1, 2, 3]
+    L[0] BOLE TOH {\"a\": 1}
+BAS ITNA HI
+AAO KABHI HAVELI PE jod(A, B)
+    LAUT KE AAJA A + B
+PHIR MILENGE
+EK DO TEEN I BOLE TOH 1 SE 5 TAK KADAM 2
+    PRINT BASANTI PRINT jod(I, SHABD LE LE RE BABA)
+JAHAN
+KHATAM TATA BYE BYE
+"
+    );
+}
+
+#[test]
+fn test_format_keeps_comments() {
+    let code = "@ Ginti
+PARAMPARA PRATISHTA ANUSHASHAN @ shuru
+A BOLE TOH 3
+
+
+@ ulti ginti
+JAB TAK HAI JAAN A > 0 TAB TAK @ jab tak
+    PRINT BASANTI PRINT A
+  @ ek kam
+    A BOLE TOH A - 1
+JAHAN @ bas
+L BOLE TOH [1,
+    2, @ do
+    3]
+KHATAM TATA BYE BYE
+@the end";
+
+    assert_eq!(
+        assert_round_trip(code, ""),
+        "@ Ginti
+PARAMPARA PRATISHTA ANUSHASHAN @ shuru
+A BOLE TOH 3
+
+@ ulti ginti
+JAB TAK HAI JAAN A > 0 TAB TAK @ jab tak
+    PRINT BASANTI PRINT A
+    @ ek kam
+    A BOLE TOH A - 1
+JAHAN @ bas
+@ do
+L BOLE TOH [1, 2, 3]
+KHATAM TATA BYE BYE
+@the end
+"
+    );
+}
+
+#[test]
+fn test_format_keeps_meaning() {
+    // A prefix operator applies to everything after it, so its parentheses
+    // matter as much as the ones precedence needs
+    let code = "PARAMPARA PRATISHTA ANUSHASHAN
+A BOLE TOH 4
+PRINT BASANTI PRINT -A + 1
+PRINT BASANTI PRINT (-A) + 1
+PRINT BASANTI PRINT 2 * -A
+PRINT BASANTI PRINT (2 * -A) + 1
+PRINT BASANTI PRINT 10 - (4 - 3) - 2
+PRINT BASANTI PRINT (10 - 4) - (3 - 2)
+PRINT BASANTI PRINT !(A > 3) || A == 4 && !false
+PRINT BASANTI PRINT ((A + 1) * 2) % 3
+PRINT BASANTI PRINT [1, 2, 3][1:][0]
+PRINT BASANTI PRINT \"kaho \\\"na\\\" \\\\ pyaar hai\"
+KHATAM TATA BYE BYE";
+
+    let formatted = assert_round_trip(code, "");
+    assert!(formatted.contains("PRINT BASANTI PRINT -(A + 1)\n"));
+    assert!(formatted.contains("PRINT BASANTI PRINT (-A) + 1\n"));
+    assert!(formatted.contains("PRINT BASANTI PRINT 2 * (-A) + 1\n"));
+    assert!(formatted.contains("PRINT BASANTI PRINT 10 - (4 - 3) - 2\n"));
+    assert!(formatted.contains("PRINT BASANTI PRINT 10 - 4 - (3 - 2)\n"));
+    assert!(formatted.contains("PRINT BASANTI PRINT (A + 1) * 2 % 3\n"));
+}
+
+#[test]
+fn test_format_nested_blocks() {
+    let code = "PARAMPARA PRATISHTA ANUSHASHAN
+AAO KABHI HAVELI PE fizz(N)
+EK DO TEEN I BOLE TOH 1 SE N TAK
+AGAR I % 15 == 0 TAB
+PRINT BASANTI PRINT \"FizzBuzz\"
+WARNA AGAR I % 3 == 0 TAB @ teen
+PRINT BASANTI PRINT \"Fizz\"
+NHI TOH
+@ baaki sab
+AGAR I % 5 == 0 TAB
+PRINT BASANTI PRINT \"Buzz\"
+NHI TOH
+PRINT BASANTI PRINT I
+BAS ITNA HI
+BAS ITNA HI
+JAHAN
+PHIR MILENGE
+fizz(15)
+KHATAM TATA BYE BYE";
+
+    let formatted = assert_round_trip(code, "");
+    assert!(formatted.contains(
+        "        NHI TOH
+            @ baaki sab
+            AGAR I % 5 == 0 TAB
+                PRINT BASANTI PRINT \"Buzz\"
+            NHI TOH
+"
+    ));
+}
+
+#[test]
+fn test_format_reports_syntax_errors() {
+    let code = "PARAMPARA PRATISHTA ANUSHASHAN\nA BOLE TOH (1\nKHATAM TATA BYE BYE";
+    assert!(format_code(code.to_string()).is_err());
+}
@@ -1,4 +1,8 @@
-use zen::lexer::{lexer_util::Lexer, token_type::TokenType};
+use zen::lexer::{
+    lexer_util::Lexer,
+    span::Span,
+    token_type::{TokenClass, TokenType},
+};
 
 #[test]
 fn test_lex_tokens() {
@@ -91,6 +95,24 @@ fn test_input_from_console() {
     assert_eq!(Lexer::lex_tokens(input), expected_output);
 }
 
+#[test]
+fn test_word_and_line_input() {
+    let input = b"C BOLE TOH SHABD LE LE RE BABA D BOLE TOH PURI  LINE LE LE RE BABA";
+    let expected_output = Ok((
+        &b""[..],
+        vec![
+            TokenType::Identifier("C".to_string()),
+            TokenType::Assign,
+            TokenType::InputWord,
+            TokenType::Identifier("D".to_string()),
+            TokenType::Assign,
+            TokenType::InputLine,
+            TokenType::Eof,
+        ],
+    ));
+    assert_eq!(Lexer::lex_tokens(input), expected_output);
+}
+
 #[test]
 fn test_if_statement() {
     let input = b"AGAR A > 3 TAB PRINT BASANTI PRINT 3 BAS ITNA HI";
@@ -288,3 +310,219 @@ fn test_lex_newline() {
 
     assert_eq!(Lexer::lex_tokens(input), expected_output);
 }
+
+#[test]
+fn test_function_keywords() {
+    let input = b"AAO KABHI HAVELI PE jodo(a, b)\nLAUT KE AAJA a + b\nPHIR MILENGE";
+    let expected_output = Ok((
+        &b""[..],
+        vec![
+            TokenType::FunctionStart,
+            TokenType::Identifier("jodo".to_string()),
+            TokenType::LeftParen,
+            TokenType::Identifier("a".to_string()),
+            TokenType::Comma,
+            TokenType::Identifier("b".to_string()),
+            TokenType::RightParen,
+            TokenType::EndOfStatement,
+            TokenType::Return,
+            TokenType::Identifier("a".to_string()),
+            TokenType::Plus,
+            TokenType::Identifier("b".to_string()),
+            TokenType::EndOfStatement,
+            TokenType::FunctionEnd,
+            TokenType::Eof,
+        ],
+    ));
+    assert_eq!(Lexer::lex_tokens(input), expected_output);
+}
+
+#[test]
+fn test_local_keyword() {
+    let input = b"APNA A BOLE TOH 1 APNAPAN";
+    let expected_output = Ok((
+        &b""[..],
+        vec![
+            TokenType::Local,
+            TokenType::Identifier("A".to_string()),
+            TokenType::Assign,
+            TokenType::Number(1.0),
+            TokenType::Identifier("APNAPAN".to_string()),
+            TokenType::Eof,
+        ],
+    ));
+    assert_eq!(Lexer::lex_tokens(input), expected_output);
+}
+
+#[test]
+fn test_index_and_slice_punctuations() {
+    let input = b"NAAM[1:3]";
+    let expected_output = Ok((
+        &b""[..],
+        vec![
+            TokenType::Identifier("NAAM".to_string()),
+            TokenType::LeftBracket,
+            TokenType::Number(1.0),
+            TokenType::Colon,
+            TokenType::Number(3.0),
+            TokenType::RightBracket,
+            TokenType::Eof,
+        ],
+    ));
+    assert_eq!(Lexer::lex_tokens(input), expected_output);
+}
+
+#[test]
+fn test_list_literal() {
+    let input = b"[1, \"do\", true]";
+    let expected_output = Ok((
+        &b""[..],
+        vec![
+            TokenType::LeftBracket,
+            TokenType::Number(1.0),
+            TokenType::Comma,
+            TokenType::StringLiteral("do".to_owned()),
+            TokenType::Comma,
+            TokenType::BooleanLiteral(true),
+            TokenType::RightBracket,
+            TokenType::Eof,
+        ],
+    ));
+    assert_eq!(Lexer::lex_tokens(input), expected_output);
+}
+
+#[test]
+fn test_map_literal() {
+    let input = b"{\"a\": 1, 2: \"do\"}";
+    let expected_output = Ok((
+        &b""[..],
+        vec![
+            TokenType::LeftBrace,
+            TokenType::StringLiteral("a".to_owned()),
+            TokenType::Colon,
+            TokenType::Number(1.0),
+            TokenType::Comma,
+            TokenType::Number(2.0),
+            TokenType::Colon,
+            TokenType::StringLiteral("do".to_owned()),
+            TokenType::RightBrace,
+            TokenType::Eof,
+        ],
+    ));
+    assert_eq!(Lexer::lex_tokens(input), expected_output);
+}
+
+#[test]
+fn test_for_keywords() {
+    let input = b"EK DO TEEN I BOLE TOH 1 SE 10 TAK KADAM 2\nJAHAN SEVA TAKLA";
+    let expected_output = Ok((
+        &b""[..],
+        vec![
+            TokenType::For,
+            TokenType::Identifier("I".to_string()),
+            TokenType::Assign,
+            TokenType::Number(1.0),
+            TokenType::From,
+            TokenType::Number(10.0),
+            TokenType::To,
+            TokenType::Step,
+            TokenType::Number(2.0),
+            TokenType::EndOfStatement,
+            TokenType::EndWhile,
+            TokenType::Identifier("SEVA".to_string()),
+            TokenType::Identifier("TAKLA".to_string()),
+            TokenType::Eof,
+        ],
+    ));
+    assert_eq!(Lexer::lex_tokens(input), expected_output);
+}
+
+#[test]
+fn test_loop_control_keywords() {
+    let input = b"BAS KAR PAGLE\nJAANE BHI DO\nBAS ITNA HI";
+    let expected_output = Ok((
+        &b""[..],
+        vec![
+            TokenType::Break,
+            TokenType::EndOfStatement,
+            TokenType::Continue,
+            TokenType::EndOfStatement,
+            TokenType::EndIf,
+            TokenType::Eof,
+        ],
+    ));
+    assert_eq!(Lexer::lex_tokens(input), expected_output);
+}
+
+#[test]
+fn test_token_spans() {
+    let input = "A BOLE TOH 1\n  PRINT BASANTI PRINT \"hé\"".as_bytes();
+    let span = |start, end, line, column| Span {
+        start,
+        end,
+        line,
+        column,
+    };
+    let expected_output = Ok((
+        &b""[..],
+        (
+            vec![
+                TokenType::Identifier("A".to_string()),
+                TokenType::Assign,
+                TokenType::Number(1.0),
+                TokenType::EndOfStatement,
+                TokenType::Print,
+                TokenType::StringLiteral("hé".to_owned()),
+                TokenType::Eof,
+            ],
+            vec![
+                span(0, 1, 1, 1),
+                span(2, 10, 1, 3),
+                span(11, 12, 1, 12),
+                span(12, 13, 1, 13),
+                span(15, 34, 2, 3),
+                span(35, 40, 2, 23),
+                span(40, 40, 2, 27),
+            ],
+        ),
+    ));
+    assert_eq!(Lexer::lex_tokens_with_spans(input), expected_output);
+}
+
+#[test]
+fn test_comments() {
+    let input = "A BOLE TOH 1 @ ek\n@ do\nB BOLE TOH 2\n@teen";
+    let comments = Lexer::lex_comments(input.as_bytes());
+    let texts: Vec<_> = comments
+        .iter()
+        .map(|comment| comment.text.as_str())
+        .collect();
+    assert_eq!(texts, vec![" ek", " do", "teen"]);
+    assert_eq!((comments[0].span.line, comments[0].span.column), (1, 14));
+    assert_eq!((comments[2].span.line, comments[2].span.column), (4, 1));
+}
+
+#[test]
+fn test_classified_tokens() {
+    let input = "JAB TAK HAI JAAN A >= \"x\" TAB TAK @ loop\n$ L[1]";
+    let classified: Vec<_> = Lexer::lex_classified(input.as_bytes())
+        .iter()
+        .map(|token| (token.class, &input[token.span.start..token.span.end]))
+        .collect();
+    assert_eq!(
+        classified,
+        vec![
+            (TokenClass::Keyword, "JAB TAK HAI JAAN"),
+            (TokenClass::Identifier, "A"),
+            (TokenClass::Operator, ">="),
+            (TokenClass::String, "\"x\""),
+            (TokenClass::Keyword, "TAB TAK"),
+            (TokenClass::Comment, "@ loop"),
+            // '$' is not Zen, and is left out
+            (TokenClass::Identifier, "L"),
+            (TokenClass::Operator, "["),
+            (TokenClass::Number, "1"),
+            (TokenClass::Operator, "]"),
+        ]
+    );
+}
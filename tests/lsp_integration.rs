@@ -0,0 +1,230 @@
+use serde_json::{Value, json};
+use std::io::{BufReader, Write};
+use std::process::{Command, Stdio};
+use zen::lsp::{read_message, write_message};
+
+const URI: &str = "file:///ginti.zen";
+
+const PROGRAM: &str = "PARAMPARA PRATISHTA ANUSHASHAN
+AAO KABHI HAVELI PE jod(A, B)
+    APNA C BOLE TOH A + B
+    LAUT KE AAJA C
+PHIR MILENGE
+N BOLE TOH 3
+EK DO TEEN I BOLE TOH 1 SE N TAK
+    PRINT BASANTI PRINT jod(I, N)
+JAHAN
+KHATAM TATA BYE BYE";
+
+/// Plays `messages` to the zen-lsp binary, giving what it sent back and
+/// its exit code
+fn session(messages: &[Value]) -> (Vec<Value>, Option<i32>) {
+    let mut server = Command::new(env!("CARGO_BIN_EXE_zen-lsp"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    let mut stdin = server.stdin.take().unwrap();
+    for message in messages {
+        write_message(&mut stdin, message).unwrap();
+    }
+    stdin.flush().unwrap();
+    drop(stdin);
+
+    let mut stdout = BufReader::new(server.stdout.take().unwrap());
+    let mut replies = Vec::new();
+    while let Some(body) = read_message(&mut stdout).unwrap() {
+        replies.push(serde_json::from_str(&body).unwrap());
+    }
+    (replies, server.wait().unwrap().code())
+}
+
+fn request(id: i64, method: &str, params: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params })
+}
+
+fn notification(method: &str, params: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "method": method, "params": params })
+}
+
+fn open(text: &str) -> Value {
+    notification(
+        "textDocument/didOpen",
+        json!({ "textDocument": { "uri": URI, "languageId": "zen", "version": 1, "text": text } }),
+    )
+}
+
+fn at(id: i64, method: &str, line: u64, character: u64) -> Value {
+    request(
+        id,
+        method,
+        json!({ "textDocument": { "uri": URI }, "position": { "line": line, "character": character } }),
+    )
+}
+
+/// The result of the request with `id`
+fn result(replies: &[Value], id: i64) -> &Value {
+    let reply = replies.iter().find(|reply| reply["id"] == id).unwrap();
+    &reply["result"]
+}
+
+#[test]
+fn test_lsp_lifecycle() {
+    let (replies, code) = session(&[
+        request(1, "initialize", json!({ "capabilities": {} })),
+        notification("initialized", json!({})),
+        request(2, "zen/unknown", json!({})),
+        request(3, "shutdown", Value::Null),
+        request(4, "textDocument/hover", json!({})),
+        notification("exit", Value::Null),
+    ]);
+
+    let capabilities = &result(&replies, 1)["capabilities"];
+    assert_eq!(capabilities["textDocumentSync"]["change"], 1);
+    assert_eq!(capabilities["hoverProvider"], true);
+    assert_eq!(capabilities["definitionProvider"], true);
+    assert_eq!(capabilities["documentSymbolProvider"], true);
+
+    let unknown = replies.iter().find(|reply| reply["id"] == 2).unwrap();
+    assert_eq!(unknown["error"]["code"], -32601);
+    assert_eq!(*result(&replies, 3), Value::Null);
+    let after_shutdown = replies.iter().find(|reply| reply["id"] == 4).unwrap();
+    assert_eq!(after_shutdown["error"]["code"], -32600);
+    assert_eq!(code, Some(0));
+
+    // Exiting without a shutdown first is an error
+    let (_, code) = session(&[notification("exit", Value::Null)]);
+    assert_eq!(code, Some(1));
+}
+
+#[test]
+fn test_lsp_diagnostics() {
+    let broken = "PARAMPARA PRATISHTA ANUSHASHAN\nA BOLE TOH (1\nKHATAM TATA BYE BYE";
+    let (replies, _) = session(&[
+        open(broken),
+        notification(
+            "textDocument/didChange",
+            json!({ "textDocument": { "uri": URI, "version": 2 }, "contentChanges": [{ "text": PROGRAM }] }),
+        ),
+        notification(
+            "textDocument/didClose",
+            json!({ "textDocument": { "uri": URI } }),
+        ),
+    ]);
+
+    assert_eq!(replies.len(), 3);
+    for reply in &replies {
+        assert_eq!(reply["method"], "textDocument/publishDiagnostics");
+        assert_eq!(reply["params"]["uri"], URI);
+    }
+    let found = replies[0]["params"]["diagnostics"].as_array().unwrap();
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0]["code"], "SyntaxError");
+    assert_eq!(found[0]["severity"], 1);
+    assert_eq!(
+        found[0]["range"]["start"],
+        json!({ "line": 2, "character": 0 })
+    );
+    assert_eq!(replies[1]["params"]["diagnostics"], json!([]));
+    assert_eq!(replies[2]["params"]["diagnostics"], json!([]));
+}
+
+#[test]
+fn test_lsp_completion() {
+    let text = "PARAMPARA PRATISHTA ANUSHASHAN\nGINTI BOLE TOH 1\nJAB TA";
+    let (replies, _) = session(&[open(text), at(1, "textDocument/completion", 2, 6)]);
+
+    let items = result(&replies, 1).as_array().unwrap();
+    let keyword = items
+        .iter()
+        .find(|item| item["label"] == "JAB TAK HAI JAAN")
+        .unwrap();
+    assert_eq!(keyword["kind"], 14);
+    // The words typed so far are replaced, not just the last one
+    assert_eq!(
+        keyword["textEdit"]["range"],
+        json!({ "start": { "line": 2, "character": 0 }, "end": { "line": 2, "character": 6 } })
+    );
+    assert_eq!(keyword["textEdit"]["newText"], "JAB TAK HAI JAAN");
+
+    let variable = items.iter().find(|item| item["label"] == "GINTI").unwrap();
+    assert_eq!(variable["kind"], 6);
+}
+
+#[test]
+fn test_lsp_navigation() {
+    let (replies, _) = session(&[
+        open(PROGRAM),
+        // C in `LAUT KE AAJA C`, a variable of the function
+        at(1, "textDocument/definition", 3, 17),
+        // N in `jod(I, N)`, a variable of the program
+        at(2, "textDocument/definition", 7, 31),
+        at(3, "textDocument/hover", 7, 25),
+        at(4, "textDocument/hover", 6, 2),
+        request(
+            5,
+            "textDocument/documentSymbol",
+            json!({ "textDocument": { "uri": URI } }),
+        ),
+    ]);
+
+    let definition = result(&replies, 1);
+    assert_eq!(definition["uri"], URI);
+    assert_eq!(
+        definition["range"]["start"],
+        json!({ "line": 2, "character": 9 })
+    );
+    assert_eq!(
+        result(&replies, 2)["range"]["start"],
+        json!({ "line": 5, "character": 0 })
+    );
+
+    let hover = result(&replies, 3)["contents"]["value"].as_str().unwrap();
+    assert!(hover.contains("AAO KABHI HAVELI PE jod(A, B)"));
+    assert!(hover.contains("function, defined on line 2"));
+    let hover = result(&replies, 4)["contents"]["value"].as_str().unwrap();
+    assert!(hover.contains("Counts through a range"));
+
+    let symbols = result(&replies, 5).as_array().unwrap();
+    let names: Vec<_> = symbols.iter().map(|symbol| &symbol["name"]).collect();
+    assert_eq!(names, ["jod", "N", "I"]);
+    assert_eq!(symbols[0]["kind"], 12);
+    assert_eq!(symbols[0]["detail"], "(A, B)");
+    assert_eq!(
+        symbols[0]["range"]["end"],
+        json!({ "line": 4, "character": 12 })
+    );
+    let children: Vec<_> = symbols[0]["children"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|symbol| &symbol["name"])
+        .collect();
+    assert_eq!(children, ["A", "B", "C"]);
+}
+
+#[test]
+fn test_lsp_semantic_tokens() {
+    let text = "PARAMPARA PRATISHTA ANUSHASHAN\nA BOLE TOH \"hé\" @ naam\nKHATAM TATA BYE BYE";
+    let (replies, _) = session(&[
+        open(text),
+        request(
+            1,
+            "textDocument/semanticTokens/full",
+            json!({ "textDocument": { "uri": URI } }),
+        ),
+    ]);
+
+    assert_eq!(
+        result(&replies, 1)["data"],
+        json!([
+            0, 0, 30, 0, 0, // PARAMPARA PRATISHTA ANUSHASHAN
+            1, 0, 1, 1, 0, // A
+            0, 2, 8, 0, 0, // BOLE TOH
+            0, 9, 4, 3, 0, // "hé"
+            0, 5, 6, 4, 0, // @ naam
+            1, 0, 19, 0, 0, // KHATAM TATA BYE BYE
+        ])
+    );
+}
@@ -1,4 +1,4 @@
-use zen::lexer::{lexer_util::Lexer, tokens::Tokens};
+use zen::lexer::{lexer_util::Lexer, span::Span, token_type::TokenType, tokens::Tokens};
 use zen::parser::ast::*;
 use zen::parser::parser_util::Parser;
 
@@ -64,7 +64,7 @@ fn test_input_from_console() {
     let program: Program = Program {
         statements: vec![Statement::Let {
             name: Ident("C".to_owned()),
-            value: Expression::Input,
+            value: Expression::Input(InputKind::Number),
         }],
     };
     assert_input_with_program(input, program);
@@ -451,7 +451,7 @@ fn test_initialization_by_input_and_if_statement() {
             Statement::ProgramStart,
             Statement::Let {
                 name: Ident("A".to_string()),
-                value: Expression::Input,
+                value: Expression::Input(InputKind::Number),
             },
             Statement::If {
                 condition: Box::new(Expression::InfixExpr {
@@ -598,7 +598,7 @@ fn test_complex_program() {
             },
             Statement::Let {
                 name: Ident("C".to_string()),
-                value: Expression::Input,
+                value: Expression::Input(InputKind::Number),
             },
             Statement::If {
                 condition: Box::new(Expression::InfixExpr {
@@ -748,3 +748,381 @@ fn test_complex_expression_123() {
     };
     assert_input_with_program(input, program);
 }
+
+#[test]
+fn test_function_definition() {
+    let input = "AAO KABHI HAVELI PE jodo(a, b)
+        LAUT KE AAJA a + b
+    PHIR MILENGE"
+        .as_bytes();
+    let program: Program = Program {
+        statements: vec![Statement::Function {
+            name: Ident("jodo".to_string()),
+            parameters: vec![Ident("a".to_string()), Ident("b".to_string())],
+            body: vec![Statement::Return(Some(Expression::InfixExpr {
+                left: Box::new(Expression::IdentifierExpr(Ident("a".to_string()))),
+                operator: Infix::Plus,
+                right: Box::new(Expression::IdentifierExpr(Ident("b".to_string()))),
+            }))],
+        }],
+    };
+    assert_input_with_program(input, program);
+}
+
+#[test]
+fn test_bare_return_statement() {
+    let input = "AAO KABHI HAVELI PE kuch_nahi()\nLAUT KE AAJA\nPHIR MILENGE".as_bytes();
+    let program: Program = Program {
+        statements: vec![Statement::Function {
+            name: Ident("kuch_nahi".to_string()),
+            parameters: vec![],
+            body: vec![Statement::Return(None)],
+        }],
+    };
+    assert_input_with_program(input, program);
+}
+
+#[test]
+fn test_call_expression() {
+    let input = "A BOLE TOH jodo(1, B * 2) + 3".as_bytes();
+    let program: Program = Program {
+        statements: vec![Statement::Let {
+            name: Ident("A".to_owned()),
+            value: Expression::InfixExpr {
+                left: Box::new(Expression::Call {
+                    function: Ident("jodo".to_string()),
+                    arguments: vec![
+                        Expression::LiteralExpr(Literal::Number(1.0)),
+                        Expression::InfixExpr {
+                            left: Box::new(Expression::IdentifierExpr(Ident("B".to_string()))),
+                            operator: Infix::Multiply,
+                            right: Box::new(Expression::LiteralExpr(Literal::Number(2.0))),
+                        },
+                    ],
+                }),
+                operator: Infix::Plus,
+                right: Box::new(Expression::LiteralExpr(Literal::Number(3.0))),
+            },
+        }],
+    };
+    assert_input_with_program(input, program);
+}
+
+#[test]
+fn test_local_variable_declaration() {
+    let input = "APNA A BOLE TOH 10".as_bytes();
+    let program: Program = Program {
+        statements: vec![Statement::LocalLet {
+            name: Ident("A".to_owned()),
+            value: Expression::LiteralExpr(Literal::Number(10.0)),
+        }],
+    };
+    assert_input_with_program(input, program);
+}
+
+#[test]
+fn test_index_expression() {
+    let input = "PRINT BASANTI PRINT NAAM[I + 1]".as_bytes();
+    let program: Program = Program {
+        statements: vec![Statement::Print(Box::new(Expression::Index {
+            left: Box::new(Expression::IdentifierExpr(Ident("NAAM".to_string()))),
+            index: Box::new(Expression::InfixExpr {
+                left: Box::new(Expression::IdentifierExpr(Ident("I".to_string()))),
+                operator: Infix::Plus,
+                right: Box::new(Expression::LiteralExpr(Literal::Number(1.0))),
+            }),
+        }))],
+    };
+    assert_input_with_program(input, program);
+}
+
+#[test]
+fn test_slice_expression() {
+    let input = "A BOLE TOH \"Basanti\"[:3] + NAAM[2:]".as_bytes();
+    let program: Program = Program {
+        statements: vec![Statement::Let {
+            name: Ident("A".to_owned()),
+            value: Expression::InfixExpr {
+                left: Box::new(Expression::Slice {
+                    left: Box::new(Expression::LiteralExpr(Literal::StringLiteral(
+                        "Basanti".to_string(),
+                    ))),
+                    start: None,
+                    end: Some(Box::new(Expression::LiteralExpr(Literal::Number(3.0)))),
+                }),
+                operator: Infix::Plus,
+                right: Box::new(Expression::Slice {
+                    left: Box::new(Expression::IdentifierExpr(Ident("NAAM".to_string()))),
+                    start: Some(Box::new(Expression::LiteralExpr(Literal::Number(2.0)))),
+                    end: None,
+                }),
+            },
+        }],
+    };
+    assert_input_with_program(input, program);
+}
+
+#[test]
+fn test_list_literal() {
+    let input = "A BOLE TOH [1, \"do\",\n [true]]".as_bytes();
+    let program: Program = Program {
+        statements: vec![Statement::Let {
+            name: Ident("A".to_owned()),
+            value: Expression::ListExpr(vec![
+                Expression::LiteralExpr(Literal::Number(1.0)),
+                Expression::LiteralExpr(Literal::StringLiteral("do".to_string())),
+                Expression::ListExpr(vec![Expression::LiteralExpr(Literal::BoolLiteral(true))]),
+            ]),
+        }],
+    };
+    assert_input_with_program(input, program);
+}
+
+#[test]
+fn test_index_assignment() {
+    let input = "A[0][I] BOLE TOH 5".as_bytes();
+    let program: Program = Program {
+        statements: vec![Statement::IndexLet {
+            left: Expression::Index {
+                left: Box::new(Expression::IdentifierExpr(Ident("A".to_string()))),
+                index: Box::new(Expression::LiteralExpr(Literal::Number(0.0))),
+            },
+            index: Expression::IdentifierExpr(Ident("I".to_string())),
+            value: Expression::LiteralExpr(Literal::Number(5.0)),
+        }],
+    };
+    assert_input_with_program(input, program);
+}
+
+#[test]
+fn test_map_literal() {
+    let input = "A BOLE TOH {\"a\": 1,\n 2: [true]}".as_bytes();
+    let program: Program = Program {
+        statements: vec![Statement::Let {
+            name: Ident("A".to_owned()),
+            value: Expression::MapExpr(vec![
+                (
+                    Expression::LiteralExpr(Literal::StringLiteral("a".to_string())),
+                    Expression::LiteralExpr(Literal::Number(1.0)),
+                ),
+                (
+                    Expression::LiteralExpr(Literal::Number(2.0)),
+                    Expression::ListExpr(vec![Expression::LiteralExpr(Literal::BoolLiteral(true))]),
+                ),
+            ]),
+        }],
+    };
+    assert_input_with_program(input, program);
+}
+
+#[test]
+fn test_for_statement() {
+    let input = "EK DO TEEN I BOLE TOH 1 SE N TAK\n PRINT BASANTI PRINT I\n JAHAN
+        EK DO TEEN J BOLE TOH 10 SE 0 TAK KADAM -2 JAHAN"
+        .as_bytes();
+    let program: Program = Program {
+        statements: vec![
+            Statement::For {
+                variable: Ident("I".to_string()),
+                start: Expression::LiteralExpr(Literal::Number(1.0)),
+                end: Expression::IdentifierExpr(Ident("N".to_string())),
+                step: None,
+                body: vec![Statement::Print(Box::new(Expression::IdentifierExpr(
+                    Ident("I".to_string()),
+                )))],
+            },
+            Statement::For {
+                variable: Ident("J".to_string()),
+                start: Expression::LiteralExpr(Literal::Number(10.0)),
+                end: Expression::LiteralExpr(Literal::Number(0.0)),
+                step: Some(Expression::PrefixExpr {
+                    operator: Prefix::PrefixMinus,
+                    right: Box::new(Expression::LiteralExpr(Literal::Number(2.0))),
+                }),
+                body: vec![],
+            },
+        ],
+    };
+    assert_input_with_program(input, program);
+}
+
+#[test]
+fn test_loop_control_statements() {
+    let input = "JAB TAK HAI JAAN true TAB TAK
+            AGAR A TAB BAS KAR PAGLE
+            BAS ITNA HI
+            JAANE BHI DO
+        JAHAN"
+        .as_bytes();
+    let program: Program = Program {
+        statements: vec![Statement::While {
+            condition: Box::new(Expression::LiteralExpr(Literal::BoolLiteral(true))),
+            body: vec![
+                Statement::If {
+                    condition: Box::new(Expression::IdentifierExpr(Ident("A".to_string()))),
+                    consequence: vec![Statement::Break],
+                    alternative: None,
+                },
+                Statement::Continue,
+            ],
+        }],
+    };
+    assert_input_with_program(input, program);
+}
+
+#[test]
+fn test_spanned_statement() {
+    let input = "A BOLE TOH 1\n + B".as_bytes();
+    let (_, (r, spans)) = Lexer::lex_tokens_with_spans(input).unwrap();
+    let (_, result) = Parser::parse_tokens(Tokens::new_with_spans(&r, &spans)).unwrap();
+
+    let span = |start, end, line, column| Span {
+        start,
+        end,
+        line,
+        column,
+    };
+    let program: Program = Program {
+        statements: vec![Statement::Spanned {
+            span: span(0, 17, 1, 1),
+            statement: Box::new(Statement::Let {
+                name: Ident("A".to_owned()),
+                value: Expression::Spanned {
+                    span: span(11, 17, 1, 12),
+                    expression: Box::new(Expression::InfixExpr {
+                        left: Box::new(Expression::Spanned {
+                            span: span(11, 12, 1, 12),
+                            expression: Box::new(Expression::LiteralExpr(Literal::Number(1.0))),
+                        }),
+                        operator: Infix::Plus,
+                        right: Box::new(Expression::Spanned {
+                            span: span(16, 17, 2, 4),
+                            expression: Box::new(Expression::IdentifierExpr(Ident(
+                                "B".to_string(),
+                            ))),
+                        }),
+                    }),
+                },
+            }),
+        }],
+    };
+    assert_eq!(result, program);
+}
+
+#[test]
+fn test_parse_error_inside_block() {
+    let input = "PARAMPARA PRATISHTA ANUSHASHAN
+AAO KABHI HAVELI PE bhaag(X)
+    AGAR X > 1 TAB
+        LAUT KE AAJA (X
+    BAS ITNA HI
+PHIR MILENGE
+KHATAM TATA BYE BYE";
+    let (r, spans) = Lexer::lex_program(input.as_bytes()).unwrap();
+    let errors = Parser::parse_program(Tokens::new_with_spans(&r, &spans)).unwrap_err();
+    let [err] = &errors[..] else {
+        panic!("expected a single parse error, got {:?}", errors);
+    };
+
+    assert_eq!(err.expected, vec!["')'".to_string()]);
+    assert_eq!(err.found, TokenType::EndIf);
+    assert_eq!(err.span.map(|span| (span.line, span.column)), Some((5, 5)));
+    assert_eq!(
+        err.to_string(),
+        "Mogambo dukhi hua! Expected ')' but found 'BAS ITNA HI'"
+    );
+}
+
+#[test]
+fn test_parse_error_at_name_after_value() {
+    let input = "PARAMPARA PRATISHTA ANUSHASHAN
+PRINT BASANTI PRINT 1 + 1 X
+KHATAM TATA BYE BYE";
+    let (r, spans) = Lexer::lex_program(input.as_bytes()).unwrap();
+    let errors = Parser::parse_program(Tokens::new_with_spans(&r, &spans)).unwrap_err();
+    let [err] = &errors[..] else {
+        panic!("expected a single parse error, got {:?}", errors);
+    };
+
+    assert_eq!(err.found, TokenType::Identifier("X".to_owned()));
+    assert_eq!(err.span.map(|span| (span.line, span.column)), Some((2, 27)));
+    assert_eq!(
+        err.to_string(),
+        "Mogambo dukhi hua! Expected an operator but found the name 'X'"
+    );
+}
+
+#[test]
+fn test_parse_errors_are_collected() {
+    let input = "PARAMPARA PRATISHTA ANUSHASHAN
+A BOLE TOH 1 +
+JAB TAK HAI JAAN A < 3 TAB TAK
+    PRINT BASANTI PRINT (A
+    A BOLE TOH A + 1
+JAHAN
+AGAR A > TAB
+    PRINT BASANTI PRINT A
+BAS ITNA HI
+JAHAN
+B BOLE TOH ]
+PRINT BASANTI PRINT A
+KHATAM TATA BYE BYE";
+    let (r, spans) = Lexer::lex_program(input.as_bytes()).unwrap();
+    let errors = Parser::parse_program(Tokens::new_with_spans(&r, &spans)).unwrap_err();
+
+    let found: Vec<(usize, TokenType)> = errors
+        .iter()
+        .map(|err| (err.span.unwrap().line, err.found.clone()))
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            (2, TokenType::EndOfStatement),
+            (5, TokenType::Identifier("A".to_owned())),
+            (7, TokenType::Then),
+            (10, TokenType::EndWhile),
+            (11, TokenType::RightBracket),
+        ]
+    );
+    assert_eq!(errors[0].expected, vec!["an expression".to_string()]);
+    assert_eq!(errors[3].expected, vec!["a statement".to_string()]);
+}
+
+#[test]
+fn test_parse_errors_after_a_swallowed_line_ending() {
+    // 'BOLE TOH' takes the line ending along, so the next line must still be
+    // parsed on its own
+    let input = "PARAMPARA PRATISHTA ANUSHASHAN
+A BOLE TOH
+PRINT BASANTI PRINT (2
+B BOLE TOH 3
+KHATAM TATA BYE BYE";
+    let (r, spans) = Lexer::lex_program(input.as_bytes()).unwrap();
+    let errors = Parser::parse_program(Tokens::new_with_spans(&r, &spans)).unwrap_err();
+
+    let found: Vec<(usize, TokenType)> = errors
+        .iter()
+        .map(|err| (err.span.unwrap().line, err.found.clone()))
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            (3, TokenType::Print),
+            (4, TokenType::Identifier("B".to_owned())),
+        ]
+    );
+    assert_eq!(errors[1].expected, vec!["')'".to_string()]);
+}
+
+#[test]
+fn test_invalid_statement_is_kept() {
+    let input = "PRINT BASANTI PRINT 1 +\nPRINT BASANTI PRINT 2".as_bytes();
+    let (_, r) = Lexer::lex_tokens(input).unwrap();
+    let (_, result) = Parser::parse_tokens(Tokens::new(&r)).unwrap();
+
+    assert!(matches!(result.statements[0], Statement::Invalid(_)));
+    assert_eq!(
+        result.statements[1],
+        Statement::Print(Box::new(Expression::LiteralExpr(Literal::Number(2.0))))
+    );
+}
@@ -0,0 +1,101 @@
+use zen::error::ZenError;
+use zen::repl::{Repl, Step};
+
+fn output(step: Step) -> String {
+    match step {
+        Step::Output(output) => output,
+        other => panic!("expected output, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_repl_keeps_state_between_lines() {
+    let mut repl = Repl::new(false);
+    assert_eq!(output(repl.feed("A BOLE TOH 5")), "");
+    assert_eq!(output(repl.feed("PRINT BASANTI PRINT A * 2")), "10\n");
+
+    assert!(matches!(
+        repl.feed("AAO KABHI HAVELI PE dugna(X)"),
+        Step::More
+    ));
+    assert!(repl.is_pending());
+    assert!(matches!(repl.feed("    LAUT KE AAJA X * 2"), Step::More));
+    assert_eq!(output(repl.feed("PHIR MILENGE")), "");
+    assert!(!repl.is_pending());
+    assert_eq!(output(repl.feed("PRINT BASANTI PRINT dugna(A)")), "10\n");
+}
+
+#[test]
+fn test_repl_waits_for_nested_blocks() {
+    let mut repl = Repl::new(false);
+    let lines = [
+        "EK DO TEEN I BOLE TOH 1 SE 3 TAK",
+        "    AGAR I == 2 TAB",
+        "        JAANE BHI DO",
+        "    WARNA AGAR I == 3 TAB",
+        "        PRINT BASANTI PRINT \"teen\"",
+        "    NHI TOH",
+        "        PRINT BASANTI PRINT I",
+        "    BAS ITNA HI",
+    ];
+    for line in lines {
+        assert!(matches!(repl.feed(line), Step::More), "{}", line);
+    }
+    assert_eq!(output(repl.feed("JAHAN")), "1\nteen\n");
+}
+
+#[test]
+fn test_repl_reports_errors_and_carries_on() {
+    let mut repl = Repl::new(false);
+    repl.feed("A BOLE TOH 1");
+
+    let Step::Error { error, source } = repl.feed("PRINT BASANTI PRINT A / 0") else {
+        panic!("expected an error");
+    };
+    assert!(matches!(error, ZenError::Runtime(_)));
+    assert_eq!(source, "PRINT BASANTI PRINT A / 0\n");
+
+    // A failing function call leaves the session at the top level
+    repl.feed("AAO KABHI HAVELI PE gadbad()");
+    repl.feed("    APNA B BOLE TOH 2");
+    repl.feed("    LAUT KE AAJA B + \"do\" * 2");
+    repl.feed("PHIR MILENGE");
+    assert!(matches!(repl.feed("gadbad()"), Step::Error { .. }));
+    repl.feed("C BOLE TOH 3");
+
+    assert!(matches!(
+        repl.feed("PRINT BASANTI PRINT (A"),
+        Step::Error {
+            error: ZenError::Parse(_),
+            ..
+        }
+    ));
+    assert!(!repl.is_pending());
+    assert_eq!(output(repl.feed(":vars")), "A = 1\nC = 3\n");
+}
+
+#[test]
+fn test_repl_commands() {
+    let mut repl = Repl::new(false);
+    assert_eq!(output(repl.feed(":vars")).lines().count(), 1);
+    repl.feed("L BOLE TOH [1, \"do\"]");
+    assert_eq!(output(repl.feed(":vars")), "L = [1, \"do\"]\n");
+
+    output(repl.feed(":reset"));
+    assert!(matches!(
+        repl.feed("PRINT BASANTI PRINT L"),
+        Step::Error { .. }
+    ));
+
+    assert_eq!(output(repl.feed(":ast")), "AST display on\n");
+    assert!(output(repl.feed("PRINT BASANTI PRINT 1")).starts_with("Here is your AST:"));
+    output(repl.feed(":ast"));
+    assert_eq!(output(repl.feed(":tokens")), "Token display on\n");
+    assert_eq!(
+        output(repl.feed("PRINT BASANTI PRINT 1")),
+        "Here are your tokens:\n [Print, Number(1.0), EndOfStatement]\n1\n"
+    );
+
+    assert!(output(repl.feed(":nacho")).starts_with("Unknown command ':nacho'"));
+    assert!(matches!(repl.feed(":quit"), Step::Quit));
+}
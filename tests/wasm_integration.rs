@@ -0,0 +1,357 @@
+use wasmi::{Caller, Engine, Extern, Linker, Module, Store};
+use zen::error::ZenError;
+use zen::{compile_to_wat, run_program};
+
+/// What a compiled program did: the lines it printed and the runtime error it
+/// stopped with, formatted like the C backend's
+#[derive(Default)]
+struct Host {
+    input: Vec<String>,
+    output: String,
+    error: Option<String>,
+}
+
+fn read(caller: &Caller<'_, Host>, ptr: i32, len: i32) -> String {
+    let memory = caller
+        .get_export("memory")
+        .and_then(Extern::into_memory)
+        .unwrap();
+    let bytes = &memory.data(caller)[ptr as usize..(ptr + len) as usize];
+    String::from_utf8(bytes.to_vec()).unwrap()
+}
+
+/// Compiles `code` to WebAssembly and runs it with wasmi, serving the lines
+/// of `stdin` to it
+fn run_compiled(code: &str, stdin: &str) -> Host {
+    let wat = compile_to_wat(code.to_string()).unwrap();
+    let wasm = wat::parse_str(&wat).unwrap_or_else(|e| panic!("{}\n{}", e, wat));
+
+    let engine = Engine::default();
+    let module = Module::new(&engine, &wasm[..]).unwrap();
+    let input = stdin.lines().rev().map(str::to_string).collect();
+    let mut store = Store::new(
+        &engine,
+        Host {
+            input,
+            ..Host::default()
+        },
+    );
+
+    let mut linker = <Linker<Host>>::new(&engine);
+    linker
+        .func_wrap(
+            "env",
+            "print",
+            |mut caller: Caller<'_, Host>, ptr: i32, len: i32| {
+                let text = read(&caller, ptr, len);
+                caller.data_mut().output += &(text + "\n");
+            },
+        )
+        .unwrap();
+    linker
+        .func_wrap(
+            "env",
+            "input",
+            |mut caller: Caller<'_, Host>, ptr: i32, cap: i32| {
+                let Some(line) = caller.data_mut().input.pop() else {
+                    return -1;
+                };
+                let len = line.len().min(cap as usize);
+                let memory = caller
+                    .get_export("memory")
+                    .and_then(Extern::into_memory)
+                    .unwrap();
+                memory.data_mut(&mut caller)[ptr as usize..ptr as usize + len]
+                    .copy_from_slice(&line.as_bytes()[..len]);
+                len as i32
+            },
+        )
+        .unwrap();
+    linker
+        .func_wrap(
+            "env",
+            "error",
+            |mut caller: Caller<'_, Host>,
+             code: i32,
+             code_len: i32,
+             msg: i32,
+             msg_len: i32,
+             line: i32,
+             column: i32| {
+                let mut error = format!(
+                    "error[{}]: {}\n",
+                    read(&caller, code, code_len),
+                    read(&caller, msg, msg_len)
+                );
+                if line != 0 {
+                    error += &format!(" --> {}:{}\n", line, column);
+                }
+                caller.data_mut().error = Some(error);
+            },
+        )
+        .unwrap();
+
+    let instance = linker
+        .instantiate(&mut store, &module)
+        .unwrap()
+        .start(&mut store)
+        .unwrap();
+    let run = instance.get_typed_func::<(), ()>(&store, "run").unwrap();
+    let result = run.call(&mut store, ());
+    let host = store.into_data();
+    // Programs only trap right after reporting an error
+    assert_eq!(result.is_err(), host.error.is_some(), "{:?}", result);
+    host
+}
+
+fn assert_same_output(code: &str) {
+    let expected = run_program(code.to_string(), "", false).unwrap();
+    let host = run_compiled(code, "");
+    assert_eq!(host.error, None);
+    assert_eq!(host.output, expected);
+}
+
+/// The compiled program must fail with the interpreter's error, after
+/// printing whatever it printed before failing
+fn assert_same_error(code: &str, printed: &str) {
+    let Err(ZenError::Runtime(err)) = run_program(code.to_string(), "", false) else {
+        panic!("expected a runtime error");
+    };
+    let mut expected = format!("error[{}]: {}\n", err.error_type, err);
+    if let Some(span) = err.span {
+        expected += &format!(" --> {}:{}\n", span.line, span.column);
+    }
+
+    let host = run_compiled(code, "");
+    assert_eq!(host.output, printed);
+    assert_eq!(host.error, Some(expected));
+}
+
+#[test]
+fn test_wasm_numbers_and_strings() {
+    let input = r#"PARAMPARA PRATISHTA ANUSHASHAN
+PRINT BASANTI PRINT 0.1 + 0.2
+PRINT BASANTI PRINT 1 / 3
+PRINT BASANTI PRINT 10 / 4 * 2
+PRINT BASANTI PRINT 7 % 3 - 2.5
+PRINT BASANTI PRINT -7.5 % 2
+PRINT BASANTI PRINT -0
+PRINT BASANTI PRINT 100000000000000000000000 * 10
+PRINT BASANTI PRINT 1 / 1000000000
+PRINT BASANTI PRINT 2 / 3 * 1000000000000000000000000000000
+PRINT BASANTI PRINT 1 / 3 / 1000000000000000000000000000000
+PRINT BASANTI PRINT 123456789012345678
+PRINT BASANTI PRINT ANK("1e308") * 10
+PRINT BASANTI PRINT ANK("5e-324") + ANK("2.2250738585072014e-308")
+PRINT BASANTI PRINT ANK("-inf") + ANK("  0.30000000000000004 ")
+PRINT BASANTI PRINT ANK("9007199254740993") + ANK("1.7976931348623157e308")
+PRINT BASANTI PRINT "Mogambo" + " khush hua " + 2 + true
+PRINT BASANTI PRINT 1 < 2 && !(2 >= 3) || false
+PRINT BASANTI PRINT "abc" == "abc"
+PRINT BASANTI PRINT [1, 2] != [1, 2]
+KHATAM TATA BYE BYE"#;
+    assert_same_output(input);
+}
+
+#[test]
+fn test_wasm_loops_and_functions() {
+    let input = "PARAMPARA PRATISHTA ANUSHASHAN
+AAO KABHI HAVELI PE fib(N)
+    AGAR N < 2 TAB
+        LAUT KE AAJA N
+    BAS ITNA HI
+    LAUT KE AAJA fib(N - 1) + fib(N - 2)
+PHIR MILENGE
+AAO KABHI HAVELI PE pehla_bada(LIST, LIMIT)
+    EK DO TEEN I BOLE TOH 0 SE LAMBAI(LIST) - 1 TAK
+        AGAR LIST[I] > LIMIT TAB
+            LAUT KE AAJA I
+        BAS ITNA HI
+    JAHAN
+    LAUT KE AAJA -1
+PHIR MILENGE
+PRINT BASANTI PRINT fib(15)
+PRINT BASANTI PRINT pehla_bada([3, 8, 1, 9], 5)
+K BOLE TOH 0
+JAB TAK HAI JAAN K < 10 TAB TAK
+    K BOLE TOH K + 1
+    AGAR K % 2 == 0 TAB
+        JAANE BHI DO
+    WARNA AGAR K > 7 TAB
+        BAS KAR PAGLE
+    BAS ITNA HI
+    PRINT BASANTI PRINT K
+JAHAN
+EK DO TEEN X BOLE TOH 1 SE 0 TAK KADAM -0.25
+    PRINT BASANTI PRINT X
+JAHAN
+KHATAM TATA BYE BYE";
+    assert_same_output(input);
+}
+
+#[test]
+fn test_wasm_scopes() {
+    let input = "PARAMPARA PRATISHTA ANUSHASHAN
+A BOLE TOH 1
+AAO KABHI HAVELI PE badlo()
+    PRINT BASANTI PRINT A
+    A BOLE TOH 2
+    LAUT KE AAJA A
+PHIR MILENGE
+PRINT BASANTI PRINT badlo()
+PRINT BASANTI PRINT A
+AGAR true TAB
+    APNA A BOLE TOH 3
+    PRINT BASANTI PRINT A
+BAS ITNA HI
+PRINT BASANTI PRINT A
+KHATAM TATA BYE BYE";
+    assert_same_output(input);
+}
+
+#[test]
+fn test_wasm_collections() {
+    let input = r#"PARAMPARA PRATISHTA ANUSHASHAN
+L BOLE TOH [1, "do", [3]]
+JODO(L, 4)
+L[0] BOLE TOH 10
+PRINT BASANTI PRINT L
+PRINT BASANTI PRINT L[1:]
+PRINT BASANTI PRINT L[:1]
+PRINT BASANTI PRINT HATAO(L, 2)
+M BOLE TOH {"b": 2, 1: "ek", "a": [1], -0: "shunya"}
+M["c"] BOLE TOH "ch\"a"
+PRINT BASANTI PRINT M
+PRINT BASANTI PRINT CHABIYAN(M)
+PRINT BASANTI PRINT HAI_KYA(M, "z")
+PRINT BASANTI PRINT HATAO(M, 1)
+PRINT BASANTI PRINT LAMBAI(M)
+S BOLE TOH "Basanti"
+PRINT BASANTI PRINT S[2] + S[1:4] + S[10:]
+PRINT BASANTI PRINT BADA(S) + CHHOTA("NACHO")
+PRINT BASANTI PRINT KHOJO(S, "nti")
+PRINT BASANTI PRINT ANK(" 4.5 ") * 2
+PRINT BASANTI PRINT AKSHAR(L) + "!"
+KHATAM TATA BYE BYE"#;
+    assert_same_output(input);
+}
+
+#[test]
+fn test_wasm_unicode_case() {
+    // Letters that grow, titlecase letters, and 'Σ' that lowers differently
+    // at the end of a word
+    let input = r#"PARAMPARA PRATISHTA ANUSHASHAN
+PRINT BASANTI PRINT BADA("héllo straße ﬁ ŉ ǅ ÿ 𐐨")
+PRINT BASANTI PRINT CHHOTA("ΟΔΟΣ ΟΔΟΣ. Σ İ ǅ ΑΣ'Σ ÀÉÎ")
+KHATAM TATA BYE BYE"#;
+    assert_same_output(input);
+}
+
+#[test]
+fn test_wasm_input() {
+    let input = "PARAMPARA PRATISHTA ANUSHASHAN
+A BOLE TOH INPUT LE LE RE BABA
+B BOLE TOH INPUT LE LE RE BABA
+PRINT BASANTI PRINT A * B
+KHATAM TATA BYE BYE";
+    let host = run_compiled(input, "6\n 7 \n");
+    assert_eq!(host.output, "42\n");
+
+    let host = run_compiled(input, "chhe\n");
+    assert!(host.error.unwrap().starts_with("error[InvalidInputError]"));
+
+    let input = "PARAMPARA PRATISHTA ANUSHASHAN
+N BOLE TOH INPUT LE LE RE BABA
+NAAM BOLE TOH SHABD LE LE RE BABA
+BAAKI BOLE TOH PURI LINE LE LE RE BABA
+AGLI BOLE TOH PURI LINE LE LE RE BABA
+PRINT BASANTI PRINT [N, NAAM, BAAKI, AGLI]
+KHATAM TATA BYE BYE";
+    let stdin = "21 Gabbar Singh  Thakur\n  Ramgarh \n";
+    let expected = run_program(input.to_string(), stdin, false).unwrap();
+    assert_eq!(run_compiled(input, stdin).output, expected);
+
+    let host = run_compiled(input, "7 Jai\n");
+    assert!(
+        host.error
+            .unwrap()
+            .starts_with("error[EmptyCustomInputStack]")
+    );
+}
+
+#[test]
+fn test_wasm_runtime_errors() {
+    let input = "PARAMPARA PRATISHTA ANUSHASHAN
+A BOLE TOH 5
+AAO KABHI HAVELI PE bhaag(X)
+    LAUT KE AAJA X / (A - 5)
+PHIR MILENGE
+PRINT BASANTI PRINT 1
+PRINT BASANTI PRINT 1 + bhaag(2)
+KHATAM TATA BYE BYE";
+    assert_same_error(input, "1\n");
+
+    let input = "PARAMPARA PRATISHTA ANUSHASHAN
+AAO KABHI HAVELI PE kuch_nahi()
+    PRINT BASANTI PRINT 0
+PHIR MILENGE
+X BOLE TOH kuch_nahi()
+KHATAM TATA BYE BYE";
+    assert_same_error(input, "0\n");
+
+    let input = "PARAMPARA PRATISHTA ANUSHASHAN
+AAO KABHI HAVELI PE hamesha(N)
+    LAUT KE AAJA hamesha(N + 1)
+PHIR MILENGE
+hamesha(0)
+KHATAM TATA BYE BYE";
+    assert_same_error(input, "");
+
+    let input = "PARAMPARA PRATISHTA ANUSHASHAN
+AGAR true TAB
+    APNA Y BOLE TOH 3
+BAS ITNA HI
+PRINT BASANTI PRINT [1, 2][Y]
+KHATAM TATA BYE BYE";
+    assert_same_error(input, "");
+
+    let input = "PARAMPARA PRATISHTA ANUSHASHAN
+AAO KABHI HAVELI PE ruko()
+    BAS KAR PAGLE
+PHIR MILENGE
+EK DO TEEN I BOLE TOH 1 SE 3 TAK
+    ruko()
+JAHAN
+KHATAM TATA BYE BYE";
+    assert_same_error(input, "");
+
+    let input = "PARAMPARA PRATISHTA ANUSHASHAN
+LAUT KE AAJA 1
+KHATAM TATA BYE BYE";
+    assert_same_error(input, "");
+
+    let input = r#"PARAMPARA PRATISHTA ANUSHASHAN
+PRINT BASANTI PRINT {"a": 1}["b"]
+KHATAM TATA BYE BYE"#;
+    assert_same_error(input, "");
+
+    let input = r#"PARAMPARA PRATISHTA ANUSHASHAN
+PRINT BASANTI PRINT KHOJO("abc", 1, 2)
+KHATAM TATA BYE BYE"#;
+    assert_same_error(input, "");
+
+    let input = "PARAMPARA PRATISHTA ANUSHASHAN
+EK DO TEEN I BOLE TOH 1 SE 3 TAK KADAM 0
+JAHAN
+KHATAM TATA BYE BYE";
+    assert_same_error(input, "");
+}
+
+#[test]
+fn test_compile_to_wat_reports_syntax_errors() {
+    let input = "PARAMPARA PRATISHTA ANUSHASHAN\nA BOLE TOH (1\nKHATAM TATA BYE BYE";
+    assert!(matches!(
+        compile_to_wat(input.to_string()),
+        Err(ZenError::Parse(_))
+    ));
+}